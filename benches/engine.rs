@@ -0,0 +1,41 @@
+use chess_game::board::Board;
+use chess_game::engine::bench::DEFAULT_BENCH_DEPTH;
+use chess_game::engine::Engine;
+use chess_game::perft::REFERENCE_POSITIONS;
+use std::time::Instant;
+
+// the perft depth every reference position has a count for
+const PERFT_DEPTH: u32 = 4;
+
+// the move generation alone, then the whole search; either's node count changing
+// means its behaviour did, and the speeds are what to watch for regressions
+fn main() {
+    let start = Instant::now();
+    let mut nodes = 0;
+    for &(name, fen, counts) in REFERENCE_POSITIONS {
+        let board = Board::from_fen(fen).expect("reference fen is valid");
+        let count = board.perft(PERFT_DEPTH);
+        assert_eq!(
+            Some(&count),
+            counts.get(PERFT_DEPTH as usize - 1),
+            "{}",
+            name
+        );
+        nodes += count;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "perft:  {:>10} nodes in {:.3}s, {:.0} nodes/s",
+        nodes,
+        elapsed,
+        nodes as f64 / elapsed.max(1e-9)
+    );
+
+    let bench = Engine::new().bench(DEFAULT_BENCH_DEPTH, |_, _| {});
+    println!(
+        "search: {:>10} nodes in {:.3}s, {} nodes/s",
+        bench.nodes,
+        bench.elapsed.as_secs_f64(),
+        bench.nodes_per_second()
+    );
+}
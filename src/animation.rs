@@ -0,0 +1,42 @@
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+// something on the screen changing for a short while after it starts, like a piece
+// sliding over or fading away; it's drawn at its progress every frame until it's done,
+// and starting another in its place cuts it short
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+    started: Instant,
+    duration: Duration,
+}
+
+impl Animation {
+    pub fn start(duration: Duration) -> Animation {
+        Animation {
+            started: Instant::now(),
+            duration,
+        }
+    }
+
+    // from 0 when it starts to 1 once it's over
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
+
+// from 0 to 1 quickly at first, settling gently at the end
+pub fn ease_out(t: f32) -> f32 {
+    1.0 - (1.0 - t) * (1.0 - t)
+}
+
+// from 0 up to 1 and back again `beats` times as t goes from 0 to 1
+pub fn pulse(t: f32, beats: u32) -> f32 {
+    (1.0 - (2.0 * PI * t * beats as f32).cos()) / 2.0
+}
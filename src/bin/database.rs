@@ -0,0 +1,93 @@
+use chess_game::board::Board;
+use chess_game::database::{self, Database};
+use chess_game::pgn::PgnGame;
+use std::process;
+
+const USAGE: &str = "usage: database [--db directory] import file.pgn...
+       database [--db directory] search position
+       database [--db directory] show number
+import adds the games of pgn files, search lists the games that reached a position,
+given as a fen or a pgn file that's played to its end, and show prints a game's pgn.
+the games are kept in the config directory unless told otherwise";
+
+fn main() {
+    let mut directory = None;
+    let mut words = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--db" => directory = Some(args.next().unwrap_or_else(|| exit_with_usage())),
+            _ if arg.starts_with("--") => exit_with_usage(),
+            _ => words.push(arg),
+        }
+    }
+    let directory = directory
+        .map(Into::into)
+        .or_else(Database::default_path)
+        .unwrap_or_else(|| fail("There's no config directory; --db says where the games go"));
+    let mut db = Database::open(&directory)
+        .unwrap_or_else(|error| fail(&format!("Couldn't open {}: {}", directory.display(), error)));
+
+    match words.split_first() {
+        Some((command, paths)) if command == "import" && !paths.is_empty() => {
+            for path in paths {
+                let text = std::fs::read_to_string(path)
+                    .unwrap_or_else(|error| fail(&format!("Couldn't read {}: {}", path, error)));
+                match db.import(&text) {
+                    Ok(count) => println!("{} games from {}", count, path),
+                    Err(error) => fail(&format!("Couldn't import {}: {}", path, error)),
+                }
+            }
+            println!("{} games in {}", db.len(), directory.display());
+        }
+        Some((command, [position])) if command == "search" => {
+            let board = load(position).unwrap_or_else(|error| fail(&error));
+            let hits = db.search(&board);
+            for hit in &hits {
+                match db.game(hit.game) {
+                    Ok(game) => println!(
+                        "{:>6}  {}, after {} moves",
+                        hit.game + 1,
+                        database::summary(&game),
+                        hit.ply.div_ceil(2)
+                    ),
+                    Err(error) => fail(&format!("Couldn't read game {}: {}", hit.game + 1, error)),
+                }
+            }
+            println!("{} of {} games", hits.len(), db.len());
+        }
+        Some((command, [number])) if command == "show" => {
+            let index = match number.parse::<usize>() {
+                Ok(number) if number > 0 => number - 1,
+                _ => exit_with_usage(),
+            };
+            match db.game(index) {
+                Ok(game) => print!("{}", game.to_pgn()),
+                Err(error) => fail(&format!("Couldn't read game {}: {}", number, error)),
+            }
+        }
+        _ => exit_with_usage(),
+    }
+}
+
+// a file that's there is read as a pgn, anything else is taken for a fen
+fn load(position: &str) -> Result<Board, String> {
+    match std::fs::read_to_string(position) {
+        Ok(text) => {
+            let game = PgnGame::from_pgn(&text)
+                .map_err(|error| format!("Couldn't read {}: {}", position, error))?;
+            Ok(game.board_at(game.moves().len()))
+        }
+        Err(_) => Board::from_fen(position).map_err(|error| format!("Invalid FEN: {}", error)),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
@@ -0,0 +1,62 @@
+use chess_game::board::Board;
+use chess_game::diagram::Diagram;
+use chess_game::pgn::PgnGame;
+use std::process;
+
+const USAGE: &str = "usage: diagram [--size pixels] [--flip] [--plain] position output.png
+the position is a fen or a pgn file, which is drawn after its last move with that
+move highlighted; --plain leaves out the coordinates and the highlight";
+
+fn main() {
+    let mut diagram = Diagram::default();
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => {
+                let size = args.next().and_then(|size| size.parse().ok());
+                diagram.size = size.unwrap_or_else(|| exit_with_usage());
+            }
+            "--flip" => diagram.flipped = true,
+            "--plain" => {
+                diagram.coordinates = false;
+                diagram.last_move = None;
+            }
+            _ if arg.starts_with("--") => exit_with_usage(),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() != 2 {
+        exit_with_usage();
+    }
+
+    let board = match load(&paths[0]) {
+        Ok(board) => board,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    if let Err(error) = diagram.render(&board).save(&paths[1]) {
+        eprintln!("Couldn't write {}: {}", paths[1], error);
+        process::exit(1);
+    }
+}
+
+// a file that's there is read as a pgn, anything else is taken for a fen
+fn load(position: &str) -> Result<Board, String> {
+    match std::fs::read_to_string(position) {
+        Ok(text) => {
+            let game = PgnGame::from_pgn(&text)
+                .map_err(|error| format!("Couldn't read {}: {}", position, error))?;
+            Ok(game.board_at(game.moves().len()))
+        }
+        Err(_) => Board::from_fen(position).map_err(|error| format!("Invalid FEN: {}", error)),
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
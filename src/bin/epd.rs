@@ -0,0 +1,93 @@
+use chess_game::engine::{Engine, SearchLimits};
+use chess_game::epd::parse_epd;
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: epd [--movetime ms | --depth plies] [--threads count] file
+searches each position of a test suite like wac or sts, a second each unless told
+otherwise, and counts the ones where the engine played a bm move and no am move";
+
+fn main() {
+    let mut limits = SearchLimits::time(Duration::from_secs(1));
+    let mut threads = 1;
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut number = || {
+            args.next()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_else(|| exit_with_usage())
+        };
+        match arg.as_str() {
+            "--movetime" => limits = SearchLimits::time(Duration::from_millis(number())),
+            "--depth" => limits = SearchLimits::depth(number() as u32),
+            "--threads" => threads = number() as usize,
+            _ if arg.starts_with("--") || path.is_some() => exit_with_usage(),
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.unwrap_or_else(|| exit_with_usage());
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|error| {
+        eprintln!("Couldn't read {}: {}", path, error);
+        process::exit(1);
+    });
+    let positions = parse_epd(&text).unwrap_or_else(|error| {
+        eprintln!("Invalid EPD in {}: {}", path, error);
+        process::exit(1);
+    });
+
+    let mut engine = Engine::new();
+    engine.set_threads(threads);
+    let start = Instant::now();
+    let mut solved = 0;
+    for (i, epd) in positions.iter().enumerate() {
+        // each position on its own, like it would be the first time it's seen
+        engine.clear_hash();
+        let result = engine.search(&epd.board, limits.clone());
+        let found = result.best_move.is_some_and(|mv| epd.is_solved_by(mv));
+        solved += found as usize;
+        let played = result
+            .best_move
+            .map_or("none".to_string(), |mv| mv.to_san(&epd.board));
+        let expected = |moves: &[_]| {
+            moves
+                .iter()
+                .map(|&mv: &chess_game::moves::Move| mv.to_san(&epd.board))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let mut wanted = String::new();
+        if !epd.best_moves.is_empty() {
+            wanted.push_str(&format!("bm {}", expected(&epd.best_moves)));
+        }
+        if !epd.avoid_moves.is_empty() {
+            if !wanted.is_empty() {
+                wanted.push_str(", ");
+            }
+            wanted.push_str(&format!("am {}", expected(&epd.avoid_moves)));
+        }
+        let id = epd
+            .id()
+            .map_or_else(|| format!("#{}", i + 1), str::to_string);
+        println!(
+            "{:<12} {:<4} {:<8} {} (depth {})",
+            id,
+            if found { "ok" } else { "--" },
+            played,
+            wanted,
+            result.depth
+        );
+    }
+    println!(
+        "Solved {} of {} ({:.1}%) in {:.1}s",
+        solved,
+        positions.len(),
+        solved as f64 * 100.0 / positions.len().max(1) as f64,
+        start.elapsed().as_secs_f64()
+    );
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
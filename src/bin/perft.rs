@@ -0,0 +1,86 @@
+use chess_game::board::Board;
+use chess_game::fen::STARTING_FEN;
+use chess_game::perft::REFERENCE_POSITIONS;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: perft <depth> [fen]
+       perft --suite [max depth]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("--suite") => {
+            let max_depth = match args.get(1).map(|depth| depth.parse()) {
+                Some(Ok(depth)) => depth,
+                Some(Err(_)) => exit_with_usage(),
+                None => 4,
+            };
+            if !run_suite(max_depth) {
+                process::exit(1);
+            }
+        }
+        Some(depth) => {
+            let depth = depth.parse().unwrap_or_else(|_| exit_with_usage());
+            let fen = if args.len() > 1 {
+                args[1..].join(" ")
+            } else {
+                STARTING_FEN.to_string()
+            };
+            match Board::from_fen(&fen) {
+                Ok(board) => divide(&board, depth),
+                Err(error) => {
+                    eprintln!("Invalid FEN: {}", error);
+                    process::exit(1);
+                }
+            }
+        }
+        None => exit_with_usage(),
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn divide(board: &Board, depth: u32) {
+    let start = Instant::now();
+    let mut total = 0;
+    for (mv, nodes) in board.perft_divide(depth) {
+        println!("{}: {}", mv, nodes);
+        total += nodes;
+    }
+    let elapsed = start.elapsed();
+    println!();
+    println!("Nodes searched: {}", total);
+    println!(
+        "Time: {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        total as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+}
+
+// checks every reference position up to `max_depth`, returns whether all counts matched
+fn run_suite(max_depth: u32) -> bool {
+    let mut passed = true;
+    for &(name, fen, counts) in REFERENCE_POSITIONS {
+        let board = Board::from_fen(fen).expect("reference fen is valid");
+        for (depth, &expected) in (1..=max_depth).zip(counts) {
+            let start = Instant::now();
+            let nodes = board.perft(depth);
+            let status = if nodes == expected { "ok" } else { "FAILED" };
+            println!(
+                "{:<12} depth {}: {:>10} (expected {:>10}) {:>6} in {:.3}s",
+                name,
+                depth,
+                nodes,
+                expected,
+                status,
+                start.elapsed().as_secs_f64()
+            );
+            passed &= nodes == expected;
+        }
+    }
+    passed
+}
@@ -0,0 +1,365 @@
+use chess_game::board::{Board, Color};
+use chess_game::book::Book;
+use chess_game::clock::{Clock, TimeControl};
+use chess_game::engine::external::ExternalEngine;
+use chess_game::engine::{Engine, SearchLimits};
+use chess_game::moves::Move;
+use chess_game::pgn::PgnGame;
+use chess_game::status::GameStatus;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str =
+    "usage: selfplay [--games count] [--movetime ms | --depth plies | --tc minutes+increment]
+                [--book-plies plies] [--max-plies plies] [--pgn file] first second
+each engine is builtin, builtin:option=value,... or the path of a uci engine; the
+builtin options are threads, hash, nullmove, lmr, killers, history and aspiration, like
+builtin:nullmove=false,threads=2. the games come in pairs playing the same opening from
+the bundled book with the colours swapped, and the elo is the first engine's";
+
+// one of the two sides of the match
+enum Player {
+    Builtin(Engine),
+    External(ExternalEngine),
+}
+
+impl Player {
+    fn from_spec(spec: &str) -> Result<Player, String> {
+        let options = match spec.strip_prefix("builtin") {
+            Some(options) => options,
+            None => {
+                let mut engine = ExternalEngine::spawn(spec)
+                    .map_err(|error| format!("couldn't start {}: {}", spec, error))?;
+                engine
+                    .new_game()
+                    .map_err(|error| format!("{} stopped working: {}", spec, error))?;
+                return Ok(Player::External(engine));
+            }
+        };
+        let mut engine = Engine::new();
+        let mut features = engine.features();
+        for option in options.trim_start_matches(':').split(',') {
+            if option.is_empty() {
+                continue;
+            }
+            let (name, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("{} should be name=value", option))?;
+            let on = || match value {
+                "true" | "on" => Ok(true),
+                "false" | "off" => Ok(false),
+                _ => Err(format!("{} should be true or false", name)),
+            };
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("{} should be a number", name))
+            };
+            match name.to_lowercase().as_str() {
+                "threads" => engine.set_threads(number()?),
+                "hash" => engine.set_hash_size(number()?),
+                "nullmove" => features.null_move = on()?,
+                "lmr" => features.late_move_reductions = on()?,
+                "killers" => features.killers = on()?,
+                "history" => features.history = on()?,
+                "aspiration" => features.aspiration_windows = on()?,
+                _ => return Err(format!("there's no builtin option called {}", name)),
+            }
+        }
+        engine.set_features(features);
+        Ok(Player::Builtin(engine))
+    }
+
+    fn best_move(&mut self, board: &Board, limits: &SearchLimits) -> io::Result<Option<Move>> {
+        match self {
+            Player::Builtin(engine) => Ok(engine.best_move(board, limits.clone())),
+            Player::External(engine) => engine.best_move(board, limits),
+        }
+    }
+
+    // a fresh start for every game, so one game's table doesn't help in the next
+    fn new_game(&mut self) -> io::Result<()> {
+        match self {
+            Player::Builtin(engine) => {
+                engine.clear_hash();
+                Ok(())
+            }
+            Player::External(engine) => engine.new_game(),
+        }
+    }
+}
+
+// how long each side gets to think
+enum Limit {
+    Search(SearchLimits),
+    Clock(TimeControl),
+}
+
+struct Match {
+    players: [Player; 2],
+    names: [String; 2],
+    limit: Limit,
+    book: Book,
+    book_plies: u32,
+    // a game still going after this many plies is a draw
+    max_plies: usize,
+    pgn: Option<String>,
+}
+
+// wins, draws and losses from the first engine's point of view
+#[derive(Default)]
+struct Score {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Score {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // the elo difference the score suggests, with the margin of the 95% confidence
+    // interval around it; none until both sides have scored something
+    fn elo(&self) -> Option<(f64, f64)> {
+        let games = self.games() as f64;
+        let points = self.wins as f64 + self.draws as f64 / 2.0;
+        let share = points / games;
+        if share <= 0.0 || share >= 1.0 {
+            return None;
+        }
+        let variance = (self.wins as f64 * (1.0 - share).powi(2)
+            + self.draws as f64 * (0.5 - share).powi(2)
+            + self.losses as f64 * share.powi(2))
+            / games;
+        let margin = 1.96 * (variance / games).sqrt();
+        // adding zero turns the -0 of an even score into 0
+        let elo = |share: f64| -400.0 * (1.0 / share.clamp(1e-6, 1.0 - 1e-6) - 1.0).log10() + 0.0;
+        Some((
+            elo(share),
+            (elo(share + margin) - elo(share - margin)) / 2.0,
+        ))
+    }
+}
+
+impl Match {
+    // a book opening for the pair of games, as far as the book goes
+    fn opening(&self) -> Vec<Move> {
+        let mut board = Board::new();
+        let mut moves = Vec::new();
+        while (moves.len() as u32) < self.book_plies {
+            match self.book.pick(&board) {
+                Some(mv) => {
+                    board.make_move(mv);
+                    moves.push(mv);
+                }
+                None => break,
+            }
+        }
+        moves
+    }
+
+    // `first_white` says whether the first engine has white; the result is how the
+    // game ended and why, an engine that fails losing it as if on time
+    fn play(
+        &mut self,
+        opening: &[Move],
+        first_white: bool,
+        round: u32,
+    ) -> io::Result<(GameStatus, String)> {
+        let mut board = Board::new();
+        let mut record = PgnGame::new(&board);
+        let (white, black) = if first_white { (0, 1) } else { (1, 0) };
+        record.set_header("Event", "Selfplay");
+        record.set_header("Round", &round.to_string());
+        record.set_player(Color::White, &self.names[white]);
+        record.set_player(Color::Black, &self.names[black]);
+        for player in self.players.iter_mut() {
+            player.new_game()?;
+        }
+        for &mv in opening {
+            record.push(mv, None);
+            board.make_move(mv);
+        }
+        let mut clock = match &self.limit {
+            Limit::Clock(control) => {
+                record.set_header("TimeControl", &control.to_pgn());
+                let mut clock = Clock::new(control.clone());
+                clock.start(board.side_to_move());
+                Some(clock)
+            }
+            Limit::Search(_) => None,
+        };
+
+        let (status, reason) = loop {
+            let status = board.status();
+            if status.is_over() {
+                break (status, status.to_string());
+            }
+            if record.moves().len() >= self.max_plies {
+                // a draw the rules don't know about, any drawn status writes it as one
+                let reason = format!("Drawn after {} plies", self.max_plies);
+                break (GameStatus::InsufficientMaterial, reason);
+            }
+            let color = board.side_to_move();
+            let index = if color == Color::White { white } else { black };
+            let limits = match (&self.limit, &clock) {
+                (Limit::Search(limits), _) => limits.clone(),
+                (_, Some(clock)) => clock.search_limits(color),
+                _ => unreachable!(),
+            };
+            let thinking = Instant::now();
+            let mv = match self.players[index].best_move(&board, &limits) {
+                Ok(Some(mv)) => mv,
+                Ok(None) => {
+                    let reason = format!("{} had no move to play", self.names[index]);
+                    break (GameStatus::Timeout(color.opposite()), reason);
+                }
+                Err(error) => {
+                    let reason = format!("{} stopped working: {}", self.names[index], error);
+                    break (GameStatus::Timeout(color.opposite()), reason);
+                }
+            };
+            if let Some(clock) = &mut clock {
+                if clock.flagged() == Some(color) {
+                    let status = GameStatus::Timeout(color.opposite());
+                    break (status, status.to_string());
+                }
+                clock.press();
+            }
+            // the time taken only says something when it came off a clock
+            record.push(mv, clock.as_ref().map(|_| thinking.elapsed()));
+            board.make_move(mv);
+        };
+        record.set_result(status);
+        record.set_header("Termination", &reason);
+        if let Some(path) = &self.pgn {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(record.to_pgn().as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        Ok((status, reason))
+    }
+}
+
+fn main() {
+    let mut games = 10;
+    let mut limit = Limit::Search(SearchLimits::time(Duration::from_millis(100)));
+    let mut book_plies = 8;
+    let mut max_plies = 400;
+    let mut pgn = None;
+    let mut specs = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| exit_with_usage());
+        match arg.as_str() {
+            "--games" => games = value().parse().unwrap_or_else(|_| exit_with_usage()),
+            "--movetime" => {
+                let ms = value().parse().unwrap_or_else(|_| exit_with_usage());
+                limit = Limit::Search(SearchLimits::time(Duration::from_millis(ms)));
+            }
+            "--depth" => {
+                let depth = value().parse().unwrap_or_else(|_| exit_with_usage());
+                limit = Limit::Search(SearchLimits::depth(depth));
+            }
+            "--tc" => {
+                let control = TimeControl::parse(&value()).unwrap_or_else(|| exit_with_usage());
+                limit = Limit::Clock(control);
+            }
+            "--book-plies" => book_plies = value().parse().unwrap_or_else(|_| exit_with_usage()),
+            "--max-plies" => max_plies = value().parse().unwrap_or_else(|_| exit_with_usage()),
+            "--pgn" => pgn = Some(value()),
+            _ if arg.starts_with("--") => exit_with_usage(),
+            _ => specs.push(arg),
+        }
+    }
+    if specs.len() != 2 {
+        exit_with_usage();
+    }
+
+    let mut players = Vec::new();
+    let mut names = Vec::new();
+    for (i, spec) in specs.iter().enumerate() {
+        match Player::from_spec(spec) {
+            Ok(player) => {
+                let name = match &player {
+                    Player::External(engine) => engine.name().unwrap_or(spec).to_string(),
+                    Player::Builtin(_) => spec.clone(),
+                };
+                names.push(format!("{} ({})", name, i + 1));
+                players.push(player);
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+    }
+    let second = players.pop().unwrap();
+    let first = players.pop().unwrap();
+    let mut tournament = Match {
+        players: [first, second],
+        names: [names[0].clone(), names[1].clone()],
+        limit,
+        book: Book::bundled(),
+        book_plies,
+        max_plies,
+        pgn,
+    };
+
+    let mut score = Score::default();
+    let mut opening = Vec::new();
+    for game in 0..games {
+        if game % 2 == 0 {
+            opening = tournament.opening();
+        }
+        let first_white = game % 2 == 0;
+        let (status, reason) = match tournament.play(&opening, first_white, game + 1) {
+            Ok(outcome) => outcome,
+            Err(error) => {
+                eprintln!("Couldn't play game {}: {}", game + 1, error);
+                process::exit(1);
+            }
+        };
+        let winner = status.winner();
+        let first_color = if first_white {
+            Color::White
+        } else {
+            Color::Black
+        };
+        match winner {
+            Some(color) if color == first_color => score.wins += 1,
+            Some(_) => score.losses += 1,
+            None => score.draws += 1,
+        }
+        println!(
+            "Game {} of {}: {} as {:?}, {}; +{} ={} -{}",
+            game + 1,
+            games,
+            tournament.names[0],
+            first_color,
+            reason,
+            score.wins,
+            score.draws,
+            score.losses
+        );
+    }
+
+    println!(
+        "{} vs {}: {} wins, {} draws, {} losses",
+        tournament.names[0], tournament.names[1], score.wins, score.draws, score.losses
+    );
+    match score.elo() {
+        Some((elo, margin)) => println!("Elo difference: {:+.1} +/- {:.1}", elo, margin),
+        None if score.games() == 0 => {}
+        None => println!("Elo difference: can't be told from a score of 0 or 100%"),
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
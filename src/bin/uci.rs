@@ -0,0 +1,540 @@
+use chess_game::board::{Board, Color};
+use chess_game::book::{Book, DEFAULT_BOOK_PLIES};
+use chess_game::engine::bench::{BENCH_POSITIONS, DEFAULT_BENCH_DEPTH};
+use chess_game::engine::eval::{breakdown, MAX_PHASE};
+#[cfg(feature = "nnue")]
+use chess_game::engine::nnue::Network;
+use chess_game::engine::time::{TimeBudget, DEFAULT_MOVE_OVERHEAD};
+use chess_game::engine::{
+    mate_in, Engine, SearchLimits, SearchResult, DEFAULT_HASH_MB, MAX_HASH_MB, MAX_THREADS,
+};
+use chess_game::fen::STARTING_FEN;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const NAME: &str = "chess-game";
+const AUTHOR: &str = "Luca Manolache";
+
+// check options for the parts of the search that can be switched off, to measure what
+// each of them is worth
+const SEARCH_FEATURES: [&str; 5] = [
+    "NullMove",
+    "LateMoveReductions",
+    "Killers",
+    "HistoryHeuristic",
+    "AspirationWindows",
+];
+
+// talks the uci protocol on stdin and stdout; the search runs on its own thread so
+// stop and isready are answered while it's thinking
+struct Uci {
+    board: Board,
+    engine: Option<Engine>,
+    stop: Arc<AtomicBool>,
+    // set by go ponder and cleared by ponderhit, see Engine::ponder_flag
+    ponder: Arc<AtomicBool>,
+    searching: Option<JoinHandle<Engine>>,
+    // guis usually bring their own book, so ours is only used when asked for
+    own_book: bool,
+    // a polyglot book to use instead of the bundled one
+    book_file: Option<String>,
+    book_plies: u32,
+    // UCI_Chess960: castling is written as the king taking its own rook
+    chess960: bool,
+    // kept on the clock for the gui and the connection to pass the move on
+    move_overhead: Duration,
+    // the network read from EvalFile, evaluating once UseNNUE is switched on
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<Network>>,
+    #[cfg(feature = "nnue")]
+    use_nnue: bool,
+}
+
+impl Uci {
+    fn new() -> Uci {
+        let engine = Engine::new();
+        Uci {
+            board: Board::new(),
+            stop: engine.stop_flag(),
+            ponder: engine.ponder_flag(),
+            engine: Some(engine),
+            searching: None,
+            own_book: false,
+            book_file: None,
+            book_plies: DEFAULT_BOOK_PLIES,
+            chess960: false,
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            #[cfg(feature = "nnue")]
+            network: None,
+            #[cfg(feature = "nnue")]
+            use_nnue: false,
+        }
+    }
+
+    // returns false once it's time to quit
+    fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                println!("id name {}", NAME);
+                println!("id author {}", AUTHOR);
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH_MB, MAX_HASH_MB
+                );
+                println!(
+                    "option name Threads type spin default 1 min 1 max {}",
+                    MAX_THREADS
+                );
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!(
+                    "option name BookDepth type spin default {} min 0 max 100",
+                    DEFAULT_BOOK_PLIES
+                );
+                println!("option name UCI_Chess960 type check default false");
+                println!(
+                    "option name Move Overhead type spin default {} min 0 max 5000",
+                    DEFAULT_MOVE_OVERHEAD.as_millis()
+                );
+                // the gui decides whether to ponder, this only says it's supported
+                println!("option name Ponder type check default false");
+                for name in SEARCH_FEATURES.iter() {
+                    println!("option name {} type check default true", name);
+                }
+                #[cfg(feature = "nnue")]
+                {
+                    println!("option name EvalFile type string default <empty>");
+                    println!("option name UseNNUE type check default false");
+                }
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => {
+                self.wait_for_search();
+                self.set_option(&tokens.collect::<Vec<_>>());
+            }
+            Some("ucinewgame") => {
+                self.wait_for_search();
+                self.board = Board::new();
+                self.board.set_chess960(self.chess960);
+                if let Some(engine) = &mut self.engine {
+                    engine.clear_hash();
+                }
+            }
+            Some("position") => {
+                self.wait_for_search();
+                if let Err(error) = self.set_position(&tokens.collect::<Vec<_>>()) {
+                    println!("info string {}", error);
+                }
+            }
+            Some("go") => {
+                self.wait_for_search();
+                self.go(&tokens.collect::<Vec<_>>());
+            }
+            // not part of uci: the evaluation term by term, of the position or of a fen
+            Some("eval") => {
+                let fen = tokens.collect::<Vec<_>>().join(" ");
+                let board = match fen.as_str() {
+                    "" => self.board.clone(),
+                    _ => match Board::from_fen(&fen) {
+                        Ok(board) => board,
+                        Err(error) => {
+                            println!("info string invalid fen: {}", error);
+                            return true;
+                        }
+                    },
+                };
+                print_eval(&board);
+                #[cfg(feature = "nnue")]
+                if let Some(network) = &self.network {
+                    let score = network.evaluate(&board);
+                    let white = match board.side_to_move() {
+                        Color::White => score,
+                        Color::Black => -score,
+                    };
+                    println!("nnue {}", white);
+                }
+            }
+            // not part of uci either: searches the bench positions, see Engine::bench
+            Some("bench") => {
+                self.wait_for_search();
+                match tokens.next().map(str::parse) {
+                    Some(Ok(depth)) => self.bench(depth),
+                    Some(Err(_)) => println!("info string invalid bench depth"),
+                    None => self.bench(DEFAULT_BENCH_DEPTH),
+                }
+            }
+            // the expected reply was played, the search carries on with its limits running
+            Some("ponderhit") => self.ponder.store(false, Ordering::Relaxed),
+            Some("stop") => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.wait_for_search();
+            }
+            Some("quit") => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.wait_for_search();
+                return false;
+            }
+            // unknown commands are ignored, as the protocol asks
+            _ => {}
+        }
+        io::stdout().flush().unwrap();
+        true
+    }
+
+    fn wait_for_search(&mut self) {
+        if let Some(handle) = self.searching.take() {
+            self.engine = Some(handle.join().expect("search thread panicked"));
+        }
+    }
+
+    // a line for each position, then the totals; the node count is the number to
+    // compare between versions, the speed depends on the machine
+    fn bench(&mut self, depth: u32) {
+        let engine = match &mut self.engine {
+            Some(engine) => engine,
+            None => return,
+        };
+        self.stop.store(false, Ordering::Relaxed);
+        let bench = engine.bench(depth, |i, result| {
+            println!(
+                "info string position {}/{} depth {} nodes {} time {} bestmove {}",
+                i + 1,
+                BENCH_POSITIONS.len(),
+                result.depth,
+                result.nodes,
+                result.time_ms,
+                result
+                    .best_move
+                    .map_or("0000".to_string(), |mv| mv.to_string())
+            );
+            io::stdout().flush().unwrap();
+        });
+        println!("Nodes searched: {}", bench.nodes);
+        println!("Time: {}ms", bench.elapsed.as_millis());
+        println!("Nodes/second: {}", bench.nodes_per_second());
+    }
+
+    // setoption name <name> [value <value>], where the name can have spaces in it
+    fn set_option(&mut self, tokens: &[&str]) {
+        let value_at = tokens
+            .iter()
+            .position(|&token| token == "value")
+            .unwrap_or(tokens.len());
+        let name = tokens[..value_at]
+            .iter()
+            .skip_while(|&&token| token == "name")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let value = tokens.get(value_at + 1..).unwrap_or(&[]).join(" ");
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(megabytes) => {
+                    if let Some(engine) = &mut self.engine {
+                        engine.set_hash_size(megabytes);
+                    }
+                }
+                Err(_) => println!("info string invalid hash size {}", value),
+            },
+            "threads" => match value.parse::<usize>() {
+                Ok(threads) => {
+                    if let Some(engine) = &mut self.engine {
+                        engine.set_threads(threads);
+                    }
+                }
+                Err(_) => println!("info string invalid thread count {}", value),
+            },
+            "ownbook" => {
+                self.own_book = value == "true";
+                self.update_book();
+            }
+            "bookfile" => {
+                self.book_file = match value.as_str() {
+                    "" | "<empty>" => None,
+                    _ => Some(value),
+                };
+                self.update_book();
+            }
+            "bookdepth" => match value.parse() {
+                Ok(plies) => {
+                    self.book_plies = plies;
+                    self.update_book();
+                }
+                Err(_) => println!("info string invalid book depth {}", value),
+            },
+            "ponder" => {}
+            #[cfg(feature = "nnue")]
+            "evalfile" => {
+                self.network = match value.as_str() {
+                    "" | "<empty>" => None,
+                    path => match Network::load(path.as_ref()) {
+                        Ok(network) => {
+                            println!(
+                                "info string read {}, {} hidden neurons",
+                                path,
+                                network.hidden_size()
+                            );
+                            Some(Arc::new(network))
+                        }
+                        Err(error) => {
+                            println!("info string couldn't read {}: {}", path, error);
+                            None
+                        }
+                    },
+                };
+                self.update_network();
+            }
+            #[cfg(feature = "nnue")]
+            "usennue" => {
+                self.use_nnue = value == "true";
+                self.update_network();
+            }
+            "move overhead" => match value.parse::<u64>() {
+                Ok(ms) => self.move_overhead = Duration::from_millis(ms.min(5000)),
+                Err(_) => println!("info string invalid move overhead {}", value),
+            },
+            "uci_chess960" => {
+                self.chess960 = value == "true";
+                self.board.set_chess960(self.chess960);
+            }
+            feature
+                if SEARCH_FEATURES
+                    .iter()
+                    .any(|name| name.to_lowercase() == feature) =>
+            {
+                if let Some(engine) = &mut self.engine {
+                    let mut features = engine.features();
+                    let switch = match feature {
+                        "nullmove" => &mut features.null_move,
+                        "latemovereductions" => &mut features.late_move_reductions,
+                        "killers" => &mut features.killers,
+                        "historyheuristic" => &mut features.history,
+                        _ => &mut features.aspiration_windows,
+                    };
+                    *switch = value == "true";
+                    engine.set_features(features);
+                }
+            }
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+
+    // the network only evaluates once it's both read and switched on
+    #[cfg(feature = "nnue")]
+    fn update_network(&mut self) {
+        let network = self.network.clone().filter(|_| self.use_nnue);
+        if self.use_nnue && network.is_none() {
+            println!("info string UseNNUE needs an EvalFile, evaluating classically");
+        }
+        if let Some(engine) = &mut self.engine {
+            engine.set_network(network);
+        }
+    }
+
+    fn update_book(&mut self) {
+        let book = match (&self.book_file, self.own_book) {
+            (_, false) => None,
+            (None, true) => Some(Book::bundled()),
+            (Some(path), true) => match Book::open(path) {
+                Ok(book) => Some(book),
+                Err(error) => {
+                    println!("info string couldn't read {}: {}", path, error);
+                    None
+                }
+            },
+        };
+        if let Some(engine) = &mut self.engine {
+            engine.set_book(book, self.book_plies);
+        }
+    }
+
+    // position [startpos | fen <fen>] [moves <move>...]
+    fn set_position(&mut self, tokens: &[&str]) -> Result<(), String> {
+        let moves_at = tokens.iter().position(|&token| token == "moves");
+        let (setup, moves) = match moves_at {
+            Some(i) => (&tokens[..i], &tokens[i + 1..]),
+            None => (tokens, &[][..]),
+        };
+        let mut board = match setup.first() {
+            Some(&"startpos") => Board::from_fen(STARTING_FEN).unwrap(),
+            Some(&"fen") => Board::from_fen(&setup[1..].join(" ")).map_err(|e| e.to_string())?,
+            _ => return Err("expected startpos or fen".to_string()),
+        };
+        if self.chess960 {
+            board.set_chess960(true);
+        }
+        for text in moves {
+            let mv = board
+                .parse_uci_move(text)
+                .ok_or_else(|| format!("illegal move {}", text))?;
+            board.make_move(mv);
+        }
+        self.board = board;
+        Ok(())
+    }
+
+    fn go(&mut self, tokens: &[&str]) {
+        let options = GoOptions::parse(tokens);
+        let limits = options.limits(self.board.side_to_move(), self.move_overhead);
+        let board = self.board.clone();
+        let (stop, ponder) = (self.stop.clone(), self.ponder.clone());
+        let mut engine = match self.engine.take() {
+            Some(engine) => engine,
+            None => return,
+        };
+        stop.store(false, Ordering::Relaxed);
+        ponder.store(options.ponder, Ordering::Relaxed);
+
+        self.searching = Some(thread::spawn(move || {
+            let result = engine.search_with_info(&board, limits, print_info);
+            // an infinite search only reports its move once told to stop, and a ponder
+            // search once the reply it was pondering is played or it's stopped
+            while (options.infinite || ponder.load(Ordering::Relaxed))
+                && !stop.load(Ordering::Relaxed)
+            {
+                thread::sleep(Duration::from_millis(5));
+            }
+            ponder.store(false, Ordering::Relaxed);
+            // the reply it expects, for the gui to ponder on
+            match (result.best_move, result.pv.get(1)) {
+                (Some(mv), Some(reply)) => println!("bestmove {} ponder {}", mv, reply),
+                (Some(mv), None) => println!("bestmove {}", mv),
+                _ => println!("bestmove 0000"),
+            }
+            io::stdout().flush().unwrap();
+            engine
+        }));
+    }
+}
+
+// a row for each term in centipawns from white's point of view, the middlegame and
+// endgame values and the two blended by the phase
+fn print_eval(board: &Board) {
+    let terms = breakdown(board);
+    println!(
+        "{:<12} {:>10} {:>10} {:>10}",
+        "term", "middlegame", "endgame", "blended"
+    );
+    let total = ("total", terms.total());
+    for &(name, score) in terms.terms().iter().chain(std::iter::once(&total)) {
+        println!(
+            "{:<12} {:>10} {:>10} {:>10}",
+            name,
+            score.middlegame,
+            score.endgame,
+            score.taper(terms.phase)
+        );
+    }
+    println!("phase {} of {}", terms.phase, MAX_PHASE);
+}
+
+fn print_info(result: &SearchResult) {
+    let score = match mate_in(result.score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let nps = result.nodes * 1000 / result.time_ms.max(1);
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+    println!(
+        "info depth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        nps,
+        result.hashfull,
+        result.time_ms,
+        pv.join(" ")
+    );
+    io::stdout().flush().unwrap();
+}
+
+#[derive(Default)]
+struct GoOptions {
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    movetime: Option<u64>,
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: u64,
+    binc: u64,
+    movestogo: Option<u64>,
+    infinite: bool,
+    // searching the position after the reply it expects, on the opponent's time
+    ponder: bool,
+}
+
+impl GoOptions {
+    fn parse(tokens: &[&str]) -> GoOptions {
+        let mut options = GoOptions::default();
+        let mut tokens = tokens.iter();
+        while let Some(&token) = tokens.next() {
+            let mut value = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+            match token {
+                "depth" => options.depth = value().map(|depth| depth as u32),
+                "nodes" => options.nodes = value(),
+                "movetime" => options.movetime = value(),
+                "wtime" => options.wtime = value(),
+                "btime" => options.btime = value(),
+                "winc" => options.winc = value().unwrap_or(0),
+                "binc" => options.binc = value().unwrap_or(0),
+                "movestogo" => options.movestogo = value(),
+                "infinite" => options.infinite = true,
+                "ponder" => options.ponder = true,
+                _ => {}
+            }
+        }
+        options
+    }
+
+    // movetime is a hard limit of its own, otherwise the clock's budgeted
+    fn limits(&self, color: Color, overhead: Duration) -> SearchLimits {
+        let (time, increment) = match color {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        let budget = time.filter(|_| self.movetime.is_none()).map(|time| {
+            TimeBudget::new(
+                Duration::from_millis(time),
+                Duration::from_millis(increment),
+                self.movestogo.map(|moves| moves as u32),
+                overhead,
+            )
+        });
+        SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            time: self
+                .movetime
+                .map(Duration::from_millis)
+                .or(budget.map(|budget| budget.hard)),
+            budget,
+        }
+    }
+}
+
+fn main() {
+    let mut uci = Uci::new();
+    // `uci bench [depth]` runs the bench and exits, for scripts tracking the speed
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        uci.handle(&args.join(" "));
+        return;
+    }
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !uci.handle(&line) {
+            break;
+        }
+    }
+    uci.stop.store(true, Ordering::Relaxed);
+    uci.wait_for_search();
+}
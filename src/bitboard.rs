@@ -0,0 +1,411 @@
+use crate::board::{Color, Square};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+use std::sync::OnceLock;
+
+// one bit per square, bit 0 is a1 and bit 63 is h8 like Square's index
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+
+    pub fn from_square(square: Square) -> Bitboard {
+        Bitboard(1 << square.index())
+    }
+
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & (1 << square.index()) != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    // the lowest set square
+    pub fn first(self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(Square::from_index(self.0.trailing_zeros() as usize))
+        }
+    }
+
+    pub fn squares(self) -> Squares {
+        Squares(self.0)
+    }
+}
+
+pub struct Squares(u64);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(Square::from_index(index))
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+    fn bitand(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 & other.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+    fn bitor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 | other.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+    fn bitxor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ other.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, other: Bitboard) {
+        self.0 &= other.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, other: Bitboard) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, other: Bitboard) {
+        self.0 ^= other.0;
+    }
+}
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+// fancy magic bitboards: the relevant blockers of a slider are multiplied by a magic
+// number so their top bits index straight into that square's slice of a shared table
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: u64) -> usize {
+        self.offset + (((occupied & self.mask).wrapping_mul(self.magic)) >> self.shift) as usize
+    }
+}
+
+struct Tables {
+    knight: [u64; 64],
+    king: [u64; 64],
+    pawn: [[u64; 64]; 2],
+    rook_magics: Vec<Magic>,
+    bishop_magics: Vec<Magic>,
+    slider_attacks: Vec<u64>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+impl Tables {
+    fn new() -> Tables {
+        let step_table = |offsets: &[(i8, i8)]| {
+            let mut table = [0; 64];
+            for (index, attacks) in table.iter_mut().enumerate() {
+                let square = Square::from_index(index);
+                for &(df, dr) in offsets {
+                    if let Some(target) = square.offset(df, dr) {
+                        *attacks |= 1 << target.index();
+                    }
+                }
+            }
+            table
+        };
+
+        let mut slider_attacks = Vec::new();
+        let rook_magics = build_magics(&ROOK_DIRECTIONS, &ROOK_MAGICS, &mut slider_attacks);
+        let bishop_magics = build_magics(&BISHOP_DIRECTIONS, &BISHOP_MAGICS, &mut slider_attacks);
+
+        Tables {
+            knight: step_table(&KNIGHT_OFFSETS),
+            king: step_table(&KING_OFFSETS),
+            pawn: [
+                step_table(&[(-1, 1), (1, 1)]),
+                step_table(&[(-1, -1), (1, -1)]),
+            ],
+            rook_magics,
+            bishop_magics,
+            slider_attacks,
+        }
+    }
+}
+
+// walks each ray until it leaves the board or hits a blocker, which is included
+fn slow_slider_attacks(square: Square, directions: &[(i8, i8)], occupied: u64) -> u64 {
+    let mut attacks = 0;
+    for &(df, dr) in directions {
+        let mut current = square.offset(df, dr);
+        while let Some(target) = current {
+            attacks |= 1 << target.index();
+            if occupied & (1 << target.index()) != 0 {
+                break;
+            }
+            current = target.offset(df, dr);
+        }
+    }
+    attacks
+}
+
+// the squares whose occupancy matters, which leaves out the last square of each ray
+fn relevant_mask(square: Square, directions: &[(i8, i8)]) -> u64 {
+    let mut mask = 0;
+    for &(df, dr) in directions {
+        let mut current = square.offset(df, dr);
+        while let Some(target) = current {
+            current = target.offset(df, dr);
+            if current.is_some() {
+                mask |= 1 << target.index();
+            }
+        }
+    }
+    mask
+}
+
+// found by trying random sparse numbers for each square until one mapped every blocker
+// subset without a harmful collision
+const ROOK_MAGICS: [u64; 64] = [
+    0x2080002080400010,
+    0x00c0002001401000,
+    0x2100110008402002,
+    0x0880080081041000,
+    0x0200020020041008,
+    0x2300040008010012,
+    0x0c00283004008201,
+    0x0180010000407a80,
+    0x0168800080400020,
+    0x0010400040201000,
+    0x1001002001001048,
+    0x1001002408100100,
+    0x0801000408010012,
+    0x4001000209000400,
+    0x08a20004c8020001,
+    0x2002801145002280,
+    0x0080860021004200,
+    0x001000c009402002,
+    0x00b0002004002800,
+    0x100a808010020800,
+    0x8101010008000410,
+    0x0244008002000480,
+    0x0000040010810208,
+    0x2000020000448534,
+    0x4104400480008033,
+    0x0000810100204000,
+    0x0440430900200010,
+    0x4600240900100100,
+    0x0060080080040080,
+    0x0001000300080400,
+    0x0004084400011002,
+    0x0023040200008041,
+    0x0580050043002080,
+    0x0400804002802008,
+    0x0001002001004010,
+    0x1000200901001000,
+    0x4410800801800c00,
+    0xa012003806001004,
+    0x0020100104008802,
+    0x0004808402000041,
+    0x0010400170898000,
+    0x0080500020004004,
+    0x1040408012020020,
+    0x8010040008004040,
+    0x2001080100110004,
+    0x0000020004008080,
+    0x0021010810040002,
+    0x0800008c43020024,
+    0x0000800021005100,
+    0x0070201040008080,
+    0x0000d04282006a00,
+    0x0010014400080240,
+    0x0001080110050100,
+    0x0012000810240600,
+    0x0402000801040200,
+    0x028100108a004100,
+    0x0050800300102045,
+    0x8208210040120882,
+    0x8010600101183441,
+    0x020b000910006045,
+    0x0241001002480005,
+    0x0081000400880241,
+    0x0000009008024124,
+    0x0048122980410402,
+];
+
+const BISHOP_MAGICS: [u64; 64] = [
+    0x8008029802002200,
+    0x4291040808802804,
+    0x0008180040800300,
+    0x00088a0202aa1050,
+    0x000410a800000000,
+    0x0009100804040009,
+    0x0801140121080011,
+    0xa040808400824000,
+    0x000008a004040048,
+    0x0600200440808114,
+    0x2020410401204403,
+    0x000404106200c001,
+    0x0100011040800026,
+    0x00080088200a0820,
+    0x0008004804642080,
+    0x4000004402981800,
+    0x0710002220020088,
+    0x2010808202020402,
+    0x8010080844002820,
+    0x800c000124028000,
+    0x0002000422010040,
+    0x6438402200422000,
+    0x0010a1004c0c2000,
+    0x000a00e109010190,
+    0x08022010400414c0,
+    0x8428022220240101,
+    0x0008088004040010,
+    0x0008080000220020,
+    0x0421010000104000,
+    0x219102082500a000,
+    0x0018008042120150,
+    0x02108020a09c0402,
+    0x301c202000890208,
+    0xa004022000080100,
+    0x100c024100881200,
+    0x8000080800460a00,
+    0x1004010804440040,
+    0x420c920080041000,
+    0x05018c0114440100,
+    0x00040100308a0080,
+    0x0020821042801000,
+    0x0202026120001c02,
+    0x0002001044000800,
+    0x20aa844200800801,
+    0x0000012011001200,
+    0x0860209008808042,
+    0x0008100080a80200,
+    0x0808020050420201,
+    0x00051c0104c00000,
+    0x0000840108820022,
+    0x000a461842080004,
+    0x2400400914880002,
+    0x00040040102481b4,
+    0x2104a14202020060,
+    0x0004081041020060,
+    0x00a0840082005100,
+    0x0000412210101482,
+    0x0108504208042210,
+    0x000020044c040405,
+    0x4140050206051401,
+    0x0122008051820200,
+    0x0082800428109100,
+    0x9104042454440401,
+    0x141e200c00820848,
+];
+
+fn build_magics(directions: &[(i8, i8)], magics: &[u64; 64], attacks: &mut Vec<u64>) -> Vec<Magic> {
+    let mut table = Vec::with_capacity(64);
+    for (index, &magic) in magics.iter().enumerate() {
+        let square = Square::from_index(index);
+        let mask = relevant_mask(square, directions);
+        let bits = mask.count_ones();
+        let entry = Magic {
+            mask,
+            magic,
+            shift: 64 - bits,
+            offset: attacks.len(),
+        };
+        attacks.resize(attacks.len() + (1 << bits), 0);
+
+        // every subset of the mask, enumerated with the carry-rippler trick
+        let mut subset: u64 = 0;
+        loop {
+            let attack = slow_slider_attacks(square, directions, subset);
+            let slot = entry.index(subset);
+            debug_assert!(attacks[slot] == 0 || attacks[slot] == attack);
+            attacks[slot] = attack;
+            subset = subset.wrapping_sub(mask) & mask;
+            if subset == 0 {
+                break;
+            }
+        }
+        table.push(entry);
+    }
+    table
+}
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+    Bitboard(tables().knight[square.index()])
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    Bitboard(tables().king[square.index()])
+}
+
+// the squares a pawn of `color` on `square` captures on
+pub fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    Bitboard(tables().pawn[color as usize][square.index()])
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    Bitboard(tables.slider_attacks[tables.rook_magics[square.index()].index(occupied.0)])
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    Bitboard(tables.slider_attacks[tables.bishop_magics[square.index()].index(occupied.0)])
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
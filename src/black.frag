@@ -1,9 +0,0 @@
-#version 330 core
-#
-
-out vec4 Color;
-
-void main()
-{
-    Color = vec4(0.0f, 0.0f, 0.0f, 1.0f);
-}
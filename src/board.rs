@@ -0,0 +1,573 @@
+use crate::bitboard::Bitboard;
+use crate::moves::Move;
+use crate::zobrist;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub kind: PieceKind,
+    pub color: Color,
+}
+
+impl Piece {
+    pub fn new(kind: PieceKind, color: Color) -> Piece {
+        Piece { kind, color }
+    }
+
+    // the fen letter for the piece, uppercase for white
+    pub fn to_char(self) -> char {
+        let c = match self.kind {
+            PieceKind::Pawn => 'p',
+            PieceKind::Knight => 'n',
+            PieceKind::Bishop => 'b',
+            PieceKind::Rook => 'r',
+            PieceKind::Queen => 'q',
+            PieceKind::King => 'k',
+        };
+        match self.color {
+            Color::White => c.to_ascii_uppercase(),
+            Color::Black => c,
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Piece> {
+        let kind = match c.to_ascii_lowercase() {
+            'p' => PieceKind::Pawn,
+            'n' => PieceKind::Knight,
+            'b' => PieceKind::Bishop,
+            'r' => PieceKind::Rook,
+            'q' => PieceKind::Queen,
+            'k' => PieceKind::King,
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(Piece::new(kind, color))
+    }
+}
+
+// squares are numbered from a1 = 0 to h8 = 63, going along the ranks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    pub fn new(file: u8, rank: u8) -> Square {
+        debug_assert!(file < 8 && rank < 8);
+        Square(rank * 8 + file)
+    }
+
+    pub fn from_index(index: usize) -> Square {
+        debug_assert!(index < 64);
+        Square(index as u8)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    pub fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    // parses a square name like "e4"
+    pub fn parse(name: &str) -> Option<Square> {
+        let bytes = name.as_bytes();
+        if bytes.len() != 2 {
+            return None;
+        }
+        let file = bytes[0].wrapping_sub(b'a');
+        let rank = bytes[1].wrapping_sub(b'1');
+        if file < 8 && rank < 8 {
+            Some(Square::new(file, rank))
+        } else {
+            None
+        }
+    }
+
+    // the square `files` to the right and `ranks` up from white's point of view, if it's on the board
+    pub fn offset(self, files: i8, ranks: i8) -> Option<Square> {
+        let file = self.file() as i8 + files;
+        let rank = self.rank() as i8 + ranks;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Some(Square::new(file as u8, rank as u8))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+
+    pub fn none() -> CastlingRights {
+        CastlingRights {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        }
+    }
+
+    // the kingside and queenside rights of one side
+    pub fn get(self, color: Color) -> (bool, bool) {
+        match color {
+            Color::White => (self.white_kingside, self.white_queenside),
+            Color::Black => (self.black_kingside, self.black_queenside),
+        }
+    }
+
+    pub fn set(&mut self, color: Color, kingside: bool, queenside: bool) {
+        match color {
+            Color::White => {
+                self.white_kingside = kingside;
+                self.white_queenside = queenside;
+            }
+            Color::Black => {
+                self.black_kingside = kingside;
+                self.black_queenside = queenside;
+            }
+        }
+    }
+}
+
+// the pieces a side has captured in crazyhouse and can drop back onto the board as
+// its own, counted for each kind from pawns to queens
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Pocket([u8; 5]);
+
+impl Pocket {
+    // the kinds a pocket can hold, kings never being captured
+    pub const KINDS: [PieceKind; 5] = [
+        PieceKind::Pawn,
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ];
+
+    pub fn count(self, kind: PieceKind) -> u8 {
+        match kind {
+            PieceKind::King => 0,
+            _ => self.0[kind as usize],
+        }
+    }
+
+    pub fn add(&mut self, kind: PieceKind) {
+        if kind != PieceKind::King {
+            self.0[kind as usize] += 1;
+        }
+    }
+
+    pub fn remove(&mut self, kind: PieceKind) {
+        if kind != PieceKind::King {
+            self.0[kind as usize] = self.0[kind as usize].saturating_sub(1);
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0.iter().all(|&count| count == 0)
+    }
+}
+
+// what make_move() needs to remember to take a move back: everything about the position
+// that can't be worked out from the position after it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Undo {
+    pub mv: Move,
+    // the piece that stood on the target square, en passant captures aren't included
+    pub captured: Option<Piece>,
+    pub castling: CastlingRights,
+    // where the rook started and where the king and the rook went, for castling
+    pub castle: Option<(Square, Square, Square)>,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    // the pockets and promoted pieces, which a crazyhouse capture or drop can change
+    pub pockets: Option<[Pocket; 2]>,
+    pub promoted: Bitboard,
+    // the position_key() of the position the move was made from
+    pub key: u64,
+}
+
+// the mailbox answers "what's on this square", the bitboards kept alongside it answer
+// "where are the white knights" for move generation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    squares: [Option<Piece>; 64],
+    by_kind: [Bitboard; 6],
+    by_color: [Bitboard; 2],
+    side_to_move: Color,
+    castling: CastlingRights,
+    // the files the rooks castle from, kingside and then queenside for white and then
+    // black; always the h and a files unless it's chess960
+    castling_rooks: [(u8, u8); 2],
+    // castling is written as the king taking its own rook, since in chess960 the king
+    // can end up where it started or only a square away
+    chess960: bool,
+    // what each side has to drop, white's first, only in variants with drops
+    pockets: Option<[Pocket; 2]>,
+    // the pieces that were pawns before they promoted, which go back in a pocket as
+    // pawns when they're captured; only kept track of along with the pockets
+    promoted: Bitboard,
+    // the square a pawn skipped over with its double step last move, as in fen
+    en_passant: Option<Square>,
+    // plies since the last capture or pawn move, for the fifty move rule
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    // the zobrist key of everything but the en passant square, kept up to date by the
+    // setters; position_key() adds the en passant square when it matters
+    key: u64,
+    // every move made with make_move(), for taking them back and spotting repetitions
+    history: Vec<Undo>,
+}
+
+impl Board {
+    // the standard starting position
+    pub fn new() -> Board {
+        let mut board = Board::empty();
+        let back_rank = [
+            PieceKind::Rook,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,
+        ];
+        for (file, &kind) in back_rank.iter().enumerate() {
+            let file = file as u8;
+            board.set_piece(Square::new(file, 0), Some(Piece::new(kind, Color::White)));
+            board.set_piece(
+                Square::new(file, 1),
+                Some(Piece::new(PieceKind::Pawn, Color::White)),
+            );
+            board.set_piece(
+                Square::new(file, 6),
+                Some(Piece::new(PieceKind::Pawn, Color::Black)),
+            );
+            board.set_piece(Square::new(file, 7), Some(Piece::new(kind, Color::Black)));
+        }
+        board.set_castling(CastlingRights::all());
+        board
+    }
+
+    pub fn empty() -> Board {
+        Board {
+            squares: [None; 64],
+            by_kind: [Bitboard::EMPTY; 6],
+            by_color: [Bitboard::EMPTY; 2],
+            side_to_move: Color::White,
+            castling: CastlingRights::none(),
+            castling_rooks: [(7, 0); 2],
+            chess960: false,
+            pockets: None,
+            promoted: Bitboard::EMPTY,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            key: zobrist::side(Color::White),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.squares[square.index()]
+    }
+
+    pub fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        let bit = Bitboard::from_square(square);
+        if let Some(old) = self.squares[square.index()] {
+            self.by_kind[old.kind as usize] ^= bit;
+            self.by_color[old.color as usize] ^= bit;
+            self.key ^= zobrist::piece(old, square);
+        }
+        if let Some(new) = piece {
+            self.by_kind[new.kind as usize] |= bit;
+            self.by_color[new.color as usize] |= bit;
+            self.key ^= zobrist::piece(new, square);
+        }
+        self.squares[square.index()] = piece;
+    }
+
+    pub fn pieces_of(&self, kind: PieceKind, color: Color) -> Bitboard {
+        self.by_kind[kind as usize] & self.by_color[color as usize]
+    }
+
+    pub fn occupied_by(&self, color: Color) -> Bitboard {
+        self.by_color[color as usize]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.by_color[0] | self.by_color[1]
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    pub fn set_side_to_move(&mut self, color: Color) {
+        self.key ^= zobrist::side(self.side_to_move) ^ zobrist::side(color);
+        self.side_to_move = color;
+    }
+
+    pub fn castling(&self) -> CastlingRights {
+        self.castling
+    }
+
+    pub fn set_castling(&mut self, castling: CastlingRights) {
+        self.key ^= zobrist::castling(self.castling) ^ zobrist::castling(castling);
+        self.castling = castling;
+    }
+
+    // the file of the rook that castles on that side
+    pub fn castling_rook(&self, color: Color, kingside: bool) -> u8 {
+        let (king, queen) = self.castling_rooks[color as usize];
+        if kingside {
+            king
+        } else {
+            queen
+        }
+    }
+
+    pub fn set_castling_rook(&mut self, color: Color, kingside: bool, file: u8) {
+        let files = &mut self.castling_rooks[color as usize];
+        if kingside {
+            files.0 = file;
+        } else {
+            files.1 = file;
+        }
+    }
+
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    // from_fen turns it on by itself when the castling rights need it, it only has to
+    // be set for chess960 positions that castle like standard chess
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    // whether captured pieces can be dropped back on, as in crazyhouse
+    pub fn has_pockets(&self) -> bool {
+        self.pockets.is_some()
+    }
+
+    pub fn pockets(&self) -> Option<[Pocket; 2]> {
+        self.pockets
+    }
+
+    // empty when the board has no pockets
+    pub fn pocket(&self, color: Color) -> Pocket {
+        self.pockets
+            .map_or(Pocket::default(), |pockets| pockets[color as usize])
+    }
+
+    // gives the board pockets if it didn't have them yet
+    pub fn set_pocket(&mut self, color: Color, pocket: Pocket) {
+        let mut pockets = self.pockets.unwrap_or_default();
+        let old = pockets[color as usize];
+        for &kind in Pocket::KINDS.iter() {
+            self.key ^= zobrist::pocket(color, kind, old.count(kind))
+                ^ zobrist::pocket(color, kind, pocket.count(kind));
+        }
+        pockets[color as usize] = pocket;
+        self.pockets = Some(pockets);
+    }
+
+    // the squares of the pieces that promoted, as far as the board knows
+    pub fn promoted(&self) -> Bitboard {
+        self.promoted
+    }
+
+    pub fn set_promoted(&mut self, promoted: Bitboard) {
+        self.promoted = promoted;
+    }
+
+    // puts the pockets and promoted pieces back the way they were before a move
+    pub(crate) fn restore_pockets(&mut self, pockets: Option<[Pocket; 2]>, promoted: Bitboard) {
+        match pockets {
+            Some(pockets) => {
+                self.set_pocket(Color::White, pockets[0]);
+                self.set_pocket(Color::Black, pockets[1]);
+            }
+            None => self.pockets = None,
+        }
+        self.promoted = promoted;
+    }
+
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    pub fn set_en_passant(&mut self, square: Option<Square>) {
+        self.en_passant = square;
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: u32) {
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn set_fullmove_number(&mut self, fullmove_number: u32) {
+        self.fullmove_number = fullmove_number;
+    }
+
+    // the zobrist key kept up to date as pieces, the side to move, the castling rights
+    // and the pockets change; position_key() adds the en passant square when it counts
+    pub(crate) fn partial_key(&self) -> u64 {
+        self.key
+    }
+
+    // the keys of the positions each move made with make_move() was made from, oldest first
+    pub fn history(&self) -> impl Iterator<Item = u64> + '_ {
+        self.history.iter().map(|undo| undo.key)
+    }
+
+    // the moves made with make_move(), oldest first
+    pub fn moves_made(&self) -> impl Iterator<Item = Move> + '_ {
+        self.history.iter().map(|undo| undo.mv)
+    }
+
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|undo| undo.mv)
+    }
+
+    pub(crate) fn push_history(&mut self, undo: Undo) {
+        self.history.push(undo);
+    }
+
+    pub(crate) fn pop_history(&mut self) -> Option<Undo> {
+        self.history.pop()
+    }
+
+    pub(crate) fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    // every occupied square with its piece, from a1 to h8
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        self.squares
+            .iter()
+            .enumerate()
+            .filter_map(|(i, piece)| piece.map(|piece| (Square::from_index(i), piece)))
+    }
+}
+
+impl Default for Board {
+    fn default() -> Board {
+        Board::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::STARTING_FEN;
+
+    // plays the moves from `fen`, checking after each that the key kept up to date is
+    // the one worked out from scratch, and that taking them all back gets the first
+    // key back
+    fn keys_follow(fen: &str, sans: &[&str]) {
+        let mut board = Board::from_fen(fen).unwrap();
+        let start = (board.partial_key(), board.position_key());
+        for san in sans {
+            let mv = board.parse_san(san).unwrap();
+            board.make_move(mv);
+            let fresh = Board::from_fen(&board.to_fen()).unwrap();
+            assert_eq!(board.partial_key(), fresh.partial_key(), "after {}", san);
+            assert_eq!(board.position_key(), fresh.position_key(), "after {}", san);
+        }
+        for _ in sans {
+            board.unmake_move();
+        }
+        assert_eq!((board.partial_key(), board.position_key()), start);
+    }
+
+    #[test]
+    fn the_key_follows_captures() {
+        keys_follow(STARTING_FEN, &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxg2"]);
+    }
+
+    #[test]
+    fn the_key_follows_castling() {
+        keys_follow(
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            &["O-O", "O-O-O", "Rfe1", "Kb8"],
+        );
+    }
+
+    #[test]
+    fn the_key_follows_en_passant() {
+        keys_follow("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", &["e4", "dxe3", "Kf1"]);
+    }
+
+    #[test]
+    fn the_key_follows_promotions() {
+        keys_follow(
+            "4r3/3P2k1/8/8/8/8/8/4K3 w - - 0 1",
+            &["dxe8=N+", "Kf8", "Nd6"],
+        );
+        keys_follow("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", &["e8=Q", "Kb3", "Qb5+"]);
+    }
+}
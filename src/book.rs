@@ -0,0 +1,282 @@
+use crate::board::{Board, PieceKind, Square};
+use crate::moves::Move;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// how many plies into the game the engine keeps playing from its book
+pub const DEFAULT_BOOK_PLIES: u32 = 16;
+
+// one move of a polyglot book: 16 bytes on disk, all big endian
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    // the position_key() of the position the move is played in
+    pub key: u64,
+    // to file, to rank, from file, from rank and promotion, three bits each from the
+    // bottom; castling is written as the king taking its own rook
+    pub mv: u16,
+    // how often the move should be picked compared to the others in the position
+    pub weight: u16,
+    pub learn: u32,
+}
+
+const ENTRY_SIZE: usize = 16;
+
+// an opening book in the polyglot .bin format, sorted by key so every move in a
+// position can be found with a binary search
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        Ok(Book::from_bytes(&std::fs::read(path)?))
+    }
+
+    // a trailing partial entry is ignored
+    pub fn from_bytes(bytes: &[u8]) -> Book {
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| {
+                let mut key = [0; 8];
+                key.copy_from_slice(&chunk[0..8]);
+                let mut learn = [0; 4];
+                learn.copy_from_slice(&chunk[12..16]);
+                BookEntry {
+                    key: u64::from_be_bytes(key),
+                    mv: u16::from_be_bytes([chunk[8], chunk[9]]),
+                    weight: u16::from_be_bytes([chunk[10], chunk[11]]),
+                    learn: u32::from_be_bytes(learn),
+                }
+            })
+            .collect();
+        // books should already be sorted, but a binary search on one that isn't finds nothing
+        entries.sort_by_key(|entry| entry.key);
+        Book { entries }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.mv.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    // the book that comes with the game: a few dozen main lines of the common openings
+    pub fn bundled() -> Book {
+        Book::from_lines(include_str!("book.txt"))
+    }
+
+    // builds a book from lines of san moves from the starting position, one line per
+    // row; each time a move comes up in a position it weighs a bit more
+    pub fn from_lines(text: &str) -> Book {
+        let mut book = Book::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut board = Board::new();
+            for san in line.split_whitespace() {
+                let mv = match board.parse_san(san) {
+                    Ok(mv) => mv,
+                    Err(_) => break,
+                };
+                book.add(&board, mv, 1);
+                board.make_move(mv);
+            }
+        }
+        book
+    }
+
+    // adds `weight` to the move in the position, keeping the entries sorted
+    pub fn add(&mut self, board: &Board, mv: Move, weight: u16) {
+        let key = board.position_key();
+        let encoded = encode_move(board, mv);
+        let range = self.range(key);
+        match self.entries[range.clone()]
+            .iter()
+            .position(|entry| entry.mv == encoded)
+        {
+            Some(i) => {
+                let entry = &mut self.entries[range.start + i];
+                entry.weight = entry.weight.saturating_add(weight);
+            }
+            None => self.entries.insert(
+                range.end,
+                BookEntry {
+                    key,
+                    mv: encoded,
+                    weight,
+                    learn: 0,
+                },
+            ),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn range(&self, key: u64) -> std::ops::Range<usize> {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        start..end
+    }
+
+    // the book's legal moves in the position with their weights, heaviest first
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        let mut moves: Vec<(Move, u16)> = self.entries[self.range(board.position_key())]
+            .iter()
+            .filter(|entry| entry.weight > 0)
+            .filter_map(|entry| {
+                let mv = decode_move(board, entry.mv)?;
+                Some((mv, entry.weight))
+            })
+            .collect();
+        moves.sort_by_key(|&(_, weight)| std::cmp::Reverse(weight));
+        moves
+    }
+
+    // the move the book likes best
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        self.moves(board).first().map(|&(mv, _)| mv)
+    }
+
+    // one of the book's moves at random, in proportion to their weights
+    pub fn pick(&self, board: &Board) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u64 = moves.iter().map(|&(_, weight)| weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut choice = random() % total;
+        for (mv, weight) in moves {
+            if choice < weight as u64 {
+                return Some(mv);
+            }
+            choice -= weight as u64;
+        }
+        None
+    }
+}
+
+fn encode_move(board: &Board, mv: Move) -> u16 {
+    let to = match board.castling_squares(mv) {
+        Some((rook_from, _, _)) => rook_from,
+        None => mv.to,
+    };
+    let promotion = match mv.promotion {
+        Some(PieceKind::Knight) => 1,
+        Some(PieceKind::Bishop) => 2,
+        Some(PieceKind::Rook) => 3,
+        Some(PieceKind::Queen) => 4,
+        _ => 0,
+    };
+    to.file() as u16
+        | (to.rank() as u16) << 3
+        | (mv.from.file() as u16) << 6
+        | (mv.from.rank() as u16) << 9
+        | promotion << 12
+}
+
+// the legal move an entry stands for, if it is one
+fn decode_move(board: &Board, encoded: u16) -> Option<Move> {
+    let square = |bits: u16| Square::new((bits & 7) as u8, ((bits >> 3) & 7) as u8);
+    let from = square(encoded >> 6);
+    let mut to = square(encoded);
+    // polyglot writes castling as the king taking its own rook, which is how chess960
+    // moves are made already; only standard chess moves the king two squares instead
+    let is_king = board.piece_at(from).map(|piece| piece.kind) == Some(PieceKind::King);
+    if is_king && !board.is_chess960() && from.file() == 4 && from.rank() == to.rank() {
+        match to.file() {
+            7 => to = Square::new(6, to.rank()),
+            0 => to = Square::new(2, to.rank()),
+            _ => {}
+        }
+    }
+    let promotion = match (encoded >> 12) & 7 {
+        1 => Some(PieceKind::Knight),
+        2 => Some(PieceKind::Bishop),
+        3 => Some(PieceKind::Rook),
+        4 => Some(PieceKind::Queen),
+        _ => None,
+    };
+    let mv = Move {
+        from,
+        to,
+        promotion,
+        drop: None,
+    };
+    if board.is_legal(mv) {
+        Some(mv)
+    } else {
+        None
+    }
+}
+
+// good enough to vary the openings from game to game, not for anything else
+pub(crate) fn random() -> u64 {
+    static STATE: AtomicU64 = AtomicU64::new(0);
+    let mut state = STATE.load(Ordering::Relaxed);
+    if state == 0 {
+        state = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |time| time.as_nanos() as u64)
+            | 1;
+    }
+    // xorshift64
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    STATE.store(state, Ordering::Relaxed);
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn castling_board() -> Board {
+        Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap()
+    }
+
+    #[test]
+    fn standard_castling_comes_back_as_the_king_moving_two() {
+        let board = castling_board();
+        for &(to, king_to) in [(7, 6), (0, 2)].iter() {
+            let castle = Move::new(Square::new(4, 0), Square::new(king_to, 0));
+            let encoded = encode_move(&board, castle);
+            assert_eq!(encoded & 0o77, to);
+            assert_eq!(decode_move(&board, encoded), Some(castle));
+        }
+    }
+
+    #[test]
+    fn chess960_castling_stays_the_king_taking_the_rook() {
+        let mut board = castling_board();
+        board.set_chess960(true);
+        for &to in [7, 0].iter() {
+            let castle = Move::new(Square::new(4, 0), Square::new(to, 0));
+            assert!(board.is_legal(castle));
+            assert_eq!(
+                decode_move(&board, encode_move(&board, castle)),
+                Some(castle)
+            );
+        }
+    }
+}
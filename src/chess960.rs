@@ -0,0 +1,88 @@
+use crate::board::{Board, CastlingRights, Color, Piece, PieceKind, Square};
+use crate::book;
+
+// the 960 starting positions of fischer random chess, numbered the usual way from 0
+// to 959 so a game can be set up again from its number
+pub const POSITIONS: u16 = 960;
+
+// the standard starting position has a number too
+pub const STANDARD: u16 = 518;
+
+// the back rank of a position: the bishops go on opposite colours, then the queen
+// and the knights on the squares left, and the rooks either side of the king on the
+// three that remain
+pub fn back_rank(number: u16) -> Option<[PieceKind; 8]> {
+    if number >= POSITIONS {
+        return None;
+    }
+    let mut rank = [None; 8];
+    let mut n = number as usize;
+    rank[n % 4 * 2 + 1] = Some(PieceKind::Bishop);
+    n /= 4;
+    rank[n % 4 * 2] = Some(PieceKind::Bishop);
+    n /= 4;
+    let mut place = |kind: PieceKind, nth: usize| {
+        let file = (0..8)
+            .filter(|&file| rank[file].is_none())
+            .nth(nth)
+            .unwrap();
+        rank[file] = Some(kind);
+    };
+    place(PieceKind::Queen, n % 6);
+    n /= 6;
+    // which two of the five squares left get the knights
+    let knights = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+    let (first, second) = knights[n];
+    // the second knight goes first, so placing it doesn't move the square of the other
+    place(PieceKind::Knight, second);
+    place(PieceKind::Knight, first);
+    place(PieceKind::Rook, 0);
+    place(PieceKind::King, 0);
+    place(PieceKind::Rook, 0);
+    let mut pieces = [PieceKind::Pawn; 8];
+    for (piece, kind) in pieces.iter_mut().zip(rank.iter()) {
+        *piece = kind.unwrap();
+    }
+    Some(pieces)
+}
+
+pub fn starting_position(number: u16) -> Option<Board> {
+    let back_rank = back_rank(number)?;
+    let mut board = Board::empty();
+    for (file, &kind) in back_rank.iter().enumerate() {
+        let file = file as u8;
+        for &(color, home, pawns) in [(Color::White, 0, 1), (Color::Black, 7, 6)].iter() {
+            board.set_piece(Square::new(file, home), Some(Piece::new(kind, color)));
+            board.set_piece(
+                Square::new(file, pawns),
+                Some(Piece::new(PieceKind::Pawn, color)),
+            );
+        }
+    }
+    let rooks: Vec<u8> = (0..8)
+        .filter(|&file| back_rank[file as usize] == PieceKind::Rook)
+        .collect();
+    for &color in [Color::White, Color::Black].iter() {
+        board.set_castling_rook(color, true, rooks[1]);
+        board.set_castling_rook(color, false, rooks[0]);
+    }
+    board.set_castling(CastlingRights::all());
+    // the ones with the king and rooks where they usually are castle like standard chess
+    board.set_chess960(back_rank[4] != PieceKind::King || rooks != [0, 7]);
+    Some(board)
+}
+
+pub fn random_number() -> u16 {
+    (book::random() % POSITIONS as u64) as u16
+}
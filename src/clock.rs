@@ -0,0 +1,320 @@
+use crate::board::Color;
+use crate::engine::SearchLimits;
+use std::time::{Duration, Instant};
+
+// `moves` moves in `time`, or the rest of the game in it if there's no move count,
+// with `increment` added after every move
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeStage {
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub increment: Duration,
+}
+
+// one or more stages played in order; a last stage with a move count repeats
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub stages: Vec<TimeStage>,
+}
+
+impl TimeControl {
+    pub fn new(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl {
+            stages: vec![TimeStage {
+                moves: None,
+                time,
+                increment,
+            }],
+        }
+    }
+
+    // stages like [moves/]minutes[+seconds] separated by commas: 5+3 is blitz and
+    // 40/90+30,30+30 is 90 minutes for 40 moves then 30 for the rest, all with 30
+    // seconds a move
+    pub fn parse(text: &str) -> Option<TimeControl> {
+        let mut stages = Vec::new();
+        for stage in text.split(',') {
+            let (moves, rest) = match stage.split_once('/') {
+                Some((moves, rest)) => (Some(moves.trim().parse().ok().filter(|&m| m > 0)?), rest),
+                None => (None, stage),
+            };
+            let (minutes, seconds) = match rest.split_once('+') {
+                Some((minutes, seconds)) => (minutes, seconds.trim().parse::<f64>().ok()?),
+                None => (rest, 0.0),
+            };
+            let minutes = minutes.trim().parse::<f64>().ok()?;
+            if !(minutes > 0.0 && minutes.is_finite() && seconds >= 0.0 && seconds.is_finite()) {
+                return None;
+            }
+            stages.push(TimeStage {
+                moves,
+                time: Duration::from_secs_f64(minutes * 60.0),
+                increment: Duration::from_secs_f64(seconds),
+            });
+        }
+        // only the last stage can go on for the rest of the game
+        let open_ended = stages[..stages.len() - 1]
+            .iter()
+            .any(|stage| stage.moves.is_none());
+        if open_ended {
+            return None;
+        }
+        Some(TimeControl { stages })
+    }
+
+    // the pgn TimeControl tag, which counts in seconds, like 300+3 or 40/5400+30:1800+30
+    pub fn to_pgn(&self) -> String {
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let mut text = String::new();
+                if let Some(moves) = stage.moves {
+                    text.push_str(&format!("{}/", moves));
+                }
+                text.push_str(&stage.time.as_secs().to_string());
+                if stage.increment > Duration::ZERO {
+                    text.push_str(&format!("+{}", stage.increment.as_secs()));
+                }
+                text
+            })
+            .collect();
+        stages.join(":")
+    }
+
+    // reads what to_pgn() writes; "-" and "?", no time control or an unknown one, give None
+    pub fn from_pgn(text: &str) -> Option<TimeControl> {
+        let mut stages = Vec::new();
+        for stage in text.split(':') {
+            let (moves, rest) = match stage.split_once('/') {
+                Some((moves, rest)) => (Some(moves.parse().ok().filter(|&m| m > 0)?), rest),
+                None => (None, stage),
+            };
+            let (time, increment) = match rest.split_once('+') {
+                Some((time, increment)) => (time, increment.parse().ok()?),
+                None => (rest, 0),
+            };
+            let time: u64 = time.parse().ok().filter(|&time| time > 0)?;
+            stages.push(TimeStage {
+                moves,
+                time: Duration::from_secs(time),
+                increment: Duration::from_secs(increment),
+            });
+        }
+        Some(TimeControl { stages })
+    }
+}
+
+// a clock for each side, only one of them running at a time
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    // what was left when each clock was last stopped
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    // moves made in the current stage
+    stage_moves: [u32; 2],
+    running: Option<(Color, Instant)>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        let time = control.stages[0].time;
+        Clock {
+            control,
+            remaining: [time; 2],
+            stage: [0; 2],
+            stage_moves: [0; 2],
+            running: None,
+        }
+    }
+
+    // a clock partway through a game, stopped: `moves` have been made since `first`
+    // was to move, and each side has the time given left
+    pub fn resumed(
+        control: TimeControl,
+        first: Color,
+        moves: usize,
+        white: Duration,
+        black: Duration,
+    ) -> Clock {
+        // pressing the clock once for every move puts each side in the right stage
+        let mut clock = Clock::new(control);
+        clock.start(first);
+        for _ in 0..moves {
+            clock.press();
+        }
+        clock.stop();
+        clock.set_remaining(Color::White, white);
+        clock.set_remaining(Color::Black, black);
+        clock
+    }
+
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    pub fn remaining(&self, color: Color) -> Duration {
+        let remaining = self.remaining[color as usize];
+        match self.running {
+            Some((running, started)) if running == color => {
+                remaining.saturating_sub(started.elapsed())
+            }
+            _ => remaining,
+        }
+    }
+
+    // the side whose time ran out, if it's happened
+    pub fn flagged(&self) -> Option<Color> {
+        [Color::White, Color::Black]
+            .iter()
+            .copied()
+            .find(|&color| self.remaining(color) == Duration::ZERO)
+    }
+
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    // corrects a side's time, like when the other player's computer says how long it
+    // has left
+    pub fn set_remaining(&mut self, color: Color, remaining: Duration) {
+        self.remaining[color as usize] = remaining;
+        if let Some((running, started)) = &mut self.running {
+            if *running == color {
+                *started = Instant::now();
+            }
+        }
+    }
+
+    pub fn start(&mut self, color: Color) {
+        self.stop();
+        self.running = Some((color, Instant::now()));
+    }
+
+    pub fn stop(&mut self) {
+        if let Some((color, _)) = self.running {
+            self.remaining[color as usize] = self.remaining(color);
+            self.running = None;
+        }
+    }
+
+    // the side that was running has moved: it gets its increment, and its next stage's
+    // time if it made the stage's moves, then the other side's clock starts
+    pub fn press(&mut self) {
+        let color = match self.running() {
+            Some(color) => color,
+            None => return,
+        };
+        self.stop();
+        let side = color as usize;
+        if self.remaining[side] > Duration::ZERO {
+            let stage = &self.control.stages[self.stage[side]];
+            self.remaining[side] += stage.increment;
+            self.stage_moves[side] += 1;
+            if Some(self.stage_moves[side]) == stage.moves {
+                self.stage[side] = (self.stage[side] + 1).min(self.control.stages.len() - 1);
+                self.stage_moves[side] = 0;
+                self.remaining[side] += self.control.stages[self.stage[side]].time;
+            }
+        }
+        self.start(color.opposite());
+    }
+
+    pub fn increment(&self, color: Color) -> Duration {
+        self.control.stages[self.stage[color as usize]].increment
+    }
+
+    // moves left until the next stage's time is added, if there is one
+    pub fn moves_to_go(&self, color: Color) -> Option<u32> {
+        let side = color as usize;
+        self.control.stages[self.stage[side]]
+            .moves
+            .map(|moves| moves - self.stage_moves[side])
+    }
+
+    // how long an engine playing `color` should think about its move
+    pub fn search_limits(&self, color: Color) -> SearchLimits {
+        SearchLimits::from_clock(
+            self.remaining(color),
+            self.increment(color),
+            self.moves_to_go(color),
+        )
+    }
+}
+
+// minutes and seconds, with the hours in front after an hour and tenths of a second
+// once it's nearly out
+pub fn clock_text(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else if seconds < 10 {
+        format!("{}.{}", seconds, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a press only ever takes a moment, so the times are checked to within a second
+    fn about(time: Duration, seconds: u64) -> bool {
+        time <= Duration::from_secs(seconds) && time > Duration::from_secs(seconds - 1)
+    }
+
+    #[test]
+    fn the_increment_is_added_after_a_move() {
+        let mut clock = Clock::new(TimeControl::parse("5+3").unwrap());
+        clock.start(Color::White);
+        clock.press();
+        assert!(about(clock.remaining(Color::White), 303));
+        assert_eq!(clock.running(), Some(Color::Black));
+        assert!(about(clock.remaining(Color::Black), 300));
+    }
+
+    #[test]
+    fn the_next_stage_starts_at_its_move_count() {
+        let mut clock = Clock::new(TimeControl::parse("2/1,1+5").unwrap());
+        clock.start(Color::White);
+        assert_eq!(clock.moves_to_go(Color::White), Some(2));
+        clock.press();
+        clock.press();
+        assert_eq!(clock.moves_to_go(Color::White), Some(1));
+        assert_eq!(clock.increment(Color::White), Duration::ZERO);
+        clock.press();
+        assert_eq!(clock.moves_to_go(Color::White), None);
+        assert_eq!(clock.increment(Color::White), Duration::from_secs(5));
+        assert!(about(clock.remaining(Color::White), 120));
+        assert_eq!(clock.moves_to_go(Color::Black), Some(1));
+    }
+
+    #[test]
+    fn stages_come_back_from_the_pgn_tag() {
+        let control = TimeControl::from_pgn("40/5400+30:1800+30").unwrap();
+        assert_eq!(TimeControl::parse("40/90+30,30+30"), Some(control.clone()));
+        assert_eq!(control.to_pgn(), "40/5400+30:1800+30");
+        assert_eq!(TimeControl::from_pgn(&control.to_pgn()), Some(control));
+        assert_eq!(TimeControl::from_pgn("-"), None);
+    }
+
+    #[test]
+    fn running_out_flags_the_side_whose_clock_it_was() {
+        let mut clock = Clock::new(TimeControl::parse("1+2").unwrap());
+        clock.start(Color::White);
+        clock.press();
+        assert_eq!(clock.flagged(), None);
+        clock.set_remaining(Color::Black, Duration::ZERO);
+        assert_eq!(clock.flagged(), Some(Color::Black));
+        // and no increment brings it back
+        clock.press();
+        assert_eq!(clock.remaining(Color::Black), Duration::ZERO);
+    }
+}
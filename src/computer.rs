@@ -0,0 +1,184 @@
+use chess_game::board::{Board, Color};
+use chess_game::clock::Clock;
+use chess_game::engine::external::ExternalEngine;
+use chess_game::engine::{Engine, SearchLimits};
+use chess_game::moves::Move;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+// a move, and the reply the engine expects to it if it knows one
+type Thought = (io::Result<Option<Move>>, Option<Move>);
+
+// whatever is playing the computer's side
+pub enum Opponent {
+    Builtin(Engine),
+    External(ExternalEngine),
+}
+
+impl Opponent {
+    fn best_move(&mut self, board: &Board, limits: &SearchLimits) -> Thought {
+        match self {
+            Opponent::Builtin(engine) => {
+                let result = engine.search(board, limits.clone());
+                (Ok(result.best_move), result.pv.get(1).copied())
+            }
+            Opponent::External(engine) => (engine.best_move(board, limits), None),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Opponent::Builtin(_) => "chess-game",
+            Opponent::External(engine) => engine.name().unwrap_or("?"),
+        }
+    }
+}
+
+// the builtin engine thinking on the player's time, about the position after the
+// reply it expects
+struct Ponder {
+    board: Board,
+    flag: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+// the opponent playing one side; it thinks on its own thread so the frontend keeps
+// responding, and hands itself back along with its move when done
+pub struct Computer {
+    pub color: Color,
+    limits: SearchLimits,
+    opponent: Option<Opponent>,
+    thinking: Option<JoinHandle<(Opponent, Thought)>>,
+    // the reply the last search expected
+    expected: Option<Move>,
+    // set while the thread is pondering instead of thinking about its own move
+    ponder: Option<Ponder>,
+}
+
+impl Computer {
+    pub fn new(color: Color, opponent: Opponent, limits: SearchLimits) -> Computer {
+        Computer {
+            color,
+            limits,
+            opponent: Some(opponent),
+            thinking: None,
+            expected: None,
+            ponder: None,
+        }
+    }
+
+    // pondering doesn't count, the computer's waiting for the player then
+    #[cfg(feature = "gui")]
+    pub fn is_thinking(&self) -> bool {
+        self.thinking.is_some() && self.ponder.is_none()
+    }
+
+    // with a clock running the engine has to budget its own time. when it's been
+    // pondering the position, the search it's got going carries on as this one
+    pub fn start_thinking(&mut self, board: &Board, clock: Option<&Clock>) {
+        if self
+            .ponder
+            .as_ref()
+            .is_some_and(|ponder| ponder.board == *board)
+        {
+            let ponder = self.ponder.take().unwrap();
+            ponder.flag.store(false, Ordering::Relaxed);
+            return;
+        }
+        self.stop_pondering();
+        let limits = self.limits(clock);
+        self.think(board, limits);
+    }
+
+    // keeps thinking once its move's been played, on the position after the reply it
+    // expects, so a ponder hit has a head start; the builtin engine only
+    #[cfg(feature = "gui")]
+    pub fn start_pondering(&mut self, board: &Board, clock: Option<&Clock>) {
+        let reply = match self.expected.take() {
+            Some(reply) if self.thinking.is_none() && board.is_legal(reply) => reply,
+            _ => return,
+        };
+        let (flag, stop) = match &self.opponent {
+            Some(Opponent::Builtin(engine)) => (engine.ponder_flag(), engine.stop_flag()),
+            _ => return,
+        };
+        let mut pondered = board.clone();
+        pondered.make_move(reply);
+        flag.store(true, Ordering::Relaxed);
+        self.ponder = Some(Ponder {
+            board: pondered.clone(),
+            flag,
+            stop,
+        });
+        let limits = self.limits(clock);
+        self.think(&pondered, limits);
+    }
+
+    // throws away the search on a position that didn't come up
+    pub fn stop_pondering(&mut self) {
+        let ponder = match self.ponder.take() {
+            Some(ponder) => ponder,
+            None => return,
+        };
+        ponder.stop.store(true, Ordering::Relaxed);
+        self.wait_for_move();
+        self.expected = None;
+        ponder.flag.store(false, Ordering::Relaxed);
+        ponder.stop.store(false, Ordering::Relaxed);
+    }
+
+    fn limits(&self, clock: Option<&Clock>) -> SearchLimits {
+        match clock {
+            Some(clock) => {
+                let timed = clock.search_limits(self.color);
+                SearchLimits {
+                    time: timed.time,
+                    budget: timed.budget,
+                    ..self.limits.clone()
+                }
+            }
+            None => self.limits.clone(),
+        }
+    }
+
+    fn think(&mut self, board: &Board, limits: SearchLimits) {
+        if let Some(mut opponent) = self.opponent.take() {
+            let board = board.clone();
+            self.thinking = Some(std::thread::spawn(move || {
+                let thought = opponent.best_move(&board, &limits);
+                (opponent, thought)
+            }));
+        }
+    }
+
+    // the move once the search is over, without waiting for it; one found by pondering
+    // waits for the reply it was pondering to be played
+    #[cfg(feature = "gui")]
+    pub fn finished_move(&mut self) -> Option<io::Result<Option<Move>>> {
+        if self.ponder.is_some() || !self.thinking.as_ref()?.is_finished() {
+            return None;
+        }
+        self.wait_for_move()
+    }
+
+    // the move, waiting for the search to end if it hasn't
+    pub fn wait_for_move(&mut self) -> Option<io::Result<Option<Move>>> {
+        let (opponent, (mv, expected)) = self
+            .thinking
+            .take()?
+            .join()
+            .expect("engine thread panicked");
+        self.opponent = Some(opponent);
+        self.expected = expected;
+        Some(mv)
+    }
+}
+
+impl Drop for Computer {
+    // a ponder search has no limits running, it'd go on until it ran out of depth
+    fn drop(&mut self) {
+        self.stop_pondering();
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use std::path::PathBuf;
+
+// the settings files: just enough toml for tables of keys set to strings, numbers and
+// booleans, with comments. there are no arrays, inline tables or dates, and no serde
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    // integers count as well, so 1 can be written for 1.0
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(number) => Some(*number as f64),
+            Value::Float(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+// what's wrong with a file, and on which line counting from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    // in the order they're in the file, each key with its table in front, like
+    // board.light for light under [board]
+    entries: Vec<(String, Value)>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        let mut table = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| ConfigError {
+                line: i + 1,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix('[') {
+                let name = match rest.split_once(']') {
+                    Some((name, after)) if is_blank(after) => name.trim(),
+                    _ => return Err(error(format!("expected a table name, found {}", line))),
+                };
+                if !is_key(name) {
+                    return Err(error(format!("'{}' isn't a table name", name)));
+                }
+                table = format!("{}.", name);
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(error(format!("expected key = value, found {}", line))),
+            };
+            if !is_key(key) {
+                return Err(error(format!("'{}' isn't a key", key)));
+            }
+            let key = format!("{}{}", table, key);
+            if entries.iter().any(|(known, _)| *known == key) {
+                return Err(error(format!("{} is set twice", key)));
+            }
+            let value = match parse_value(value) {
+                Some((value, after)) if is_blank(after) => value,
+                _ => return Err(error(format!("{} has an invalid value", key))),
+            };
+            entries.push((key, value));
+        }
+        Ok(Config { entries })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(known, _)| known == key)
+            .map(|(_, value)| value)
+    }
+
+    // every key that's set, with its table in front
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(key, _)| key.as_str())
+    }
+}
+
+// the game's own directory, chess in the user's config directory, where the settings
+// files go whether or not it exists yet
+pub fn directory() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("chess"))
+}
+
+// what's left after a value: nothing, or a comment
+fn is_blank(text: &str) -> bool {
+    let text = text.trim();
+    text.is_empty() || text.starts_with('#')
+}
+
+// bare keys and table names, dotted ones included
+fn is_key(text: &str) -> bool {
+    !text.is_empty()
+        && text.split('.').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+// a value and whatever follows it on the line
+fn parse_value(text: &str) -> Option<(Value, &str)> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Some((Value::String(value), &rest[i + 1..])),
+                '\\' => value.push(match chars.next()?.1 {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '"' => '"',
+                    '\\' => '\\',
+                    _ => return None,
+                }),
+                _ => value.push(c),
+            }
+        }
+        return None;
+    }
+    // no escapes in single quotes, which suits windows paths
+    if let Some(rest) = text.strip_prefix('\'') {
+        let (value, after) = rest.split_once('\'')?;
+        return Some((Value::String(value.to_string()), after));
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '#')
+        .unwrap_or(text.len());
+    let (word, after) = text.split_at(end);
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => {
+            let number = word.replace('_', "");
+            match number.parse() {
+                Ok(number) => Value::Integer(number),
+                Err(_) => Value::Float(number.parse().ok()?),
+            }
+        }
+    };
+    Some((value, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_take_their_table_in_front() {
+        let config =
+            Config::parse("top = 1\n[board]\nlight = \"#eeeed2\"\n\n[engine.nnue]\nok = true\n")
+                .unwrap();
+        assert_eq!(config.get("top"), Some(&Value::Integer(1)));
+        assert_eq!(
+            config.get("board.light").and_then(Value::as_str),
+            Some("#eeeed2")
+        );
+        assert_eq!(config.get("engine.nnue.ok"), Some(&Value::Boolean(true)));
+        assert_eq!(config.get("light"), None);
+        let keys: Vec<&str> = config.keys().collect();
+        assert_eq!(keys, ["top", "board.light", "engine.nnue.ok"]);
+    }
+
+    #[test]
+    fn strings_have_escapes_in_double_quotes_only() {
+        let text = "a = \"say \\\"hi\\\"\\tthen\\\\\\n\"\nb = 'C:\\games\\chess'\n";
+        let config = Config::parse(text).unwrap();
+        assert_eq!(
+            config.get("a").and_then(Value::as_str),
+            Some("say \"hi\"\tthen\\\n")
+        );
+        assert_eq!(
+            config.get("b").and_then(Value::as_str),
+            Some("C:\\games\\chess")
+        );
+        assert!(Config::parse("a = \"\\q\"").is_err());
+        assert!(Config::parse("a = \"open").is_err());
+    }
+
+    #[test]
+    fn comments_are_left_out() {
+        let text =
+            "# the clock\n[game] # for every game\nclock = \"5+3\" # blitz\nmoves = 1_000 #\n";
+        let config = Config::parse(text).unwrap();
+        assert_eq!(
+            config.get("game.clock").and_then(Value::as_str),
+            Some("5+3")
+        );
+        assert_eq!(config.get("game.moves"), Some(&Value::Integer(1000)));
+        let config = Config::parse("hash = \"#fff\" # a colour").unwrap();
+        assert_eq!(config.get("hash").and_then(Value::as_str), Some("#fff"));
+    }
+
+    #[test]
+    fn a_bad_line_says_where_it_is() {
+        let text = "[game]\nclock = \"5+3\"\n\n# fine so far\nclock is 5+3\n";
+        let error = Config::parse(text).unwrap_err();
+        assert_eq!(error.line, 5);
+        assert_eq!(
+            error.to_string(),
+            "line 5: expected key = value, found clock is 5+3"
+        );
+
+        let error = Config::parse("a = 1\na = 2").unwrap_err();
+        assert_eq!((error.line, error.message.as_str()), (2, "a is set twice"));
+        assert_eq!(Config::parse("[bad table").unwrap_err().line, 1);
+        assert_eq!(Config::parse("\n\nx = yes").unwrap_err().line, 3);
+    }
+}
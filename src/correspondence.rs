@@ -0,0 +1,210 @@
+use crate::board::Color;
+use crate::config;
+#[cfg(feature = "lichess")]
+use crate::lichess::Client;
+use crate::pgn::{PgnError, PgnGame};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum CorrespondenceError {
+    Io(io::Error),
+    Pgn(PgnError),
+    // names are file names, so only letters, digits, - and _
+    Name(String),
+    // the ply where the moves from elsewhere stop matching the ones kept here
+    Diverged(usize),
+    IllegalMove(String),
+}
+
+impl fmt::Display for CorrespondenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CorrespondenceError::Io(error) => write!(f, "{}", error),
+            CorrespondenceError::Pgn(error) => write!(f, "invalid PGN: {}", error),
+            CorrespondenceError::Name(name) => write!(
+                f,
+                "{:?} can't be a game's name, which only has letters, digits, - and _",
+                name
+            ),
+            CorrespondenceError::Diverged(ply) => {
+                write!(f, "the game elsewhere has a different move {}", ply + 1)
+            }
+            CorrespondenceError::IllegalMove(text) => write!(f, "illegal move {}", text),
+        }
+    }
+}
+
+impl std::error::Error for CorrespondenceError {}
+
+impl From<io::Error> for CorrespondenceError {
+    fn from(error: io::Error) -> CorrespondenceError {
+        CorrespondenceError::Io(error)
+    }
+}
+
+impl From<PgnError> for CorrespondenceError {
+    fn from(error: PgnError) -> CorrespondenceError {
+        CorrespondenceError::Pgn(error)
+    }
+}
+
+// a game played over days instead of minutes, kept under its name in the config
+// directory so the program can be closed between moves. like a saved game it's pgn
+// with headers of its own: the side played from here, and the lichess game it follows
+// when the opponent's moves come from there rather than being typed in here
+#[derive(Clone, Debug)]
+pub struct CorrespondenceGame {
+    pub name: String,
+    pub record: PgnGame,
+    pub color: Color,
+    pub lichess: Option<String>,
+}
+
+impl CorrespondenceGame {
+    pub fn new(
+        name: &str,
+        record: PgnGame,
+        color: Color,
+        lichess: Option<String>,
+    ) -> Result<CorrespondenceGame, CorrespondenceError> {
+        check_name(name)?;
+        Ok(CorrespondenceGame {
+            name: name.to_string(),
+            record,
+            color,
+            lichess,
+        })
+    }
+
+    // correspondence in the config directory, one pgn file for each game
+    pub fn directory() -> Option<PathBuf> {
+        config::directory().map(|directory| directory.join("correspondence"))
+    }
+
+    fn path(name: &str) -> io::Result<PathBuf> {
+        CorrespondenceGame::directory()
+            .map(|directory| directory.join(format!("{}.pgn", name)))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "there's no home directory"))
+    }
+
+    pub fn exists(name: &str) -> bool {
+        check_name(name).is_ok() && CorrespondenceGame::path(name).is_ok_and(|path| path.exists())
+    }
+
+    // the names of every game kept, in order
+    pub fn list() -> io::Result<Vec<String>> {
+        let directory = match CorrespondenceGame::directory() {
+            Some(directory) if directory.exists() => directory,
+            _ => return Ok(Vec::new()),
+        };
+        let mut names = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "pgn") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn load(name: &str) -> Result<CorrespondenceGame, CorrespondenceError> {
+        check_name(name)?;
+        let text = fs::read_to_string(CorrespondenceGame::path(name)?)?;
+        CorrespondenceGame::from_pgn(name, &text)
+    }
+
+    // replaces what was kept before, straight after every move so nothing's lost
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = CorrespondenceGame::path(&self.name)?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(&path, self.to_pgn())?;
+        Ok(path)
+    }
+
+    pub fn from_pgn(name: &str, text: &str) -> Result<CorrespondenceGame, CorrespondenceError> {
+        // the headers only mean something here, so they're left out of the record
+        let mut record = PgnGame::from_pgn(text)?;
+        let color = match record.take_header("Correspondence").as_deref() {
+            Some("Black") => Color::Black,
+            _ => Color::White,
+        };
+        let lichess = record.take_header("LichessGame");
+        CorrespondenceGame::new(name, record, color, lichess)
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let mut record = self.record.clone();
+        record.set_header("Correspondence", &format!("{:?}", self.color));
+        if let Some(game) = &self.lichess {
+            record.set_header("LichessGame", game);
+        }
+        record.to_pgn()
+    }
+
+    pub fn is_our_move(&self) -> bool {
+        let board = self.record.board_at(self.record.moves().len());
+        board.side_to_move() == self.color
+    }
+
+    // every move played elsewhere from the start, in uci notation, of which the ones
+    // not kept here yet are added; gives back those in san, to say what happened
+    pub fn catch_up(&mut self, moves: &[String]) -> Result<Vec<String>, CorrespondenceError> {
+        let kept = self.record.moves();
+        if moves.len() < kept.len() {
+            return Err(CorrespondenceError::Diverged(moves.len()));
+        }
+        let variant = self.record.variant();
+        let mut board = self.record.start().clone();
+        for (ply, (text, kept)) in moves.iter().zip(kept).enumerate() {
+            if board.parse_uci_move(text) != Some(kept.mv) {
+                return Err(CorrespondenceError::Diverged(ply));
+            }
+            board.make_move(kept.mv);
+        }
+        // all of them or none
+        let mut record = self.record.clone();
+        let mut played = Vec::new();
+        for text in &moves[kept.len()..] {
+            let mv = board
+                .parse_uci_move(text)
+                .filter(|&mv| variant.is_legal(&board, mv))
+                .ok_or_else(|| CorrespondenceError::IllegalMove(text.clone()))?;
+            played.push(mv.to_san(&board));
+            record.push(mv, None);
+            board.make_move(mv);
+        }
+        if !played.is_empty() {
+            record.set_result(variant.status(&board));
+            self.record = record;
+        }
+        Ok(played)
+    }
+
+    // fetches the moves made on lichess since the game was last looked at, for a game
+    // that follows one there
+    #[cfg(feature = "lichess")]
+    pub fn sync(&mut self, client: &Client) -> Result<Vec<String>, CorrespondenceError> {
+        let game = match &self.lichess {
+            Some(game) => game.clone(),
+            None => return Ok(Vec::new()),
+        };
+        let full = client.game(&game)?;
+        self.catch_up(&full.state.moves)
+    }
+}
+
+fn check_name(name: &str) -> Result<(), CorrespondenceError> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || !name.chars().all(allowed) {
+        return Err(CorrespondenceError::Name(name.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use crate::board::Board;
+use crate::config;
+use crate::pgn::{parse_pgn, PgnError, PgnGame};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Io(io::Error),
+    Pgn(PgnError),
+    // a game that isn't in the database, counting from 0
+    NoGame(usize),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatabaseError::Io(error) => write!(f, "{}", error),
+            DatabaseError::Pgn(error) => write!(f, "invalid PGN: {}", error),
+            DatabaseError::NoGame(index) => write!(f, "there's no game {}", index + 1),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<io::Error> for DatabaseError {
+    fn from(error: io::Error) -> DatabaseError {
+        DatabaseError::Io(error)
+    }
+}
+
+impl From<PgnError> for DatabaseError {
+    fn from(error: PgnError) -> DatabaseError {
+        DatabaseError::Pgn(error)
+    }
+}
+
+// a position that came up in a game of the database
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hit {
+    // counting from 0 in the order they were imported
+    pub game: usize,
+    // how many moves of its main line it took to get there
+    pub ply: usize,
+}
+
+// where a game's pgn is in games.pgn
+#[derive(Clone, Copy, Debug)]
+struct Offset {
+    start: u64,
+    length: u32,
+}
+
+// one position of one game: 16 bytes on disk, all big endian and padded like a
+// polyglot book entry
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    key: u64,
+    game: u32,
+    ply: u16,
+}
+
+const OFFSET_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 16;
+
+// games kept in a directory to be found by the positions they reached: every game's
+// pgn one after the other in games.pgn, where each starts in games.idx, and the
+// position_key() of every position of their main lines in positions.idx, sorted so a
+// position's games can be found with a binary search. the indexes are read into memory
+// when it's opened, the games only when they're asked for
+pub struct Database {
+    directory: PathBuf,
+    offsets: Vec<Offset>,
+    entries: Vec<Entry>,
+}
+
+impl Database {
+    // games in the config directory, for when there's no other
+    pub fn default_path() -> Option<PathBuf> {
+        config::directory().map(|directory| directory.join("games"))
+    }
+
+    // an empty database if there isn't one there yet; the directory's only made once
+    // something's imported
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Database, DatabaseError> {
+        let directory = directory.as_ref().to_path_buf();
+        let read = |name: &str| match fs::read(directory.join(name)) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            result => result,
+        };
+        let offsets = read("games.idx")?
+            .chunks_exact(OFFSET_SIZE)
+            .map(|chunk| {
+                let mut start = [0; 8];
+                start.copy_from_slice(&chunk[0..8]);
+                let mut length = [0; 4];
+                length.copy_from_slice(&chunk[8..12]);
+                Offset {
+                    start: u64::from_be_bytes(start),
+                    length: u32::from_be_bytes(length),
+                }
+            })
+            .collect();
+        let entries = read("positions.idx")?
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| {
+                let mut key = [0; 8];
+                key.copy_from_slice(&chunk[0..8]);
+                let mut game = [0; 4];
+                game.copy_from_slice(&chunk[8..12]);
+                Entry {
+                    key: u64::from_be_bytes(key),
+                    game: u32::from_be_bytes(game),
+                    ply: u16::from_be_bytes([chunk[12], chunk[13]]),
+                }
+            })
+            .collect();
+        Ok(Database {
+            directory,
+            offsets,
+            entries,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    // adds every game of a pgn collection, handing back how many there were. nothing's
+    // added if any of them doesn't read
+    pub fn import(&mut self, text: &str) -> Result<usize, DatabaseError> {
+        let games = parse_pgn(text)?;
+        fs::create_dir_all(&self.directory)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.directory.join("games.pgn"))?;
+        let mut start = file.seek(SeekFrom::End(0))?;
+        for game in &games {
+            let pgn = game.to_pgn() + "\n";
+            file.write_all(pgn.as_bytes())?;
+            self.add_positions(game);
+            self.offsets.push(Offset {
+                start,
+                length: pgn.len() as u32,
+            });
+            start += pgn.len() as u64;
+        }
+        file.flush()?;
+        self.save()?;
+        Ok(games.len())
+    }
+
+    // each position once a game, at the first ply it came up
+    fn add_positions(&mut self, game: &PgnGame) {
+        let index = self.offsets.len() as u32;
+        let mut seen = HashSet::new();
+        let mut board = game.start().clone();
+        for ply in 0..=game.moves().len().min(u16::MAX as usize) {
+            if seen.insert(board.position_key()) {
+                self.entries.push(Entry {
+                    key: board.position_key(),
+                    game: index,
+                    ply: ply as u16,
+                });
+            }
+            if let Some(record) = game.moves().get(ply) {
+                board.make_move(record.mv);
+            }
+        }
+    }
+
+    fn save(&mut self) -> io::Result<()> {
+        self.entries.sort_by_key(|entry| (entry.key, entry.game));
+        let mut offsets = Vec::with_capacity(self.offsets.len() * OFFSET_SIZE);
+        for offset in &self.offsets {
+            offsets.extend_from_slice(&offset.start.to_be_bytes());
+            offsets.extend_from_slice(&offset.length.to_be_bytes());
+        }
+        let mut entries = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            entries.extend_from_slice(&entry.key.to_be_bytes());
+            entries.extend_from_slice(&entry.game.to_be_bytes());
+            entries.extend_from_slice(&entry.ply.to_be_bytes());
+            entries.extend_from_slice(&[0, 0]);
+        }
+        fs::write(self.directory.join("games.idx"), offsets)?;
+        fs::write(self.directory.join("positions.idx"), entries)
+    }
+
+    // the games that reached the position, in the order they were imported
+    pub fn search(&self, board: &Board) -> Vec<Hit> {
+        let key = board.position_key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .map(|entry| Hit {
+                game: entry.game as usize,
+                ply: entry.ply as usize,
+            })
+            .collect()
+    }
+
+    // reads a game back from games.pgn
+    pub fn game(&self, index: usize) -> Result<PgnGame, DatabaseError> {
+        let offset = self
+            .offsets
+            .get(index)
+            .ok_or(DatabaseError::NoGame(index))?;
+        let mut file = File::open(self.directory.join("games.pgn"))?;
+        file.seek(SeekFrom::Start(offset.start))?;
+        let mut text = String::new();
+        file.take(offset.length as u64).read_to_string(&mut text)?;
+        Ok(PgnGame::from_pgn(&text)?)
+    }
+}
+
+// a line about a game for a list of them, like "Carlsen - Caruana, 1-0, London 2018"
+pub fn summary(game: &PgnGame) -> String {
+    let mut text = format!(
+        "{} - {}, {}",
+        game.header("White").unwrap_or("?"),
+        game.header("Black").unwrap_or("?"),
+        game.header("Result").unwrap_or("*")
+    );
+    let event = game
+        .header("Event")
+        .filter(|event| !event.is_empty() && *event != "?");
+    let year = game
+        .header("Date")
+        .and_then(|date| date.get(..4))
+        .filter(|year| !year.contains('?'));
+    match (event, year) {
+        (Some(event), Some(year)) => text.push_str(&format!(", {} {}", event, year)),
+        (Some(event), None) => text.push_str(&format!(", {}", event)),
+        (None, Some(year)) => text.push_str(&format!(", {}", year)),
+        (None, None) => {}
+    }
+    text
+}
@@ -0,0 +1,212 @@
+use crate::board::{Board, Color, PieceKind, Square};
+use crate::glyphs::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use image::imageops::{self, FilterType};
+use image::{GenericImageView, Rgba, RgbaImage};
+
+// how a diagram looks; colours are (red, green, blue, alpha) from 0 to 1 like the
+// window's theme
+#[derive(Clone, Debug)]
+pub struct Diagram {
+    // the width and height of the image, rounded down to a whole number of pixels a
+    // square
+    pub size: u32,
+    pub flipped: bool,
+    // file letters along the bottom and rank numbers down the left, in the corners of
+    // the squares so they don't need a margin
+    pub coordinates: bool,
+    pub light_squares: (f32, f32, f32, f32),
+    pub dark_squares: (f32, f32, f32, f32),
+    // over the squares of the board's last move, if it knows one
+    pub last_move: Option<(f32, f32, f32, f32)>,
+}
+
+impl Default for Diagram {
+    fn default() -> Diagram {
+        Diagram {
+            size: 512,
+            flipped: false,
+            coordinates: true,
+            light_squares: (0.94, 0.85, 0.71, 1.0),
+            dark_squares: (0.71, 0.53, 0.39, 1.0),
+            last_move: Some((0.9, 0.8, 0.2, 0.45)),
+        }
+    }
+}
+
+impl Diagram {
+    // draws the pieces from the same sprite sheet the window uses; pockets aren't
+    // shown, only what's on the board
+    pub fn render(&self, board: &Board) -> RgbaImage {
+        let square_size = (self.size / 8).max(1);
+        let mut image = RgbaImage::new(square_size * 8, square_size * 8);
+        // the column from the left and row from the top a square is drawn in
+        let cell = |square: Square| {
+            if self.flipped {
+                (7 - square.file() as u32, square.rank() as u32)
+            } else {
+                (square.file() as u32, 7 - square.rank() as u32)
+            }
+        };
+        let is_dark = |square: Square| (square.file() + square.rank()).is_multiple_of(2);
+
+        for i in 0..64 {
+            let square = Square::from_index(i);
+            let color = if is_dark(square) {
+                self.dark_squares
+            } else {
+                self.light_squares
+            };
+            let (column, row) = cell(square);
+            fill(
+                &mut image,
+                column * square_size,
+                row * square_size,
+                square_size,
+                color,
+            );
+        }
+        if let (Some(mv), Some((red, green, blue, alpha))) = (board.last_move(), self.last_move) {
+            // the square moved from a little fainter than the one moved to, as in the window
+            for &(square, alpha) in &[(mv.from, alpha * 0.75), (mv.to, alpha)] {
+                let (column, row) = cell(square);
+                let (x, y) = (column * square_size, row * square_size);
+                fill(&mut image, x, y, square_size, (red, green, blue, alpha));
+            }
+        }
+
+        if self.coordinates {
+            // the font is bundled, so it always parses
+            let glyphs = glyphs::bundled().unwrap();
+            let scale = (square_size / 24).max(1);
+            let margin = (square_size / 20).max(1);
+            for i in 0..64 {
+                let square = Square::from_index(i);
+                let (column, row) = cell(square);
+                // in the colour of the other squares so they show on either
+                let color = if is_dark(square) {
+                    self.light_squares
+                } else {
+                    self.dark_squares
+                };
+                let (left, top) = (column * square_size, row * square_size);
+                if row == 7 {
+                    let letter = (b'a' + square.file()) as char;
+                    let x = left + square_size - margin - GLYPH_WIDTH * scale;
+                    let y = top + square_size - margin - GLYPH_HEIGHT * scale;
+                    glyph(&mut image, &glyphs, letter, (x, y), scale, color);
+                }
+                if column == 0 {
+                    let number = (b'1' + square.rank()) as char;
+                    let (x, y) = (left + margin, top + margin);
+                    glyph(&mut image, &glyphs, number, (x, y), scale, color);
+                }
+            }
+        }
+
+        // the sprite sheet is bundled, so it always loads
+        let sheet = image::load_from_memory(include_bytes!("pieces.png"))
+            .unwrap()
+            .to_rgba();
+        let (sprite_width, sprite_height) = (sheet.width() / 6, sheet.height() / 2);
+        let mut sprites = Vec::with_capacity(12);
+        for row in 0..2 {
+            for col in 0..6 {
+                let sprite = sheet
+                    .view(
+                        col * sprite_width,
+                        row * sprite_height,
+                        sprite_width,
+                        sprite_height,
+                    )
+                    .to_image();
+                sprites.push(imageops::resize(
+                    &sprite,
+                    square_size,
+                    square_size,
+                    FilterType::Triangle,
+                ));
+            }
+        }
+        for (square, piece) in board.pieces() {
+            // the usual 6x2 layout: king to pawn from the left, white on the top row
+            let col = match piece.kind {
+                PieceKind::King => 0,
+                PieceKind::Queen => 1,
+                PieceKind::Bishop => 2,
+                PieceKind::Knight => 3,
+                PieceKind::Rook => 4,
+                PieceKind::Pawn => 5,
+            };
+            let row = match piece.color {
+                Color::White => 0,
+                Color::Black => 1,
+            };
+            let (column, screen_row) = cell(square);
+            imageops::overlay(
+                &mut image,
+                &sprites[row * 6 + col],
+                column * square_size,
+                screen_row * square_size,
+            );
+        }
+        image
+    }
+}
+
+impl Board {
+    // a picture of the position for documentation or a bot to post, in the default
+    // style; save() on the image writes it out as a png
+    pub fn render_to_image(&self) -> RgbaImage {
+        Diagram::default().render(self)
+    }
+}
+
+// blends a colour over a square of the image
+fn fill(image: &mut RgbaImage, x: u32, y: u32, size: u32, color: (f32, f32, f32, f32)) {
+    for py in y..y + size {
+        for px in x..x + size {
+            blend(image, px, py, color);
+        }
+    }
+}
+
+// the character's pixels `scale` times as big, from its top left corner; it's left
+// out if the font doesn't have it
+fn glyph(
+    image: &mut RgbaImage,
+    glyphs: &[glyphs::Glyph],
+    c: char,
+    (x, y): (u32, u32),
+    scale: u32,
+    color: (f32, f32, f32, f32),
+) {
+    let rows = match glyphs.iter().find(|(known, _)| *known == c) {
+        Some((_, rows)) => rows,
+        None => return,
+    };
+    for (row, &bits) in rows.iter().enumerate() {
+        for column in 0..GLYPH_WIDTH {
+            if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                continue;
+            }
+            let (left, top) = (x + column * scale, y + row as u32 * scale);
+            fill(image, left, top, scale, color);
+        }
+    }
+}
+
+fn blend(image: &mut RgbaImage, x: u32, y: u32, (red, green, blue, alpha): (f32, f32, f32, f32)) {
+    let Rgba(below) = *image.get_pixel(x, y);
+    let mix = |over: f32, under: u8| (over * 255.0 * alpha + under as f32 * (1.0 - alpha)) as u8;
+    let opacity = alpha * 255.0 + below[3] as f32 * (1.0 - alpha);
+    image.put_pixel(
+        x,
+        y,
+        Rgba([
+            mix(red, below[0]),
+            mix(green, below[1]),
+            mix(blue, below[2]),
+            opacity as u8,
+        ]),
+    );
+}
@@ -0,0 +1,94 @@
+use crate::board::Board;
+use crate::moves::Move;
+use std::collections::HashMap;
+
+// an opening name from the eco classification, like C60 for the Ruy Lopez
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    pub eco: String,
+    pub name: String,
+    // in san from the starting position
+    pub moves: Vec<String>,
+}
+
+// openings looked up by the position their moves reach, so a game that gets there by
+// another move order still gets the name
+#[derive(Clone, Debug, Default)]
+pub struct Eco {
+    openings: Vec<Opening>,
+    positions: HashMap<u64, usize>,
+}
+
+impl Eco {
+    // the main lines of the common openings, which come with the game
+    pub fn bundled() -> Eco {
+        Eco::parse(include_str!("eco.txt"))
+    }
+
+    // lines of "code | name | san moves" from the starting position; a line whose moves
+    // don't all parse is left out, and a later line for a position already named
+    // replaces the earlier one
+    pub fn parse(text: &str) -> Eco {
+        let mut eco = Eco::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+            if fields.len() != 3 {
+                continue;
+            }
+            let mut board = Board::new();
+            let moves: Vec<String> = fields[2].split_whitespace().map(String::from).collect();
+            let mut playable = true;
+            for san in &moves {
+                match board.parse_san(san) {
+                    Ok(mv) => board.make_move(mv),
+                    Err(_) => {
+                        playable = false;
+                        break;
+                    }
+                }
+            }
+            if !playable {
+                continue;
+            }
+            eco.positions
+                .insert(board.position_key(), eco.openings.len());
+            eco.openings.push(Opening {
+                eco: fields[0].to_string(),
+                name: fields[1].to_string(),
+                moves,
+            });
+        }
+        eco
+    }
+
+    pub fn len(&self) -> usize {
+        self.openings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.openings.is_empty()
+    }
+
+    // the opening this exact position is named for
+    pub fn lookup(&self, board: &Board) -> Option<&Opening> {
+        self.positions
+            .get(&board.position_key())
+            .map(|&index| &self.openings[index])
+    }
+
+    // the name of the game so far: the last position on the way that has one, since the
+    // game carries that name once it leaves the known lines
+    pub fn classify(&self, start: &Board, moves: &[Move]) -> Option<&Opening> {
+        let mut board = start.clone();
+        let mut opening = self.lookup(&board);
+        for &mv in moves {
+            board.make_move(mv);
+            opening = self.lookup(&board).or(opening);
+        }
+        opening
+    }
+}
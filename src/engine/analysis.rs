@@ -0,0 +1,83 @@
+use super::{Engine, SearchLimits, SearchResult};
+use crate::board::Board;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+// an engine searching one position on its own thread with no limit, for as long as
+// the position is being looked at; every iteration it completes is sent back, so
+// there's always the deepest one so far to show
+pub struct Analysis {
+    board: Board,
+    iterations: Receiver<SearchResult>,
+    latest: Option<SearchResult>,
+    stop: Arc<AtomicBool>,
+    // hands the engine back, with what it learned, once the search is stopped
+    thread: Option<JoinHandle<Engine>>,
+}
+
+impl Analysis {
+    // a position with one legal move or none never gets an iteration, there's nothing
+    // to search
+    pub fn start(mut engine: Engine, board: &Board) -> Analysis {
+        let (sender, iterations) = mpsc::channel();
+        let stop = engine.stop_flag();
+        let searched = board.clone();
+        let thread = thread::spawn(move || {
+            engine.search_with_info(&searched, SearchLimits::default(), |result| {
+                // nobody's listening any more once the analysis is dropped
+                let _ = sender.send(result.clone());
+            });
+            engine
+        });
+        Analysis {
+            board: board.clone(),
+            iterations,
+            latest: None,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    // the position being analysed
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    // takes in the iterations finished since last time, true if there were any
+    pub fn update(&mut self) -> bool {
+        let before = self.latest.as_ref().map(|result| result.depth);
+        while let Ok(result) = self.iterations.try_recv() {
+            self.latest = Some(result);
+        }
+        self.latest.as_ref().map(|result| result.depth) != before
+    }
+
+    // the deepest iteration taken in by update
+    pub fn latest(&self) -> Option<&SearchResult> {
+        self.latest.as_ref()
+    }
+
+    // ends the search and gives the engine back, to analyse the next position with
+    pub fn stop(mut self) -> Engine {
+        self.stop.store(true, Ordering::Relaxed);
+        let engine = self
+            .thread
+            .take()
+            .unwrap()
+            .join()
+            .expect("analysis thread panicked");
+        self.stop.store(false, Ordering::Relaxed);
+        engine
+    }
+}
+
+impl Drop for Analysis {
+    // without waiting for it, the search notices soon enough
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
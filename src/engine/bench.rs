@@ -0,0 +1,70 @@
+use super::{Engine, SearchLimits, SearchResult};
+use crate::board::Board;
+use std::time::{Duration, Instant};
+
+// openings, middlegames and endgames, quiet and sharp, so a change to any part of
+// the move generation, the evaluation or the search shows in the node count
+pub const BENCH_POSITIONS: &[&str] = &[
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "2K5/p7/7P/5pR1/8/5k2/r7/8 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+    "8/3k4/8/8/8/4B3/4KB2/2B5 w - - 0 1",
+];
+
+// deep enough that the search, not setting it up, is what's measured, and still over
+// in seconds
+pub const DEFAULT_BENCH_DEPTH: u32 = 8;
+
+// what searching all the positions came to
+#[derive(Clone, Copy, Debug)]
+pub struct Bench {
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl Bench {
+    pub fn nodes_per_second(&self) -> u64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-9)) as u64
+    }
+}
+
+impl Engine {
+    // searches every bench position to `depth`, each from an empty table, on one thread
+    // and without the book, so the node count is the same every time and only changes
+    // when the search does; the time says how fast it is. `on_position` sees each
+    // position's search once it's done. the threads and the book are put back after,
+    // the table is left empty
+    pub fn bench<F>(&mut self, depth: u32, mut on_position: F) -> Bench
+    where
+        F: FnMut(usize, &SearchResult),
+    {
+        let helpers = self.helpers;
+        let book = self.book.take();
+        self.helpers = 0;
+        let mut bench = Bench {
+            nodes: 0,
+            elapsed: Duration::default(),
+        };
+        for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+            let board = Board::from_fen(fen).expect("bench fen is valid");
+            self.clear_hash();
+            let start = Instant::now();
+            let result = self.search(&board, SearchLimits::depth(depth));
+            bench.elapsed += start.elapsed();
+            bench.nodes += result.nodes;
+            on_position(i, &result);
+        }
+        self.clear_hash();
+        self.helpers = helpers;
+        self.book = book;
+        bench
+    }
+}
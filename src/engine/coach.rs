@@ -0,0 +1,210 @@
+use super::report::{self, Report, REPORT_TIME};
+use super::{Engine, SearchLimits, MATE};
+use crate::board::Board;
+use crate::moves::Move;
+use crate::pgn::PgnGame;
+use crate::status::GameStatus;
+use std::collections::VecDeque;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// how long a hint is thought about
+pub const HINT_TIME: Duration = Duration::from_millis(1000);
+
+// how long each of the two positions around a reviewed move is looked at
+pub const REVIEW_TIME: Duration = Duration::from_millis(250);
+
+// losing more than this many centipawns with a move makes it a blunder
+pub const BLUNDER_THRESHOLD: i32 = 200;
+
+// mates count as this many centipawns when working out what a move lost, so missing
+// one is a blunder without the difference being absurd
+const MATE_WORTH: i32 = 2000;
+
+pub enum Question {
+    // a move for the side to move
+    Hint(Board),
+    // the position a move was played in, and the move
+    Review(Board, Move),
+    // every move of a finished game
+    Report(Box<PgnGame>),
+}
+
+pub enum Answer {
+    // the position asked about, which might not be on the board any more
+    Hint(Board, Option<Move>),
+    Review(Review),
+    Report(Report),
+}
+
+// what a move did to the position, both scores in centipawns from the point of view of
+// the side that played it, see is_mate_score
+#[derive(Clone, Debug)]
+pub struct Review {
+    pub board: Board,
+    pub mv: Move,
+    pub before: i32,
+    pub after: i32,
+    // the move that should have been played instead, when it wasn't this one
+    pub better: Option<Move>,
+}
+
+impl Review {
+    // from what the position was worth to the side to move along with its best move,
+    // and what it was worth to the other side after `mv`
+    pub(super) fn new(
+        board: Board,
+        mv: Move,
+        (before, best): (i32, Option<Move>),
+        reply: i32,
+    ) -> Review {
+        let after = -reply;
+        // a forced move can't lose anything
+        let before = if board.legal_moves().len() > 1 {
+            before
+        } else {
+            after
+        };
+        Review {
+            before,
+            after,
+            better: best.filter(|&best| best != mv),
+            board,
+            mv,
+        }
+    }
+
+    // never negative, a search that found more after the move than before it only
+    // didn't look as deep
+    pub fn loss(&self) -> i32 {
+        let worth = |score: i32| score.clamp(-MATE_WORTH, MATE_WORTH);
+        (worth(self.before) - worth(self.after)).max(0)
+    }
+
+    pub fn is_blunder(&self) -> bool {
+        self.loss() > BLUNDER_THRESHOLD
+    }
+}
+
+// a second opinion for the player, asked on a thread of its own so the game goes on
+// meanwhile: a move to play, whether the last one threw away much of what the position
+// was worth, or how good every move of a game was. questions are answered one after
+// another in the order asked
+#[derive(Default)]
+pub struct Coach {
+    // away on the thread while a question's being answered
+    engine: Option<Engine>,
+    thinking: Option<JoinHandle<(Engine, Answer)>>,
+    questions: VecDeque<Question>,
+}
+
+impl Coach {
+    // an engine with a book to take hints from; the book isn't used for reviews
+    pub fn new(engine: Engine) -> Coach {
+        Coach {
+            engine: Some(engine),
+            thinking: None,
+            questions: VecDeque::new(),
+        }
+    }
+
+    pub fn ask(&mut self, question: Question) {
+        // a newer hint makes an older one waiting its turn pointless
+        if let Question::Hint(_) = question {
+            self.questions
+                .retain(|asked| !matches!(asked, Question::Hint(_)));
+        }
+        self.questions.push_back(question);
+        self.start_next();
+    }
+
+    pub fn is_thinking(&self) -> bool {
+        self.thinking.is_some() || !self.questions.is_empty()
+    }
+
+    // the next answer once it's ready, without waiting for it
+    pub fn answer(&mut self) -> Option<Answer> {
+        if !self.thinking.as_ref()?.is_finished() {
+            return None;
+        }
+        let (engine, answer) = self
+            .thinking
+            .take()
+            .unwrap()
+            .join()
+            .expect("coach thread panicked");
+        self.engine = Some(engine);
+        self.start_next();
+        Some(answer)
+    }
+
+    fn start_next(&mut self) {
+        if self.thinking.is_some() {
+            return;
+        }
+        let mut engine = match self.engine.take() {
+            Some(engine) => engine,
+            None => return,
+        };
+        let question = match self.questions.pop_front() {
+            Some(question) => question,
+            None => return self.engine = Some(engine),
+        };
+        self.thinking = Some(thread::spawn(move || {
+            let answer = match question {
+                Question::Hint(board) => {
+                    let mv = engine.best_move(&board, SearchLimits::time(HINT_TIME));
+                    Answer::Hint(board, mv)
+                }
+                Question::Review(board, mv) => Answer::Review(review(&mut engine, board, mv)),
+                Question::Report(record) => {
+                    let limits = SearchLimits::time(REPORT_TIME);
+                    Answer::Report(report::analyse(&mut engine, &record, &limits, |_| {}))
+                }
+            };
+            (engine, answer)
+        }));
+    }
+}
+
+fn review(engine: &mut Engine, board: Board, mv: Move) -> Review {
+    let limits = SearchLimits::time(REVIEW_TIME);
+    without_book(engine, |engine| {
+        let mut next = board.clone();
+        next.make_move(mv);
+        let (reply, _) = look(engine, &next, &limits);
+        let looked = look(engine, &board, &limits);
+        Review::new(board, mv, looked, reply)
+    })
+}
+
+// the book's moves are all fine, but they'd come back without a score
+pub(super) fn without_book<T>(engine: &mut Engine, work: impl FnOnce(&mut Engine) -> T) -> T {
+    let book = engine.book.take();
+    let answer = work(engine);
+    engine.book = book;
+    answer
+}
+
+// what the position is worth to the side to move, and the best move in it
+pub(super) fn look(
+    engine: &mut Engine,
+    board: &Board,
+    limits: &SearchLimits,
+) -> (i32, Option<Move>) {
+    match board.status() {
+        GameStatus::Checkmate(_) => (-MATE, None),
+        status if status.is_over() => (0, None),
+        _ => {
+            let moves = board.legal_moves();
+            if moves.len() == 1 {
+                // the engine doesn't search a forced move, so the reply is looked at
+                let mut next = board.clone();
+                next.make_move(moves[0]);
+                return (-look(engine, &next, limits).0, Some(moves[0]));
+            }
+            let result = engine.search(board, limits.clone());
+            (result.score, result.best_move)
+        }
+    }
+}
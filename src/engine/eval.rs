@@ -0,0 +1,408 @@
+use crate::bitboard::{self, Bitboard};
+use crate::board::{Board, Color, PieceKind, Pocket, Square};
+use std::ops::{AddAssign, Sub};
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+
+pub fn piece_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => PAWN_VALUE,
+        PieceKind::Knight => KNIGHT_VALUE,
+        PieceKind::Bishop => BISHOP_VALUE,
+        PieceKind::Rook => ROOK_VALUE,
+        PieceKind::Queen => QUEEN_VALUE,
+        PieceKind::King => 0,
+    }
+}
+
+// piece-square tables from white's point of view, written with rank 8 on top so they
+// read like a board; index them with the square mirrored for white
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+// tucked away behind its pawns while there are pieces about
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+// and in the middle of things once they're gone
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+fn table(kind: PieceKind) -> &'static [i32; 64] {
+    match kind {
+        PieceKind::Pawn => &PAWN_TABLE,
+        PieceKind::Knight => &KNIGHT_TABLE,
+        PieceKind::Bishop => &BISHOP_TABLE,
+        PieceKind::Rook => &ROOK_TABLE,
+        PieceKind::Queen => &QUEEN_TABLE,
+        PieceKind::King => &KING_TABLE,
+    }
+}
+
+// a term's worth in the middlegame and in the endgame, the two being blended by how
+// many pieces are left
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub middlegame: i32,
+    pub endgame: i32,
+}
+
+impl Score {
+    const fn new(middlegame: i32, endgame: i32) -> Score {
+        Score {
+            middlegame,
+            endgame,
+        }
+    }
+
+    fn times(self, count: i32) -> Score {
+        Score::new(self.middlegame * count, self.endgame * count)
+    }
+
+    // all middlegame at MAX_PHASE, all endgame at 0
+    pub fn taper(self, phase: i32) -> i32 {
+        (self.middlegame * phase + self.endgame * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        self.middlegame += other.middlegame;
+        self.endgame += other.endgame;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    fn sub(self, other: Score) -> Score {
+        Score::new(
+            self.middlegame - other.middlegame,
+            self.endgame - other.endgame,
+        )
+    }
+}
+
+// the phase with every piece but the pawns and kings still about, see phase_weight
+pub const MAX_PHASE: i32 = 24;
+
+fn phase_weight(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Knight | PieceKind::Bishop => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Queen => 4,
+        PieceKind::Pawn | PieceKind::King => 0,
+    }
+}
+
+const DOUBLED_PAWN: Score = Score::new(-10, -20);
+const ISOLATED_PAWN: Score = Score::new(-10, -15);
+// by how far the pawn has come, counted from its own side
+const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(5, 10),
+    Score::new(10, 20),
+    Score::new(15, 35),
+    Score::new(25, 60),
+    Score::new(40, 90),
+    Score::new(60, 130),
+    Score::new(0, 0),
+];
+const BISHOP_PAIR: Score = Score::new(30, 50);
+// for each of the king's own pawns on the three files around it, one or two ranks ahead
+const PAWN_SHIELD: Score = Score::new(12, 0);
+// for each attack on the squares around the enemy king, weighed by the attacker
+const KING_ATTACK: Score = Score::new(6, 0);
+// for each square a piece can go beyond about as many as it usually has, safe from
+// enemy pawns
+const MOBILITY: [(PieceKind, u32, Score); 4] = [
+    (PieceKind::Knight, 4, Score::new(4, 4)),
+    (PieceKind::Bishop, 7, Score::new(5, 5)),
+    (PieceKind::Rook, 7, Score::new(2, 4)),
+    (PieceKind::Queen, 14, Score::new(1, 2)),
+];
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+
+fn file(file: u8) -> Bitboard {
+    Bitboard(FILE_A << file)
+}
+
+// the files either side, not the file itself
+fn neighbour_files(square: Square) -> Bitboard {
+    let mut files = Bitboard::EMPTY;
+    if square.file() > 0 {
+        files |= file(square.file() - 1);
+    }
+    if square.file() < 7 {
+        files |= file(square.file() + 1);
+    }
+    files
+}
+
+// every rank ahead of the square from the colour's point of view
+fn ranks_ahead(color: Color, square: Square) -> Bitboard {
+    let rank = square.rank() as u32;
+    match color {
+        Color::White if rank == 7 => Bitboard::EMPTY,
+        Color::White => Bitboard(!0 << (8 * (rank + 1))),
+        Color::Black => Bitboard((1 << (8 * rank)) - 1),
+    }
+}
+
+fn relative_rank(color: Color, square: Square) -> usize {
+    match color {
+        Color::White => square.rank() as usize,
+        Color::Black => 7 - square.rank() as usize,
+    }
+}
+
+fn attacks(kind: PieceKind, square: Square, occupied: Bitboard) -> Bitboard {
+    match kind {
+        PieceKind::Knight => bitboard::knight_attacks(square),
+        PieceKind::Bishop => bitboard::bishop_attacks(square, occupied),
+        PieceKind::Rook => bitboard::rook_attacks(square, occupied),
+        PieceKind::Queen => bitboard::queen_attacks(square, occupied),
+        _ => Bitboard::EMPTY,
+    }
+}
+
+// each term from white's point of view, white's side of it less black's
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    // pockets included
+    pub material: Score,
+    pub placement: Score,
+    pub pawns: Score,
+    pub king_safety: Score,
+    pub mobility: Score,
+    pub bishop_pair: Score,
+    pub phase: i32,
+}
+
+impl Breakdown {
+    pub fn terms(&self) -> [(&'static str, Score); 6] {
+        [
+            ("material", self.material),
+            ("placement", self.placement),
+            ("pawns", self.pawns),
+            ("king safety", self.king_safety),
+            ("mobility", self.mobility),
+            ("bishop pair", self.bishop_pair),
+        ]
+    }
+
+    pub fn total(&self) -> Score {
+        let mut total = Score::default();
+        for &(_, score) in self.terms().iter() {
+            total += score;
+        }
+        total
+    }
+
+    // in centipawns from white's point of view
+    pub fn score(&self) -> i32 {
+        self.total().taper(self.phase)
+    }
+}
+
+// one side's share of every term
+fn side(board: &Board, color: Color) -> Breakdown {
+    let mut terms = Breakdown::default();
+    let own = board.occupied_by(color);
+    let occupied = board.occupied();
+    let enemy = color.opposite();
+
+    for square in own.squares() {
+        let kind = board.piece_at(square).unwrap().kind;
+        let value = piece_value(kind);
+        terms.material += Score::new(value, value);
+        // the tables have a8 first, so white reads them upside down
+        let index = match color {
+            Color::White => square.index() ^ 56,
+            Color::Black => square.index(),
+        };
+        terms.placement += match kind {
+            PieceKind::King => Score::new(KING_TABLE[index], KING_ENDGAME_TABLE[index]),
+            _ => Score::new(table(kind)[index], table(kind)[index]),
+        };
+        terms.phase += phase_weight(kind);
+    }
+    // pieces in a crazyhouse pocket count as much as ones on the board, so dropping
+    // them doesn't look like winning material
+    if board.has_pockets() {
+        let pocket = board.pocket(color);
+        for &kind in Pocket::KINDS.iter() {
+            let count = pocket.count(kind) as i32;
+            terms.material += Score::new(piece_value(kind), piece_value(kind)).times(count);
+            terms.phase += phase_weight(kind) * count;
+        }
+    }
+
+    let pawns = board.pieces_of(PieceKind::Pawn, color);
+    let enemy_pawns = board.pieces_of(PieceKind::Pawn, enemy);
+    for square in pawns.squares() {
+        let on_file = file(square.file());
+        let ahead = ranks_ahead(color, square);
+        // the rearmost of doubled pawns is the one that counts against them
+        if !(pawns & on_file & ahead).is_empty() {
+            terms.pawns += DOUBLED_PAWN;
+        }
+        if (pawns & neighbour_files(square)).is_empty() {
+            terms.pawns += ISOLATED_PAWN;
+        }
+        if (enemy_pawns & (on_file | neighbour_files(square)) & ahead).is_empty() {
+            terms.pawns += PASSED_PAWN[relative_rank(color, square)];
+        }
+    }
+
+    if board.pieces_of(PieceKind::Bishop, color).count() >= 2 {
+        terms.bishop_pair = BISHOP_PAIR;
+    }
+
+    let mut enemy_pawn_attacks = Bitboard::EMPTY;
+    for square in enemy_pawns.squares() {
+        enemy_pawn_attacks |= bitboard::pawn_attacks(enemy, square);
+    }
+    let enemy_king = board.pieces_of(PieceKind::King, enemy).first();
+    let enemy_king_zone = enemy_king.map_or(Bitboard::EMPTY, |king| {
+        bitboard::king_attacks(king) | Bitboard::from_square(king)
+    });
+    // the pressure this side puts on the enemy king counts to this side's safety as
+    // much as to the enemy's lack of it
+    let mut attacks_on_king = 0;
+    for &(kind, usual, weight) in MOBILITY.iter() {
+        for square in board.pieces_of(kind, color).squares() {
+            let reach = attacks(kind, square, occupied);
+            let safe = (reach & !own & !enemy_pawn_attacks).count();
+            terms.mobility += weight.times(safe as i32 - usual as i32);
+            let attacker = match kind {
+                PieceKind::Queen => 4,
+                PieceKind::Rook => 2,
+                _ => 1,
+            };
+            attacks_on_king += attacker * (reach & enemy_king_zone).count() as i32;
+        }
+    }
+    terms.king_safety = KING_ATTACK.times(attacks_on_king);
+
+    if let Some(king) = board.pieces_of(PieceKind::King, color).first() {
+        let forward = match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        let mut shield = 0;
+        for files in -1..=1 {
+            for ranks in 1..=2 {
+                let square = king.offset(files, ranks * forward);
+                shield += square.is_some_and(|square| pawns.contains(square)) as i32;
+            }
+        }
+        terms.king_safety += PAWN_SHIELD.times(shield);
+    }
+    terms
+}
+
+// every term for both sides, see evaluate
+pub fn breakdown(board: &Board) -> Breakdown {
+    let (white, black) = (side(board, Color::White), side(board, Color::Black));
+    Breakdown {
+        material: white.material - black.material,
+        placement: white.placement - black.placement,
+        pawns: white.pawns - black.pawns,
+        king_safety: white.king_safety - black.king_safety,
+        mobility: white.mobility - black.mobility,
+        bishop_pair: white.bishop_pair - black.bishop_pair,
+        // promoted pieces can take it past the usual number
+        phase: (white.phase + black.phase).min(MAX_PHASE),
+    }
+}
+
+// material, piece-square bonuses, pawn structure, king safety, mobility and the bishop
+// pair in centipawns, from the side to move's point of view; each term has a middlegame
+// and an endgame value, tapered between by how many pieces are left
+pub fn evaluate(board: &Board) -> i32 {
+    let score = breakdown(board).score();
+    match board.side_to_move() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
@@ -0,0 +1,167 @@
+use super::{SearchLimits, MAX_DEPTH};
+use crate::board::Board;
+use crate::moves::Move;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+// a uci engine such as stockfish running as a child process, spoken to over its
+// stdin and stdout
+pub struct ExternalEngine {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    name: Option<String>,
+    // the names of the options the engine said it supports
+    options: Vec<String>,
+    // whether UCI_Chess960 is on, so castling is written as the king taking its rook
+    chess960: bool,
+}
+
+impl ExternalEngine {
+    // starts the engine and waits for it to finish the uci handshake
+    pub fn spawn(path: &str) -> io::Result<ExternalEngine> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let input = child.stdin.take().unwrap();
+        let output = BufReader::new(child.stdout.take().unwrap());
+        let mut engine = ExternalEngine {
+            child,
+            input,
+            output,
+            name: None,
+            options: Vec::new(),
+            chess960: false,
+        };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if line == "uciok" {
+                break;
+            }
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.to_string());
+            } else if let Some(option) = line.strip_prefix("option name ") {
+                // the name runs up to the type, and can have spaces in it
+                let name = option.split(" type ").next().unwrap_or(option);
+                engine.options.push(name.to_string());
+            }
+        }
+        engine.wait_until_ready()?;
+        Ok(engine)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options
+            .iter()
+            .any(|option| option.eq_ignore_ascii_case(name))
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.wait_until_ready()
+    }
+
+    // stockfish and most engines derived from it take a skill level from 0 to 20
+    pub fn set_skill_level(&mut self, level: u32) -> io::Result<()> {
+        if !self.has_option("Skill Level") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the engine has no Skill Level option",
+            ));
+        }
+        self.set_option("Skill Level", &level.to_string())
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.wait_until_ready()
+    }
+
+    // asks for a move in the position, within the limits; none means the engine had
+    // nothing to play, and a move that isn't legal is an error
+    pub fn best_move(&mut self, board: &Board, limits: &SearchLimits) -> io::Result<Option<Move>> {
+        // engines without the option can still play chess960 positions that castle with
+        // the king on the e file, since either way of writing castling is understood
+        if board.is_chess960() != self.chess960 && self.has_option("UCI_Chess960") {
+            self.set_option("UCI_Chess960", &board.is_chess960().to_string())?;
+            self.chess960 = board.is_chess960();
+        }
+        self.send(&format!("position fen {}", board.to_fen()))?;
+
+        let mut go = String::from("go");
+        if let Some(depth) = limits.depth {
+            go.push_str(&format!(" depth {}", depth));
+        }
+        // it'd spend all of a fixed time, so it gets what the move's budgeted instead
+        // of the most it could take
+        let time = limits.budget.map(|budget| budget.soft).or(limits.time);
+        if let Some(time) = time {
+            go.push_str(&format!(" movetime {}", time.as_millis()));
+        }
+        if let Some(nodes) = limits.nodes {
+            go.push_str(&format!(" nodes {}", nodes));
+        }
+        if go == "go" {
+            // the same as the built in engine with no limits, never go infinite since
+            // nothing would stop it
+            go.push_str(&format!(" depth {}", MAX_DEPTH));
+        }
+        self.send(&go)?;
+
+        loop {
+            let line = self.read_line()?;
+            let mut tokens = line.split_whitespace();
+            if tokens.next() != Some("bestmove") {
+                continue;
+            }
+            return match tokens.next() {
+                None | Some("(none)") | Some("0000") => Ok(None),
+                Some(text) => board.parse_uci_move(text).map(Some).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("the engine played an illegal move {}", text),
+                    )
+                }),
+            };
+        }
+    }
+
+    fn wait_until_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        while self.read_line()? != "readyok" {}
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.input, "{}", command)?;
+        self.input.flush()
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.output.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the engine closed its output",
+            ));
+        }
+        Ok(line.trim().to_string())
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        // give it the chance to exit on its own, it may have died already
+        if self.send("quit").is_err() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,386 @@
+pub mod analysis;
+pub mod bench;
+pub mod coach;
+pub mod eval;
+pub mod external;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+pub mod report;
+mod search;
+pub mod time;
+mod tt;
+
+use crate::board::{Board, Color};
+use crate::book::Book;
+use crate::moves::Move;
+use crate::variant::{self, Standard, Variant};
+#[cfg(feature = "nnue")]
+use nnue::Network;
+use search::Search;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use time::{TimeBudget, TimeManager, DEFAULT_MOVE_OVERHEAD};
+use tt::TranspositionTable;
+
+pub use search::{is_mate_score, MATE};
+pub use tt::{DEFAULT_HASH_MB, MAX_HASH_MB};
+
+// the number of moves until mate, negative when the side to move is getting mated
+pub fn mate_in(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+    let moves = (MATE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+// any limit that's set ends the search when reached, with none set it runs to MAX_DEPTH
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    // the hard limit on how long the move can take
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+    // with a clock running, when to stop short of the hard limit
+    pub budget: Option<TimeBudget>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn time(time: Duration) -> SearchLimits {
+        SearchLimits {
+            time: Some(time),
+            ..SearchLimits::default()
+        }
+    }
+
+    // a share of the time left on the clock, see TimeBudget::new
+    pub fn from_clock(
+        remaining: Duration,
+        increment: Duration,
+        moves_to_go: Option<u32>,
+    ) -> SearchLimits {
+        let budget = TimeBudget::new(remaining, increment, moves_to_go, DEFAULT_MOVE_OVERHEAD);
+        SearchLimits::from_budget(budget)
+    }
+
+    pub fn from_budget(budget: TimeBudget) -> SearchLimits {
+        SearchLimits {
+            time: Some(budget.hard),
+            budget: Some(budget),
+            ..SearchLimits::default()
+        }
+    }
+}
+
+// the parts of the search that can be switched off, to see what each is worth; they're
+// all on by default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchFeatures {
+    // letting the opponent move twice and seeing if the position is still too good
+    pub null_move: bool,
+    // searching the last quiet moves less deeply
+    pub late_move_reductions: bool,
+    // ordering quiet moves that cut off the search at the same ply first
+    pub killers: bool,
+    // ordering the other quiet moves by how often they cut it off anywhere
+    pub history: bool,
+    // searching close to the last iteration's score before the whole range
+    pub aspiration_windows: bool,
+}
+
+impl Default for SearchFeatures {
+    fn default() -> SearchFeatures {
+        SearchFeatures {
+            null_move: true,
+            late_move_reductions: true,
+            killers: true,
+            history: true,
+            aspiration_windows: true,
+        }
+    }
+}
+
+pub const MAX_DEPTH: u32 = 64;
+
+pub const MAX_THREADS: usize = 64;
+
+// the outcome of the deepest completed iteration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    // centipawns from the side to move's point of view, see is_mate_score
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub time_ms: u64,
+    pub pv: Vec<Move>,
+    // how full the transposition table is, in permille
+    pub hashfull: u32,
+}
+
+#[derive(Default)]
+pub struct Engine {
+    stop: Arc<AtomicBool>,
+    // set while searching the position after the opponent's expected reply, on their
+    // time, see ponder_flag
+    ponder: Arc<AtomicBool>,
+    // kept between searches, what was learned about the last position is mostly still
+    // true after the next couple of moves
+    tt: TranspositionTable,
+    book: Option<Book>,
+    // how many plies into the game to keep playing book moves
+    book_plies: u32,
+    // threads searching alongside the main one, one less than the number asked for
+    helpers: usize,
+    features: SearchFeatures,
+    // the rules searched by, standard chess without
+    variant: Option<Box<dyn Variant>>,
+    // evaluates instead of eval::evaluate when there is one
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<Network>>,
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        Engine::default()
+    }
+
+    // setting the flag from another thread ends the current search as if a limit ran
+    // out; it stays set until whoever set it clears it again
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    // while the flag is set the search thinks on the opponent's time and its time and
+    // node limits don't run; it can still run out of depth or find a mate, and the move
+    // it finds then is for whoever asked to hold on to. clearing the flag once the
+    // expected reply is played (a ponder hit) starts the limits from then, so the move
+    // takes no longer than it would have, with what was found meanwhile to go on
+    pub fn ponder_flag(&self) -> Arc<AtomicBool> {
+        self.ponder.clone()
+    }
+
+    // resizes the transposition table to about this many megabytes, which clears it
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        self.tt = TranspositionTable::new(megabytes);
+    }
+
+    // forgets everything learned in earlier searches, for a new game
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+    }
+
+    // searches on this many threads from the next search on, see search_with_info
+    pub fn set_threads(&mut self, threads: usize) {
+        self.helpers = threads.clamp(1, MAX_THREADS) - 1;
+    }
+
+    pub fn features(&self) -> SearchFeatures {
+        self.features
+    }
+
+    pub fn set_features(&mut self, features: SearchFeatures) {
+        self.features = features;
+    }
+
+    // searches with the network's evaluation from the next search on, or with the
+    // classical one again without
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.network = network;
+    }
+
+    // searches by the variant's rules from the next search on, so its wins and
+    // losses end a line like a mate does
+    pub fn set_variant(&mut self, variant: &dyn Variant) {
+        self.variant = variant::by_name(variant.name());
+    }
+
+    // plays moves from the book while it has some, up to `plies` into the game
+    pub fn set_book(&mut self, book: Option<Book>, plies: u32) {
+        self.book = book;
+        self.book_plies = plies;
+    }
+
+    fn book_move(&self, board: &Board) -> Option<Move> {
+        let book = self.book.as_ref()?;
+        let ply = board.fullmove_number().saturating_sub(1) * 2
+            + (board.side_to_move() == Color::Black) as u32;
+        if ply >= self.book_plies {
+            return None;
+        }
+        book.pick(board)
+    }
+
+    pub fn best_move(&mut self, board: &Board, limits: SearchLimits) -> Option<Move> {
+        self.search(board, limits).best_move
+    }
+
+    pub fn search(&mut self, board: &Board, limits: SearchLimits) -> SearchResult {
+        self.search_with_info(board, limits, |_| {})
+    }
+
+    // iterative deepening: each iteration searches one ply deeper, starting with the
+    // previous best move, until a limit runs out; `on_iteration` sees every completed one.
+    // with more than one thread the others search the same position at the same time
+    // (lazy smp), without limits and reporting nothing, only filling the shared table
+    // with what the main search then finds waiting for it; they stop when it does. a
+    // node limit only counts the main search's nodes
+    pub fn search_with_info<F>(
+        &mut self,
+        board: &Board,
+        limits: SearchLimits,
+        mut on_iteration: F,
+    ) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        // the search keeps the game's positions to see repetitions of, copying them into
+        // every node would be a waste
+        let game: Vec<u64> = board.history().collect();
+        let mut root = board.clone();
+        root.clear_history();
+
+        let variant = self.variant.as_deref().unwrap_or(&Standard);
+        let legal = variant.legal_moves(&root);
+        let mut result = SearchResult {
+            best_move: legal.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            time_ms: 0,
+            pv: Vec::new(),
+            hashfull: 0,
+        };
+        if legal.len() <= 1 {
+            return result;
+        }
+        // a book move is reported as a search that didn't need to look any deeper
+        if let Some(mv) = self.book_move(&root) {
+            result.best_move = Some(mv);
+            result.pv = vec![mv];
+            on_iteration(&result);
+            return result;
+        }
+
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        self.tt.new_search();
+        let tt = &self.tt;
+        let pondering = &*self.ponder;
+        #[cfg(feature = "nnue")]
+        let network = self.network.as_deref();
+        let features = self.features;
+        let main_done = AtomicBool::new(false);
+        let unlimited = SearchLimits::default();
+        thread::scope(|scope| {
+            let helpers: Vec<_> = (0..self.helpers)
+                .map(|i| {
+                    let (root, first) = (root.clone(), result.best_move);
+                    let (main_done, unlimited, game) = (&main_done, &unlimited, &game);
+                    scope.spawn(move || {
+                        let mut search = Search::new(
+                            unlimited, features, variant, main_done, pondering, tt, game,
+                        );
+                        #[cfg(feature = "nnue")]
+                        search.use_network(network);
+                        let (mut best, mut score) = (first, None);
+                        // every other helper a ply ahead, so they don't all work on the
+                        // same iteration
+                        for depth in 1 + i as u32 % 2..=max_depth {
+                            let (found, pv) = search.root(&root, depth, best, score);
+                            if search.stopped {
+                                break;
+                            }
+                            best = pv.first().copied().or(best);
+                            score = Some(found);
+                        }
+                        search.nodes
+                    })
+                })
+                .collect();
+
+            let mut search =
+                Search::new(&limits, features, variant, &self.stop, pondering, tt, &game);
+            #[cfg(feature = "nnue")]
+            search.use_network(network);
+            let mut manager = limits.budget.map(TimeManager::new);
+            for depth in 1..=max_depth {
+                let previous = Some(result.score).filter(|_| depth > 1);
+                let (score, pv) = search.root(&root, depth, result.best_move, previous);
+                if search.stopped {
+                    break;
+                }
+                result = SearchResult {
+                    best_move: pv.first().copied().or(result.best_move),
+                    score,
+                    depth,
+                    nodes: search.nodes,
+                    time_ms: search.elapsed_ms(),
+                    pv,
+                    hashfull: tt.hashfull(),
+                };
+                on_iteration(&result);
+                // nothing deeper will find a faster mate
+                if is_mate_score(score) {
+                    break;
+                }
+                // a deeper iteration is only worth starting while there's time for it;
+                // when pondering the time hasn't started yet
+                if let Some(manager) = &mut manager {
+                    manager.iteration(result.best_move, score);
+                    let elapsed = search.limits_elapsed();
+                    if !pondering.load(Ordering::Relaxed) && manager.should_stop(elapsed) {
+                        break;
+                    }
+                }
+            }
+            main_done.store(true, Ordering::Relaxed);
+            let helper_nodes: u64 = helpers
+                .into_iter()
+                .map(|helper| helper.join().expect("search thread panicked"))
+                .sum();
+            result.nodes = search.nodes + helper_nodes;
+            result.time_ms = search.elapsed_ms();
+            result.hashfull = tt.hashfull();
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::KingOfTheHill;
+
+    #[test]
+    fn the_king_walks_up_the_hill_to_win() {
+        let board = Board::from_fen("4k3/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+        let mut engine = Engine::new();
+        engine.set_variant(&KingOfTheHill);
+        let result = engine.search(&board, SearchLimits::depth(3));
+        assert_eq!(mate_in(result.score), Some(1));
+        let to = result.best_move.unwrap().to.to_string();
+        assert!(to == "d4" || to == "e4", "went to {}", to);
+    }
+
+    #[test]
+    fn a_queen_down_going_back_to_a_position_is_a_draw() {
+        let mut board = Board::from_fen("1q5k/8/8/8/8/8/8/6NK w - - 0 1").unwrap();
+        for san in ["Nf3", "Kg8", "Ng1", "Kh8"].iter() {
+            let mv = board.parse_san(san).unwrap();
+            board.make_move(mv);
+        }
+        let result = Engine::new().search(&board, SearchLimits::depth(2));
+        assert_eq!(result.score, 0);
+        assert_eq!(result.best_move.unwrap().to_string(), "g1f3");
+    }
+}
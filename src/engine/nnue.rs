@@ -0,0 +1,248 @@
+use crate::board::{Board, Color, Piece, PieceKind, Square};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+// the network is (768 -> N)x2 -> 1: a piece on a square is one of 768 inputs, seen
+// from each side's point of view, each side gets N hidden values from them (its
+// accumulator), and the output weighs the side to move's then the other's. it's read
+// from the little-endian i16s bullet's simple example saves, in this order:
+//
+//     feature weights  768 x N, the N weights of one input after another
+//     feature biases   N
+//     output weights   2N, the side to move's half first
+//     output bias      1
+//
+// with the hidden size worked out from the file's length, which can have up to 64
+// bytes of padding at the end
+const INPUTS: usize = 768;
+
+// how the trained floats were scaled to integers for the feature and output layers
+const QA: i32 = 255;
+const QB: i32 = 64;
+// from the network's output to centipawns
+const SCALE: i32 = 400;
+
+// what bullet pads a saved network out to a multiple of
+const PADDING: usize = 64;
+
+#[derive(Debug)]
+pub enum NnueError {
+    Io(io::Error),
+    // a length that isn't a whole network of any size, in bytes
+    BadSize(usize),
+}
+
+impl fmt::Display for NnueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NnueError::Io(error) => write!(f, "{}", error),
+            NnueError::BadSize(length) => {
+                write!(
+                    f,
+                    "{} bytes isn't the size of a (768 -> N)x2 -> 1 network",
+                    length
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for NnueError {}
+
+impl From<io::Error> for NnueError {
+    fn from(error: io::Error) -> NnueError {
+        NnueError::Io(error)
+    }
+}
+
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+impl Network {
+    pub fn load(path: &Path) -> Result<Network, NnueError> {
+        Network::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, NnueError> {
+        // 768N + N + 2N + 1 values of two bytes each
+        let values = bytes.len() / 2;
+        let hidden = values.saturating_sub(1) / (INPUTS + 3);
+        let used = 2 * ((INPUTS + 3) * hidden + 1);
+        if hidden == 0 || bytes.len() - used >= PADDING {
+            return Err(NnueError::BadSize(bytes.len()));
+        }
+        let mut numbers = bytes[..used]
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+        let mut take = |count: usize| numbers.by_ref().take(count).collect::<Vec<_>>();
+        let feature_weights = take(INPUTS * hidden);
+        let feature_biases = take(hidden);
+        let output_weights = take(2 * hidden);
+        let output_bias = take(1)[0];
+        Ok(Network {
+            hidden,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    // in centipawns from the side to move's point of view, like eval::evaluate; worked
+    // out from scratch, the search keeps its accumulators up to date instead
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        let mut accumulator = Accumulator::new(self.hidden);
+        self.refresh(&mut accumulator, board);
+        self.output(&accumulator, board.side_to_move())
+    }
+
+    fn refresh(&self, accumulator: &mut Accumulator, board: &Board) {
+        accumulator.white.copy_from_slice(&self.feature_biases);
+        accumulator.black.copy_from_slice(&self.feature_biases);
+        for (square, piece) in board.pieces() {
+            self.change(accumulator, piece, square, true);
+        }
+    }
+
+    // a piece put on the square, or taken off it
+    fn change(&self, accumulator: &mut Accumulator, piece: Piece, square: Square, added: bool) {
+        let mut sides = [
+            (Color::White, &mut accumulator.white),
+            (Color::Black, &mut accumulator.black),
+        ];
+        for (perspective, values) in sides.iter_mut() {
+            let weights = self.weights(feature(*perspective, piece, square));
+            for (value, &weight) in values.iter_mut().zip(weights) {
+                *value = if added {
+                    value.wrapping_add(weight)
+                } else {
+                    value.wrapping_sub(weight)
+                };
+            }
+        }
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    fn output(&self, accumulator: &Accumulator, side_to_move: Color) -> i32 {
+        let (us, them) = match side_to_move {
+            Color::White => (&accumulator.white, &accumulator.black),
+            Color::Black => (&accumulator.black, &accumulator.white),
+        };
+        // clipped relu, between 0 and QA
+        let activate = |values: &[i16], weights: &[i16]| -> i32 {
+            values
+                .iter()
+                .zip(weights)
+                .map(|(&value, &weight)| (value as i32).clamp(0, QA) * weight as i32)
+                .sum()
+        };
+        let (ours, theirs) = self.output_weights.split_at(self.hidden);
+        let sum = activate(us, ours) + activate(them, theirs) + self.output_bias as i32;
+        sum * SCALE / (QA * QB)
+    }
+}
+
+// the input for a piece on a square from one side's point of view: their own pieces
+// first, with the board turned round for black so each sees it from their side
+fn feature(perspective: Color, piece: Piece, square: Square) -> usize {
+    let theirs = piece.color != perspective;
+    let index = match perspective {
+        Color::White => square.index(),
+        Color::Black => square.index() ^ 56,
+    };
+    let kind = match piece.kind {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => 5,
+    };
+    theirs as usize * 384 + kind * 64 + index
+}
+
+// each side's hidden values for a position, before they're clipped
+#[derive(Clone)]
+struct Accumulator {
+    white: Vec<i16>,
+    black: Vec<i16>,
+}
+
+impl Accumulator {
+    fn new(hidden: usize) -> Accumulator {
+        Accumulator {
+            white: vec![0; hidden],
+            black: vec![0; hidden],
+        }
+    }
+}
+
+const KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+// an accumulator for every ply of the search. making a move works out the child's
+// from its parent's by what the move changed; unmaking it is going back to the
+// parent's, which is still there a ply up
+pub(super) struct Accumulators<'a> {
+    network: &'a Network,
+    stack: Vec<Accumulator>,
+}
+
+impl<'a> Accumulators<'a> {
+    pub fn new(network: &'a Network, plies: usize) -> Accumulators<'a> {
+        Accumulators {
+            network,
+            stack: vec![Accumulator::new(network.hidden); plies + 1],
+        }
+    }
+
+    // from scratch, for the root
+    pub fn refresh(&mut self, ply: usize, board: &Board) {
+        self.network.refresh(&mut self.stack[ply], board);
+    }
+
+    // the accumulator a ply further on, for `child` played from `parent`. only the
+    // pieces that came or went are looked at, which covers captures, castling, en
+    // passant, promotions and drops alike
+    pub fn update(&mut self, ply: usize, parent: &Board, child: &Board) {
+        let (before, after) = self.stack.split_at_mut(ply + 1);
+        let (from, to) = (&before[ply], &mut after[0]);
+        to.white.copy_from_slice(&from.white);
+        to.black.copy_from_slice(&from.black);
+        for &color in &[Color::White, Color::Black] {
+            for &kind in &KINDS {
+                let (was, is) = (parent.pieces_of(kind, color), child.pieces_of(kind, color));
+                let piece = Piece::new(kind, color);
+                for square in (was & !is).squares() {
+                    self.network.change(to, piece, square, false);
+                }
+                for square in (is & !was).squares() {
+                    self.network.change(to, piece, square, true);
+                }
+            }
+        }
+    }
+
+    pub fn evaluate(&self, ply: usize, side_to_move: Color) -> i32 {
+        self.network.output(&self.stack[ply], side_to_move)
+    }
+}
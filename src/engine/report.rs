@@ -0,0 +1,195 @@
+use super::coach::{look, without_book, Review, BLUNDER_THRESHOLD};
+use super::{mate_in, Engine, SearchLimits};
+use crate::board::Color;
+use crate::pgn::PgnGame;
+use std::fmt;
+use std::time::Duration;
+
+// how long each position of a finished game is looked at
+pub const REPORT_TIME: Duration = Duration::from_millis(200);
+
+// how good a move was, by how much of what the position was worth it gave away
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Classification {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Classification {
+    pub fn of(review: &Review) -> Classification {
+        // the engine would have played it too
+        if review.better.is_none() {
+            return Classification::Best;
+        }
+        match review.loss() {
+            0..=10 => Classification::Best,
+            11..=50 => Classification::Good,
+            51..=100 => Classification::Inaccuracy,
+            loss if loss <= BLUNDER_THRESHOLD => Classification::Mistake,
+            _ => Classification::Blunder,
+        }
+    }
+
+    // ?!, ? and ?? as numeric annotation glyphs
+    pub fn nag(self) -> Option<u8> {
+        match self {
+            Classification::Inaccuracy => Some(6),
+            Classification::Mistake => Some(2),
+            Classification::Blunder => Some(4),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Classification::Best => "Best",
+            Classification::Good => "Good",
+            Classification::Inaccuracy => "Inaccuracy",
+            Classification::Mistake => "Mistake",
+            Classification::Blunder => "Blunder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// every move of a game looked over, in the order they were played
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub moves: Vec<(Review, Classification)>,
+}
+
+impl Report {
+    // whether the report is about the game as it was recorded, and not one that's
+    // had moves taken back since it was asked for
+    pub fn is_for(&self, record: &PgnGame) -> bool {
+        self.moves.len() == record.moves().len()
+            && self
+                .moves
+                .iter()
+                .zip(record.moves())
+                .all(|((review, _), played)| review.mv == played.mv)
+    }
+
+    fn moves_by(&self, color: Color) -> impl Iterator<Item = &(Review, Classification)> {
+        self.moves
+            .iter()
+            .filter(move |(review, _)| review.board.side_to_move() == color)
+    }
+
+    pub fn count(&self, color: Color, classification: Classification) -> usize {
+        self.moves_by(color)
+            .filter(|(_, class)| *class == classification)
+            .count()
+    }
+
+    // centipawns given away a move on average
+    pub fn average_loss(&self, color: Color) -> f64 {
+        let losses: Vec<i32> = self
+            .moves_by(color)
+            .map(|(review, _)| review.loss())
+            .collect();
+        losses.iter().sum::<i32>() as f64 / losses.len().max(1) as f64
+    }
+
+    // from 0 to 100, by how much each move lowered the chance of winning, like lichess
+    // works it out
+    pub fn accuracy(&self, color: Color) -> f64 {
+        let winning = |score: i32| {
+            let score = score.clamp(-1000, 1000) as f64;
+            50.0 + 50.0 * (2.0 / (1.0 + (-0.003_682_08 * score).exp()) - 1.0)
+        };
+        let accuracies: Vec<f64> = self
+            .moves_by(color)
+            .map(|(review, _)| {
+                let lost = (winning(review.before) - winning(review.after)).max(0.0);
+                (103.1668 * (-0.04354 * lost).exp() - 3.1669).clamp(0.0, 100.0)
+            })
+            .collect();
+        if accuracies.is_empty() {
+            return 100.0;
+        }
+        accuracies.iter().sum::<f64>() / accuracies.len() as f64
+    }
+
+    // a line about how one side played
+    pub fn summary(&self, color: Color) -> String {
+        let counted = |classification, one, more| match self.count(color, classification) {
+            1 => format!("1 {}", one),
+            count => format!("{} {}", count, more),
+        };
+        format!(
+            "{:?}: {:.0}% accuracy, {:.0} centipawns lost a move, {}, {} and {}",
+            color,
+            self.accuracy(color),
+            self.average_loss(color),
+            counted(Classification::Inaccuracy, "inaccuracy", "inaccuracies"),
+            counted(Classification::Mistake, "mistake", "mistakes"),
+            counted(Classification::Blunder, "blunder", "blunders")
+        )
+    }
+
+    // gives each move of the record the glyph for how it was classified and an %eval
+    // comment with white's score after it, saying what was best after a bad one; the
+    // comments replace whatever the moves had
+    pub fn annotate(&self, record: &mut PgnGame) {
+        for (played, (review, class)) in record.moves_mut().iter_mut().zip(&self.moves) {
+            let white = match review.board.side_to_move() {
+                Color::White => review.after,
+                Color::Black => -review.after,
+            };
+            // nothing to say about a position where the game's over
+            let mut comment = match mate_in(white) {
+                Some(0) => String::new(),
+                Some(moves) => format!("[%eval #{}]", moves),
+                None => format!("[%eval {:.2}]", white as f32 / 100.0),
+            };
+            if let (Some(nag), Some(best)) = (class.nag(), review.better) {
+                played.nags.retain(|&old| !matches!(old, 1..=6));
+                played.nags.push(nag);
+                if !comment.is_empty() {
+                    comment.push(' ');
+                }
+                comment.push_str(&format!(
+                    "{}, {} was best",
+                    class,
+                    best.to_san(&review.board)
+                ));
+            }
+            played.comment = Some(comment).filter(|comment| !comment.is_empty());
+        }
+    }
+}
+
+// goes over every position of the game; `on_move` hears how many moves have been
+// looked at so far
+pub fn analyse<F>(
+    engine: &mut Engine,
+    record: &PgnGame,
+    limits: &SearchLimits,
+    mut on_move: F,
+) -> Report
+where
+    F: FnMut(usize),
+{
+    without_book(engine, |engine| {
+        let mut board = record.start().clone();
+        let mut looked = look(engine, &board, limits);
+        let mut moves = Vec::new();
+        for (i, played) in record.moves().iter().enumerate() {
+            let before = board.clone();
+            board.make_move(played.mv);
+            let next = look(engine, &board, limits);
+            let review = Review::new(before, played.mv, looked, next.0);
+            let class = Classification::of(&review);
+            moves.push((review, class));
+            looked = next;
+            on_move(i + 1);
+        }
+        Report { moves }
+    })
+}
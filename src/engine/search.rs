@@ -0,0 +1,460 @@
+use super::eval::{evaluate, piece_value};
+#[cfg(feature = "nnue")]
+use super::nnue::{Accumulators, Network};
+use super::tt::{Bound, TranspositionTable};
+use super::{SearchFeatures, SearchLimits};
+use crate::board::{Board, PieceKind};
+use crate::moves::Move;
+use crate::variant::Variant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub const INFINITY: i32 = 1_000_000;
+// scores above this are mates, MATE - n meaning mate in n plies
+pub const MATE: i32 = 100_000;
+pub const MAX_PLY: usize = 128;
+
+// how far either side of the last iteration's score the next one looks first
+const ASPIRATION_WINDOW: i32 = 50;
+
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() > MATE - MAX_PLY as i32
+}
+
+pub(super) struct Search<'a> {
+    limits: &'a SearchLimits,
+    features: SearchFeatures,
+    variant: &'a dyn Variant,
+    stop: &'a AtomicBool,
+    // while it's set the limits don't run, they count from when it's cleared
+    pondering: &'a AtomicBool,
+    pub tt: &'a TranspositionTable,
+    start: Instant,
+    // where the limits count from: the start, or the end of pondering
+    limits_start: (Instant, u64),
+    pub nodes: u64,
+    pub stopped: bool,
+    // the network's accumulators for each ply, when it's evaluating
+    #[cfg(feature = "nnue")]
+    nnue: Option<Accumulators<'a>>,
+    // the last two quiet moves at each ply that made the opponent's move look bad,
+    // which will often do the same after one of the opponent's other moves
+    killers: Vec<[Option<Move>; 2]>,
+    // how much each quiet move from one square to another has been cutting the search
+    // off anywhere in the tree, by the squares' indices
+    history: Vec<[i32; 64]>,
+    // the keys of the positions before the one being searched, the game's and then
+    // the line's, so going back to one of them is seen as the draw it can be made
+    path: Vec<u64>,
+    // how many of them are the game's
+    game: usize,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(
+        limits: &'a SearchLimits,
+        features: SearchFeatures,
+        variant: &'a dyn Variant,
+        stop: &'a AtomicBool,
+        pondering: &'a AtomicBool,
+        tt: &'a TranspositionTable,
+        game: &[u64],
+    ) -> Search<'a> {
+        Search {
+            limits,
+            features,
+            variant,
+            stop,
+            pondering,
+            tt,
+            start: Instant::now(),
+            limits_start: (Instant::now(), 0),
+            nodes: 0,
+            stopped: false,
+            #[cfg(feature = "nnue")]
+            nnue: None,
+            killers: vec![[None; 2]; MAX_PLY],
+            history: vec![[0; 64]; 64],
+            path: game.to_vec(),
+            game: game.len(),
+        }
+    }
+
+    #[cfg(feature = "nnue")]
+    pub fn use_network(&mut self, network: Option<&'a Network>) {
+        self.nnue = network.map(|network| Accumulators::new(network, MAX_PLY + 1));
+    }
+
+    // the network's evaluation when it has one, kept up to date ply by ply
+    #[cfg_attr(not(feature = "nnue"), allow(unused_variables))]
+    fn evaluate(&self, board: &Board, ply: usize) -> i32 {
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &self.nnue {
+            return nnue.evaluate(ply, board.side_to_move());
+        }
+        evaluate(board)
+    }
+
+    // `child` is about to be searched a ply below `board`
+    #[cfg_attr(not(feature = "nnue"), allow(unused_variables))]
+    fn descend(&mut self, board: &Board, child: &Board, ply: usize) {
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            nnue.update(ply, board, child);
+        }
+    }
+
+    // a game the variant has already decided, scored like a mate so the winner heads
+    // for the quickest one and the loser puts it off
+    fn variant_result(&self, board: &Board, ply: usize) -> Option<i32> {
+        let winner = self.variant.winner(board)?.winner()?;
+        Some(if winner == board.side_to_move() {
+            MATE - ply as i32
+        } else {
+            -MATE + ply as i32
+        })
+    }
+
+    // reached already since the last capture or pawn move; once is enough, whoever
+    // could go back to it once can do it again
+    fn is_repetition(&self, key: u64, halfmove_clock: u32) -> bool {
+        self.path
+            .iter()
+            .rev()
+            .take(halfmove_clock as usize)
+            .any(|&earlier| earlier == key)
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    // how long the limits have been running for
+    pub fn limits_elapsed(&self) -> Duration {
+        self.limits_start.0.elapsed()
+    }
+
+    // checking the clock on every node would be slow, so only look every so often
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.nodes.is_multiple_of(1024) {
+            if self.pondering.load(Ordering::Relaxed) {
+                self.limits_start = (Instant::now(), self.nodes);
+            }
+            let (since, nodes_before) = self.limits_start;
+            let out_of_time = self.limits.time.is_some_and(|time| since.elapsed() >= time);
+            let out_of_nodes = self
+                .limits
+                .nodes
+                .is_some_and(|nodes| self.nodes - nodes_before >= nodes);
+            self.stopped = out_of_time || out_of_nodes || self.stop.load(Ordering::Relaxed);
+        }
+        self.stopped
+    }
+
+    // searches the root moves, trying `first` before the rest, and returns the score
+    // along with the principal variation. with the previous iteration's score it first
+    // looks only close to it, widening the window each time the score falls outside
+    pub fn root(
+        &mut self,
+        board: &Board,
+        depth: u32,
+        first: Option<Move>,
+        previous: Option<i32>,
+    ) -> (i32, Vec<Move>) {
+        let mut pv = Vec::new();
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            nnue.refresh(0, board);
+        }
+        let previous = previous.filter(|&score| {
+            self.features.aspiration_windows && depth >= 4 && !is_mate_score(score)
+        });
+        let mut window = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match previous {
+            Some(score) => (score - window, score + window),
+            None => (-INFINITY, INFINITY),
+        };
+        loop {
+            let score = self.negamax(board, depth, 0, alpha, beta, first, true, &mut pv);
+            if self.stopped {
+                return (score, pv);
+            }
+            if score <= alpha && alpha > -INFINITY {
+                window *= 2;
+                alpha = (score - window).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                window *= 2;
+                beta = (score + window).min(INFINITY);
+            } else {
+                return (score, pv);
+            }
+        }
+    }
+
+    // `null_allowed` is false straight after a null move, two in a row would only
+    // hand the move back
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        board: &Board,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        first: Option<Move>,
+        null_allowed: bool,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        let key = board.position_key();
+        self.path.truncate(self.game + ply);
+        if ply > 0 && self.is_repetition(key, board.halfmove_clock()) {
+            return 0;
+        }
+        self.path.push(key);
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+        self.nodes += 1;
+        if let Some(score) = self.variant_result(board, ply) {
+            return score;
+        }
+        if ply > 0 && (board.halfmove_clock() >= 100 || board.is_insufficient_material()) {
+            return 0;
+        }
+
+        let entry = self.tt.probe(key);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = entry.score(ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                pv.extend(entry.best_move);
+                return score;
+            }
+        }
+
+        let color = board.side_to_move();
+        let in_check = board.is_in_check(color);
+        let mut line = Vec::new();
+
+        // passing and still being too good for the opponent means a real move would
+        // be too; not in check, where passing isn't possible, nor with only pawns,
+        // where zugzwang makes passing the best move there is
+        let has_pieces =
+            board.occupied_by(color).count() > board.pieces_of(PieceKind::Pawn, color).count() + 1;
+        if self.features.null_move
+            && null_allowed
+            && ply > 0
+            && depth >= 3
+            && !in_check
+            && has_pieces
+            && !is_mate_score(beta)
+        {
+            let reduction = if depth > 6 { 3 } else { 2 };
+            let mut child = board.clone();
+            child.set_en_passant(None);
+            child.set_side_to_move(color.opposite());
+            self.descend(board, &child, ply);
+            let score = -self.negamax(
+                &child,
+                depth - 1 - reduction,
+                ply + 1,
+                -beta,
+                -beta + 1,
+                None,
+                false,
+                &mut line,
+            );
+            if self.should_stop() {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+        }
+
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+        self.order_moves(
+            board,
+            &mut moves,
+            first.or_else(|| entry.and_then(|entry| entry.best_move)),
+            ply,
+        );
+
+        let original_alpha = alpha;
+        let mut best_move = None;
+        for (searched, mv) in moves.into_iter().enumerate() {
+            let quiet = !board.is_capture(mv) && mv.promotion.is_none();
+            let mut child = board.clone();
+            child.apply_move(mv);
+            self.descend(board, &child, ply);
+            // the late quiet moves of a well ordered list rarely turn out best, so
+            // they're looked at less deeply unless that makes them look good
+            let reduction = if self.features.late_move_reductions
+                && searched >= 3
+                && depth >= 3
+                && quiet
+                && !in_check
+                && !child.is_in_check(child.side_to_move())
+                && !self.killers[ply].contains(&Some(mv))
+            {
+                if searched >= 6 && depth >= 6 {
+                    2
+                } else {
+                    1
+                }
+            } else {
+                0
+            };
+            let mut score = alpha + 1;
+            if reduction > 0 {
+                score = -self.negamax(
+                    &child,
+                    depth - 1 - reduction,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    None,
+                    true,
+                    &mut line,
+                );
+            }
+            if score > alpha {
+                score = -self.negamax(
+                    &child,
+                    depth - 1,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                    None,
+                    true,
+                    &mut line,
+                );
+            }
+            if self.should_stop() {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+                if score >= beta {
+                    if quiet {
+                        self.remember_cutoff(mv, depth, ply);
+                    }
+                    break;
+                }
+            }
+        }
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(key, best_move, alpha, depth, bound, ply);
+        alpha
+    }
+
+    fn remember_cutoff(&mut self, mv: Move, depth: u32, ply: usize) {
+        if self.features.killers && self.killers[ply][0] != Some(mv) {
+            self.killers[ply] = [Some(mv), self.killers[ply][0]];
+        }
+        if self.features.history {
+            let history = &mut self.history[mv.from.index()][mv.to.index()];
+            *history += (depth * depth) as i32;
+            // kept below where killers are ordered
+            if *history > 8_000 {
+                for row in self.history.iter_mut() {
+                    for value in row.iter_mut() {
+                        *value /= 2;
+                    }
+                }
+            }
+        }
+    }
+
+    // only captures and promotions, so the evaluation isn't taken in the middle of an
+    // exchange; standing pat lets the side to move decline them
+    fn quiescence(&mut self, board: &Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if let Some(score) = self.variant_result(board, ply) {
+            return score;
+        }
+        let stand_pat = self.evaluate(board, ply);
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|&mv| board.is_capture(mv) || mv.promotion == Some(PieceKind::Queen))
+            .collect();
+        self.order_moves(board, &mut moves, None, MAX_PLY);
+
+        for mv in moves {
+            let mut child = board.clone();
+            child.apply_move(mv);
+            self.descend(board, &child, ply);
+            let score = -self.quiescence(&child, ply + 1, -beta, -alpha);
+            if self.should_stop() {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+        alpha
+    }
+
+    // the best move from the last iteration first, then captures with the most
+    // valuable victim and least valuable attacker, then the killers at this ply,
+    // promotions, and the other quiet moves by their history
+    fn order_moves(&self, board: &Board, moves: &mut [Move], first: Option<Move>, ply: usize) {
+        let killers = self.killers.get(ply).copied().unwrap_or([None; 2]);
+        moves.sort_by_cached_key(|&mv| {
+            if Some(mv) == first {
+                return i32::MIN;
+            }
+            let mut score = 0;
+            if board.is_capture(mv) {
+                let victim = board
+                    .piece_at(mv.to)
+                    .map_or(PieceKind::Pawn, |piece| piece.kind);
+                let attacker = board
+                    .piece_at(mv.from)
+                    .map_or(PieceKind::Pawn, |piece| piece.kind);
+                score -= 10 * piece_value(victim) - piece_value(attacker) + 10_000;
+            } else if killers[0] == Some(mv) {
+                score -= 9_002;
+            } else if killers[1] == Some(mv) {
+                score -= 9_001;
+            } else if mv.promotion.is_none() {
+                score -= self.history[mv.from.index()][mv.to.index()];
+            }
+            if let Some(kind) = mv.promotion {
+                score -= piece_value(kind);
+            }
+            score
+        });
+    }
+}
@@ -0,0 +1,99 @@
+use crate::moves::Move;
+use std::time::Duration;
+
+// kept on the clock for the time it takes the move to get from the search to the
+// board, so a move never arrives after the flag has fallen
+pub const DEFAULT_MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+// the moves a game is guessed to have left when the clock doesn't say
+const MOVES_LEFT: u32 = 25;
+// how far past the soft limit the hard one is, for a move that needs it
+const HARD_FACTOR: u32 = 4;
+// a score this much worse than the last iteration's is worth thinking longer about
+const SCORE_DROP: i32 = 30;
+
+// how long a move with a clock running may take: the search stops at the soft limit
+// once an iteration's done, stretched while the best move keeps changing, and never
+// goes past the hard one however far it's got
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeBudget {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+impl TimeBudget {
+    // the time left spread over the moves still to play, plus most of the increment.
+    // a move can use at most half of what's left, or nearly all of it right before
+    // the time control, and the overhead is always kept back
+    pub fn new(
+        remaining: Duration,
+        increment: Duration,
+        moves_to_go: Option<u32>,
+        overhead: Duration,
+    ) -> TimeBudget {
+        let usable = remaining
+            .saturating_sub(overhead)
+            .max(Duration::from_millis(1));
+        let moves = moves_to_go.unwrap_or(MOVES_LEFT).clamp(1, 50);
+        let cap = if moves == 1 {
+            usable * 9 / 10
+        } else {
+            usable / 2
+        };
+        let soft = usable / moves + increment * 3 / 4;
+        let hard = (soft * HARD_FACTOR).min(cap);
+        TimeBudget {
+            soft: soft.min(hard),
+            hard,
+        }
+    }
+}
+
+// follows the iterations of one search to decide when it's seen enough: a best move
+// that hasn't changed for a while is played early, one that keeps changing or a score
+// that's dropping gets more time, up to the hard limit
+pub(super) struct TimeManager {
+    budget: TimeBudget,
+    best_move: Option<Move>,
+    score: Option<i32>,
+    // how often the best move changed lately, halving with each iteration
+    changes: f32,
+    dropping: bool,
+}
+
+impl TimeManager {
+    pub fn new(budget: TimeBudget) -> TimeManager {
+        TimeManager {
+            budget,
+            best_move: None,
+            score: None,
+            changes: 0.0,
+            dropping: false,
+        }
+    }
+
+    // after each completed iteration
+    pub fn iteration(&mut self, best_move: Option<Move>, score: i32) {
+        let changed = self.best_move.is_some() && best_move != self.best_move;
+        self.changes = self.changes / 2.0 + if changed { 1.0 } else { 0.0 };
+        self.dropping = self
+            .score
+            .is_some_and(|previous| score < previous - SCORE_DROP);
+        self.best_move = best_move;
+        self.score = Some(score);
+    }
+
+    // the soft limit for the search so far, less when it's settled and more when it
+    // hasn't
+    pub fn soft_limit(&self) -> Duration {
+        let mut factor = 0.7 + self.changes * 0.6;
+        if self.dropping {
+            factor *= 1.25;
+        }
+        self.budget.soft.mul_f32(factor).min(self.budget.hard)
+    }
+
+    pub fn should_stop(&self, elapsed: Duration) -> bool {
+        elapsed >= self.soft_limit()
+    }
+}
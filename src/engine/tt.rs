@@ -0,0 +1,157 @@
+use super::search::is_mate_score;
+use crate::moves::Move;
+use std::mem;
+use std::sync::Mutex;
+
+// what a stored score says about the position's real score
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the search failed high, the real score is at least this
+    Lower,
+    // nothing beat alpha, the real score is at most this
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    key: u64,
+    pub best_move: Option<Move>,
+    score: i32,
+    pub depth: u32,
+    pub bound: Bound,
+    // the search that stored it, old entries are the first to be replaced
+    generation: u8,
+}
+
+impl Entry {
+    // mate scores are stored as the distance from this node rather than from the root,
+    // so they stay right when the position is reached at a different ply
+    pub fn score(&self, ply: usize) -> i32 {
+        if !is_mate_score(self.score) {
+            self.score
+        } else if self.score > 0 {
+            self.score - ply as i32
+        } else {
+            self.score + ply as i32
+        }
+    }
+}
+
+const BUCKET_SIZE: usize = 4;
+
+type Bucket = [Option<Entry>; BUCKET_SIZE];
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_HASH_MB: usize = 1024;
+
+// positions seen by the search and what it found out about them, so a position reached
+// again by a different move order doesn't have to be searched twice; a key picks a
+// bucket and the entry least worth keeping in it gets replaced. each bucket has its
+// own lock so the threads of a search can share the table, with so many of them
+// that two threads hardly ever want the same one
+pub struct TranspositionTable {
+    buckets: Vec<Mutex<Bucket>>,
+    generation: u8,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let megabytes = megabytes.clamp(1, MAX_HASH_MB);
+        let len = (megabytes * 1024 * 1024 / mem::size_of::<Mutex<Bucket>>()).max(1);
+        TranspositionTable {
+            buckets: (0..len).map(|_| Mutex::new([None; BUCKET_SIZE])).collect(),
+            generation: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            *bucket.get_mut().unwrap() = [None; BUCKET_SIZE];
+        }
+        self.generation = 0;
+    }
+
+    // called before each search so what it stores can be told apart from older entries
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn bucket(&self, key: u64) -> usize {
+        (key % self.buckets.len() as u64) as usize
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.buckets[self.bucket(key)]
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .find(|entry| entry.key == key)
+            .copied()
+    }
+
+    pub fn store(
+        &self,
+        key: u64,
+        best_move: Option<Move>,
+        score: i32,
+        depth: u32,
+        bound: Bound,
+        ply: usize,
+    ) {
+        let generation = self.generation;
+        let index = self.bucket(key);
+        let mut bucket = self.buckets[index].lock().unwrap();
+        // the same position, then an empty slot, then the shallowest entry with every
+        // search since it was stored counting as some depth lost
+        let worth = |entry: &Option<Entry>| match entry {
+            Some(entry) if entry.key == key => i32::MIN,
+            Some(entry) => {
+                let age = generation.wrapping_sub(entry.generation) as i32;
+                entry.depth as i32 - 8 * age
+            }
+            None => i32::MIN + 1,
+        };
+        let slot = (0..BUCKET_SIZE).min_by_key(|&i| worth(&bucket[i])).unwrap();
+
+        // a search that found no best move shouldn't wipe out one found before
+        let best_move = match bucket[slot] {
+            Some(old) if old.key == key && best_move.is_none() => old.best_move,
+            _ => best_move,
+        };
+        let score = if !is_mate_score(score) {
+            score
+        } else if score > 0 {
+            score + ply as i32
+        } else {
+            score - ply as i32
+        };
+        bucket[slot] = Some(Entry {
+            key,
+            best_move,
+            score,
+            depth,
+            bound,
+            generation,
+        });
+    }
+
+    // how full the table is in permille, as uci's hashfull, from a sample of it
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.buckets.len().min(1000 / BUCKET_SIZE);
+        let used = self.buckets[..sample]
+            .iter()
+            .flat_map(|bucket| *bucket.lock().unwrap())
+            .flatten()
+            .filter(|entry| entry.generation == self.generation)
+            .count();
+        (used * 1000 / (sample * BUCKET_SIZE)) as u32
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> TranspositionTable {
+        TranspositionTable::new(DEFAULT_HASH_MB)
+    }
+}
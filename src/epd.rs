@@ -0,0 +1,170 @@
+use crate::board::Board;
+use crate::fen::FenError;
+use crate::moves::Move;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EpdError {
+    // the line number, counting from 1, and what was wrong with its position
+    InvalidFen(usize, FenError),
+    // a bm or am move that isn't legal in the position
+    IllegalMove(usize, String),
+    UnclosedString(usize),
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpdError::InvalidFen(line, error) => write!(f, "line {}: {}", line, error),
+            EpdError::IllegalMove(line, san) => {
+                write!(f, "line {}: illegal move '{}'", line, san)
+            }
+            EpdError::UnclosedString(line) => write!(f, "line {}: a string is never closed", line),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+// a position from an epd file: the first four fields of a fen, then operations like
+// bm Qg6; id "WAC.001"; each an opcode followed by its operands, up to a semicolon
+#[derive(Clone, Debug)]
+pub struct Epd {
+    pub board: Board,
+    pub operations: Vec<(String, Vec<String>)>,
+    // bm, the moves a test suite expects to be found
+    pub best_moves: Vec<Move>,
+    // am, the moves it expects to be avoided
+    pub avoid_moves: Vec<Move>,
+}
+
+impl Epd {
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id")?.first().map(String::as_str)
+    }
+
+    // whether a move passes the test: one of the best moves if there are any, and
+    // none of the ones to avoid
+    pub fn is_solved_by(&self, mv: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&mv))
+            && !self.avoid_moves.contains(&mv)
+    }
+}
+
+// every position in the text, one a line; blank lines and ones starting with # are
+// skipped
+pub fn parse_epd(text: &str) -> Result<Vec<Epd>, EpdError> {
+    let mut positions = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        positions.push(parse_line(line, number)?);
+    }
+    Ok(positions)
+}
+
+fn parse_line(line: &str, number: usize) -> Result<Epd, EpdError> {
+    let mut fields = Vec::new();
+    let mut rest = line;
+    for _ in 0..4 {
+        let text = rest.trim_start();
+        let end = text.find(char::is_whitespace).unwrap_or(text.len());
+        fields.push(&text[..end]);
+        rest = &text[end..];
+    }
+    let position = fields.join(" ");
+    let mut board =
+        Board::from_fen(&position).map_err(|error| EpdError::InvalidFen(number, error))?;
+    let operations = parse_operations(rest, number)?;
+
+    // the move counters are operations in epd, when they're there at all
+    let counter = |opcode: &str| {
+        operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .and_then(|(_, operands)| operands.first()?.parse().ok())
+    };
+    if let Some(clock) = counter("hmvc") {
+        board.set_halfmove_clock(clock);
+    }
+    if let Some(fullmove) = counter("fmvn").filter(|&fullmove| fullmove > 0) {
+        board.set_fullmove_number(fullmove);
+    }
+
+    let moves = |opcode: &str| -> Result<Vec<Move>, EpdError> {
+        let mut moves = Vec::new();
+        for (_, operands) in operations.iter().filter(|(name, _)| name == opcode) {
+            for san in operands {
+                let mv = board
+                    .parse_san(san)
+                    .map_err(|_| EpdError::IllegalMove(number, san.clone()))?;
+                moves.push(mv);
+            }
+        }
+        Ok(moves)
+    };
+    Ok(Epd {
+        best_moves: moves("bm")?,
+        avoid_moves: moves("am")?,
+        board,
+        operations,
+    })
+}
+
+// opcode and operands up to each semicolon, where an operand in double quotes can
+// have spaces and semicolons in it
+fn parse_operations(text: &str, number: usize) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut operations = Vec::new();
+    let mut words = Vec::new();
+    let mut chars = text.chars();
+    let mut word = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => quoted.push(c),
+                        None => return Err(EpdError::UnclosedString(number)),
+                    }
+                }
+                words.push(quoted);
+            }
+            ';' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                if !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    // the last operation doesn't always get its semicolon
+    if !word.is_empty() {
+        words.push(word);
+    }
+    if !words.is_empty() {
+        let opcode = words.remove(0);
+        operations.push((opcode, words));
+    }
+    Ok(operations)
+}
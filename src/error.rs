@@ -0,0 +1,50 @@
+use crate::fen::FenError;
+use std::fmt;
+
+// what can go wrong starting a game and drawing it, for the frontends to report
+// instead of crashing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChessError {
+    // no window could be opened, like when there's no display
+    WindowInit(String),
+    // the file name of the shader and what the driver said about it
+    ShaderCompile(String, String),
+    ShaderLink(String),
+    TextureLoad(String),
+    FontLoad(String),
+    InvalidFen(FenError),
+    // a variant known by that name somewhere else, but not here
+    UnknownVariant(String),
+    // a move, usually in uci notation, that isn't legal where it was played
+    IllegalMove(String),
+    // the board couldn't be drawn off screen or written out as a png
+    Screenshot(String),
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChessError::WindowInit(reason) => write!(f, "couldn't open the window: {}", reason),
+            ChessError::ShaderCompile(name, log) => {
+                write!(f, "couldn't compile {}: {}", name, log.trim())
+            }
+            ChessError::ShaderLink(log) => write!(f, "couldn't link the shaders: {}", log.trim()),
+            ChessError::TextureLoad(reason) => write!(f, "couldn't load a texture: {}", reason),
+            ChessError::FontLoad(reason) => write!(f, "the font is broken: {}", reason),
+            ChessError::InvalidFen(error) => write!(f, "invalid FEN: {}", error),
+            ChessError::UnknownVariant(name) => write!(f, "there's no variant called {}", name),
+            ChessError::IllegalMove(mv) => write!(f, "illegal move {}", mv),
+            ChessError::Screenshot(reason) => {
+                write!(f, "couldn't save a picture of the board: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChessError {}
+
+impl From<FenError> for ChessError {
+    fn from(error: FenError) -> ChessError {
+        ChessError::InvalidFen(error)
+    }
+}
@@ -0,0 +1,159 @@
+use crate::board::{Board, Color, Piece, PieceKind};
+use crate::clock::{clock_text, Clock};
+use crate::moves::Move;
+use crate::pgn::result_token;
+use crate::status::GameStatus;
+use std::io::Write;
+
+// something told about the game as it's played, for logging it, streaming it or a bot
+// to react to, without the front end having to know about it. every method does
+// nothing unless it's overridden, so an observer only has the ones it wants
+pub trait GameObserver {
+    // every move played, with the position before and after it
+    fn on_move(&mut self, _before: &Board, _mv: Move, _after: &Board) {}
+
+    // a move that took a piece, after on_move
+    fn on_capture(&mut self, _before: &Board, _mv: Move, _taken: Piece) {}
+
+    // a move that left `color`'s king attacked, after on_move and on_capture
+    fn on_check(&mut self, _after: &Board, _color: Color) {}
+
+    // the game ending, once, however it did
+    fn on_game_end(&mut self, _status: GameStatus) {}
+
+    // each whole second that goes by on the side's clock that's running
+    fn on_clock_tick(&mut self, _clock: &Clock, _color: Color) {}
+}
+
+// the observers of one game, handed the events as they happen; the front end only
+// has to say what happened, and the captures, checks and ticks are worked out here
+#[derive(Default)]
+pub struct Observers {
+    observers: Vec<Box<dyn GameObserver>>,
+    // the side and the seconds it had left when it was last told about
+    ticked: Option<(Color, u64)>,
+}
+
+impl Observers {
+    pub fn add(&mut self, observer: impl GameObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    // `after` is `before` with `mv` made
+    pub fn moved(&mut self, before: &Board, mv: Move, after: &Board) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let taken = taken_piece(before, mv);
+        let checked = after.side_to_move();
+        let check = after.is_in_check(checked);
+        for observer in self.observers.iter_mut() {
+            observer.on_move(before, mv, after);
+            if let Some(taken) = taken {
+                observer.on_capture(before, mv, taken);
+            }
+            if check {
+                observer.on_check(after, checked);
+            }
+        }
+    }
+
+    pub fn game_ended(&mut self, status: GameStatus) {
+        self.ticked = None;
+        for observer in self.observers.iter_mut() {
+            observer.on_game_end(status);
+        }
+    }
+
+    // as often as the front end likes; the observers only hear about whole seconds
+    pub fn clock_running(&mut self, clock: &Clock) {
+        let color = match clock.running() {
+            Some(color) => color,
+            None => return,
+        };
+        let seconds = clock.remaining(color).as_secs();
+        if self.ticked == Some((color, seconds)) {
+            return;
+        }
+        self.ticked = Some((color, seconds));
+        for observer in self.observers.iter_mut() {
+            observer.on_clock_tick(clock, color);
+        }
+    }
+}
+
+// the piece a move takes, which en passant isn't on the square moved to and a
+// chess960 king going onto its own rook doesn't
+fn taken_piece(board: &Board, mv: Move) -> Option<Piece> {
+    if !board.is_capture(mv) {
+        return None;
+    }
+    let mover = board.side_to_move();
+    Some(
+        board
+            .piece_at(mv.to)
+            .unwrap_or_else(|| Piece::new(PieceKind::Pawn, mover.opposite())),
+    )
+}
+
+// writes a line for every event, like "move 12. Nxe5" or "end 1-0 Checkmate, White
+// wins", to a file or stderr; the clock only every ten seconds so it isn't
+// most of the log
+pub struct EventLog<W: Write> {
+    out: W,
+    // it's no use carrying on writing once it's stopped working
+    failed: bool,
+}
+
+impl<W: Write> EventLog<W> {
+    pub fn new(out: W) -> EventLog<W> {
+        EventLog { out, failed: false }
+    }
+
+    fn line(&mut self, text: &str) {
+        if self.failed {
+            return;
+        }
+        if let Err(error) = writeln!(self.out, "{}", text).and_then(|_| self.out.flush()) {
+            eprintln!("Couldn't write the event log: {}", error);
+            self.failed = true;
+        }
+    }
+}
+
+impl<W: Write> GameObserver for EventLog<W> {
+    fn on_move(&mut self, before: &Board, mv: Move, _after: &Board) {
+        let number = before.fullmove_number();
+        let dots = match before.side_to_move() {
+            Color::White => ".",
+            Color::Black => "...",
+        };
+        self.line(&format!("move {}{} {}", number, dots, mv.to_san(before)));
+    }
+
+    fn on_capture(&mut self, _before: &Board, mv: Move, taken: Piece) {
+        self.line(&format!(
+            "capture {:?} {:?} on {}",
+            taken.color, taken.kind, mv.to
+        ));
+    }
+
+    fn on_check(&mut self, _after: &Board, color: Color) {
+        self.line(&format!("check {:?}", color));
+    }
+
+    fn on_game_end(&mut self, status: GameStatus) {
+        self.line(&format!("end {} {}", result_token(status), status));
+    }
+
+    fn on_clock_tick(&mut self, clock: &Clock, color: Color) {
+        let remaining = clock.remaining(color);
+        if remaining.as_secs().is_multiple_of(10) {
+            self.line(&format!("clock {:?} {}", color, clock_text(remaining)));
+        }
+    }
+}
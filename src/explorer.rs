@@ -0,0 +1,135 @@
+use crate::board::Board;
+use crate::moves::Move;
+use crate::pgn::{parse_pgn, PgnError, PgnGame};
+use std::collections::HashMap;
+
+// how far into each game the explorer looks; past the opening almost every position
+// only comes up once
+pub const EXPLORER_PLIES: usize = 30;
+
+// a move played in a position and how the games with it went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Continuation {
+    pub mv: Move,
+    pub games: u32,
+    // the games that ended each way; the rest had no result, like the bundled lines
+    pub white: u32,
+    pub draws: u32,
+    pub black: u32,
+}
+
+impl Continuation {
+    // how many of the games with it have a result
+    pub fn decided(&self) -> u32 {
+        self.white + self.draws + self.black
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Outcome {
+    White,
+    Draw,
+    Black,
+}
+
+// the moves played from each position of a collection of games, keyed by
+// position_key() so transpositions come together
+#[derive(Clone, Debug, Default)]
+pub struct Explorer {
+    positions: HashMap<u64, Vec<Continuation>>,
+    games: u32,
+}
+
+impl Explorer {
+    pub fn new() -> Explorer {
+        Explorer::default()
+    }
+
+    // the lines of the bundled opening book, each counted as a game without a result
+    pub fn bundled() -> Explorer {
+        let mut explorer = Explorer::new();
+        for line in include_str!("book.txt").lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut board = Board::new();
+            for san in line.split_whitespace() {
+                let mv = match board.parse_san(san) {
+                    Ok(mv) => mv,
+                    Err(_) => break,
+                };
+                explorer.add(&board, mv, None);
+                board.make_move(mv);
+            }
+            explorer.games += 1;
+        }
+        explorer
+    }
+
+    // every game of a pgn collection
+    pub fn from_pgn(text: &str) -> Result<Explorer, PgnError> {
+        let mut explorer = Explorer::new();
+        for game in parse_pgn(text)? {
+            explorer.add_game(&game);
+        }
+        Ok(explorer)
+    }
+
+    // the first EXPLORER_PLIES moves of the game's main line, with its result
+    pub fn add_game(&mut self, game: &PgnGame) {
+        let result = game.header("Result").and_then(|result| match result {
+            "1-0" => Some(Outcome::White),
+            "1/2-1/2" => Some(Outcome::Draw),
+            "0-1" => Some(Outcome::Black),
+            _ => None,
+        });
+        let mut board = game.start().clone();
+        for record in game.moves().iter().take(EXPLORER_PLIES) {
+            self.add(&board, record.mv, result);
+            board.make_move(record.mv);
+        }
+        self.games += 1;
+    }
+
+    fn add(&mut self, board: &Board, mv: Move, result: Option<Outcome>) {
+        let moves = self.positions.entry(board.position_key()).or_default();
+        let index = match moves.iter().position(|known| known.mv == mv) {
+            Some(index) => index,
+            None => {
+                moves.push(Continuation {
+                    mv,
+                    games: 0,
+                    white: 0,
+                    draws: 0,
+                    black: 0,
+                });
+                moves.len() - 1
+            }
+        };
+        let continuation = &mut moves[index];
+        continuation.games += 1;
+        match result {
+            Some(Outcome::White) => continuation.white += 1,
+            Some(Outcome::Draw) => continuation.draws += 1,
+            Some(Outcome::Black) => continuation.black += 1,
+            None => {}
+        }
+    }
+
+    // how many games went in
+    pub fn games(&self) -> u32 {
+        self.games
+    }
+
+    // the moves played in the position, the most common first
+    pub fn continuations(&self, board: &Board) -> Vec<Continuation> {
+        let mut moves = self
+            .positions
+            .get(&board.position_key())
+            .cloned()
+            .unwrap_or_default();
+        moves.sort_by_key(|continuation| std::cmp::Reverse(continuation.games));
+        moves
+    }
+}
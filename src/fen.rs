@@ -0,0 +1,372 @@
+use crate::bitboard::Bitboard;
+use crate::board::{Board, CastlingRights, Color, Piece, PieceKind, Pocket, Square};
+use std::fmt;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    // ranks are counted as in the fen, 8 first
+    InvalidRank(u8),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    InvalidPocket(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => {
+                write!(f, "expected 4 to 6 space separated fields, found {}", count)
+            }
+            FenError::WrongRankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+            FenError::InvalidRank(rank) => write!(f, "rank {} doesn't describe 8 squares", rank),
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a piece", c),
+            FenError::InvalidSideToMove(side) => {
+                write!(f, "side to move must be 'w' or 'b', not '{}'", side)
+            }
+            FenError::InvalidCastling(castling) => {
+                write!(f, "invalid castling rights '{}'", castling)
+            }
+            FenError::InvalidEnPassant(square) => {
+                write!(f, "invalid en passant square '{}'", square)
+            }
+            FenError::InvalidHalfmoveClock(clock) => {
+                write!(f, "invalid halfmove clock '{}'", clock)
+            }
+            FenError::InvalidFullmoveNumber(number) => {
+                write!(f, "invalid fullmove number '{}'", number)
+            }
+            FenError::InvalidPocket(pocket) => write!(f, "invalid pocket '{}'", pocket),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Board {
+    // the move counters are optional since plenty of tools (and epd) leave them off.
+    // crazyhouse positions have the pockets in brackets after the placement, like
+    // [QNp], and a ~ after each piece that was a pawn
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let mut board = Board::empty();
+
+        let (placement, pockets) = match fields[0].split_once('[') {
+            Some((placement, rest)) => {
+                let pockets = rest
+                    .strip_suffix(']')
+                    .ok_or_else(|| FenError::InvalidPocket(rest.to_string()))?;
+                (placement, Some(pockets))
+            }
+            None => (fields[0], None),
+        };
+        if let Some(text) = pockets {
+            let mut pockets = [Pocket::default(); 2];
+            for c in text.chars() {
+                match Piece::from_char(c).filter(|piece| piece.kind != PieceKind::King) {
+                    Some(piece) => pockets[piece.color as usize].add(piece.kind),
+                    None => return Err(FenError::InvalidPocket(text.to_string())),
+                }
+            }
+            board.set_pocket(Color::White, pockets[0]);
+            board.set_pocket(Color::Black, pockets[1]);
+        }
+
+        let mut promoted = Bitboard::EMPTY;
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::WrongRankCount(ranks.len()));
+        }
+        for (i, rank_text) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
+            let mut file = 0;
+            let mut previous = None;
+            for c in rank_text.chars() {
+                if c == '~' {
+                    let square = previous
+                        .take()
+                        .filter(|_| pockets.is_some())
+                        .ok_or(FenError::InvalidRank(rank + 1))?;
+                    promoted |= Bitboard::from_square(square);
+                    continue;
+                }
+                previous = None;
+                if let Some(skip) = c.to_digit(10) {
+                    if skip == 0 || skip > 8 {
+                        return Err(FenError::InvalidRank(rank + 1));
+                    }
+                    file += skip as u8;
+                } else {
+                    let piece = Piece::from_char(c).ok_or(FenError::InvalidPiece(c))?;
+                    if file >= 8 {
+                        return Err(FenError::InvalidRank(rank + 1));
+                    }
+                    board.set_piece(Square::new(file, rank), Some(piece));
+                    previous = Some(Square::new(file, rank));
+                    file += 1;
+                }
+                if file > 8 {
+                    return Err(FenError::InvalidRank(rank + 1));
+                }
+            }
+            if file != 8 {
+                return Err(FenError::InvalidRank(rank + 1));
+            }
+        }
+
+        board.set_promoted(promoted);
+
+        board.set_side_to_move(match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            side => return Err(FenError::InvalidSideToMove(side.to_string())),
+        });
+
+        // x-fen: KQkq for the outermost rook on each side, or the rook's file (as in
+        // shredder-fen) when it's another one, which only happens in chess960
+        let mut castling = CastlingRights::none();
+        let invalid = || FenError::InvalidCastling(fields[2].to_string());
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let color = if c.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                let rank = if color == Color::White { 0 } else { 7 };
+                let king = board.king_square(color).filter(|king| king.rank() == rank);
+                let rook = Piece::new(PieceKind::Rook, color);
+                let (kingside, file) = match (c.to_ascii_lowercase(), king) {
+                    // a right the position doesn't back up is kept, castling just
+                    // never becomes possible
+                    ('k', None) => (true, 7),
+                    ('q', None) => (false, 0),
+                    ('k', Some(king)) => {
+                        let file = (king.file() + 1..8)
+                            .rev()
+                            .find(|&file| board.piece_at(Square::new(file, rank)) == Some(rook));
+                        (true, file.unwrap_or(7))
+                    }
+                    ('q', Some(king)) => {
+                        let file = (0..king.file())
+                            .find(|&file| board.piece_at(Square::new(file, rank)) == Some(rook));
+                        (false, file.unwrap_or(0))
+                    }
+                    (letter @ 'a'..='h', Some(king)) if letter as u8 - b'a' != king.file() => {
+                        let file = letter as u8 - b'a';
+                        (file > king.file(), file)
+                    }
+                    _ => return Err(invalid()),
+                };
+                let (mut king_right, mut queen_right) = castling.get(color);
+                let right = if kingside {
+                    &mut king_right
+                } else {
+                    &mut queen_right
+                };
+                if *right {
+                    return Err(invalid());
+                }
+                *right = true;
+                castling.set(color, king_right, queen_right);
+                board.set_castling_rook(color, kingside, file);
+                let standard = file == if kingside { 7 } else { 0 }
+                    && king.is_none_or(|king| king.file() == 4);
+                if !standard {
+                    board.set_chess960(true);
+                }
+            }
+        }
+        board.set_castling(castling);
+
+        if fields[3] != "-" {
+            let square = Square::parse(fields[3])
+                .filter(|square| square.rank() == 2 || square.rank() == 5)
+                .ok_or_else(|| FenError::InvalidEnPassant(fields[3].to_string()))?;
+            board.set_en_passant(Some(square));
+        }
+
+        if let Some(clock) = fields.get(4) {
+            let clock = clock
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(clock.to_string()))?;
+            board.set_halfmove_clock(clock);
+        }
+        if let Some(number) = fields.get(5) {
+            let number = number
+                .parse()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(number.to_string()))?;
+            board.set_fullmove_number(number);
+        }
+
+        Ok(board)
+    }
+
+    // whether K or Q says which rook castles, because there's no other rook of the same
+    // colour further out on the home rank
+    fn is_outermost_rook(&self, color: Color, kingside: bool) -> bool {
+        let rank = if color == Color::White { 0 } else { 7 };
+        let file = self.castling_rook(color, kingside);
+        let beyond = if kingside { file + 1..8 } else { 0..file };
+        let rook = Some(Piece::new(PieceKind::Rook, color));
+        beyond
+            .into_iter()
+            .all(|other| self.piece_at(Square::new(other, rank)) != rook)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::with_capacity(90);
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::new(file, rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.to_char());
+                        if self.has_pockets() && self.promoted().contains(Square::new(file, rank)) {
+                            fen.push('~');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+        if self.has_pockets() {
+            fen.push('[');
+            for &color in [Color::White, Color::Black].iter() {
+                let pocket = self.pocket(color);
+                for &kind in Pocket::KINDS.iter().rev() {
+                    for _ in 0..pocket.count(kind) {
+                        fen.push(Piece::new(kind, color).to_char());
+                    }
+                }
+            }
+            fen.push(']');
+        }
+
+        fen.push(' ');
+        fen.push(match self.side_to_move() {
+            Color::White => 'w',
+            Color::Black => 'b',
+        });
+
+        fen.push(' ');
+        let castling = self.castling();
+        let before = fen.len();
+        for &color in [Color::White, Color::Black].iter() {
+            let (kingside, queenside) = castling.get(color);
+            for &(allowed, side) in [(kingside, true), (queenside, false)].iter() {
+                if !allowed {
+                    continue;
+                }
+                let letter = if self.is_outermost_rook(color, side) {
+                    if side {
+                        'k'
+                    } else {
+                        'q'
+                    }
+                } else {
+                    (b'a' + self.castling_rook(color, side)) as char
+                };
+                fen.push(match color {
+                    Color::White => letter.to_ascii_uppercase(),
+                    Color::Black => letter,
+                });
+            }
+        }
+        if fen.len() == before {
+            fen.push('-');
+        }
+
+        fen.push(' ');
+        match self.en_passant() {
+            Some(square) => fen.push_str(&square.to_string()),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock(),
+            self.fullmove_number()
+        ));
+        fen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::REFERENCE_POSITIONS;
+
+    // the chess960 ones are written back with KQkq, as x-fen has it for the outermost
+    // rooks, so it's the board that has to come back the same
+    #[test]
+    fn reference_positions_come_back_the_same() {
+        for &(name, fen, _) in REFERENCE_POSITIONS {
+            let board = Board::from_fen(fen).unwrap();
+            let written = board.to_fen();
+            assert_eq!(Board::from_fen(&written), Ok(board.clone()), "{}", name);
+            if !board.is_chess960() {
+                assert_eq!(written, fen, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn a_missing_or_extra_field_is_refused() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq";
+        assert_eq!(Board::from_fen(fen), Err(FenError::WrongFieldCount(3)));
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 x";
+        assert_eq!(Board::from_fen(fen), Err(FenError::WrongFieldCount(7)));
+    }
+
+    #[test]
+    fn a_castling_field_with_something_else_in_it_is_refused() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1";
+        let error = FenError::InvalidCastling("KQkx".to_string());
+        assert_eq!(Board::from_fen(fen), Err(error));
+    }
+
+    #[test]
+    fn an_en_passant_square_off_the_third_and_sixth_ranks_is_refused() {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e5 0 2";
+        let error = FenError::InvalidEnPassant("e5".to_string());
+        assert_eq!(Board::from_fen(fen), Err(error));
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq i3 0 1";
+        let error = FenError::InvalidEnPassant("i3".to_string());
+        assert_eq!(Board::from_fen(fen), Err(error));
+    }
+
+    #[test]
+    fn a_rank_of_more_than_eight_squares_is_refused() {
+        let fen = "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(Board::from_fen(fen), Err(FenError::InvalidRank(7)));
+        let fen = "rnbqkbnr/pppppppp/8/8/9/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(Board::from_fen(fen), Err(FenError::InvalidRank(4)));
+        let fen = "rnbqkbnr/pppppppp/8/8/4P4/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(Board::from_fen(fen), Err(FenError::InvalidRank(4)));
+    }
+}
@@ -0,0 +1,36 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform vec4 color;
+// 0 fills the square, 1 draws a dot in its middle, 2 a ring around its edge and 3
+// an arrow along line
+uniform int shape;
+// the arrow's tail and tip, in the same coordinates as TexCoord
+uniform vec4 line;
+
+void main()
+{
+	float from_center = length(TexCoord - vec2(0.5));
+	if (shape == 1 && from_center > 0.16)
+		discard;
+	if (shape == 2 && (from_center < 0.42 || from_center > 0.5))
+		discard;
+	if (shape == 3) {
+		vec2 direction = normalize(line.zw - line.xy);
+		vec2 point = TexCoord - line.xy;
+		float along = dot(point, direction);
+		float across = abs(dot(point, vec2(-direction.y, direction.x)));
+		float span = distance(line.xy, line.zw);
+		// the head takes up the last stretch and narrows to the tip
+		float head = 0.05;
+		if (along < 0.0 || along > span)
+			discard;
+		if (along < span - head && across > 0.012)
+			discard;
+		if (along >= span - head && across > 0.035 * (span - along) / head)
+			discard;
+	}
+	FragColor = color;
+}
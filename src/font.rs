@@ -0,0 +1,57 @@
+use crate::renderer::Texture;
+use chess_game::error::ChessError;
+use chess_game::glyphs::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+// every cell of the atlas has a column to the right of its glyph and a row above it,
+// so text laid out cell after cell gets its spacing for free
+pub const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+pub const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+const COLUMNS: u32 = 16;
+
+// a bitmap font in one texture, white where the letters are and transparent elsewhere
+pub struct Font {
+    texture: Texture,
+    chars: Vec<char>,
+}
+
+impl Font {
+    pub fn new() -> Result<Font, ChessError> {
+        let glyphs = glyphs::bundled().map_err(ChessError::FontLoad)?;
+        let rows = (glyphs.len() as u32).div_ceil(COLUMNS);
+        let mut atlas = RgbaImage::new(COLUMNS * CELL_WIDTH, rows * CELL_HEIGHT);
+        for (i, (_, pixels)) in glyphs.iter().enumerate() {
+            let left = i as u32 % COLUMNS * CELL_WIDTH;
+            let top = i as u32 / COLUMNS * CELL_HEIGHT + 1;
+            for (y, &row) in pixels.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        atlas.put_pixel(left + x, top + y as u32, Rgba([255; 4]));
+                    }
+                }
+            }
+        }
+        let texture = Texture::from_image(&DynamicImage::ImageRgba8(atlas), COLUMNS, rows)?;
+        texture.set_pixelated();
+        Ok(Font {
+            texture,
+            chars: glyphs.into_iter().map(|(c, _)| c).collect(),
+        })
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    // the part of the texture with the character's cell, a question mark standing in
+    // for anything the font doesn't have
+    pub fn uv(&self, c: char) -> (f32, f32, f32, f32) {
+        let i = self
+            .chars
+            .iter()
+            .position(|&known| known == c)
+            .or_else(|| self.chars.iter().position(|&known| known == '?'))
+            .unwrap_or(0) as u32;
+        self.texture.uv_rect(i % COLUMNS, i / COLUMNS)
+    }
+}
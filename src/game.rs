@@ -0,0 +1,211 @@
+use crate::board::{Board, Color};
+use crate::clock::{Clock, TimeControl};
+use crate::moves::Move;
+use crate::pgn::{PgnGame, PgnMove};
+use crate::status::{DrawRules, GameStatus};
+use crate::variant::Variant;
+use std::time::Instant;
+
+// a game as the window and the terminal both play it: the position, its moves, the
+// clock and how it stands by the rules. what's shown of it, and who or what plays
+// each side, is up to them
+pub struct Game {
+    pub board: Board,
+    pub variant: Box<dyn Variant>,
+    // every move played so far, with the headers
+    pub record: PgnGame,
+    pub status: GameStatus,
+    // whether the fifty move rule and repetitions end the game or can be claimed
+    pub draw_rules: DrawRules,
+    pub clock: Option<Clock>,
+    // moves taken back, the most recent last, until a different move is played
+    pub taken_back: Vec<PgnMove>,
+    // when the side to move started thinking
+    pub turn_started: Instant,
+}
+
+impl Game {
+    // nothing played yet from `board`, which the variant has already set up
+    pub fn new(board: Board, variant: Box<dyn Variant>, draw_rules: DrawRules) -> Game {
+        let mut record = PgnGame::new(&board);
+        record.set_variant(variant.as_ref());
+        Game {
+            board,
+            variant,
+            record,
+            status: GameStatus::Ongoing,
+            draw_rules,
+            clock: None,
+            taken_back: Vec::new(),
+            turn_started: Instant::now(),
+        }
+    }
+
+    // carries on from the end of a recorded game, in its variant; the clock is left
+    // as it is
+    pub fn load(&mut self, record: PgnGame) {
+        self.variant = record.variant();
+        self.board = record.board_at(record.moves().len());
+        self.record = record;
+        self.taken_back.clear();
+    }
+
+    // plays with a clock, which starts running straight away
+    pub fn set_clock(&mut self, control: TimeControl) {
+        self.record.set_header("TimeControl", &control.to_pgn());
+        let mut clock = Clock::new(control);
+        if !self.status.is_over() {
+            clock.start(self.board.side_to_move());
+        }
+        self.clock = Some(clock);
+    }
+
+    // how the position stands by the variant's rules
+    pub fn rules_status(&self) -> GameStatus {
+        self.variant.status_by(&self.board, self.draw_rules)
+    }
+
+    // the game's result from the rules, or from something else like a resignation or
+    // the clock, which stops once it's over; true if that's what just ended it
+    pub fn set_status(&mut self, status: GameStatus) -> bool {
+        let was_over = self.status.is_over();
+        self.status = status;
+        self.record.set_result(status);
+        if status.is_over() {
+            if let Some(clock) = &mut self.clock {
+                clock.stop();
+            }
+        }
+        status.is_over() && !was_over
+    }
+
+    // plays a legal move on the board and in the record, with the time it took, and
+    // hands the clock to the other side; gives back the move with its number, like
+    // "12... Nf6". the status is the caller's to bring up to date
+    pub fn play_move(&mut self, mv: Move) -> String {
+        self.record.push(mv, Some(self.turn_started.elapsed()));
+        let san = &self.record.moves().last().unwrap().san;
+        let text = match self.board.side_to_move() {
+            Color::White => format!("{}. {}", self.board.fullmove_number(), san),
+            Color::Black => format!("{}... {}", self.board.fullmove_number(), san),
+        };
+        self.turn_started = Instant::now();
+        self.board.make_move(mv);
+        if let Some(clock) = &mut self.clock {
+            clock.press();
+        }
+        self.taken_back.clear();
+        text
+    }
+
+    // takes back the last move, and the one before while it's `computer`'s turn, so
+    // it's the player's again; false if there was nothing to take back
+    pub fn take_back(&mut self, computer: Option<Color>) -> bool {
+        let mut taken = false;
+        while let Some(record) = self.record.pop() {
+            self.board.unmake_move();
+            self.taken_back.push(record);
+            taken = true;
+            if Some(self.board.side_to_move()) != computer {
+                break;
+            }
+        }
+        taken
+    }
+
+    // plays the moves taken back again, up to the player's next turn
+    pub fn replay_taken_back(&mut self, computer: Option<Color>) -> bool {
+        let mut played = false;
+        while let Some(record) = self.taken_back.pop() {
+            self.record.push(record.mv, record.elapsed);
+            self.board.make_move(record.mv);
+            played = true;
+            if Some(self.board.side_to_move()) != computer {
+                break;
+            }
+        }
+        played
+    }
+
+    // after the moves were changed and the status brought up to date: the side to
+    // move starts its turn again, the clocks keeping the time already used
+    pub fn restart_turn(&mut self) {
+        self.turn_started = Instant::now();
+        if let Some(clock) = &mut self.clock {
+            if !self.status.is_over() {
+                clock.start(self.board.side_to_move());
+            }
+        }
+    }
+
+    // under fide's rules, the draw there is to claim in the position
+    pub fn claimable_draw(&self) -> Option<GameStatus> {
+        if self.draw_rules != DrawRules::Fide || self.status.is_over() {
+            return None;
+        }
+        self.board.claimable_draw()
+    }
+
+    // `color` claims the draw there is, which is said in the record after the last
+    // move; gives back the draw to end the game with and what the record says
+    pub fn claim_draw(&mut self, color: Color) -> Option<(GameStatus, String)> {
+        let draw = self.claimable_draw()?;
+        Some((draw, self.record.note_claim(color, draw)))
+    }
+
+    // the win on time there is once a flag has fallen
+    pub fn flagged(&self) -> Option<GameStatus> {
+        if self.status.is_over() {
+            return None;
+        }
+        let color = self.clock.as_ref()?.flagged()?;
+        Some(GameStatus::Timeout(color.opposite()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::STARTING_FEN;
+    use crate::variant::Standard;
+
+    fn game(rules: DrawRules) -> Game {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        Game::new(board, Box::new(Standard), rules)
+    }
+
+    fn play(game: &mut Game, sans: &[&str]) {
+        for san in sans {
+            let mv = game.board.parse_san(san).unwrap();
+            game.play_move(mv);
+        }
+    }
+
+    #[test]
+    fn taking_back_against_the_computer_goes_back_to_the_players_turn() {
+        let mut game = game(DrawRules::Automatic);
+        play(&mut game, &["e4", "e5", "Nf3"]);
+        assert!(game.take_back(Some(Color::White)));
+        assert_eq!(game.record.moves().len(), 1);
+        assert_eq!(game.board.side_to_move(), Color::Black);
+        assert!(game.replay_taken_back(Some(Color::White)));
+        assert_eq!(game.record.moves().len(), 3);
+        assert!(!game.replay_taken_back(Some(Color::White)));
+    }
+
+    #[test]
+    fn a_repetition_is_only_claimed_under_fides_rules() {
+        let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"];
+        let mut automatic = game(DrawRules::Automatic);
+        play(&mut automatic, &shuffle);
+        assert_eq!(automatic.claim_draw(Color::White), None);
+
+        let mut fide = game(DrawRules::Fide);
+        play(&mut fide, &shuffle);
+        let (draw, _) = fide.claim_draw(Color::White).unwrap();
+        assert_eq!(draw, GameStatus::ThreefoldRepetition);
+        assert!(fide.set_status(draw));
+        assert!(!fide.set_status(draw));
+        assert!(fide.record.moves().last().unwrap().comment.is_some());
+    }
+}
@@ -0,0 +1,38 @@
+// the glyphs of font.txt, each the character on a line of its own (or the word space
+// for the space) followed by its rows of pixels, where # is lit. the window builds
+// its font texture from them and diagrams draw their coordinates with them
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+
+pub type Glyph = (char, [u8; GLYPH_HEIGHT as usize]);
+
+pub fn bundled() -> Result<Vec<Glyph>, String> {
+    parse(include_str!("font.txt"))
+}
+
+// each row of a glyph is a bit for every pixel, the leftmost one the highest
+pub fn parse(text: &str) -> Result<Vec<Glyph>, String> {
+    let mut glyphs = Vec::new();
+    let mut lines = text.lines().filter(|line| !line.is_empty());
+    while let Some(name) = lines.next() {
+        let mut chars = name.chars();
+        let c = match (chars.next(), chars.next()) {
+            _ if name == "space" => ' ',
+            (Some(c), None) => c,
+            _ => return Err(format!("font.txt: expected a character, found {:?}", name)),
+        };
+        let mut pixels = [0; GLYPH_HEIGHT as usize];
+        for row in pixels.iter_mut() {
+            match lines.next() {
+                Some(line) if line.chars().count() == GLYPH_WIDTH as usize => {
+                    *row = line
+                        .chars()
+                        .fold(0, |bits, pixel| bits << 1 | (pixel == '#') as u8)
+                }
+                _ => return Err(format!("font.txt: {:?} has a bad row", c)),
+            }
+        }
+        glyphs.push((c, pixels));
+    }
+    Ok(glyphs)
+}
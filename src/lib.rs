@@ -0,0 +1,37 @@
+pub mod bitboard;
+pub mod board;
+pub mod book;
+pub mod chess960;
+pub mod clock;
+pub mod config;
+pub mod correspondence;
+pub mod database;
+#[cfg(feature = "diagram")]
+pub mod diagram;
+pub mod eco;
+pub mod engine;
+pub mod epd;
+pub mod error;
+pub mod events;
+pub mod explorer;
+pub mod fen;
+pub mod game;
+pub mod glyphs;
+#[cfg(feature = "lichess")]
+pub mod lichess;
+pub mod material;
+pub mod moves;
+pub mod net;
+pub mod perft;
+pub mod pgn;
+pub mod puzzle;
+pub mod san;
+pub mod saved;
+pub mod settings;
+pub mod setup;
+#[cfg(feature = "spectate")]
+pub mod spectate;
+pub mod speech;
+pub mod status;
+pub mod variant;
+pub mod zobrist;
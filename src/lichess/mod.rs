@@ -0,0 +1,336 @@
+pub mod json;
+
+use crate::board::Color;
+use crate::moves::Move;
+use crate::settings::Settings;
+use json::Json;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+// playing on lichess.org with the board api. there's no http or tls library to lean
+// on, so requests go through curl, which most systems already have
+
+const API: &str = "https://lichess.org/api";
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    token: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Player {
+    // missing for anonymous players and the ai
+    pub id: Option<String>,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameState {
+    // every move since the start, in uci notation
+    pub moves: Vec<String>,
+    pub white_time: Duration,
+    pub black_time: Duration,
+    // started while the game is going, otherwise mate, resign, outoftime, draw and so on
+    pub status: String,
+    pub winner: Option<Color>,
+    // the side offering a draw, until it's answered
+    pub draw_offer: Option<Color>,
+}
+
+impl GameState {
+    pub fn is_over(&self) -> bool {
+        !matches!(self.status.as_str(), "created" | "started")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameFull {
+    pub id: String,
+    pub white: Player,
+    pub black: Player,
+    // "startpos" for the standard position
+    pub initial_fen: String,
+    // lichess's key for it, like standard, crazyhouse or kingOfTheHill
+    pub variant: String,
+    // the starting time and increment, correspondence games have no clock
+    pub clock: Option<(Duration, Duration)>,
+    pub state: GameState,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    // always the first thing sent, and again if the stream is opened again
+    Full(GameFull),
+    State(GameState),
+}
+
+impl Client {
+    pub fn new(token: &str) -> Client {
+        Client {
+            token: token.trim().to_string(),
+        }
+    }
+
+    // the LICHESS_TOKEN environment variable, or the token under [lichess] in
+    // config.toml in the config directory
+    pub fn from_env() -> io::Result<Client> {
+        if let Ok(token) = std::env::var("LICHESS_TOKEN") {
+            return Ok(Client::new(&token));
+        }
+        let path = Settings::default_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+        let settings = Settings::load(&path).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid settings in {}: {}", path.display(), error),
+            )
+        })?;
+        settings
+            .lichess_token
+            .map(|token| Client::new(&token))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "no LICHESS_TOKEN, and no token under [lichess] in {}",
+                        path.display()
+                    ),
+                )
+            })
+    }
+
+    // runs curl with the token passed on stdin, so it doesn't show up in the process list
+    fn curl(&self, method: &str, path: &str, streaming: bool) -> io::Result<Child> {
+        let mut command = Command::new("curl");
+        command.args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "-X",
+            method,
+            "-H",
+            "@-",
+        ]);
+        if streaming {
+            command.arg("--no-buffer");
+        }
+        let mut child = command
+            .arg(format!("{}{}", API, path))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| {
+                io::Error::new(error.kind(), format!("couldn't run curl: {}", error))
+            })?;
+        let mut input = child.stdin.take().unwrap();
+        writeln!(input, "Authorization: Bearer {}", self.token)?;
+        Ok(child)
+    }
+
+    fn request(&self, method: &str, path: &str) -> io::Result<Json> {
+        let output = self.curl(method, path, false)?.wait_with_output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        Json::parse(&text).ok_or_else(|| invalid_response(&text))
+    }
+
+    // the id of the account the token belongs to
+    pub fn account_id(&self) -> io::Result<String> {
+        let account = self.request("GET", "/account")?;
+        account
+            .get("id")
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| invalid_response("account without an id"))
+    }
+
+    pub fn make_move(&self, game: &str, mv: Move) -> io::Result<()> {
+        self.request("POST", &format!("/board/game/{}/move/{}", game, mv))
+            .map(|_| ())
+    }
+
+    pub fn resign(&self, game: &str) -> io::Result<()> {
+        self.request("POST", &format!("/board/game/{}/resign", game))
+            .map(|_| ())
+    }
+
+    // with accept, offers a draw or takes the one on offer; without, turns it down
+    pub fn answer_draw(&self, game: &str, accept: bool) -> io::Result<()> {
+        let answer = if accept { "yes" } else { "no" };
+        self.request("POST", &format!("/board/game/{}/draw/{}", game, answer))
+            .map(|_| ())
+    }
+
+    // the game as it is now, for looking in on it now and then instead of following it;
+    // it's the first thing the stream sends, so the stream's closed once that's come
+    pub fn game(&self, game: &str) -> io::Result<GameFull> {
+        let mut child = self.curl("GET", &format!("/board/game/stream/{}", game), true)?;
+        let output = BufReader::new(child.stdout.take().unwrap());
+        let mut full = None;
+        for line in output.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            full = match Json::parse(&line).as_ref().and_then(parse_event) {
+                Some(GameEvent::Full(full)) => Some(full),
+                _ => return Err(invalid_response(&line)),
+            };
+            break;
+        }
+        let _ = child.kill();
+        let output = child.wait_with_output()?;
+        full.ok_or_else(|| {
+            io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        })
+    }
+
+    // follows a game as it's played
+    pub fn stream_game(&self, game: &str) -> io::Result<GameStream> {
+        let mut child = self.curl("GET", &format!("/board/game/stream/{}", game), true)?;
+        let output = BufReader::new(child.stdout.take().unwrap());
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            for line in output.lines() {
+                let event = match line {
+                    // lichess sends empty lines to keep the connection open
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => match Json::parse(&line) {
+                        Some(json) => match parse_event(&json) {
+                            Some(event) => Ok(event),
+                            // chat and the like
+                            None => continue,
+                        },
+                        None => Err(invalid_response(&line)),
+                    },
+                    Err(error) => Err(error),
+                };
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(GameStream { child, events })
+    }
+}
+
+// the events of one game, read on their own thread so checking never blocks
+pub struct GameStream {
+    child: Child,
+    events: Receiver<io::Result<GameEvent>>,
+}
+
+impl GameStream {
+    // the next event if one has arrived; an error means the stream is over, which is
+    // also what happens when the game ends
+    pub fn poll(&mut self) -> io::Result<Option<GameEvent>> {
+        match self.events.try_recv() {
+            Ok(event) => event.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "the game stream ended",
+            )),
+        }
+    }
+}
+
+impl Drop for GameStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn invalid_response(text: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected answer from lichess: {}", text),
+    )
+}
+
+fn parse_player(json: &Json) -> Player {
+    let id = json.get("id").as_str().map(String::from);
+    let name = json
+        .get("name")
+        .as_str()
+        .map(String::from)
+        .or_else(|| {
+            json.get("aiLevel")
+                .as_u64()
+                .map(|level| format!("AI level {}", level))
+        })
+        .unwrap_or_else(|| "Anonymous".to_string());
+    Player { id, name }
+}
+
+fn parse_state(json: &Json) -> Option<GameState> {
+    let millis = |key: &str| Duration::from_millis(json.get(key).as_u64().unwrap_or(0));
+    Some(GameState {
+        moves: json
+            .get("moves")
+            .as_str()?
+            .split_whitespace()
+            .map(String::from)
+            .collect(),
+        white_time: millis("wtime"),
+        black_time: millis("btime"),
+        status: json.get("status").as_str()?.to_string(),
+        winner: match json.get("winner").as_str() {
+            Some("white") => Some(Color::White),
+            Some("black") => Some(Color::Black),
+            _ => None,
+        },
+        draw_offer: if json.get("wdraw").as_bool() == Some(true) {
+            Some(Color::White)
+        } else if json.get("bdraw").as_bool() == Some(true) {
+            Some(Color::Black)
+        } else {
+            None
+        },
+    })
+}
+
+fn parse_event(json: &Json) -> Option<GameEvent> {
+    match json.get("type").as_str()? {
+        "gameFull" => {
+            let clock = json.get("clock");
+            let clock = if clock.is_null() {
+                None
+            } else {
+                Some((
+                    Duration::from_millis(clock.get("initial").as_u64()?),
+                    Duration::from_millis(clock.get("increment").as_u64()?),
+                ))
+            };
+            Some(GameEvent::Full(GameFull {
+                id: json.get("id").as_str()?.to_string(),
+                white: parse_player(json.get("white")),
+                black: parse_player(json.get("black")),
+                initial_fen: json
+                    .get("initialFen")
+                    .as_str()
+                    .unwrap_or("startpos")
+                    .to_string(),
+                variant: json
+                    .get("variant")
+                    .get("key")
+                    .as_str()
+                    .unwrap_or("standard")
+                    .to_string(),
+                clock,
+                state: parse_state(json.get("state"))?,
+            }))
+        }
+        "gameState" => parse_state(json).map(GameEvent::State),
+        _ => None,
+    }
+}
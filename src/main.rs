@@ -1,7 +1,785 @@
+#[cfg(feature = "gui")]
+mod animation;
+mod computer;
+#[cfg(feature = "gui")]
+mod font;
+#[cfg(feature = "gui")]
+mod renderer;
+#[cfg(feature = "audio")]
+mod sound;
+#[cfg(feature = "gui")]
+mod theme;
+mod tui;
+#[cfg(feature = "gui")]
 mod window;
 
+use chess_game::board::{Board, Color};
+use chess_game::book::{Book, DEFAULT_BOOK_PLIES};
+use chess_game::chess960;
+use chess_game::clock::TimeControl;
+#[cfg(feature = "lichess")]
+use chess_game::correspondence::CorrespondenceError;
+use chess_game::correspondence::CorrespondenceGame;
+#[cfg(feature = "gui")]
+use chess_game::database::Database;
+use chess_game::engine::external::ExternalEngine;
+use chess_game::engine::{Engine, SearchLimits};
+#[cfg(feature = "gui")]
+use chess_game::error::ChessError;
+use chess_game::events::EventLog;
+#[cfg(feature = "gui")]
+use chess_game::explorer::Explorer;
+use chess_game::fen::STARTING_FEN;
+use chess_game::net::DEFAULT_PORT;
+use chess_game::pgn::PgnGame;
+#[cfg(feature = "gui")]
+use chess_game::puzzle::{self, Training};
+use chess_game::saved::SavedGame;
+use chess_game::settings::Settings;
+use chess_game::status::DrawRules;
+use chess_game::variant::{self, Variant};
+use computer::Opponent;
+use std::fs::File;
+use std::time::Duration;
+use tui::Terminal;
+#[cfg(feature = "gui")]
 use window::Game;
 
+const USAGE: &str = "usage: chess-game [--computer white|black] [--engine path] [--skill level]
+                  [--depth plies] [--movetime ms] [--threads count]
+                  [--clock minutes+increment] [--draws automatic|fide]
+                  [--fps limit] [--speak command]
+                  [--log-events file] [--correspondence name] [--correspondence-list]
+                  [--pgn file] [--resume] [--book file] [--theme file] [--flip] [--tui]
+                  [--puzzles file] [--puzzle-rating min-max] [--puzzle-theme theme]
+                  [--explorer file] [--database directory]
+                  [--host port] [--color white|black] [--join address]
+                  [--lichess game] [--spectate-port port] [--spectate-address address]
+                  [--chess960 number|random]
+                  [--variant standard|crazyhouse|kingofthehill] [fen]
+--tui plays in the terminal, which is all there is without the gui feature; the
+network and lichess games need the window, and --lichess the lichess feature.
+--spectate-port sends the position as json to websocket clients on the port after every
+move, for stream overlays; it needs the spectate feature. It only listens on 127.0.0.1
+unless --spectate-address says otherwise, like 0.0.0.0 for every interface.
+--draws fide lets a player claim draws by the fifty move rule and threefold repetition,
+which only end the game by themselves at seventy-five moves and the fifth time.
+--chess960 starts from one of the 960 fischer random positions, 518 being the usual one.
+--threads is how many the built in engine searches on, for analysis as well.
+--fps limits how often the window is drawn, which is already no more often than the
+screen refreshes and only when something changes.
+--speak runs the command with every move, like \"White knight g1 to f3, check\", and
+the square the keyboard focus lands on, so a speech synthesiser like espeak can say them.
+--correspondence plays the game kept under the name, starting it with the side from
+--color if there isn't one yet, and keeps it after every move. with --lichess when it's
+started it follows that game, and the moves made there while away are fetched first.
+--correspondence-list fetches those moves for every game kept and says whose move it is.
+--log-events writes a line to the file for every move, capture, check and the end of
+the game, and every ten seconds of the clock that's running.
+--resume plays on from the game left unfinished last time, with the computer on the
+side it had then.
+--puzzles solves tactics from a csv like lichess's puzzle database in the window, N
+skipping one; they can be narrowed down to a rating and a theme like fork or mateIn2.
+--explorer fills the opening explorer, shown with E in the window, from the games of a
+pgn file instead of the lines of the bundled book.
+--database searches the games imported into a directory with the database command
+for the position shown, with D in the window; the config directory's are the default.
+~/.config/chess/config.toml, or chess/config.toml under XDG_CONFIG_HOME, sets the
+defaults for the clock, the engine, the window and its keys, which the command line
+overrides, and the lichess token under [lichess], which LICHESS_TOKEN overrides.
+only the built in engine plays the variants";
+
+// a game played against someone else's copy of the program, only in the window
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+enum Remote {
+    Host(u16, Color),
+    Join(String),
+}
+
 fn main() {
-    Game::new().game_loop();
+    // the settings file has the defaults, and the command line has the last word
+    let settings = match Settings::default_path() {
+        Some(path) => match Settings::load(&path) {
+            Ok(settings) => settings,
+            Err(error) => return eprintln!("Invalid settings in {}: {}", path.display(), error),
+        },
+        None => Settings::default(),
+    };
+    let mut computer = None;
+    let mut engine_path = None;
+    let mut skill = settings.skill;
+    let mut depth = settings.depth;
+    let mut movetime = settings.movetime;
+    let mut threads = settings.threads.unwrap_or(1);
+    let mut pgn_path = None;
+    let mut resume = false;
+    let mut flip = false;
+    let mut tui = false;
+    let mut clock = settings.clock.clone();
+    let mut draws = settings.draws.unwrap_or_default();
+    let mut book_path = None;
+    let mut chess960_number = None;
+    let mut variant: Box<dyn Variant> = Box::new(variant::Standard);
+    #[cfg(feature = "gui")]
+    let mut theme_path = settings.theme.clone();
+    #[cfg(feature = "gui")]
+    let mut fps = settings.fps;
+    #[cfg(feature = "gui")]
+    let mut speak = None;
+    #[cfg(feature = "gui")]
+    let mut puzzle_path = None;
+    #[cfg(feature = "gui")]
+    let mut puzzle_rating = None;
+    #[cfg(feature = "gui")]
+    let mut puzzle_theme = None;
+    #[cfg(feature = "gui")]
+    let mut explorer_path = None;
+    #[cfg(feature = "gui")]
+    let mut database_path = None;
+    let mut event_log = None;
+    let mut correspondence_name = None;
+    let mut host_port = None;
+    let mut host_color = Color::White;
+    let mut join_address = None;
+    #[cfg(feature = "lichess")]
+    let mut lichess_game = None;
+    #[cfg(all(feature = "gui", feature = "spectate"))]
+    let mut spectate_port = None;
+    #[cfg(all(feature = "gui", feature = "spectate"))]
+    let mut spectate_address = chess_game::spectate::DEFAULT_ADDRESS;
+    let mut fen = Vec::new();
+
+    // started without being told what to play, so the window asks with its menu
+    #[cfg(feature = "gui")]
+    let bare = std::env::args().len() <= 1;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--computer" => match args.next().as_deref() {
+                Some("white") => computer = Some(Color::White),
+                Some("black") => computer = Some(Color::Black),
+                _ => return eprintln!("{}", USAGE),
+            },
+            "--engine" => match args.next() {
+                Some(path) => engine_path = Some(path),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--skill" => match args.next().and_then(|level| level.parse().ok()) {
+                Some(level) => skill = Some(level),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--depth" => match args.next().and_then(|plies| plies.parse().ok()) {
+                Some(plies) => depth = Some(plies),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--movetime" => match args.next().and_then(|ms| ms.parse().ok()) {
+                Some(ms) => movetime = Some(ms),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--threads" => match args.next().and_then(|count| count.parse().ok()) {
+                Some(count) if count > 0 => threads = count,
+                _ => return eprintln!("{}", USAGE),
+            },
+            "--resume" => resume = true,
+            "--flip" => flip = true,
+            "--tui" => tui = true,
+            "--clock" => match args.next().as_deref().and_then(TimeControl::parse) {
+                Some(control) => clock = Some(control),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--draws" => match args.next().as_deref().and_then(DrawRules::parse) {
+                Some(rules) => draws = rules,
+                None => return eprintln!("{}", USAGE),
+            },
+            "--pgn" => match args.next() {
+                Some(path) => pgn_path = Some(path),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--host" => match args.next().and_then(|port| port.parse().ok()) {
+                Some(port) => host_port = Some(port),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--color" => match args.next().as_deref() {
+                Some("white") => host_color = Color::White,
+                Some("black") => host_color = Color::Black,
+                _ => return eprintln!("{}", USAGE),
+            },
+            "--join" => match args.next() {
+                // the port can be left out when it's the default one
+                Some(address) if address.contains(':') => join_address = Some(address),
+                Some(address) => join_address = Some(format!("{}:{}", address, DEFAULT_PORT)),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "lichess")]
+            "--lichess" => match args.next() {
+                Some(game) => lichess_game = Some(game),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(all(feature = "gui", feature = "spectate"))]
+            "--spectate-port" => match args.next().and_then(|port| port.parse().ok()) {
+                Some(port) => spectate_port = Some(port),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(all(feature = "gui", feature = "spectate"))]
+            "--spectate-address" => match args.next().and_then(|address| address.parse().ok()) {
+                Some(address) => spectate_address = address,
+                None => return eprintln!("{}", USAGE),
+            },
+            "--book" => match args.next() {
+                Some(path) => book_path = Some(path),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "gui")]
+            "--theme" => match args.next() {
+                Some(path) => theme_path = Some(path.into()),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "gui")]
+            "--fps" => match args.next().and_then(|limit| limit.parse().ok()) {
+                Some(limit) if limit > 0 => fps = Some(limit),
+                _ => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "gui")]
+            "--explorer" => match args.next() {
+                Some(path) => explorer_path = Some(path),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "gui")]
+            "--database" => match args.next() {
+                Some(path) => database_path = Some(path.into()),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "gui")]
+            "--puzzles" => match args.next() {
+                Some(path) => puzzle_path = Some(path),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "gui")]
+            "--puzzle-rating" => {
+                let range = args.next().and_then(|range| {
+                    let (min, max) = range.split_once('-')?;
+                    Some((min.parse().ok()?, max.parse().ok()?))
+                });
+                match range {
+                    Some(range) => puzzle_rating = Some(range),
+                    None => return eprintln!("{}", USAGE),
+                }
+            }
+            #[cfg(feature = "gui")]
+            "--puzzle-theme" => match args.next() {
+                Some(theme) => puzzle_theme = Some(theme),
+                None => return eprintln!("{}", USAGE),
+            },
+            #[cfg(feature = "gui")]
+            "--speak" => match args.next() {
+                Some(command) => speak = Some(command),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--correspondence" => match args.next() {
+                Some(name) => correspondence_name = Some(name),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--correspondence-list" => return list_correspondence(),
+            "--log-events" => match args.next() {
+                Some(path) => event_log = Some(path),
+                None => return eprintln!("{}", USAGE),
+            },
+            "--chess960" => match args.next().as_deref() {
+                Some("random") => chess960_number = Some(chess960::random_number()),
+                Some(number) => match number.parse().ok().filter(|&n| n < chess960::POSITIONS) {
+                    Some(number) => chess960_number = Some(number),
+                    None => return eprintln!("{}", USAGE),
+                },
+                None => return eprintln!("{}", USAGE),
+            },
+            "--variant" => match args.next().as_deref().and_then(variant::by_name) {
+                Some(chosen) => variant = chosen,
+                None => return eprintln!("{}", USAGE),
+            },
+            "-h" | "--help" => return println!("{}", USAGE),
+            // an option this doesn't know, or one left out of this build
+            _ if arg.starts_with("--") => return eprintln!("{}", USAGE),
+            // a fen has spaces in it, so it might come in as several arguments
+            _ => fen.push(arg),
+        }
+    }
+    // a resumed game brings its own position
+    #[cfg(feature = "lichess")]
+    let elsewhere = lichess_game.is_some();
+    #[cfg(not(feature = "lichess"))]
+    let elsewhere = false;
+    // puzzles bring their own positions too, and only play in the window
+    #[cfg(feature = "gui")]
+    let solving = puzzle_path.is_some();
+    #[cfg(not(feature = "gui"))]
+    let solving = false;
+    if solving
+        && (resume
+            || tui
+            || pgn_path.is_some()
+            || !fen.is_empty()
+            || chess960_number.is_some()
+            || host_port.is_some()
+            || join_address.is_some()
+            || elsewhere)
+    {
+        return eprintln!("{}", USAGE);
+    }
+    if resume
+        && (pgn_path.is_some()
+            || !fen.is_empty()
+            || chess960_number.is_some()
+            || host_port.is_some()
+            || join_address.is_some()
+            || elsewhere)
+    {
+        return eprintln!("{}", USAGE);
+    }
+    // a correspondence game is one of its own, however it's played
+    if correspondence_name.is_some()
+        && (resume
+            || solving
+            || pgn_path.is_some()
+            || host_port.is_some()
+            || join_address.is_some())
+    {
+        return eprintln!("{}", USAGE);
+    }
+    let fen = match chess960_number {
+        Some(_) if !fen.is_empty() => return eprintln!("{}", USAGE),
+        Some(number) => {
+            // so a random one can be played again
+            println!("Chess960 position {}", number);
+            chess960::starting_position(number).unwrap().to_fen()
+        }
+        None if fen.is_empty() => STARTING_FEN.to_string(),
+        None => fen.join(" "),
+    };
+    // picking an engine without a side means playing white against it
+    if engine_path.is_some() && computer.is_none() {
+        computer = Some(Color::Black);
+    }
+    // a second a move unless told otherwise
+    if depth.is_none() && movetime.is_none() {
+        movetime = Some(1000);
+    }
+    let limits = SearchLimits {
+        depth,
+        time: movetime.map(Duration::from_millis),
+        ..SearchLimits::default()
+    };
+
+    // a loaded game is only replayed, not played on from
+    let replay = match pgn_path {
+        Some(path) => match std::fs::read_to_string(&path) {
+            Ok(text) => match PgnGame::from_pgn(&text) {
+                Ok(game) => Some(game),
+                Err(error) => return eprintln!("Invalid PGN in {}: {}", path, error),
+            },
+            Err(error) => return eprintln!("Couldn't read {}: {}", path, error),
+        },
+        None => None,
+    };
+
+    // the game left unfinished when the program last closed
+    let saved = if replay.is_none() && !solving {
+        SavedGame::load().unwrap_or_else(|error| {
+            eprintln!("Couldn't read the unfinished game: {}", error);
+            None
+        })
+    } else {
+        None
+    };
+    let resumed = match saved {
+        Some(saved) if resume => Some(saved),
+        None if resume => return eprintln!("There's no unfinished game to resume"),
+        Some(_) => {
+            println!("The last game wasn't finished; --resume plays on from it");
+            None
+        }
+        None => None,
+    };
+    let fen = match &resumed {
+        Some(saved) => {
+            variant = saved.record.variant();
+            saved.record.start().to_fen()
+        }
+        None => fen,
+    };
+
+    // a correspondence game carries on from where it was, or starts from here
+    let correspondence = match correspondence_name {
+        Some(name) => {
+            #[cfg(feature = "lichess")]
+            let link = lichess_game.clone();
+            #[cfg(not(feature = "lichess"))]
+            let link = None;
+            match open_correspondence(&name, &fen, variant.as_ref(), host_color, link) {
+                Ok(game) => Some(game),
+                Err(error) => return eprintln!("Couldn't open {}: {}", name, error),
+            }
+        }
+        None => None,
+    };
+    #[cfg(feature = "lichess")]
+    if let Some(link) = correspondence
+        .as_ref()
+        .and_then(|game| game.lichess.clone())
+    {
+        lichess_game = Some(link);
+    }
+    if let Some(game) = &correspondence {
+        variant = game.record.variant();
+    }
+
+    // a polyglot book for the built in engine and for hints, or the bundled one
+    let book = match book_path {
+        Some(path) => match Book::open(&path) {
+            Ok(book) => book,
+            Err(error) => return eprintln!("Couldn't read {}: {}", path, error),
+        },
+        None => Book::bundled(),
+    };
+
+    let opponent = match engine_path {
+        Some(_) if variant.name() != variant::Standard.name() => {
+            return eprintln!("Only the built in engine plays {}", variant.name())
+        }
+        Some(path) => match start_engine(&path, skill) {
+            Ok(engine) => Opponent::External(engine),
+            Err(error) => return eprintln!("Couldn't start {}: {}", path, error),
+        },
+        None => {
+            let mut engine = Engine::new();
+            engine.set_threads(threads);
+            engine.set_book(Some(book.clone()), DEFAULT_BOOK_PLIES);
+            Opponent::Builtin(engine)
+        }
+    };
+
+    let remote = match (host_port, join_address) {
+        (_, Some(address)) => Some(Remote::Join(address)),
+        (Some(port), None) => Some(Remote::Host(port, host_color)),
+        (None, None) => None,
+    };
+
+    // without the gui feature the terminal is all there is
+    if cfg!(feature = "gui") && !tui {
+        #[cfg(feature = "gui")]
+        match Game::new(&fen, variant) {
+            Ok(mut game) => {
+                game.set_book(book);
+                game.set_threads(threads);
+                game.set_draw_rules(draws);
+                if let Some(path) = theme_path {
+                    game.set_theme_file(path);
+                }
+                if let Some((width, height)) = settings.window_size {
+                    game.set_window_size(width, height);
+                }
+                game.set_key_bindings(settings.keys.clone());
+                if let Some(limit) = fps {
+                    game.set_fps_limit(limit);
+                }
+                if let Some(on) = settings.animations {
+                    game.set_animations(on);
+                }
+                if let Some(command) = speak {
+                    game.set_announcer(speaker(command));
+                }
+                if let Some(path) = &event_log {
+                    match open_event_log(path) {
+                        Ok(log) => game.add_observer(log),
+                        Err(error) => return eprintln!("{}", error),
+                    }
+                }
+                // the one in the config directory if there's one there
+                if let Some(path) = database_path.or_else(Database::default_path) {
+                    match Database::open(&path) {
+                        Ok(database) => game.set_database(database),
+                        Err(error) => {
+                            return eprintln!("Couldn't open {}: {}", path.display(), error)
+                        }
+                    }
+                }
+                if let Some(path) = explorer_path {
+                    match load_explorer(&path) {
+                        Ok(explorer) => game.set_explorer(explorer),
+                        Err(error) => return eprintln!("{}", error),
+                    }
+                }
+                #[cfg(feature = "spectate")]
+                if let Some(port) = spectate_port {
+                    if let Err(error) = game.spectate(spectate_address, port) {
+                        return eprintln!(
+                            "Couldn't serve spectators on {}:{}: {}",
+                            spectate_address, port, error
+                        );
+                    }
+                }
+                #[cfg(feature = "lichess")]
+                if let Some(id) = lichess_game {
+                    // lichess has the last word on the moves, which are kept here as well
+                    if let Some(kept) = correspondence {
+                        game.play_correspondence(kept);
+                    }
+                    let online = chess_game::lichess::Client::from_env()
+                        .and_then(|client| game.play_on_lichess(client, &id));
+                    if let Err(error) = online {
+                        return eprintln!("Couldn't play on lichess: {}", error);
+                    }
+                    return game.game_loop();
+                }
+                if let Some(path) = puzzle_path {
+                    match load_puzzles(&path, puzzle_rating, puzzle_theme.as_deref()) {
+                        Ok(training) => game.play_puzzles(training),
+                        Err(error) => return eprintln!("{}", error),
+                    }
+                } else if let Some(recorded) = replay {
+                    game.replay(recorded);
+                } else if let Some(saved) = resumed {
+                    // it keeps the clock it had
+                    let computer = saved.computer;
+                    game.resume(saved);
+                    if let Some(color) = computer {
+                        game.play_against_computer(color, opponent, limits);
+                    }
+                } else if let Some(kept) = correspondence {
+                    game.play_correspondence(kept);
+                } else if let Some(Remote::Join(address)) = remote {
+                    // the host decides the position and the clock
+                    if let Err(error) = game.join_network_game(&address) {
+                        return eprintln!("Couldn't connect to {}: {}", address, error);
+                    }
+                } else {
+                    if let Some(Remote::Host(port, color)) = remote {
+                        if let Err(error) = game.host_network_game(port, color) {
+                            return eprintln!("Couldn't host on port {}: {}", port, error);
+                        }
+                    } else if let Some(color) = computer {
+                        game.play_against_computer(color, opponent, limits);
+                    }
+                    if let Some(control) = clock {
+                        game.set_clock(control);
+                    }
+                }
+                if flip {
+                    game.set_flipped(true);
+                }
+                if bare {
+                    game.show_menu();
+                }
+                game.game_loop()
+            }
+            Err(ChessError::InvalidFen(error)) => eprintln!("Invalid FEN: {}", error),
+            Err(error @ ChessError::WindowInit(_)) => {
+                eprintln!(
+                    "Couldn't start: {}; --tui plays in the terminal instead",
+                    error
+                )
+            }
+            Err(error) => eprintln!("Couldn't start: {}", error),
+        }
+        return;
+    }
+
+    #[cfg(feature = "lichess")]
+    let remote_game = remote.is_some() || lichess_game.is_some();
+    #[cfg(not(feature = "lichess"))]
+    let remote_game = remote.is_some();
+    if remote_game {
+        return eprintln!("Network and lichess games can't be played in the terminal");
+    }
+    match Terminal::new(&fen, variant) {
+        Ok(mut terminal) => {
+            terminal.set_book(book);
+            terminal.set_draw_rules(draws);
+            if let Some(path) = &event_log {
+                match open_event_log(path) {
+                    Ok(log) => terminal.add_observer(log),
+                    Err(error) => return eprintln!("{}", error),
+                }
+            }
+            if let Some(recorded) = replay {
+                terminal.replay(recorded);
+            } else if let Some(saved) = resumed {
+                let computer = saved.computer;
+                terminal.resume(saved);
+                if let Some(color) = computer {
+                    terminal.play_against_computer(color, opponent, limits);
+                }
+            } else if let Some(kept) = correspondence {
+                terminal.play_correspondence(kept);
+            } else {
+                if let Some(color) = computer {
+                    terminal.play_against_computer(color, opponent, limits);
+                }
+                if let Some(control) = clock {
+                    terminal.set_clock(control);
+                }
+            }
+            if flip {
+                terminal.set_flipped(true);
+            }
+            terminal.game_loop()
+        }
+        Err(error) => eprintln!("Invalid FEN: {}", error),
+    }
+}
+
+// the puzzles in the file with a rating in the range and the theme, when there are any
+#[cfg(feature = "gui")]
+fn load_puzzles(
+    path: &str,
+    rating: Option<(u32, u32)>,
+    theme: Option<&str>,
+) -> Result<Training, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+    let puzzles: Vec<_> = puzzle::parse_puzzles(&text)
+        .map_err(|error| format!("Invalid puzzle in {}: {}", path, error))?
+        .into_iter()
+        .filter(|puzzle| rating.is_none_or(|(min, max)| (min..=max).contains(&puzzle.rating)))
+        .filter(|puzzle| theme.is_none_or(|theme| puzzle.has_theme(theme)))
+        .collect();
+    if puzzles.is_empty() {
+        return Err(format!("There are no puzzles like that in {}", path));
+    }
+    Ok(Training::new(puzzles))
+}
+
+// every game in the file, for the opening explorer
+#[cfg(feature = "gui")]
+fn load_explorer(path: &str) -> Result<Explorer, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+    let explorer =
+        Explorer::from_pgn(&text).map_err(|error| format!("Invalid PGN in {}: {}", path, error))?;
+    println!("{} games in the explorer", explorer.games());
+    Ok(explorer)
+}
+
+// the game kept under the name with whatever's been played elsewhere since, or a new
+// one from the position with `color` played here
+fn open_correspondence(
+    name: &str,
+    fen: &str,
+    variant: &dyn Variant,
+    color: Color,
+    lichess: Option<String>,
+) -> Result<CorrespondenceGame, String> {
+    if !CorrespondenceGame::exists(name) {
+        let mut start = Board::from_fen(fen).map_err(|error| error.to_string())?;
+        variant.setup(&mut start);
+        let mut record = PgnGame::new(&start);
+        record.set_variant(variant);
+        let game = CorrespondenceGame::new(name, record, color, lichess)
+            .map_err(|error| error.to_string())?;
+        let path = game.save().map_err(|error| error.to_string())?;
+        println!("Started {}, kept in {}", name, path.display());
+        return Ok(game);
+    }
+    #[cfg_attr(not(feature = "lichess"), allow(unused_mut))]
+    let mut game = CorrespondenceGame::load(name).map_err(|error| error.to_string())?;
+    #[cfg(feature = "lichess")]
+    catch_up_correspondence(&mut game);
+    Ok(game)
+}
+
+// the moves made on lichess since a game was last looked at, said and kept
+#[cfg(feature = "lichess")]
+fn catch_up_correspondence(game: &mut CorrespondenceGame) {
+    if game.lichess.is_none() {
+        return;
+    }
+    let played = chess_game::lichess::Client::from_env()
+        .map_err(CorrespondenceError::Io)
+        .and_then(|client| game.sync(&client));
+    match played {
+        Ok(played) if played.is_empty() => {}
+        Ok(played) => {
+            println!("{}: the opponent played {}", game.name, played.join(" "));
+            if let Err(error) = game.save() {
+                eprintln!("Couldn't keep {}: {}", game.name, error);
+            }
+        }
+        Err(error) => eprintln!("Couldn't catch up with {} on lichess: {}", game.name, error),
+    }
+}
+
+// every correspondence game kept, caught up with lichess, and whose move it is in each
+fn list_correspondence() {
+    let names = match CorrespondenceGame::list() {
+        Ok(names) => names,
+        Err(error) => return eprintln!("Couldn't look for correspondence games: {}", error),
+    };
+    if names.is_empty() {
+        return println!("No correspondence games yet, --correspondence starts one");
+    }
+    for name in names {
+        #[cfg_attr(not(feature = "lichess"), allow(unused_mut))]
+        let mut game = match CorrespondenceGame::load(&name) {
+            Ok(game) => game,
+            Err(error) => {
+                eprintln!("Couldn't read {}: {}", name, error);
+                continue;
+            }
+        };
+        #[cfg(feature = "lichess")]
+        catch_up_correspondence(&mut game);
+        let state = match game.record.header("Result") {
+            Some(result) if result != "*" => result.to_string(),
+            _ if game.is_our_move() => "your move".to_string(),
+            _ => "waiting for the opponent".to_string(),
+        };
+        let moves = game.record.moves().len();
+        println!("{}: {} plies, {}", name, moves, state);
+    }
+}
+
+fn open_event_log(path: &str) -> Result<EventLog<File>, String> {
+    File::create(path)
+        .map(EventLog::new)
+        .map_err(|error| format!("Couldn't create {}: {}", path, error))
+}
+
+// runs the command with a sentence as its last argument, like espeak or say; it can
+// have arguments of its own separated by spaces
+#[cfg(feature = "gui")]
+fn speaker(command: String) -> impl FnMut(&str) {
+    let mut reported = false;
+    move |text| {
+        let mut words = command.split_whitespace();
+        let program = match words.next() {
+            Some(program) => program,
+            None => return,
+        };
+        let spawned = std::process::Command::new(program)
+            .args(words)
+            .arg(text)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .spawn();
+        match spawned {
+            // reaped on its own thread so it doesn't linger as a zombie
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            // once is enough to know it isn't working
+            Err(error) if !reported => {
+                reported = true;
+                eprintln!("Couldn't run {}: {}", command, error);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+fn start_engine(path: &str, skill: Option<u32>) -> std::io::Result<ExternalEngine> {
+    let mut engine = ExternalEngine::spawn(path)?;
+    if let Some(level) = skill {
+        engine.set_skill_level(level)?;
+    }
+    engine.new_game()?;
+    Ok(engine)
 }
@@ -0,0 +1,73 @@
+use crate::board::{Board, Color, PieceKind, Pocket};
+
+// the usual points for the pieces, a pawn each to a queen's nine
+pub fn points(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => 1,
+        PieceKind::Knight | PieceKind::Bishop => 3,
+        PieceKind::Rook => 5,
+        PieceKind::Queen => 9,
+        PieceKind::King => 0,
+    }
+}
+
+// the pieces each side has in a position, on the board or in hand in crazyhouse. both
+// start with the same, so the kinds one side has more of are the ones it's taken off
+// the other, which is how the captures are shown beside the players: only the
+// difference, so a trade shows nothing and a promoted pawn counts as what it became
+pub struct Material {
+    // by each side, then each of the kinds in a pocket
+    counts: [[i32; 5]; 2],
+}
+
+impl Material {
+    pub fn of(board: &Board) -> Material {
+        let mut counts = [[0; 5]; 2];
+        for (side, &color) in [Color::White, Color::Black].iter().enumerate() {
+            let pocket = board.pocket(color);
+            for (i, &kind) in Pocket::KINDS.iter().enumerate() {
+                counts[side][i] =
+                    board.pieces_of(kind, color).count() as i32 + pocket.count(kind) as i32;
+            }
+        }
+        Material { counts }
+    }
+
+    fn count(&self, color: Color, index: usize) -> i32 {
+        self.counts[color as usize][index]
+    }
+
+    // the other side's pieces `color` is ahead by, most valuable first and each as
+    // many times as it's ahead
+    pub fn captured(&self, color: Color) -> Vec<PieceKind> {
+        let mut captured = Vec::new();
+        for (i, &kind) in Pocket::KINDS.iter().enumerate().rev() {
+            let ahead = self.count(color, i) - self.count(color.opposite(), i);
+            for _ in 0..ahead.max(0) {
+                captured.push(kind);
+            }
+        }
+        captured
+    }
+
+    // how many points `color` is up, negative when it's down
+    pub fn advantage(&self, color: Color) -> i32 {
+        Pocket::KINDS
+            .iter()
+            .enumerate()
+            .map(|(i, &kind)| {
+                (self.count(color, i) - self.count(color.opposite(), i)) * points(kind)
+            })
+            .sum()
+    }
+
+    // like "+2" for the side that's up, nothing for the other or when it's even
+    pub fn advantage_text(&self, color: Color) -> Option<String> {
+        let advantage = self.advantage(color);
+        if advantage > 0 {
+            Some(format!("+{}", advantage))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,543 @@
+use crate::bitboard::{self, Bitboard};
+use crate::board::{Board, Color, Piece, PieceKind, Pocket, Square, Undo};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceKind>,
+    // a piece put on the board from the pocket in crazyhouse, which comes from and goes
+    // to the same square
+    pub drop: Option<PieceKind>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, promotion: PieceKind) -> Move {
+        Move {
+            from,
+            to,
+            promotion: Some(promotion),
+            drop: None,
+        }
+    }
+
+    pub fn with_drop(kind: PieceKind, to: Square) -> Move {
+        Move {
+            from: to,
+            to,
+            promotion: None,
+            drop: Some(kind),
+        }
+    }
+}
+
+// long algebraic notation as used by uci, e.g. e2e4 or e7e8q, and N@f3 for drops
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(kind) = self.drop {
+            let letter = Piece::new(kind, Color::White).to_char();
+            return write!(f, "{}@{}", letter, self.to);
+        }
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(PieceKind::Knight) => write!(f, "n"),
+            Some(PieceKind::Bishop) => write!(f, "b"),
+            Some(PieceKind::Rook) => write!(f, "r"),
+            Some(PieceKind::Queen) => write!(f, "q"),
+            _ => Ok(()),
+        }
+    }
+}
+
+const PROMOTIONS: [PieceKind; 4] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+];
+
+fn forward(color: Color) -> i8 {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+fn home_rank(color: Color) -> u8 {
+    match color {
+        Color::White => 0,
+        Color::Black => 7,
+    }
+}
+
+impl Board {
+    // every move the side to move can make without leaving its own king in check,
+    // drops included when the board has pockets
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let color = self.side_to_move();
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|&mv| {
+                let mut after = self.clone();
+                after.apply_move(mv);
+                !after.is_in_check(color)
+            })
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves().contains(&mv)
+    }
+
+    // the legal move written in uci notation, like e2e4 or e7e8q; castling is taken
+    // either way it's written, e1g1 or e1h1, since a king never otherwise moves two
+    // squares or takes its own rook
+    pub fn parse_uci_move(&self, text: &str) -> Option<Move> {
+        self.legal_moves().into_iter().find(|&mv| {
+            mv.to_string() == text
+                || self
+                    .castling_squares(mv)
+                    .is_some_and(|(rook_from, king_to, _)| {
+                        let other = if self.is_chess960() {
+                            king_to
+                        } else {
+                            rook_from
+                        };
+                        Move::new(mv.from, other).to_string() == text
+                    })
+        })
+    }
+
+    pub fn is_capture(&self, mv: Move) -> bool {
+        let mover = self.piece_at(mv.from).map(|piece| piece.color);
+        self.piece_at(mv.to)
+            .is_some_and(|piece| Some(piece.color) != mover)
+            || (Some(mv.to) == self.en_passant()
+                && self.piece_at(mv.from).map(|piece| piece.kind) == Some(PieceKind::Pawn))
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.pieces_of(PieceKind::King, color).first()
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        match self.king_square(color) {
+            Some(king) => self.is_attacked(king, color.opposite()),
+            None => false,
+        }
+    }
+
+    // whether any piece of `by` attacks `square`, regardless of pins
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        !self.attackers(square, by).is_empty()
+    }
+
+    // every piece of `by` attacking `square`, found by looking outwards from the square
+    // with each piece's attack pattern
+    pub fn attackers(&self, square: Square, by: Color) -> Bitboard {
+        let occupied = self.occupied();
+        let queens = self.pieces_of(PieceKind::Queen, by);
+        // a pawn of `by` attacks the square if a pawn of the other colour standing on it
+        // would attack the pawn
+        (bitboard::pawn_attacks(by.opposite(), square) & self.pieces_of(PieceKind::Pawn, by))
+            | (bitboard::knight_attacks(square) & self.pieces_of(PieceKind::Knight, by))
+            | (bitboard::king_attacks(square) & self.pieces_of(PieceKind::King, by))
+            | (bitboard::rook_attacks(square, occupied)
+                & (self.pieces_of(PieceKind::Rook, by) | queens))
+            | (bitboard::bishop_attacks(square, occupied)
+                & (self.pieces_of(PieceKind::Bishop, by) | queens))
+    }
+
+    // for a castling move, where the rook starts and where the king and the rook end
+    // up; the king always lands on the g or c file and the rook beside it
+    pub fn castling_squares(&self, mv: Move) -> Option<(Square, Square, Square)> {
+        let king = self
+            .piece_at(mv.from)
+            .filter(|piece| piece.kind == PieceKind::King)?;
+        let rank = mv.from.rank();
+        if mv.to.rank() != rank {
+            return None;
+        }
+        let kingside = mv.to.file() > mv.from.file();
+        let rook_from = if self.is_chess960() {
+            if self.piece_at(mv.to) != Some(Piece::new(PieceKind::Rook, king.color)) {
+                return None;
+            }
+            mv.to
+        } else {
+            if (mv.to.file() as i8 - mv.from.file() as i8).abs() != 2 {
+                return None;
+            }
+            Square::new(self.castling_rook(king.color, kingside), rank)
+        };
+        let (king_to, rook_to) = if kingside { (6, 5) } else { (2, 3) };
+        Some((
+            rook_from,
+            Square::new(king_to, rank),
+            Square::new(rook_to, rank),
+        ))
+    }
+
+    // makes a move as part of the game, remembering enough to take it back with
+    // unmake_move() and to detect repetitions; like apply_move it doesn't check legality
+    pub fn make_move(&mut self, mv: Move) {
+        if self.piece_at(mv.from).is_none() && mv.drop.is_none() {
+            return;
+        }
+        let castle = self.castling_squares(mv);
+        let undo = Undo {
+            mv,
+            captured: self.piece_at(mv.to).filter(|_| castle.is_none()),
+            castling: self.castling(),
+            castle,
+            en_passant: self.en_passant(),
+            halfmove_clock: self.halfmove_clock(),
+            pockets: self.pockets(),
+            promoted: self.promoted(),
+            key: self.position_key(),
+        };
+        self.apply_move(mv);
+        self.push_history(undo);
+    }
+
+    // takes back the last move made with make_move(), returning it
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let undo = self.pop_history()?;
+        let mv = undo.mv;
+        let color = self.side_to_move().opposite();
+        if mv.drop.is_some() {
+            self.set_piece(mv.to, None);
+        } else if let Some((rook_from, king_to, rook_to)) = undo.castle {
+            let king = self.piece_at(king_to);
+            let rook = self.piece_at(rook_to);
+            self.set_piece(king_to, None);
+            self.set_piece(rook_to, None);
+            self.set_piece(rook_from, rook);
+            self.set_piece(mv.from, king);
+        } else {
+            let moved = match mv.promotion {
+                Some(_) => Piece::new(PieceKind::Pawn, color),
+                None => self.piece_at(mv.to)?,
+            };
+            self.set_piece(mv.to, undo.captured);
+            self.set_piece(mv.from, Some(moved));
+
+            if moved.kind == PieceKind::Pawn && Some(mv.to) == undo.en_passant {
+                self.set_piece(
+                    Square::new(mv.to.file(), mv.from.rank()),
+                    Some(Piece::new(PieceKind::Pawn, color.opposite())),
+                );
+            }
+        }
+
+        self.restore_pockets(undo.pockets, undo.promoted);
+        self.set_castling(undo.castling);
+        self.set_en_passant(undo.en_passant);
+        self.set_halfmove_clock(undo.halfmove_clock);
+        if color == Color::Black {
+            self.set_fullmove_number(self.fullmove_number() - 1);
+        }
+        self.set_side_to_move(color);
+        Some(mv)
+    }
+
+    // plays a move without checking that it's legal or recording it in the history
+    pub fn apply_move(&mut self, mv: Move) {
+        if let Some(kind) = mv.drop {
+            return self.apply_drop(kind, mv.to);
+        }
+        let piece = match self.piece_at(mv.from) {
+            Some(piece) => piece,
+            None => return,
+        };
+        let color = piece.color;
+        let castle = self.castling_squares(mv);
+        let captured = self.piece_at(mv.to).filter(|_| castle.is_none());
+        let en_passant = piece.kind == PieceKind::Pawn && Some(mv.to) == self.en_passant();
+
+        if self.has_pockets() {
+            // whatever's taken goes in the taker's pocket, as a pawn if it promoted
+            let mut promoted = self.promoted();
+            let taken = match captured {
+                _ if en_passant => Some(PieceKind::Pawn),
+                Some(_) if promoted.contains(mv.to) => Some(PieceKind::Pawn),
+                Some(captured) => Some(captured.kind),
+                None => None,
+            };
+            if let Some(kind) = taken {
+                let mut pocket = self.pocket(color);
+                pocket.add(kind);
+                self.set_pocket(color, pocket);
+            }
+            let was_promoted = promoted.contains(mv.from);
+            promoted &= !(Bitboard::from_square(mv.from) | Bitboard::from_square(mv.to));
+            if mv.promotion.is_some() || (was_promoted && castle.is_none()) {
+                promoted |= Bitboard::from_square(mv.to);
+            }
+            self.set_promoted(promoted);
+        }
+
+        if en_passant {
+            // the captured pawn is beside the moving one, not on the target square
+            self.set_piece(Square::new(mv.to.file(), mv.from.rank()), None);
+        }
+
+        if let Some((rook_from, king_to, rook_to)) = castle {
+            // both come off first, in chess960 either can land where the other was
+            let rook = self.piece_at(rook_from);
+            self.set_piece(mv.from, None);
+            self.set_piece(rook_from, None);
+            self.set_piece(king_to, Some(piece));
+            self.set_piece(rook_to, rook);
+        } else {
+            let placed = match mv.promotion {
+                Some(kind) => Piece::new(kind, color),
+                None => piece,
+            };
+            self.set_piece(mv.from, None);
+            self.set_piece(mv.to, Some(placed));
+        }
+
+        let double_step =
+            piece.kind == PieceKind::Pawn && (mv.to.rank() as i8 - mv.from.rank() as i8).abs() == 2;
+        self.set_en_passant(if double_step {
+            mv.from.offset(0, forward(color))
+        } else {
+            None
+        });
+
+        // moving the king loses both rights, and moving a rook off its starting square or
+        // capturing it there loses that one
+        let mut castling = self.castling();
+        if piece.kind == PieceKind::King {
+            castling.set(color, false, false);
+        }
+        for &square in [mv.from, mv.to].iter() {
+            for &color in [Color::White, Color::Black].iter() {
+                let (mut kingside, mut queenside) = castling.get(color);
+                if square.rank() == home_rank(color) {
+                    kingside &= square.file() != self.castling_rook(color, true);
+                    queenside &= square.file() != self.castling_rook(color, false);
+                }
+                castling.set(color, kingside, queenside);
+            }
+        }
+        self.set_castling(castling);
+
+        if piece.kind == PieceKind::Pawn || captured.is_some() {
+            self.set_halfmove_clock(0);
+        } else {
+            self.set_halfmove_clock(self.halfmove_clock() + 1);
+        }
+        if color == Color::Black {
+            self.set_fullmove_number(self.fullmove_number() + 1);
+        }
+        self.set_side_to_move(color.opposite());
+    }
+
+    // a piece from the side to move's pocket onto an empty square
+    fn apply_drop(&mut self, kind: PieceKind, to: Square) {
+        let color = self.side_to_move();
+        let mut pocket = self.pocket(color);
+        pocket.remove(kind);
+        self.set_pocket(color, pocket);
+        self.set_piece(to, Some(Piece::new(kind, color)));
+        self.set_en_passant(None);
+        self.set_halfmove_clock(self.halfmove_clock() + 1);
+        if color == Color::Black {
+            self.set_fullmove_number(self.fullmove_number() + 1);
+        }
+        self.set_side_to_move(color.opposite());
+    }
+
+    // where the piece on the square might be able to go once the opponent has moved,
+    // for premoves: anywhere it could reach on an otherwise empty board, a pawn's
+    // captures included, and castling while the rights last
+    pub fn premove_targets(&self, from: Square) -> Bitboard {
+        let piece = match self.piece_at(from) {
+            Some(piece) => piece,
+            None => return Bitboard::EMPTY,
+        };
+        let color = piece.color;
+        let mut targets = match piece.kind {
+            PieceKind::Pawn => {
+                let mut targets = bitboard::pawn_attacks(color, from);
+                let dir = forward(color);
+                if let Some(one) = from.offset(0, dir) {
+                    targets |= Bitboard::from_square(one);
+                    if from.rank() as i8 == home_rank(color) as i8 + dir {
+                        targets |= one
+                            .offset(0, dir)
+                            .map_or(Bitboard::EMPTY, Bitboard::from_square);
+                    }
+                }
+                targets
+            }
+            PieceKind::Knight => bitboard::knight_attacks(from),
+            PieceKind::Bishop => bitboard::bishop_attacks(from, Bitboard::EMPTY),
+            PieceKind::Rook => bitboard::rook_attacks(from, Bitboard::EMPTY),
+            PieceKind::Queen => bitboard::queen_attacks(from, Bitboard::EMPTY),
+            PieceKind::King => bitboard::king_attacks(from),
+        };
+        if piece.kind == PieceKind::King && from.rank() == home_rank(color) {
+            let (kingside, queenside) = self.castling().get(color);
+            for &(allowed, side) in [(kingside, true), (queenside, false)].iter() {
+                if !allowed {
+                    continue;
+                }
+                // the king goes where it ends up, or in chess960 onto the rook as well
+                let king_to = if side { 6 } else { 2 };
+                targets |= Bitboard::from_square(Square::new(king_to, from.rank()));
+                if self.is_chess960() {
+                    let rook = Square::new(self.castling_rook(color, side), from.rank());
+                    targets |= Bitboard::from_square(rook);
+                }
+            }
+        }
+        targets & !Bitboard::from_square(from)
+    }
+
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        let color = self.side_to_move();
+        let occupied = self.occupied();
+        let targets = !self.occupied_by(color);
+
+        self.pawn_moves(color, &mut moves);
+
+        let mut add = |from: Square, attacks: Bitboard| {
+            for to in (attacks & targets).squares() {
+                moves.push(Move::new(from, to));
+            }
+        };
+        for from in self.pieces_of(PieceKind::Knight, color).squares() {
+            add(from, bitboard::knight_attacks(from));
+        }
+        for from in self.pieces_of(PieceKind::Bishop, color).squares() {
+            add(from, bitboard::bishop_attacks(from, occupied));
+        }
+        for from in self.pieces_of(PieceKind::Rook, color).squares() {
+            add(from, bitboard::rook_attacks(from, occupied));
+        }
+        for from in self.pieces_of(PieceKind::Queen, color).squares() {
+            add(from, bitboard::queen_attacks(from, occupied));
+        }
+        if let Some(from) = self.king_square(color) {
+            add(from, bitboard::king_attacks(from));
+            self.castling_moves(from, color, &mut moves);
+        }
+        if self.has_pockets() {
+            self.drop_moves(color, &mut moves);
+        }
+        moves
+    }
+
+    // anything in the pocket can go on any empty square, except that pawns can't go on
+    // the first or last rank
+    fn drop_moves(&self, color: Color, moves: &mut Vec<Move>) {
+        let pocket = self.pocket(color);
+        let empty = !self.occupied();
+        for &kind in Pocket::KINDS.iter() {
+            if pocket.count(kind) == 0 {
+                continue;
+            }
+            for to in empty.squares() {
+                if kind == PieceKind::Pawn && (to.rank() == 0 || to.rank() == 7) {
+                    continue;
+                }
+                moves.push(Move::with_drop(kind, to));
+            }
+        }
+    }
+
+    fn pawn_moves(&self, color: Color, moves: &mut Vec<Move>) {
+        let dir = forward(color);
+        let start_rank = (home_rank(color) as i8 + dir) as u8;
+        let last_rank = home_rank(color.opposite());
+        let occupied = self.occupied();
+        let mut enemies = self.occupied_by(color.opposite());
+        if let Some(square) = self.en_passant() {
+            enemies |= Bitboard::from_square(square);
+        }
+
+        let mut push = |from: Square, to: Square| {
+            if to.rank() == last_rank {
+                for &kind in PROMOTIONS.iter() {
+                    moves.push(Move::with_promotion(from, to, kind));
+                }
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        };
+
+        for from in self.pieces_of(PieceKind::Pawn, color).squares() {
+            if let Some(one) = from.offset(0, dir) {
+                if !occupied.contains(one) {
+                    push(from, one);
+                    if from.rank() == start_rank {
+                        if let Some(two) = one.offset(0, dir) {
+                            if !occupied.contains(two) {
+                                push(from, two);
+                            }
+                        }
+                    }
+                }
+            }
+            for to in (bitboard::pawn_attacks(color, from) & enemies).squares() {
+                push(from, to);
+            }
+        }
+    }
+
+    fn castling_moves(&self, from: Square, color: Color, moves: &mut Vec<Move>) {
+        let rank = home_rank(color);
+        let home = if self.is_chess960() {
+            from.rank() == rank
+        } else {
+            from == Square::new(4, rank)
+        };
+        if !home || self.is_attacked(from, color.opposite()) {
+            return;
+        }
+        let (kingside, queenside) = self.castling().get(color);
+        let rook = Some(Piece::new(PieceKind::Rook, color));
+        let occupied = self.occupied();
+        for &(allowed, kingside) in [(kingside, true), (queenside, false)].iter() {
+            let rook_from = Square::new(self.castling_rook(color, kingside), rank);
+            if !allowed || self.piece_at(rook_from) != rook {
+                continue;
+            }
+            let (king_to, rook_to) = if kingside { (6, 5) } else { (2, 3) };
+            // everything either of them crosses or lands on is empty, apart from the
+            // two of them, and the king doesn't pass through check on the way
+            let files = [from.file(), king_to, rook_from.file(), rook_to];
+            let (low, high) = (*files.iter().min().unwrap(), *files.iter().max().unwrap());
+            let empty = (low..=high).all(|file| {
+                let square = Square::new(file, rank);
+                square == from || square == rook_from || !occupied.contains(square)
+            });
+            let path = from.file().min(king_to)..=from.file().max(king_to);
+            let safe = path
+                .into_iter()
+                .all(|file| !self.is_attacked(Square::new(file, rank), color.opposite()));
+            if empty && safe {
+                let to = if self.is_chess960() {
+                    rook_from
+                } else {
+                    Square::new(king_to, rank)
+                };
+                moves.push(Move::new(from, to));
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use crate::board::Color;
+use crate::clock::TimeControl;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// playing another copy of the game directly over tcp: one side hosts and the other
+// joins. every message is a frame of a four byte big endian length followed by that
+// much utf-8 text, the first line saying what kind of message it is
+
+pub const DEFAULT_PORT: u16 = 7272;
+pub const PROTOCOL_VERSION: u32 = 2;
+// the first version still understood, from before there were variants
+const OLDEST_VERSION: u32 = 1;
+
+// how often to send a ping when there's nothing else to say, and how long without
+// hearing anything before the other side counts as gone
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+// no message comes anywhere near this, a bigger length means the stream is garbage
+const MAX_FRAME: usize = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClockState {
+    pub control: TimeControl,
+    pub white: Duration,
+    pub black: Duration,
+}
+
+// everything a guest needs to take over the game, including after reconnecting
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameState {
+    // the side the guest plays
+    pub color: Color,
+    pub fen: String,
+    // the name of the variant, as in pgn's Variant header
+    pub variant: String,
+    // the moves since the fen in uci notation
+    pub moves: Vec<String>,
+    pub clock: Option<ClockState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    // sent by the host whenever a guest connects
+    Hello(GameState),
+    // a move in uci notation, with the mover's time left after it if there's a clock
+    Move {
+        uci: String,
+        remaining: Option<Duration>,
+    },
+    // keeps a quiet connection alive
+    Ping,
+    // the sender gives up the game
+    Resign,
+    // a draw the sender offers, which stands until it's answered or the other side moves
+    DrawOffer,
+    DrawAccept,
+    DrawDecline,
+    // the sender claims the draw its position allows under fide's rules, which the
+    // other side sees for itself
+    DrawClaim,
+    // the sender wants another game with the colours the other way round, or agrees to
+    // the one it was asked for; the host starts it once both have said so
+    Rematch,
+}
+
+impl Message {
+    pub fn to_text(&self) -> String {
+        match self {
+            Message::Hello(state) => {
+                let color = match state.color {
+                    Color::White => "white",
+                    Color::Black => "black",
+                };
+                let mut text = format!(
+                    "hello {}\ncolor {}\nfen {}\nmoves {}",
+                    PROTOCOL_VERSION,
+                    color,
+                    state.fen,
+                    state.moves.join(" ")
+                );
+                text.push_str(&format!("\nvariant {}", state.variant));
+                if let Some(clock) = &state.clock {
+                    text.push_str(&format!(
+                        "\nclock {} {} {}",
+                        clock.control.to_pgn(),
+                        clock.white.as_millis(),
+                        clock.black.as_millis()
+                    ));
+                }
+                text
+            }
+            Message::Move { uci, remaining } => match remaining {
+                Some(remaining) => format!("move {} {}", uci, remaining.as_millis()),
+                None => format!("move {}", uci),
+            },
+            Message::Ping => "ping".to_string(),
+            Message::Resign => "resign".to_string(),
+            Message::DrawOffer => "draw offer".to_string(),
+            Message::DrawAccept => "draw accept".to_string(),
+            Message::DrawDecline => "draw decline".to_string(),
+            Message::DrawClaim => "draw claim".to_string(),
+            Message::Rematch => "rematch".to_string(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Message> {
+        let mut lines = text.lines();
+        let mut first = lines.next()?.split_whitespace();
+        match first.next()? {
+            "hello" => {
+                let version = first.next()?.parse::<u32>().ok()?;
+                if !(OLDEST_VERSION..=PROTOCOL_VERSION).contains(&version) {
+                    return None;
+                }
+                let mut color = None;
+                let mut fen = None;
+                // older versions only played standard chess
+                let mut variant = "Standard".to_string();
+                let mut moves = Vec::new();
+                let mut clock = None;
+                for line in lines {
+                    let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                    match key {
+                        "color" => {
+                            color = match value {
+                                "white" => Some(Color::White),
+                                "black" => Some(Color::Black),
+                                _ => return None,
+                            }
+                        }
+                        "fen" => fen = Some(value.to_string()),
+                        "variant" => variant = value.to_string(),
+                        "moves" => moves = value.split_whitespace().map(String::from).collect(),
+                        "clock" => {
+                            let fields: Vec<&str> = value.split_whitespace().collect();
+                            if fields.len() != 3 {
+                                return None;
+                            }
+                            clock = Some(ClockState {
+                                control: TimeControl::from_pgn(fields[0])?,
+                                white: Duration::from_millis(fields[1].parse().ok()?),
+                                black: Duration::from_millis(fields[2].parse().ok()?),
+                            });
+                        }
+                        // newer versions may say more
+                        _ => {}
+                    }
+                }
+                Some(Message::Hello(GameState {
+                    color: color?,
+                    fen: fen?,
+                    variant,
+                    moves,
+                    clock,
+                }))
+            }
+            "move" => {
+                let uci = first.next()?.to_string();
+                let remaining = match first.next() {
+                    Some(ms) => Some(Duration::from_millis(ms.parse().ok()?)),
+                    None => None,
+                };
+                Some(Message::Move { uci, remaining })
+            }
+            "ping" => Some(Message::Ping),
+            "resign" => Some(Message::Resign),
+            "draw" => match first.next()? {
+                "offer" => Some(Message::DrawOffer),
+                "accept" => Some(Message::DrawAccept),
+                "decline" => Some(Message::DrawDecline),
+                "claim" => Some(Message::DrawClaim),
+                _ => None,
+            },
+            "rematch" => Some(Message::Rematch),
+            _ => None,
+        }
+    }
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<Message> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+    }
+    let mut payload = vec![0; length];
+    stream.read_exact(&mut payload)?;
+    let text = String::from_utf8(payload)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "frame isn't utf-8"))?;
+    Message::parse(&text).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected message {:?}", text),
+        )
+    })
+}
+
+// the other player's end of the connection; a thread reads what it sends so checking
+// for messages never blocks
+pub struct Peer {
+    stream: TcpStream,
+    incoming: Receiver<io::Result<Message>>,
+    last_heard: Instant,
+    last_sent: Instant,
+}
+
+impl Peer {
+    pub fn connect(address: &str) -> io::Result<Peer> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no address to connect to");
+        for address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => return Peer::new(stream),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn new(stream: TcpStream) -> io::Result<Peer> {
+        stream.set_nodelay(true)?;
+        // a peer that stops reading shouldn't hang whoever is sending to it
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut reader = stream.try_clone()?;
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || loop {
+            let message = read_frame(&mut reader);
+            let failed = message.is_err();
+            // the receiving end is gone once the peer is dropped
+            if sender.send(message).is_err() || failed {
+                break;
+            }
+        });
+        Ok(Peer {
+            stream,
+            incoming,
+            last_heard: Instant::now(),
+            last_sent: Instant::now(),
+        })
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let text = message.to_text();
+        let mut frame = (text.len() as u32).to_be_bytes().to_vec();
+        frame.extend_from_slice(text.as_bytes());
+        self.stream.write_all(&frame)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    // the next message that has arrived, if any, answering pings along the way; an
+    // error means the connection is gone, whether it was closed or went quiet
+    pub fn receive(&mut self) -> io::Result<Option<Message>> {
+        if self.last_sent.elapsed() >= PING_INTERVAL {
+            self.send(&Message::Ping)?;
+        }
+        loop {
+            match self.incoming.try_recv() {
+                Ok(Ok(Message::Ping)) => self.last_heard = Instant::now(),
+                Ok(Ok(message)) => {
+                    self.last_heard = Instant::now();
+                    return Ok(Some(message));
+                }
+                Ok(Err(error)) => return Err(error),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        "connection closed",
+                    ))
+                }
+            }
+        }
+        if self.last_heard.elapsed() >= TIMEOUT {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the other player stopped answering",
+            ));
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        // stops the reading thread too
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+// waits for guests without blocking, one at a time
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn bind(port: u16) -> io::Result<Host> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Host { listener })
+    }
+
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    // a guest that has connected since last time, if any
+    pub fn accept(&self) -> io::Result<Option<Peer>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                Peer::new(stream).map(Some)
+            }
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_hello_from_before_variants_is_standard_chess() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let text = format!("hello 1\ncolor black\nfen {}\nmoves e2e4", fen);
+        let state = match Message::parse(&text) {
+            Some(Message::Hello(state)) => state,
+            other => panic!("expected a hello, got {:?}", other),
+        };
+        assert_eq!(state.variant, "Standard");
+        assert_eq!(state.color, Color::Black);
+        assert_eq!(state.moves, vec!["e2e4".to_string()]);
+    }
+
+    #[test]
+    fn a_hello_from_a_newer_version_is_refused() {
+        let text = format!("hello {}\ncolor white\nfen x\nmoves", PROTOCOL_VERSION + 1);
+        assert_eq!(Message::parse(&text), None);
+    }
+}
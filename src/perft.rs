@@ -0,0 +1,126 @@
+use crate::board::Board;
+use crate::moves::Move;
+
+// well known positions with their node counts from depth 1 onwards, see
+// https://www.chessprogramming.org/Perft_Results
+pub const REFERENCE_POSITIONS: &[(&str, &str, &[u64])] = &[
+    (
+        "start",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[20, 400, 8902, 197_281, 4_865_609],
+    ),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2039, 97_862, 4_085_603],
+    ),
+    (
+        "position 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        &[14, 191, 2812, 43_238, 674_624],
+    ),
+    (
+        "position 4",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9467, 422_333],
+    ),
+    (
+        "position 5",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        &[44, 1486, 62_379, 2_103_487],
+    ),
+    (
+        "position 6",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        &[46, 2079, 89_890, 3_894_594],
+    ),
+    // castling with the rooks and king in other places, from
+    // https://www.chessprogramming.org/Chess960_Perft_Results
+    (
+        "chess960 1",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        &[21, 528, 12_189, 326_672, 8_146_062],
+    ),
+    (
+        "chess960 2",
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        &[21, 807, 18_002, 667_366, 16_253_601],
+    ),
+    (
+        "chess960 3",
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        &[20, 479, 10_471, 273_318, 6_417_013],
+    ),
+];
+
+impl Board {
+    // the number of move sequences `depth` plies long, for checking the move generator
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .into_iter()
+            .map(|mv| {
+                let mut after = self.clone();
+                after.apply_move(mv);
+                after.perft(depth - 1)
+            })
+            .sum()
+    }
+
+    // perft split up by the first move, which narrows down where a count goes wrong
+    // when compared against another engine
+    pub fn perft_divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        self.legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut after = self.clone();
+                after.apply_move(mv);
+                (mv, after.perft(depth.saturating_sub(1)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // deep enough to take in castling, en passant and promotions, shallow enough for a
+    // debug build
+    const DEPTH: usize = 3;
+
+    #[test]
+    fn reference_positions_match() {
+        for &(name, fen, counts) in REFERENCE_POSITIONS {
+            let board = Board::from_fen(fen).unwrap();
+            for (depth, &expected) in counts.iter().enumerate().take(DEPTH) {
+                let depth = depth as u32 + 1;
+                assert_eq!(board.perft(depth), expected, "{} at depth {}", name, depth);
+            }
+        }
+    }
+
+    // a few million moves, still only seconds in a debug build
+    #[test]
+    fn start_and_kiwipete_match_at_depth_four() {
+        for &(name, fen, counts) in &REFERENCE_POSITIONS[..2] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.perft(4), counts[3], "{}", name);
+        }
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        for &(name, fen, counts) in REFERENCE_POSITIONS {
+            let board = Board::from_fen(fen).unwrap();
+            let total: u64 = board.perft_divide(2).iter().map(|&(_, nodes)| nodes).sum();
+            assert_eq!(total, counts[1], "{}", name);
+        }
+    }
+}
@@ -0,0 +1,858 @@
+use crate::board::{Board, Color};
+use crate::fen::{FenError, STARTING_FEN};
+use crate::moves::Move;
+use crate::status::GameStatus;
+use crate::variant::{self, Standard, Variant};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// where a line of the variation tree leaves the main line: each (ply, index) goes from
+// the line so far into the index'th variation of its move at ply, so none is the main
+// line itself
+pub type Branches = Vec<(usize, usize)>;
+
+// pgn lines shouldn't be longer than this
+const LINE_LENGTH: usize = 80;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    pub mv: Move,
+    pub san: String,
+    // how long the player took over the move, kept in an %emt comment
+    pub elapsed: Option<Duration>,
+    pub comment: Option<String>,
+    // numeric annotation glyphs, with !, ?, !! and the like turned into 1 to 6
+    pub nags: Vec<u8>,
+    // other lines that could have been played instead of this move
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    fn new(mv: Move, san: String) -> PgnMove {
+        PgnMove {
+            mv,
+            san,
+            elapsed: None,
+            comment: None,
+            nags: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnError {
+    InvalidHeader(String),
+    InvalidFen(FenError),
+    // a move that isn't legal in its position, with the move number it came after
+    IllegalMove(u32, String),
+    UnclosedComment,
+    UnbalancedVariation,
+    NoGame,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::InvalidHeader(header) => write!(f, "invalid header '{}'", header),
+            PgnError::InvalidFen(error) => write!(f, "invalid FEN header: {}", error),
+            PgnError::IllegalMove(number, san) => {
+                write!(f, "illegal move '{}' at move {}", san, number)
+            }
+            PgnError::UnclosedComment => write!(f, "a comment is never closed"),
+            PgnError::UnbalancedVariation => write!(f, "unbalanced parentheses"),
+            PgnError::NoGame => write!(f, "no game found"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+    fn from(error: FenError) -> PgnError {
+        PgnError::InvalidFen(error)
+    }
+}
+
+// a game being recorded as it's played or loaded from a file, ready to be written out
+// as pgn at any point
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    // in the order they're written, the seven tag roster first
+    headers: Vec<(String, String)>,
+    start: Board,
+    // the position after the last move
+    board: Board,
+    moves: Vec<PgnMove>,
+    // a comment before the first move
+    comment: Option<String>,
+}
+
+impl PgnGame {
+    pub fn new(start: &Board) -> PgnGame {
+        let (date, time) = utc_date_time(SystemTime::now());
+        let mut game = PgnGame::empty(start);
+        game.set_header("Event", "Casual game");
+        game.set_header("Site", "?");
+        game.set_header("Date", &date);
+        game.set_header("Round", "-");
+        game.set_header("White", "?");
+        game.set_header("Black", "?");
+        game.set_header("Result", "*");
+        game.set_header("UTCTime", &time);
+        let fen = start.to_fen();
+        if start.is_chess960() {
+            game.set_header("Variant", "Chess960");
+        }
+        // crazyhouse starts from the usual position with nothing in the pockets, which
+        // the Variant header says well enough
+        let usual = fen.replacen("[]", "", 1) == STARTING_FEN;
+        if !usual || start.is_chess960() {
+            game.set_header("SetUp", "1");
+            game.set_header("FEN", &fen);
+        }
+        game
+    }
+
+    fn empty(start: &Board) -> PgnGame {
+        PgnGame {
+            headers: Vec::new(),
+            start: start.clone(),
+            board: start.clone(),
+            moves: Vec::new(),
+            comment: None,
+        }
+    }
+
+    // the first game in the text
+    pub fn from_pgn(text: &str) -> Result<PgnGame, PgnError> {
+        parse_pgn(text)?.into_iter().next().ok_or(PgnError::NoGame)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    // replaces the header if it's already there, otherwise adds it at the end
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // removes the header, handing back what it said
+    pub fn take_header(&mut self, name: &str) -> Option<String> {
+        let index = self.headers.iter().position(|(key, _)| key == name)?;
+        Some(self.headers.remove(index).1)
+    }
+
+    // what the Variant header says, standard chess if it's missing or unknown
+    pub fn variant(&self) -> Box<dyn Variant> {
+        self.header("Variant")
+            .and_then(variant::by_name)
+            .unwrap_or_else(|| Box::new(Standard))
+    }
+
+    // standard chess goes without saying, or is already said by the chess960 header
+    pub fn set_variant(&mut self, variant: &dyn Variant) {
+        if variant.name() != Standard.name() {
+            self.set_header("Variant", variant.name());
+        }
+    }
+
+    pub fn set_player(&mut self, color: Color, name: &str) {
+        match color {
+            Color::White => self.set_header("White", name),
+            Color::Black => self.set_header("Black", name),
+        }
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    pub fn moves(&self) -> &[PgnMove] {
+        &self.moves
+    }
+
+    // for annotating the moves, which stay the same moves
+    pub fn moves_mut(&mut self) -> &mut [PgnMove] {
+        &mut self.moves
+    }
+
+    // the position after the first `ply` moves of the main line
+    pub fn board_at(&self, ply: usize) -> Board {
+        let mut board = self.start.clone();
+        for mv in self.moves.iter().take(ply) {
+            board.make_move(mv.mv);
+        }
+        board
+    }
+
+    // records a legal move in the current position
+    pub fn push(&mut self, mv: Move, elapsed: Option<Duration>) {
+        let mut record = PgnMove::new(mv, mv.to_san(&self.board));
+        record.elapsed = elapsed;
+        self.board.make_move(mv);
+        self.moves.push(record);
+    }
+
+    // takes back the last move of the main line
+    pub fn pop(&mut self) -> Option<PgnMove> {
+        let record = self.moves.pop()?;
+        self.board.unmake_move();
+        Some(record)
+    }
+
+    // the moves of the line the branches lead to, if there is one
+    pub fn line(&self, branches: &[(usize, usize)]) -> Option<&[PgnMove]> {
+        let mut line = self.moves.as_slice();
+        for &(ply, index) in branches {
+            line = line.get(ply)?.variations.get(index)?;
+        }
+        Some(line)
+    }
+
+    fn line_mut(&mut self, branches: &[(usize, usize)]) -> Option<&mut Vec<PgnMove>> {
+        let mut line = &mut self.moves;
+        for &(ply, index) in branches {
+            line = line.get_mut(ply)?.variations.get_mut(index)?;
+        }
+        Some(line)
+    }
+
+    // the position after the first `ply` moves of a line, counting from where it
+    // leaves the one it came off
+    pub fn board_in_line(&self, branches: &[(usize, usize)], ply: usize) -> Option<Board> {
+        let mut board = self.start.clone();
+        let mut line = self.moves.as_slice();
+        for &(at, index) in branches {
+            for mv in line.get(..at)? {
+                board.make_move(mv.mv);
+            }
+            line = line.get(at)?.variations.get(index)?;
+        }
+        for mv in line.get(..ply)? {
+            board.make_move(mv.mv);
+        }
+        Some(board)
+    }
+
+    // a legal move after the first `ply` moves of a line, which goes on along it if
+    // it's the line's next move, into the variation that starts with it if there is
+    // one, and otherwise starts a variation of its own; at the end of a variation it's
+    // added on. the end of the main line is the game's, which only push goes on from.
+    // gives back the line and ply of the position after the move
+    pub fn add_move(
+        &mut self,
+        branches: &[(usize, usize)],
+        ply: usize,
+        mv: Move,
+    ) -> Option<(Branches, usize)> {
+        let san = mv.to_san(&self.board_in_line(branches, ply)?);
+        let is_main = branches.is_empty();
+        let line = self.line_mut(branches)?;
+        let length = line.len();
+        let mut branches = branches.to_vec();
+        match line.get_mut(ply) {
+            Some(next) if next.mv == mv => Some((branches, ply + 1)),
+            Some(next) => {
+                let starts_with = |variation: &Vec<PgnMove>| {
+                    variation.first().is_some_and(|first| first.mv == mv)
+                };
+                let index = match next.variations.iter().position(starts_with) {
+                    Some(index) => index,
+                    None => {
+                        next.variations.push(vec![PgnMove::new(mv, san)]);
+                        next.variations.len() - 1
+                    }
+                };
+                branches.push((ply, index));
+                Some((branches, 1))
+            }
+            None if !is_main && ply == length => {
+                line.push(PgnMove::new(mv, san));
+                Some((branches, ply + 1))
+            }
+            None => None,
+        }
+    }
+
+    // the positions the last of the first `ply` moves of a line could be swapped for,
+    // each just after the first move of one of the lines from there: the one the move
+    // is on, then its variations in order
+    pub fn alternatives(&self, branches: &[(usize, usize)], ply: usize) -> Vec<(Branches, usize)> {
+        // the first move of a variation is one of the moves of the line it came off
+        let (line, at) = match (branches.split_last(), ply) {
+            (_, 0) => return Vec::new(),
+            (Some((&(at, _), parent)), 1) => (parent, at),
+            _ => (branches, ply - 1),
+        };
+        let played = match self.line(line).and_then(|moves| moves.get(at)) {
+            Some(played) => played,
+            None => return Vec::new(),
+        };
+        let mut alternatives = vec![(line.to_vec(), at + 1)];
+        for (index, variation) in played.variations.iter().enumerate() {
+            if !variation.is_empty() {
+                let mut branches = line.to_vec();
+                branches.push((at, index));
+                alternatives.push((branches, 1));
+            }
+        }
+        alternatives
+    }
+
+    // swaps the variation the branches end in with the moves it's the alternative to,
+    // so it's part of the line it came off and they're its first variation, the other
+    // alternatives after them. gives back where the position `ply` moves into the
+    // variation is now
+    pub fn promote_variation(
+        &mut self,
+        branches: &[(usize, usize)],
+        ply: usize,
+    ) -> Option<(Branches, usize)> {
+        let (&(at, index), parent) = branches.split_last()?;
+        let line = self.line_mut(parent)?;
+        if line.get(at)?.variations.get(index)?.is_empty() {
+            return None;
+        }
+        let mut replaced = line.split_off(at);
+        let mut promoted = replaced[0].variations.remove(index);
+        let mut variations = mem::take(&mut replaced[0].variations);
+        variations.insert(0, replaced);
+        variations.append(&mut promoted[0].variations);
+        promoted[0].variations = variations;
+        line.append(&mut promoted);
+        if parent.is_empty() {
+            self.board = self.board_at(self.moves.len());
+        }
+        Some((parent.to_vec(), at + ply))
+    }
+
+    // takes out the variation the branches end in, with the ones inside it
+    pub fn delete_variation(&mut self, branches: &[(usize, usize)]) -> bool {
+        let (&(at, index), parent) = match branches.split_last() {
+            Some(last) => last,
+            None => return false,
+        };
+        let variations = match self.line_mut(parent).and_then(|line| line.get_mut(at)) {
+            Some(played) => &mut played.variations,
+            None => return false,
+        };
+        if index >= variations.len() {
+            return false;
+        }
+        variations.remove(index);
+        true
+    }
+
+    // a draw claimed under fide's rules, said in a comment after the last move since
+    // the result alone doesn't tell it from one the rule made by itself; gives back
+    // what the comment says
+    pub fn note_claim(&mut self, color: Color, draw: GameStatus) -> String {
+        let rule = match draw {
+            GameStatus::FiftyMoveRule => "by the fifty move rule",
+            _ => "by threefold repetition",
+        };
+        let text = format!("{:?} claims a draw {}", color, rule);
+        let comment = match self.moves.last_mut() {
+            Some(last) => &mut last.comment,
+            None => &mut self.comment,
+        };
+        *comment = Some(match comment.take() {
+            Some(earlier) => format!("{} {}", earlier, text),
+            None => text.clone(),
+        });
+        text
+    }
+
+    pub fn set_result(&mut self, status: GameStatus) {
+        self.set_header("Result", result_token(status));
+        if let GameStatus::Timeout(_) = status {
+            self.set_header("Termination", "time forfeit");
+        }
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.headers {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        if let Some(comment) = &self.comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        write_moves(&self.moves, &self.start, &mut tokens);
+        tokens.push(self.header("Result").unwrap_or("*").to_string());
+
+        let mut line_length = 0;
+        let mut previous = "";
+        for token in &tokens {
+            // parentheses hug the variation inside them
+            let spaced = previous != "(" && token != ")";
+            if line_length > 0 && line_length + 1 + token.len() > LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 && spaced {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(token);
+            previous = token;
+        }
+        pgn.push('\n');
+        pgn
+    }
+
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_pgn())
+    }
+
+    // something like game-20201014-183005.pgn, from when the game started
+    pub fn default_file_name(&self) -> String {
+        let date = self.header("Date").unwrap_or("").replace('.', "");
+        let time = self.header("UTCTime").unwrap_or("").replace(':', "");
+        format!("game-{}-{}.pgn", date, time)
+    }
+}
+
+// the movetext of a line starting in `start`, variations included
+fn write_moves(moves: &[PgnMove], start: &Board, tokens: &mut Vec<String>) {
+    let mut number = start.fullmove_number();
+    let mut color = start.side_to_move();
+    // black's moves need their number after anything that interrupted the line
+    let mut interrupted = true;
+    let mut board = start.clone();
+    for mv in moves {
+        if color == Color::White {
+            tokens.push(format!("{}.", number));
+        } else if interrupted {
+            tokens.push(format!("{}...", number));
+        }
+        tokens.push(mv.san.clone());
+        interrupted = false;
+        for nag in &mv.nags {
+            tokens.push(format!("${}", nag));
+        }
+        let mut comment = String::new();
+        if let Some(elapsed) = mv.elapsed {
+            comment.push_str(&format!("[%emt {}]", clock_time(elapsed)));
+        }
+        if let Some(text) = &mv.comment {
+            if !comment.is_empty() {
+                comment.push(' ');
+            }
+            comment.push_str(text);
+        }
+        if !comment.is_empty() {
+            tokens.push(format!("{{{}}}", comment));
+            interrupted = true;
+        }
+        for variation in &mv.variations {
+            tokens.push("(".to_string());
+            write_moves(variation, &board, tokens);
+            tokens.push(")".to_string());
+            interrupted = true;
+        }
+        board.apply_move(mv.mv);
+        if color == Color::Black {
+            number += 1;
+        }
+        color = color.opposite();
+    }
+}
+
+pub fn result_token(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::Ongoing => "*",
+        GameStatus::Checkmate(Color::White)
+        | GameStatus::Timeout(Color::White)
+        | GameStatus::KingOfTheHill(Color::White)
+        | GameStatus::Resignation(Color::White) => "1-0",
+        GameStatus::Checkmate(Color::Black)
+        | GameStatus::Timeout(Color::Black)
+        | GameStatus::KingOfTheHill(Color::Black)
+        | GameStatus::Resignation(Color::Black) => "0-1",
+        _ => "1/2-1/2",
+    }
+}
+
+// every game in the text, as in a file exported from a database
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let mut games = Vec::new();
+    while parser.peek().is_some() {
+        games.push(parser.game()?);
+    }
+    Ok(games)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Header(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    Result(String),
+    San(String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            // a % at the start of a line escapes the rest of it
+            '%' if line_start => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(PgnError::UnclosedComment),
+                    }
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '[' => {
+                let mut header = String::new();
+                let mut quoted = false;
+                loop {
+                    match chars.next() {
+                        Some('"') => {
+                            quoted = !quoted;
+                            header.push('"');
+                        }
+                        Some('\\') if quoted => {
+                            header.push('\\');
+                            header.extend(chars.next());
+                        }
+                        Some(']') if !quoted => break,
+                        Some(c) => header.push(c),
+                        None => return Err(PgnError::InvalidHeader(header)),
+                    }
+                }
+                tokens.push(parse_header(&header)?);
+            }
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                    chars.next();
+                }
+                if let Ok(nag) = digits.parse() {
+                    tokens.push(Token::Nag(nag));
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}()[];$".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokenize_word(&word, &mut tokens);
+            }
+        }
+        line_start = c == '\n';
+    }
+    Ok(tokens)
+}
+
+// a word is a move number, a result, or a move possibly numbered and annotated,
+// like 12.Nf3!?
+fn tokenize_word(word: &str, tokens: &mut Vec<Token>) {
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&word) {
+        tokens.push(Token::Result(word.to_string()));
+        return;
+    }
+    // only digits followed by dots are a move number, 0-0 is castling
+    let digits = word.len() - word.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let word = match word[digits..].strip_prefix('.') {
+        Some(rest) => rest.trim_start_matches('.'),
+        None => word,
+    };
+    let san = word.trim_end_matches(['!', '?']);
+    if !san.is_empty() {
+        tokens.push(Token::San(san.to_string()));
+    }
+    let nag = match &word[san.len()..] {
+        "!" => 1,
+        "?" => 2,
+        "!!" => 3,
+        "??" => 4,
+        "!?" => 5,
+        "?!" => 6,
+        _ => return,
+    };
+    tokens.push(Token::Nag(nag));
+}
+
+fn parse_header(header: &str) -> Result<Token, PgnError> {
+    let invalid = || PgnError::InvalidHeader(header.to_string());
+    let header = header.trim();
+    let (name, value) = header.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value.trim();
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return Err(invalid());
+    }
+    let value = value[1..value.len() - 1]
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\");
+    Ok(Token::Header(name.to_string(), value))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn game(&mut self) -> Result<PgnGame, PgnError> {
+        let mut headers = Vec::new();
+        while let Some(Token::Header(name, value)) = self.peek().cloned() {
+            headers.push((name, value));
+            self.position += 1;
+        }
+        let mut start = match headers.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Board::from_fen(fen)?,
+            None => Board::new(),
+        };
+        // the fen only gives it away when the king and rooks aren't where they usually are
+        let variant = headers.iter().find(|(name, _)| name == "Variant");
+        if variant.is_some_and(|(_, variant)| {
+            let variant = variant.to_ascii_lowercase();
+            variant.contains("960") || variant.starts_with("fischer")
+        }) {
+            start.set_chess960(true);
+        }
+        // and crazyhouse needs its pockets even without a fen
+        if let Some(variant) = variant.and_then(|(_, name)| variant::by_name(name)) {
+            variant.setup(&mut start);
+        }
+        let mut game = PgnGame::empty(&start);
+        game.headers = headers;
+        while let Some(Token::Comment(comment)) = self.peek().cloned() {
+            append_comment(&mut game.comment, &comment);
+            self.position += 1;
+        }
+
+        game.moves = self.line(&start, false)?;
+        match self.peek().cloned() {
+            Some(Token::Result(result)) => {
+                self.position += 1;
+                if game.header("Result").is_none() {
+                    game.set_header("Result", &result);
+                }
+            }
+            Some(Token::Close) => return Err(PgnError::UnbalancedVariation),
+            _ => {}
+        }
+        game.board = game.board_at(game.moves.len());
+        Ok(game)
+    }
+
+    // the moves of a line up to its end: a closing parenthesis if it's a variation,
+    // otherwise the result or the next game's headers
+    fn line(&mut self, start: &Board, variation: bool) -> Result<Vec<PgnMove>, PgnError> {
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut board = start.clone();
+        // the position before the last move, where its variations start
+        let mut before = start.clone();
+        loop {
+            match self.peek().cloned() {
+                Some(Token::San(san)) => {
+                    self.position += 1;
+                    let mv = board
+                        .parse_san(&san)
+                        .map_err(|_| PgnError::IllegalMove(board.fullmove_number(), san))?;
+                    before = board.clone();
+                    moves.push(PgnMove::new(mv, mv.to_san(&board)));
+                    board.apply_move(mv);
+                }
+                Some(Token::Comment(comment)) => {
+                    self.position += 1;
+                    if let Some(last) = moves.last_mut() {
+                        let (elapsed, rest) = split_emt(&comment);
+                        last.elapsed = last.elapsed.or(elapsed);
+                        append_comment(&mut last.comment, rest);
+                    }
+                }
+                Some(Token::Nag(nag)) => {
+                    self.position += 1;
+                    if let Some(last) = moves.last_mut() {
+                        last.nags.push(nag);
+                    }
+                }
+                Some(Token::Open) => {
+                    self.position += 1;
+                    if moves.is_empty() {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    let line = self.line(&before, true)?;
+                    if self.next() != Some(Token::Close) {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    moves.last_mut().unwrap().variations.push(line);
+                }
+                Some(Token::Close) if variation => return Ok(moves),
+                Some(Token::Result(_)) if variation => return Err(PgnError::UnbalancedVariation),
+                None if variation => return Err(PgnError::UnbalancedVariation),
+                _ => return Ok(moves),
+            }
+        }
+    }
+}
+
+fn append_comment(comment: &mut Option<String>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match comment {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(text);
+        }
+        None => *comment = Some(text.to_string()),
+    }
+}
+
+// pulls an [%emt h:mm:ss] command out of a comment
+fn split_emt(comment: &str) -> (Option<Duration>, &str) {
+    let rest = match comment.strip_prefix("[%emt ") {
+        Some(rest) => rest,
+        None => return (None, comment),
+    };
+    let end = match rest.find(']') {
+        Some(end) => end,
+        None => return (None, comment),
+    };
+    let mut seconds = 0;
+    for part in rest[..end].split(':') {
+        match part.parse::<u64>() {
+            Ok(value) => seconds = seconds * 60 + value,
+            Err(_) => return (None, comment),
+        }
+    }
+    (Some(Duration::from_secs(seconds)), rest[end + 1..].trim())
+}
+
+fn clock_time(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// the date as yyyy.mm.dd and the time as hh:mm:ss, both in utc
+fn utc_date_time(time: SystemTime) -> (String, String) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    let (days, seconds) = ((seconds / 86_400) as i64, seconds % 86_400);
+
+    // days since 1970 to a civil date, from
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (
+        format!("{:04}.{:02}.{:02}", year, month, day),
+        format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sans(line: &[PgnMove]) -> Vec<&str> {
+        line.iter().map(|mv| mv.san.as_str()).collect()
+    }
+
+    #[test]
+    fn a_variation_inside_a_variation_can_be_promoted() {
+        let text = "1. e4 (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... e5 *";
+        let mut game = parse_pgn(text).unwrap().remove(0);
+        let inner = [(0, 0), (1, 0)];
+        assert_eq!(sans(game.line(&inner).unwrap()), ["Nf6", "c4"]);
+
+        // one move into the inner line is two moves into the one it came off
+        let promoted = game.promote_variation(&inner, 1);
+        assert_eq!(promoted, Some((vec![(0, 0)], 2)));
+        assert_eq!(sans(game.line(&[(0, 0)]).unwrap()), ["d4", "Nf6", "c4"]);
+        assert_eq!(sans(game.line(&[(0, 0), (1, 0)]).unwrap()), ["d5", "c4"]);
+        assert_eq!(sans(game.moves()), ["e4", "e5"]);
+    }
+
+    #[test]
+    fn a_variation_off_the_game_can_be_promoted() {
+        let text = "1. e4 (1. d4 d5) 1... e5 *";
+        let mut game = parse_pgn(text).unwrap().remove(0);
+        assert_eq!(game.promote_variation(&[(0, 0)], 2), Some((Vec::new(), 2)));
+        assert_eq!(sans(game.moves()), ["d4", "d5"]);
+        assert_eq!(sans(game.line(&[(0, 0)]).unwrap()), ["e4", "e5"]);
+        assert_eq!(game.board.to_fen(), game.board_at(2).to_fen());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::board::{Board, Color};
+use crate::fen::FenError;
+use crate::moves::Move;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PuzzleError {
+    // the line number, counting from 1, and the field that's missing or no good
+    BadField(usize, &'static str),
+    InvalidFen(usize, FenError),
+    // a move of the solution that isn't legal where it comes
+    IllegalMove(usize, String),
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleError::BadField(line, field) => write!(f, "line {}: bad {}", line, field),
+            PuzzleError::InvalidFen(line, error) => write!(f, "line {}: {}", line, error),
+            PuzzleError::IllegalMove(line, mv) => {
+                write!(f, "line {}: illegal move '{}'", line, mv)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+// a tactic from lichess's puzzle database. the position is the one before the
+// opponent's move that sets the puzzle up, so the first move of the solution is
+// theirs and the player's are every other one after it
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    pub id: String,
+    pub board: Board,
+    pub moves: Vec<Move>,
+    pub rating: u32,
+    // like mateIn2, fork or endgame
+    pub themes: Vec<String>,
+    pub url: Option<String>,
+}
+
+impl Puzzle {
+    // the side solving it
+    pub fn color(&self) -> Color {
+        self.board.side_to_move().opposite()
+    }
+
+    pub fn has_theme(&self, theme: &str) -> bool {
+        self.themes
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(theme))
+    }
+}
+
+// every puzzle in a csv laid out like lichess's, with the columns
+// PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,...
+// where the moves are uci and the themes separated by spaces; a header line and blank
+// lines are skipped
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, PuzzleError> {
+    let mut puzzles = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("PuzzleId") {
+            continue;
+        }
+        puzzles.push(parse_line(line, i + 1)?);
+    }
+    Ok(puzzles)
+}
+
+fn parse_line(line: &str, number: usize) -> Result<Puzzle, PuzzleError> {
+    let fields: Vec<&str> = line.split(',').collect();
+    let field = |index: usize, name: &'static str| {
+        fields
+            .get(index)
+            .copied()
+            .filter(|field| !field.is_empty())
+            .ok_or(PuzzleError::BadField(number, name))
+    };
+    let board = Board::from_fen(field(1, "FEN")?)
+        .map_err(|error| PuzzleError::InvalidFen(number, error))?;
+    // the moves have to be legal one after the other to read them at all
+    let mut position = board.clone();
+    let mut moves = Vec::new();
+    for text in field(2, "Moves")?.split_whitespace() {
+        let mv = position
+            .parse_uci_move(text)
+            .filter(|&mv| position.is_legal(mv))
+            .ok_or_else(|| PuzzleError::IllegalMove(number, text.to_string()))?;
+        position.make_move(mv);
+        moves.push(mv);
+    }
+    if moves.len() < 2 {
+        return Err(PuzzleError::BadField(number, "Moves"));
+    }
+    let rating = field(3, "Rating")?
+        .parse()
+        .map_err(|_| PuzzleError::BadField(number, "Rating"))?;
+    Ok(Puzzle {
+        id: field(0, "PuzzleId")?.to_string(),
+        board,
+        moves,
+        rating,
+        themes: fields
+            .get(7)
+            .map(|themes| themes.split_whitespace().map(String::from).collect())
+            .unwrap_or_default(),
+        url: fields
+            .get(8)
+            .filter(|url| !url.is_empty())
+            .map(|url| url.to_string()),
+    })
+}
+
+// how a move the player tried went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attempt {
+    // the solution goes on with the opponent's reply()
+    Right,
+    Solved,
+    // the move the solution wanted instead; the player can try again
+    Wrong(Move),
+}
+
+// working through puzzles one after the other, keeping count of how many were solved
+// without a wrong move on the way
+pub struct Training {
+    puzzles: Vec<Puzzle>,
+    index: usize,
+    // how many moves of the current solution have been played
+    ply: usize,
+    // a wrong move was tried in the current puzzle, so solving it doesn't count
+    missed: bool,
+    streak: u32,
+    best_streak: u32,
+    solved: u32,
+    failed: u32,
+}
+
+impl Training {
+    pub fn new(puzzles: Vec<Puzzle>) -> Training {
+        Training {
+            puzzles,
+            index: 0,
+            ply: 0,
+            missed: false,
+            streak: 0,
+            best_streak: 0,
+            solved: 0,
+            failed: 0,
+        }
+    }
+
+    // none once they've all been done
+    pub fn puzzle(&self) -> Option<&Puzzle> {
+        self.puzzles.get(self.index)
+    }
+
+    // counting from 1, of how many
+    pub fn position(&self) -> (usize, usize) {
+        (self.index + 1, self.puzzles.len())
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.puzzle()
+            .is_some_and(|puzzle| self.ply >= puzzle.moves.len())
+    }
+
+    // what's left of the current solution, starting with the move to play now
+    pub fn remaining(&self) -> &[Move] {
+        match self.puzzle() {
+            Some(puzzle) => &puzzle.moves[self.ply.min(puzzle.moves.len())..],
+            None => &[],
+        }
+    }
+
+    // the opponent's next move of the solution when it's their turn in it, like the
+    // one that sets the puzzle up; it counts as played
+    pub fn reply(&mut self) -> Option<Move> {
+        let puzzle = self.puzzles.get(self.index)?;
+        if !self.ply.is_multiple_of(2) {
+            return None;
+        }
+        let mv = *puzzle.moves.get(self.ply)?;
+        self.ply += 1;
+        Some(mv)
+    }
+
+    // the player's move in `board`, the position the solution has got to. any mate
+    // solves the puzzle, even when the solution mates another way
+    pub fn attempt(&mut self, board: &Board, mv: Move) -> Attempt {
+        let expected = match self.puzzle().and_then(|puzzle| puzzle.moves.get(self.ply)) {
+            Some(&expected) => expected,
+            None => return Attempt::Solved,
+        };
+        let mut after = board.clone();
+        after.make_move(mv);
+        let mates = after.legal_moves().is_empty() && after.is_in_check(after.side_to_move());
+        if mv != expected && !mates {
+            if !self.missed {
+                self.missed = true;
+                self.failed += 1;
+                self.streak = 0;
+            }
+            return Attempt::Wrong(expected);
+        }
+        self.ply += 1;
+        if !mates && self.ply < self.puzzles[self.index].moves.len() {
+            return Attempt::Right;
+        }
+        self.ply = self.puzzles[self.index].moves.len();
+        if !self.missed {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        }
+        Attempt::Solved
+    }
+
+    // on to the next puzzle; leaving one unsolved counts as failing it
+    pub fn next_puzzle(&mut self) -> Option<&Puzzle> {
+        if self.puzzle().is_some() && !self.is_solved() && !self.missed {
+            self.failed += 1;
+            self.streak = 0;
+        }
+        self.index += 1;
+        self.ply = 0;
+        self.missed = false;
+        self.puzzle()
+    }
+
+    // puzzles solved in a row without a wrong move
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+
+    pub fn best_streak(&self) -> u32 {
+        self.best_streak
+    }
+
+    pub fn solved(&self) -> u32 {
+        self.solved
+    }
+
+    pub fn failed(&self) -> u32 {
+        self.failed
+    }
+}
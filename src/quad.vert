@@ -0,0 +1,16 @@
+#version 330 core
+layout (location = 0) in vec2 aPos;
+layout (location = 1) in vec2 aTexCoord;
+
+// (x, y, width, height) of where the unit quad goes, from its bottom left corner
+uniform vec4 rect;
+// (left, bottom, right, top) of the part of the texture to show
+uniform vec4 uv;
+
+out vec2 TexCoord;
+
+void main()
+{
+	gl_Position = vec4(rect.xy + aPos * rect.zw, 0.0, 1.0);
+	TexCoord = mix(uv.xy, uv.zw, aTexCoord);
+}
@@ -0,0 +1,280 @@
+use crate::font::{Font, CELL_HEIGHT, CELL_WIDTH};
+use chess_game::error::ChessError;
+use gpu::{ActiveProgram, Program, Quad, Shader};
+use std::ffi::CString;
+
+mod gpu;
+
+pub use gpu::{Framebuffer, Texture};
+
+// a rectangle in normalized device coordinates, from its bottom left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn offset(self, x: f32, y: f32) -> Rect {
+        Rect {
+            x: self.x + x,
+            y: self.y + y,
+            ..self
+        }
+    }
+}
+
+// the lit segments of each digit, bit 0 being the top one and going clockwise with
+// the middle last
+const SEGMENTS: [u8; 10] = [
+    0b011_1111, 0b000_0110, 0b101_1011, 0b100_1111, 0b110_0110, 0b110_1101, 0b111_1101, 0b000_0111,
+    0b111_1111, 0b110_1111,
+];
+
+// where a line of text goes across the rectangle it's drawn in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+}
+
+// what flat.frag draws inside the rectangle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Fill = 0,
+    Dot = 1,
+    Ring = 2,
+}
+
+// flat.frag's shape for arrows, which need a line as well so aren't a Shape
+const ARROW: i32 = 3;
+
+// everything is drawn with the same unit quad, which the rect uniform stretches
+// over the right part of the screen; a new layer only needs new uniforms, not
+// its own geometry
+pub struct Renderer {
+    flat_shader: Program,
+    sprite_shader: Program,
+    text_shader: Program,
+    font: Font,
+    quad: Quad,
+    // the size of the area being drawn into, in pixels
+    viewport: (i32, i32),
+}
+
+impl Renderer {
+    pub fn new() -> Result<Renderer, ChessError> {
+        // the sources are bundled, so they never have a nul in them
+        let program = |name: &str, frag: &str| -> Result<Program, ChessError> {
+            let vert = Shader::from_vert_source(&CString::new(include_str!("quad.vert")).unwrap())
+                .map_err(|log| ChessError::ShaderCompile("quad.vert".to_string(), log))?;
+            let frag = Shader::from_frag_source(&CString::new(frag).unwrap())
+                .map_err(|log| ChessError::ShaderCompile(name.to_string(), log))?;
+            Program::from_shaders(&[vert, frag]).map_err(ChessError::ShaderLink)
+        };
+        let flat_shader = program("flat.frag", include_str!("flat.frag"))?;
+        let sprite_shader = program("sprite.frag", include_str!("sprite.frag"))?;
+        let text_shader = program("text.frag", include_str!("text.frag"))?;
+
+        // the quad is the only geometry, so it can stay bound
+        let quad = Quad::new();
+        gpu::enable_blending();
+
+        Ok(Renderer {
+            flat_shader,
+            sprite_shader,
+            text_shader,
+            font: Font::new()?,
+            quad,
+            viewport: (1, 1),
+        })
+    }
+
+    // where the following draws go, in framebuffer pixels from the bottom left; the
+    // area spans -1 to 1 in both directions like the whole framebuffer normally would
+    pub fn set_viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.viewport = (width.max(1), height.max(1));
+        gpu::set_viewport(x, y, width, height);
+    }
+
+    // keeps what's drawn inside part of the framebuffer, for a viewport bigger than
+    // the space it has
+    pub fn set_clip(&self, area: Option<(i32, i32, i32, i32)>) {
+        gpu::set_clip(area);
+    }
+
+    // the whole of what's being drawn into, before anything goes on it
+    pub fn clear(&self, color: (f32, f32, f32, f32)) {
+        gpu::clear(color);
+    }
+
+    // digits, colons and points drawn like a digital clock, as big as fits in `rect`
+    // and centred in it; anything else is skipped
+    pub fn seven_segment(&self, text: &str, rect: Rect, color: (f32, f32, f32, f32)) {
+        // widths in glyph heights
+        let width_of = |c: char| if c.is_ascii_digit() { 0.55 } else { 0.2 };
+        let gap = 0.15;
+        let glyphs: Vec<char> = text
+            .chars()
+            .filter(|&c| c.is_ascii_digit() || c == ':' || c == '.')
+            .collect();
+        if glyphs.is_empty() {
+            return;
+        }
+        let text_width: f32 =
+            glyphs.iter().map(|&c| width_of(c)).sum::<f32>() + gap * (glyphs.len() - 1) as f32;
+
+        // work in pixels so the glyphs keep their shape whatever the viewport's aspect
+        let x_pixels = self.viewport.0 as f32 / 2.0;
+        let y_pixels = self.viewport.1 as f32 / 2.0;
+        let height = (rect.height * y_pixels).min(rect.width * x_pixels / text_width);
+        let thickness = height * 0.1;
+        let segment = |x: f32, y: f32, width: f32, height: f32| {
+            self.fill(
+                Rect {
+                    x: x / x_pixels,
+                    y: y / y_pixels,
+                    width: width / x_pixels,
+                    height: height / y_pixels,
+                },
+                color,
+            );
+        };
+
+        let mut x = (rect.x + rect.width / 2.0) * x_pixels - text_width * height / 2.0;
+        let bottom = (rect.y + rect.height / 2.0) * y_pixels - height / 2.0;
+        for &c in &glyphs {
+            let width = width_of(c) * height;
+            let middle = bottom + (height - thickness) / 2.0;
+            match c {
+                ':' => {
+                    let left = x + (width - thickness) / 2.0;
+                    segment(left, bottom + height * 0.25, thickness, thickness);
+                    segment(left, bottom + height * 0.65, thickness, thickness);
+                }
+                '.' => segment(x + (width - thickness) / 2.0, bottom, thickness, thickness),
+                _ => {
+                    // a to g clockwise from the top, then the middle
+                    let lit = SEGMENTS[c.to_digit(10).unwrap() as usize];
+                    let half = height / 2.0;
+                    let top = bottom + height - thickness;
+                    let right = x + width - thickness;
+                    let pieces = [
+                        (x, top, width, thickness),
+                        (right, middle, thickness, half),
+                        (right, bottom, thickness, half),
+                        (x, bottom, width, thickness),
+                        (x, bottom, thickness, half),
+                        (x, middle, thickness, half),
+                        (x, middle, width, thickness),
+                    ];
+                    for (i, &(sx, sy, sw, sh)) in pieces.iter().enumerate() {
+                        if lit & (1 << i) != 0 {
+                            segment(sx, sy, sw, sh);
+                        }
+                    }
+                }
+            }
+            x += width + gap * height;
+        }
+    }
+
+    // a line of text as big as fits in `rect`, centred from top to bottom; it's only
+    // scaled by whole pixels once it's bigger than the font, so the letters stay crisp.
+    // gives back the cells the characters went in, which are all the same width
+    pub fn text(&self, text: &str, rect: Rect, color: (f32, f32, f32, f32), align: Align) -> Rect {
+        let count = text.chars().count();
+        if count == 0 {
+            return Rect { width: 0.0, ..rect };
+        }
+        let x_pixels = self.viewport.0 as f32 / 2.0;
+        let y_pixels = self.viewport.1 as f32 / 2.0;
+        let fit = (rect.height * y_pixels / CELL_HEIGHT as f32)
+            .min(rect.width * x_pixels / (count as u32 * CELL_WIDTH) as f32);
+        // a little slack so a rect made to fit a size exactly gets that size
+        let scale = if fit >= 1.0 {
+            (fit + 1e-3).floor()
+        } else {
+            fit
+        };
+        let (cell_width, cell_height) = (CELL_WIDTH as f32 * scale, CELL_HEIGHT as f32 * scale);
+        // the last letter's spacing column doesn't count
+        let text_width = cell_width * count as f32 - scale;
+        let left = match align {
+            Align::Left => rect.x * x_pixels,
+            Align::Center => (rect.x + rect.width / 2.0) * x_pixels - text_width / 2.0,
+        };
+        let bottom = (rect.y + rect.height / 2.0) * y_pixels - cell_height / 2.0;
+        let (left, bottom) = (left.round(), bottom.round());
+
+        let program = self.text_shader.set_used();
+        program.set_vec4("color", color);
+        self.font.texture().set_used();
+        for (i, c) in text.chars().enumerate() {
+            if c == ' ' {
+                continue;
+            }
+            let cell = Rect {
+                x: (left + i as f32 * cell_width) / x_pixels,
+                y: bottom / y_pixels,
+                width: cell_width / x_pixels,
+                height: cell_height / y_pixels,
+            };
+            self.draw_quad(&program, cell, self.font.uv(c));
+        }
+        Rect {
+            x: left / x_pixels,
+            y: bottom / y_pixels,
+            width: cell_width * count as f32 / x_pixels,
+            height: cell_height / y_pixels,
+        }
+    }
+
+    pub fn fill(&self, rect: Rect, color: (f32, f32, f32, f32)) {
+        self.shape(rect, color, Shape::Fill);
+    }
+
+    pub fn shape(&self, rect: Rect, color: (f32, f32, f32, f32), shape: Shape) {
+        let program = self.flat_shader.set_used();
+        program.set_vec4("color", color);
+        program.set_int("shape", shape as i32);
+        self.draw_quad(&program, rect, (0.0, 0.0, 1.0, 1.0));
+    }
+
+    // an arrow from one point to another, both from the rect's bottom left corner in
+    // fractions of its width and height; it's only the right shape over a square rect
+    pub fn arrow(&self, rect: Rect, from: (f32, f32), to: (f32, f32), color: (f32, f32, f32, f32)) {
+        let program = self.flat_shader.set_used();
+        program.set_vec4("color", color);
+        program.set_int("shape", ARROW);
+        program.set_vec4("line", (from.0, from.1, to.0, to.1));
+        self.draw_quad(&program, rect, (0.0, 0.0, 1.0, 1.0));
+    }
+
+    // `uv` is the (left, bottom, right, top) part of the texture to show, see uv_rect
+    pub fn sprite(&self, rect: Rect, texture: &Texture, uv: (f32, f32, f32, f32)) {
+        self.faded_sprite(rect, texture, uv, 1.0);
+    }
+
+    // a sprite only partly there, from 0 for not at all to 1 for as it is
+    pub fn faded_sprite(
+        &self,
+        rect: Rect,
+        texture: &Texture,
+        uv: (f32, f32, f32, f32),
+        opacity: f32,
+    ) {
+        let program = self.sprite_shader.set_used();
+        program.set_float("opacity", opacity);
+        texture.set_used();
+        self.draw_quad(&program, rect, uv);
+    }
+
+    fn draw_quad(&self, program: &ActiveProgram, rect: Rect, uv: (f32, f32, f32, f32)) {
+        program.set_vec4("rect", (rect.x, rect.y, rect.width, rect.height));
+        program.set_vec4("uv", uv);
+        self.quad.draw(program);
+    }
+}
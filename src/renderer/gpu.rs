@@ -0,0 +1,645 @@
+use chess_game::error::ChessError;
+use gl::types::*;
+use image::{ColorType, GenericImageView};
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+// every gl object the renderer uses, each deleted when it's dropped, and the only
+// place with unsafe code in it. the gl context has to be current on this thread for
+// as long as any of them are alive, which the window makes sure of by dropping them
+// before itself
+
+// following the tutorial from http://nercury.github.io/rust/opengl/tutorial/2018/02/10/opengl-in-rust-from-scratch-03-compiling-shaders.html
+
+pub(super) struct Shader {
+    id: GLuint,
+}
+
+impl Shader {
+    fn from_source(source: &CStr, kind: GLenum) -> Result<Shader, String> {
+        let id = shader_from_source(source, kind)?;
+        Ok(Shader { id })
+    }
+
+    pub(super) fn from_vert_source(source: &CStr) -> Result<Shader, String> {
+        Shader::from_source(source, gl::VERTEX_SHADER)
+    }
+
+    pub(super) fn from_frag_source(source: &CStr) -> Result<Shader, String> {
+        Shader::from_source(source, gl::FRAGMENT_SHADER)
+    }
+
+    fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteShader(self.id);
+        }
+    }
+}
+
+pub(super) struct Program {
+    id: GLuint,
+}
+
+impl Program {
+    pub(super) fn from_shaders(shaders: &[Shader]) -> Result<Program, String> {
+        let program_id = unsafe { gl::CreateProgram() };
+
+        for shader in shaders {
+            unsafe {
+                gl::AttachShader(program_id, shader.id());
+            }
+        }
+
+        unsafe {
+            gl::LinkProgram(program_id);
+        }
+
+        let mut success: GLint = 1;
+        unsafe {
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            let mut len: GLint = 0;
+            unsafe {
+                gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut len);
+            }
+
+            let error = create_whitespace_cstring_with_len(len as usize);
+
+            unsafe {
+                gl::GetProgramInfoLog(
+                    program_id,
+                    len,
+                    std::ptr::null_mut(),
+                    error.as_ptr() as *mut GLchar,
+                );
+            }
+
+            return Err(error.to_string_lossy().into_owned());
+        }
+
+        for shader in shaders {
+            unsafe {
+                gl::DetachShader(program_id, shader.id());
+            }
+        }
+
+        Ok(Program { id: program_id })
+    }
+
+    // uniforms can only be set on the program in use, so they're set through this
+    pub(super) fn set_used(&self) -> ActiveProgram<'_> {
+        unsafe {
+            gl::UseProgram(self.id);
+        }
+        ActiveProgram {
+            program: self,
+            _not_send: PhantomData,
+        }
+    }
+
+    fn location(&self, name: &str) -> GLint {
+        // the names are all written out in the renderer, so they never have a nul in them
+        let name = CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(self.id, name.as_ptr()) }
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
+
+// a program while it's the one in use; it doesn't stop another being used after it,
+// but everything that needs one in use has to be handed this to show it was
+pub(super) struct ActiveProgram<'a> {
+    program: &'a Program,
+    // the context belongs to one thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ActiveProgram<'_> {
+    pub(super) fn set_int(&self, name: &str, value: i32) {
+        unsafe {
+            gl::Uniform1i(self.program.location(name), value);
+        }
+    }
+
+    pub(super) fn set_float(&self, name: &str, value: f32) {
+        unsafe {
+            gl::Uniform1f(self.program.location(name), value);
+        }
+    }
+
+    pub(super) fn set_vec4(&self, name: &str, value: (f32, f32, f32, f32)) {
+        unsafe {
+            gl::Uniform4f(
+                self.program.location(name),
+                value.0,
+                value.1,
+                value.2,
+                value.3,
+            );
+        }
+    }
+}
+
+pub(super) struct VertexArray {
+    id: GLuint,
+}
+
+impl VertexArray {
+    fn new() -> VertexArray {
+        let mut id = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut id);
+        }
+        VertexArray { id }
+    }
+
+    fn bind(&self) {
+        unsafe {
+            gl::BindVertexArray(self.id);
+        }
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.id);
+        }
+    }
+}
+
+// a vertex or index buffer filled once and never changed
+pub(super) struct Buffer {
+    id: GLuint,
+}
+
+impl Buffer {
+    // binds it to `target` as well, which for indices sticks to the bound vertex array
+    fn new<T: Copy>(target: GLenum, data: &[T]) -> Buffer {
+        let mut id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+            gl::BindBuffer(target, id);
+            gl::BufferData(
+                target,
+                std::mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                gl::STATIC_DRAW,
+            );
+        }
+        Buffer { id }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
+
+// the unit square everything is drawn with, from (0, 0) to (1, 1), its texture
+// coordinates the same as its position
+pub(super) struct Quad {
+    vertex_array: VertexArray,
+    _vertices: Buffer,
+    _indices: Buffer,
+}
+
+impl Quad {
+    pub(super) fn new() -> Quad {
+        // position followed by the texture coordinate, which are the same on a unit quad
+        let vertices: [f32; 16] = [
+            1.0, 1.0, 1.0, 1.0, // top right
+            1.0, 0.0, 1.0, 0.0, // bottom right
+            0.0, 0.0, 0.0, 0.0, // bottom left
+            0.0, 1.0, 0.0, 1.0, // top left
+        ];
+        let indices: [u32; 6] = [
+            0, 1, 3, // first Triangle
+            1, 2, 3, // second Triangle
+        ];
+
+        let vertex_array = VertexArray::new();
+        vertex_array.bind();
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER, &vertices);
+        let index_buffer = Buffer::new(gl::ELEMENT_ARRAY_BUFFER, &indices);
+        let stride = 4 * std::mem::size_of::<GLfloat>() as GLsizei;
+        unsafe {
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            gl::VertexAttribPointer(
+                1,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<GLfloat>()) as *const GLvoid,
+            );
+            gl::EnableVertexAttribArray(1);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0); // unbind the buffer
+        }
+        Quad {
+            vertex_array,
+            _vertices: vertex_buffer,
+            _indices: index_buffer,
+        }
+    }
+
+    // with whatever uniforms and texture the program has been given
+    pub(super) fn draw(&self, _program: &ActiveProgram) {
+        self.vertex_array.bind();
+        unsafe {
+            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+        }
+    }
+}
+
+pub struct Texture {
+    id: GLuint,
+    cols: u32,
+    rows: u32,
+}
+
+impl Texture {
+    #[allow(dead_code)]
+    pub fn from_file(path: &str) -> Result<Texture, ChessError> {
+        Texture::from_sprite_sheet(path, 1, 1)
+    }
+
+    // a sheet of equally sized cells, e.g. the usual 6x2 layout of white and black pieces
+    pub fn from_sprite_sheet(path: &str, cols: u32, rows: u32) -> Result<Texture, ChessError> {
+        let img = image::open(std::path::Path::new(path))
+            .map_err(|e| ChessError::TextureLoad(format!("{}: {}", path, e)))?;
+        Texture::from_image(&img, cols, rows)
+    }
+
+    pub fn from_image(
+        img: &image::DynamicImage,
+        cols: u32,
+        rows: u32,
+    ) -> Result<Texture, ChessError> {
+        if cols == 0 || rows == 0 {
+            return Err(ChessError::TextureLoad(
+                "a sprite sheet needs at least one column and row".to_string(),
+            ));
+        }
+        if img.width() == 0 || img.height() == 0 {
+            return Err(ChessError::TextureLoad("the image is empty".to_string()));
+        }
+
+        // gl expects the bottom row of pixels first
+        let img = img.flipv();
+        let (internal_format, format, data) = pixel_format(&img);
+
+        let mut texture_id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture_id);
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR_MIPMAP_LINEAR as i32,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format,
+                img.width() as i32,
+                img.height() as i32,
+                0,
+                format,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const c_void,
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
+
+        Ok(Texture {
+            id: texture_id,
+            cols,
+            rows,
+        })
+    }
+
+    // (left, bottom, right, top) texture coordinates of a cell, counted from the top left
+    // of the image since that's how sprite sheets are laid out
+    pub fn uv_rect(&self, col: u32, row: u32) -> (f32, f32, f32, f32) {
+        cell_uv(self.cols, self.rows, col, row)
+    }
+
+    // sharp edges instead of smooth ones when scaled, for pixel art like the font
+    pub fn set_pixelated(&self) {
+        self.set_used();
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        }
+    }
+
+    pub(super) fn set_used(&self) {
+        unsafe { gl::BindTexture(gl::TEXTURE_2D, self.id) }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+// an offscreen target for pictures of the board, drawn into instead of the window
+// while it's bound; rendering into a texture of its own means the picture can be
+// any size, whatever the window's is
+pub struct Framebuffer {
+    id: GLuint,
+    texture: GLuint,
+    size: (i32, i32),
+}
+
+impl Framebuffer {
+    pub fn new(width: i32, height: i32) -> Result<Framebuffer, ChessError> {
+        let (mut id, mut texture) = (0, 0);
+        let status = unsafe {
+            gl::GenFramebuffers(1, &mut id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as GLint,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture,
+                0,
+            );
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            status
+        };
+        let framebuffer = Framebuffer {
+            id,
+            texture,
+            size: (width, height),
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(ChessError::Screenshot(format!(
+                "the driver can't draw off screen (status {:#x})",
+                status
+            )));
+        }
+        Ok(framebuffer)
+    }
+
+    // everything drawn while the binding's alive goes here, then it's back to the window
+    pub fn bind(&self) -> FramebufferBinding<'_> {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.id) }
+        FramebufferBinding {
+            _framebuffer: PhantomData,
+        }
+    }
+
+    // what's been drawn as rgb rows from the top down, the way image files have them.
+    // alpha is left out since blending leaves it meaningless
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let (width, height) = (self.size.0 as usize, self.size.1 as usize);
+        let mut pixels = vec![0; width * height * 3];
+        let _binding = self.bind();
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                self.size.0,
+                self.size.1,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+        }
+        // gl counts its rows from the bottom
+        let row = width * 3;
+        let mut flipped = Vec::with_capacity(pixels.len());
+        for line in pixels.chunks(row).rev() {
+            flipped.extend_from_slice(line);
+        }
+        flipped
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+// the framebuffer being drawn into, which can't be deleted before drawing's gone
+// back to the window
+pub struct FramebufferBinding<'a> {
+    _framebuffer: PhantomData<&'a Framebuffer>,
+}
+
+impl Drop for FramebufferBinding<'_> {
+    fn drop(&mut self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) }
+    }
+}
+
+// where the following draws go, in framebuffer pixels from the bottom left
+pub(super) fn set_viewport(x: i32, y: i32, width: i32, height: i32) {
+    unsafe {
+        gl::Viewport(x, y, width, height);
+    }
+}
+
+// nothing's drawn outside the area while there is one, in the same pixels as the
+// viewport
+pub(super) fn set_clip(area: Option<(i32, i32, i32, i32)>) {
+    unsafe {
+        match area {
+            Some((x, y, width, height)) => {
+                gl::Enable(gl::SCISSOR_TEST);
+                gl::Scissor(x, y, width, height);
+            }
+            None => gl::Disable(gl::SCISSOR_TEST),
+        }
+    }
+}
+
+pub(super) fn clear((red, green, blue, alpha): (f32, f32, f32, f32)) {
+    unsafe {
+        gl::ClearColor(red, green, blue, alpha);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
+}
+
+// each edge is worked out the same way for the cells either side of it, so they meet
+// exactly instead of leaving a sliver of the next cell showing
+fn cell_uv(cols: u32, rows: u32, col: u32, row: u32) -> (f32, f32, f32, f32) {
+    let across = |i: u32| i as f32 / cols as f32;
+    let down = |i: u32| 1.0 - i as f32 / rows as f32;
+    (across(col), down(row + 1), across(col + 1), down(row))
+}
+
+// see-through parts of sprites and colours show what's under them
+pub(super) fn enable_blending() {
+    unsafe {
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    }
+}
+
+// picks the upload format from the image's color type so alpha channels survive,
+// converting everything else (gray, palette, bgr) to plain rgb
+fn pixel_format(img: &image::DynamicImage) -> (GLint, GLenum, Vec<u8>) {
+    match img.color() {
+        ColorType::RGBA(_) | ColorType::BGRA(_) | ColorType::GrayA(_) => {
+            (gl::RGBA8 as GLint, gl::RGBA, img.to_rgba().into_raw())
+        }
+        _ => (gl::RGB8 as GLint, gl::RGB, img.to_rgb().into_raw()),
+    }
+}
+
+fn shader_from_source(source: &CStr, kind: GLuint) -> Result<GLuint, String> {
+    let id = unsafe { gl::CreateShader(kind) };
+    unsafe {
+        gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
+        gl::CompileShader(id);
+    }
+
+    let mut success: GLint = 1;
+    unsafe {
+        gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+    }
+
+    if success == 0 {
+        let mut len: GLint = 0;
+        unsafe {
+            gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
+        }
+        let error: CString = create_whitespace_cstring_with_len(len as usize);
+        unsafe {
+            gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut GLchar);
+        }
+        return Err(error.to_string_lossy().into_owned());
+    }
+
+    Ok(id)
+}
+
+fn create_whitespace_cstring_with_len(len: usize) -> CString {
+    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
+    buffer.extend([b' '].iter().cycle().take(len));
+    unsafe { CString::from_vec_unchecked(buffer) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+
+    #[test]
+    fn sheet_cells_tile_the_texture() {
+        // the pieces and the font
+        for &(cols, rows) in [(6, 2), (16, 6), (1, 1), (7, 3)].iter() {
+            let mut area = 0.0;
+            for row in 0..rows {
+                for col in 0..cols {
+                    let (left, bottom, right, top) = cell_uv(cols, rows, col, row);
+                    assert!(left < right && bottom < top);
+                    area += (right - left) * (top - bottom);
+                    // each cell ends where the next one along and the next one down start
+                    if col + 1 < cols {
+                        assert_eq!(right, cell_uv(cols, rows, col + 1, row).0);
+                    } else {
+                        assert_eq!(right, 1.0);
+                    }
+                    if row + 1 < rows {
+                        assert_eq!(bottom, cell_uv(cols, rows, col, row + 1).3);
+                    } else {
+                        assert_eq!(bottom, 0.0);
+                    }
+                    if col == 0 {
+                        assert_eq!(left, 0.0);
+                    }
+                    if row == 0 {
+                        assert_eq!(top, 1.0);
+                    }
+                }
+            }
+            assert!((area - 1.0f32).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn alpha_channels_are_uploaded_as_rgba() {
+        for img in [
+            DynamicImage::ImageRgba8(RgbaImage::new(3, 2)),
+            DynamicImage::ImageLumaA8(GrayAlphaImage::new(3, 2)),
+        ]
+        .iter()
+        {
+            let (internal_format, format, data) = pixel_format(img);
+            assert_eq!(internal_format, gl::RGBA8 as GLint);
+            assert_eq!(format, gl::RGBA);
+            assert_eq!(data.len(), 3 * 2 * 4);
+        }
+    }
+
+    #[test]
+    fn everything_else_is_uploaded_as_rgb() {
+        for img in [
+            DynamicImage::ImageRgb8(RgbImage::new(3, 2)),
+            DynamicImage::ImageLuma8(GrayImage::new(3, 2)),
+        ]
+        .iter()
+        {
+            let (internal_format, format, data) = pixel_format(img);
+            assert_eq!(internal_format, gl::RGB8 as GLint);
+            assert_eq!(format, gl::RGB);
+            assert_eq!(data.len(), 3 * 2 * 3);
+        }
+    }
+}
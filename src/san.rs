@@ -0,0 +1,291 @@
+use crate::board::{Board, Piece, PieceKind, Square};
+use crate::moves::Move;
+use std::fmt;
+
+fn piece_letter(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+fn letter_piece(c: char) -> Option<PieceKind> {
+    match c {
+        'N' => Some(PieceKind::Knight),
+        'B' => Some(PieceKind::Bishop),
+        'R' => Some(PieceKind::Rook),
+        'Q' => Some(PieceKind::Queen),
+        'K' => Some(PieceKind::King),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SanError {
+    // not written like a move at all
+    Invalid(String),
+    // no legal move matches
+    Illegal(String),
+    // more than one legal move matches, like Nd2 with knights on b1 and f3
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "'{}' is not a move", san),
+            SanError::Illegal(san) => write!(f, "{} is not legal here", san),
+            SanError::Ambiguous(san) => write!(f, "{} could be more than one move", san),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Board {
+    // the legal move written in standard algebraic notation; check marks and
+    // annotations are ignored, as are extra disambiguation, a missing x, castling
+    // with zeros, and promotions without the =. drops are N@f3, and P@e4 or @e4
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let invalid = || SanError::Invalid(san.to_string());
+
+        if let Some((piece, to)) = text.split_once('@') {
+            let kind = match piece {
+                "" | "P" => PieceKind::Pawn,
+                _ => piece
+                    .chars()
+                    .next()
+                    .filter(|_| piece.len() == 1)
+                    .and_then(letter_piece)
+                    .filter(|&kind| kind != PieceKind::King)
+                    .ok_or_else(invalid)?,
+            };
+            let to = Square::parse(to).ok_or_else(invalid)?;
+            let mv = Move::with_drop(kind, to);
+            return if self.is_legal(mv) {
+                Ok(mv)
+            } else {
+                Err(SanError::Illegal(san.to_string()))
+            };
+        }
+
+        let castle = match text {
+            "O-O" | "0-0" => Some(6),
+            "O-O-O" | "0-0-0" => Some(2),
+            _ => None,
+        };
+        if let Some(file) = castle {
+            return self
+                .legal_moves()
+                .into_iter()
+                .find(|&mv| {
+                    self.castling_squares(mv)
+                        .is_some_and(|(_, king_to, _)| king_to.file() == file)
+                })
+                .ok_or_else(|| SanError::Illegal(san.to_string()));
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+        let kind = match chars.first().copied().and_then(letter_piece) {
+            Some(kind) => {
+                chars.remove(0);
+                kind
+            }
+            None => PieceKind::Pawn,
+        };
+
+        let mut promotion = None;
+        if let Some(kind) = chars
+            .last()
+            .and_then(|&c| letter_piece(c.to_ascii_uppercase()))
+        {
+            // a trailing b is a file, not a bishop, unless it follows a rank
+            let after_rank = chars.len() >= 2 && chars[chars.len() - 2].is_ascii_digit();
+            let after_equals = chars.len() >= 2 && chars[chars.len() - 2] == '=';
+            if after_rank || after_equals {
+                promotion = Some(kind);
+                chars.pop();
+                if after_equals {
+                    chars.pop();
+                }
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(invalid());
+        }
+        let to: String = chars[chars.len() - 2..].iter().collect();
+        let to = Square::parse(&to).ok_or_else(invalid)?;
+
+        // whatever is left between the piece and the target: a file, rank, both, or an x
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' if from_file.is_none() => from_file = Some(c as u8 - b'a'),
+                '1'..='8' if from_rank.is_none() => from_rank = Some(c as u8 - b'1'),
+                'x' | ':' | '-' => {}
+                _ => return Err(invalid()),
+            }
+        }
+
+        let mut matches = self.legal_moves().into_iter().filter(|mv| {
+            mv.to == to
+                && mv.promotion == promotion
+                && self.piece_at(mv.from).map(|piece| piece.kind) == Some(kind)
+                && from_file.is_none_or(|file| mv.from.file() == file)
+                && from_rank.is_none_or(|rank| mv.from.rank() == rank)
+        });
+        match (matches.next(), matches.next()) {
+            (Some(mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+            (None, _) => Err(SanError::Illegal(san.to_string())),
+        }
+    }
+}
+
+impl Move {
+    // standard algebraic notation like Nbd2, exd8=Q+, O-O or N@f3, for a legal move in
+    // `board`
+    pub fn to_san(self, board: &Board) -> String {
+        let piece = match (board.piece_at(self.from), self.drop) {
+            (_, Some(kind)) => Piece::new(kind, board.side_to_move()),
+            (Some(piece), None) => piece,
+            (None, None) => return self.to_string(),
+        };
+        let mut san = String::new();
+
+        if self.drop.is_some() {
+            // the same as in uci
+            san.push_str(&self.to_string());
+        } else if let Some((_, king_to, _)) = board.castling_squares(self) {
+            san.push_str(if king_to.file() == 6 { "O-O" } else { "O-O-O" });
+        } else {
+            let capture = board.is_capture(self);
+            if piece.kind == PieceKind::Pawn {
+                if capture {
+                    san.push((b'a' + self.from.file()) as char);
+                }
+            } else {
+                san.push(piece_letter(piece.kind));
+                san.push_str(&self.disambiguation(board, piece.kind));
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&self.to.to_string());
+            if let Some(kind) = self.promotion {
+                san.push('=');
+                san.push(piece_letter(kind));
+            }
+        }
+
+        let mut after = board.clone();
+        after.apply_move(self);
+        if after.is_in_check(after.side_to_move()) {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    // the file, rank or both of the starting square, when another piece of the same
+    // kind could also go to the target square
+    fn disambiguation(self, board: &Board, kind: PieceKind) -> String {
+        let rivals: Vec<Move> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| {
+                mv.to == self.to
+                    && mv.from != self.from
+                    && board.piece_at(mv.from).map(|piece| piece.kind) == Some(kind)
+            })
+            .collect();
+        if rivals.is_empty() {
+            return String::new();
+        }
+        let file = (b'a' + self.from.file()) as char;
+        let rank = (b'1' + self.from.rank()) as char;
+        if rivals.iter().all(|mv| mv.from.file() != self.from.file()) {
+            file.to_string()
+        } else if rivals.iter().all(|mv| mv.from.rank() != self.from.rank()) {
+            rank.to_string()
+        } else {
+            format!("{}{}", file, rank)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::STARTING_FEN;
+
+    // the move read from `san` is written back the same way
+    fn round_trip(fen: &str, san: &str) {
+        let board = Board::from_fen(fen).unwrap();
+        let mv = board.parse_san(san).unwrap();
+        assert_eq!(mv.to_san(&board), san);
+    }
+
+    fn after(sans: &[&str]) -> Board {
+        let mut board = Board::from_fen(STARTING_FEN).unwrap();
+        for san in sans {
+            let mv = board.parse_san(san).unwrap();
+            board.make_move(mv);
+        }
+        board
+    }
+
+    #[test]
+    fn knights_reaching_the_same_square_are_told_apart() {
+        let by_file = "1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1";
+        round_trip(by_file, "Nbd7");
+        round_trip(by_file, "Nfd7");
+        let board = Board::from_fen(by_file).unwrap();
+        assert_eq!(
+            board.parse_san("Nd7"),
+            Err(SanError::Ambiguous("Nd7".to_string()))
+        );
+
+        let by_rank = "4k3/8/8/6N1/8/8/8/4K1N1 w - - 0 1";
+        round_trip(by_rank, "N1f3");
+        round_trip(by_rank, "N5f3");
+    }
+
+    #[test]
+    fn promotions_name_the_piece() {
+        round_trip("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e8=Q");
+        round_trip("4r3/3P2k1/8/8/8/8/8/4K3 w - - 0 1", "dxe8=N+");
+        let board = Board::from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.parse_san("e8Q"), board.parse_san("e8=Q"));
+    }
+
+    #[test]
+    fn castling_is_written_with_letters() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        round_trip(fen, "O-O");
+        round_trip(fen, "O-O-O");
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(board.parse_san("0-0"), board.parse_san("O-O"));
+    }
+
+    #[test]
+    fn checks_and_mates_are_marked() {
+        let board = after(&["e4", "d5"]);
+        let mv = board.parse_san("Bb5").unwrap();
+        assert_eq!(mv.to_san(&board), "Bb5+");
+
+        let board = after(&["f3", "e5", "g4"]);
+        let mv = board.parse_san("Qh4").unwrap();
+        assert_eq!(mv.to_san(&board), "Qh4#");
+    }
+}
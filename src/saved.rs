@@ -0,0 +1,151 @@
+use crate::board::{Board, Color};
+use crate::clock::{Clock, TimeControl};
+use crate::config;
+use crate::pgn::{PgnError, PgnGame};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum SavedGameError {
+    Io(io::Error),
+    Pgn(PgnError),
+}
+
+impl fmt::Display for SavedGameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SavedGameError::Io(error) => write!(f, "{}", error),
+            SavedGameError::Pgn(error) => write!(f, "invalid PGN: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SavedGameError {}
+
+impl From<io::Error> for SavedGameError {
+    fn from(error: io::Error) -> SavedGameError {
+        SavedGameError::Io(error)
+    }
+}
+
+impl From<PgnError> for SavedGameError {
+    fn from(error: PgnError) -> SavedGameError {
+        SavedGameError::Pgn(error)
+    }
+}
+
+// a game left unfinished when the program closed, to be played on from where it was.
+// it's kept as pgn, with headers of its own for what pgn doesn't say: the time left on
+// each clock in milliseconds, and which side the computer was playing
+#[derive(Clone, Debug)]
+pub struct SavedGame {
+    pub record: PgnGame,
+    // white's and black's, when the game had a clock
+    pub remaining: Option<(Duration, Duration)>,
+    pub computer: Option<Color>,
+}
+
+impl SavedGame {
+    pub fn new(record: &PgnGame, clock: Option<&Clock>, computer: Option<Color>) -> SavedGame {
+        let mut record = record.clone();
+        if let Some(clock) = clock {
+            record.set_header("TimeControl", &clock.control().to_pgn());
+        }
+        SavedGame {
+            record,
+            remaining: clock
+                .map(|clock| (clock.remaining(Color::White), clock.remaining(Color::Black))),
+            computer,
+        }
+    }
+
+    // autosave.pgn in the config directory, whether or not there's a game in it
+    pub fn path() -> Option<PathBuf> {
+        config::directory().map(|directory| directory.join("autosave.pgn"))
+    }
+
+    // the game saved last time, if there is one
+    pub fn load() -> Result<Option<SavedGame>, SavedGameError> {
+        let path = match SavedGame::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(None),
+        };
+        let text = fs::read_to_string(path)?;
+        Ok(Some(SavedGame::from_pgn(&text)?))
+    }
+
+    // keeps the game for next time, replacing whatever was saved before
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = SavedGame::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "there's no home directory"))?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(&path, self.to_pgn())?;
+        Ok(path)
+    }
+
+    // forgets the saved game, once there's nothing left to play on from
+    pub fn discard() -> io::Result<()> {
+        match SavedGame::path() {
+            Some(path) if path.exists() => fs::remove_file(path),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn from_pgn(text: &str) -> Result<SavedGame, PgnError> {
+        // the headers only mean something here, so they're left out of the record
+        let mut record = PgnGame::from_pgn(text)?;
+        let mut time = |name: &str| {
+            record
+                .take_header(name)?
+                .parse()
+                .ok()
+                .map(Duration::from_millis)
+        };
+        let remaining = time("WhiteClock").zip(time("BlackClock"));
+        let computer = match record.take_header("Computer").as_deref() {
+            Some("White") => Some(Color::White),
+            Some("Black") => Some(Color::Black),
+            _ => None,
+        };
+        Ok(SavedGame {
+            record,
+            remaining,
+            computer,
+        })
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let mut record = self.record.clone();
+        if let Some((white, black)) = self.remaining {
+            record.set_header("WhiteClock", &white.as_millis().to_string());
+            record.set_header("BlackClock", &black.as_millis().to_string());
+        }
+        if let Some(color) = self.computer {
+            record.set_header("Computer", &format!("{:?}", color));
+        }
+        record.to_pgn()
+    }
+
+    // the position after the last move
+    pub fn board(&self) -> Board {
+        self.record.board_at(self.record.moves().len())
+    }
+
+    // the clock as it was left, stopped until the game goes on
+    pub fn clock(&self) -> Option<Clock> {
+        let control = TimeControl::from_pgn(self.record.header("TimeControl")?)?;
+        let (white, black) = self.remaining?;
+        Some(Clock::resumed(
+            control,
+            self.record.start().side_to_move(),
+            self.record.moves().len(),
+            white,
+            black,
+        ))
+    }
+}
@@ -0,0 +1,430 @@
+use crate::clock::TimeControl;
+use crate::config::{self, Config, ConfigError};
+use crate::status::DrawRules;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Config(ConfigError),
+    // a setting that's there but isn't allowed, like an unknown key or a clock that
+    // doesn't parse
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(error) => write!(f, "{}", error),
+            SettingsError::Config(error) => write!(f, "{}", error),
+            SettingsError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(error: io::Error) -> SettingsError {
+        SettingsError::Io(error)
+    }
+}
+
+impl From<ConfigError> for SettingsError {
+    fn from(error: ConfigError) -> SettingsError {
+        SettingsError::Config(error)
+    }
+}
+
+// what the window's keys do, besides the arrows, Tab, Enter and Escape, which always
+// find their way around the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Flip,
+    Undo,
+    Redo,
+    Hint,
+    Resign,
+    Save,
+    Picture,
+    Analysis,
+    BlunderCheck,
+    Explorer,
+    Database,
+    Coordinates,
+    ReloadTheme,
+    NextPuzzle,
+    Mute,
+    // offers a draw, or accepts the one on offer
+    OfferDraw,
+    DeclineDraw,
+    Rematch,
+    // sets up a position by hand to play or analyse from
+    Editor,
+    // opens another game alongside, or closes the one shown
+    NewTab,
+    CloseTab,
+    // the variations of the move on the board, in the order they're kept
+    NextVariation,
+    PreviousVariation,
+    // the variation on the board takes the place of the line it came off
+    PromoteVariation,
+    DeleteVariation,
+}
+
+// each command with its name under [keys] and the key it's on unless that says otherwise
+const COMMANDS: [(Command, &str, &str); 25] = [
+    (Command::Flip, "flip", "F"),
+    (Command::Undo, "undo", "Ctrl+Z"),
+    (Command::Redo, "redo", "Ctrl+Y"),
+    (Command::Hint, "hint", "H"),
+    (Command::Resign, "resign", "Ctrl+R"),
+    (Command::Save, "save", "S"),
+    (Command::Picture, "picture", "P"),
+    (Command::Analysis, "analysis", "A"),
+    (Command::BlunderCheck, "blunder_check", "B"),
+    (Command::Explorer, "explorer", "E"),
+    (Command::Database, "database", "D"),
+    (Command::Coordinates, "coordinates", "C"),
+    (Command::ReloadTheme, "theme", "T"),
+    (Command::NextPuzzle, "next_puzzle", "N"),
+    (Command::Mute, "mute", "M"),
+    (Command::OfferDraw, "draw", "Ctrl+D"),
+    (Command::DeclineDraw, "decline_draw", "Ctrl+Shift+D"),
+    (Command::Rematch, "rematch", "Ctrl+N"),
+    (Command::Editor, "editor", "Ctrl+E"),
+    (Command::NewTab, "new_tab", "Ctrl+T"),
+    (Command::CloseTab, "close_tab", "Ctrl+W"),
+    (Command::NextVariation, "next_variation", "PageDown"),
+    (Command::PreviousVariation, "previous_variation", "PageUp"),
+    (Command::PromoteVariation, "promote_variation", "Insert"),
+    (Command::DeleteVariation, "delete_variation", "Delete"),
+];
+
+// the keys that aren't letters, digits or F1 to F12 and can still be bound
+const NAMED_KEYS: [&str; 6] = [
+    "Space",
+    "Backspace",
+    "Delete",
+    "Insert",
+    "PageUp",
+    "PageDown",
+];
+
+impl Command {
+    pub fn name(self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(command, _, _)| *command == self)
+            .unwrap()
+            .1
+    }
+
+    // held down, it happens again and again
+    pub fn repeats(self) -> bool {
+        matches!(self, Command::Undo | Command::Redo)
+    }
+}
+
+// a key with the modifiers that have to be held with it, like Ctrl+Z
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Binding {
+    // a capital letter, a digit, F1 to F12 or one of the named keys
+    pub key: String,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Binding {
+    // modifiers and a key joined by pluses, ignoring case
+    pub fn parse(text: &str) -> Option<Binding> {
+        let mut binding = Binding {
+            key: String::new(),
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop()?;
+        for modifier in parts {
+            let held = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut binding.ctrl,
+                "shift" => &mut binding.shift,
+                "alt" => &mut binding.alt,
+                _ => return None,
+            };
+            if *held {
+                return None;
+            }
+            *held = true;
+        }
+        binding.key = if key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            key.to_ascii_uppercase()
+        } else if let Some(number) = key
+            .strip_prefix(['F', 'f'])
+            .and_then(|number| number.parse::<u8>().ok())
+            .filter(|number| (1..=12).contains(number))
+        {
+            format!("F{}", number)
+        } else {
+            NAMED_KEYS
+                .iter()
+                .find(|name| name.eq_ignore_ascii_case(key))?
+                .to_string()
+        };
+        Some(binding)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+        ] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+// the key each command is on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: Vec<(Command, Binding)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            bindings: COMMANDS
+                .iter()
+                .map(|&(command, _, key)| (command, Binding::parse(key).unwrap()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    // the command on a key, named like a Binding's, pressed with exactly these modifiers
+    pub fn command(&self, key: &str, ctrl: bool, shift: bool, alt: bool) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(_, binding)| {
+                binding.key == key
+                    && binding.ctrl == ctrl
+                    && binding.shift == shift
+                    && binding.alt == alt
+            })
+            .map(|&(command, _)| command)
+    }
+
+    pub fn binding(&self, command: Command) -> &Binding {
+        &self
+            .bindings
+            .iter()
+            .find(|(known, _)| *known == command)
+            .unwrap()
+            .1
+    }
+
+    fn set(&mut self, command: Command, binding: Binding) {
+        for (known, old) in &mut self.bindings {
+            if *known == command {
+                *old = binding.clone();
+            }
+        }
+    }
+}
+
+// the defaults for everything the command line can say as well, and what the keys do,
+// read from a toml file like
+//
+//     [game]
+//     clock = "5+3"
+//     draws = "fide"
+//
+//     [engine]
+//     skill = 10
+//     movetime = 2000
+//
+//     [window]
+//     width = 1200
+//     height = 900
+//     theme = "theme.toml"
+//     animations = false
+//
+//     [keys]
+//     flip = "Ctrl+F"
+//     resign = "F10"
+//
+//     [lichess]
+//     token = "lip_..."
+//
+// where everything can be left out. the command line wins over the file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    pub clock: Option<TimeControl>,
+    pub draws: Option<DrawRules>,
+    // the strength of an engine that takes a skill level
+    pub skill: Option<u32>,
+    pub depth: Option<u32>,
+    // in milliseconds
+    pub movetime: Option<u64>,
+    pub threads: Option<usize>,
+    // relative to the settings file
+    pub theme: Option<PathBuf>,
+    // in screen coordinates, before the screen's scale
+    pub window_size: Option<(u32, u32)>,
+    pub fps: Option<u32>,
+    // pieces sliding and fading and checks flashing, which false leaves out
+    pub animations: Option<bool>,
+    pub keys: KeyBindings,
+    // the account games on lichess are played from, unless LICHESS_TOKEN says otherwise
+    pub lichess_token: Option<String>,
+}
+
+impl Settings {
+    // config.toml in the config directory
+    pub fn default_path() -> Option<PathBuf> {
+        config::directory().map(|directory| directory.join("config.toml"))
+    }
+
+    // the defaults when there's no file there
+    pub fn load(path: &Path) -> Result<Settings, SettingsError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Settings::parse(&text, path.parent().unwrap_or_else(|| Path::new(""))),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    // `directory` is where paths in the file start from
+    pub fn parse(text: &str, directory: &Path) -> Result<Settings, SettingsError> {
+        let config = Config::parse(text)?;
+        let mut settings = Settings::default();
+        let mut width = None;
+        let mut height = None;
+        for key in config.keys() {
+            let value = config.get(key).unwrap();
+            let invalid =
+                |expected: &str| SettingsError::Invalid(format!("{} should be {}", key, expected));
+            let number = |min: i64| {
+                value
+                    .as_i64()
+                    .filter(|&number| number >= min)
+                    .ok_or_else(|| invalid(&format!("a whole number from {} up", min)))
+            };
+            match key {
+                "game.clock" => {
+                    let control = value.as_str().and_then(TimeControl::parse);
+                    settings.clock =
+                        Some(control.ok_or_else(|| invalid("a time control like \"5+3\""))?);
+                }
+                "game.draws" => {
+                    let rules = value.as_str().and_then(DrawRules::parse);
+                    settings.draws =
+                        Some(rules.ok_or_else(|| invalid("\"automatic\" or \"fide\""))?);
+                }
+                "engine.skill" => settings.skill = Some(number(0)? as u32),
+                "engine.depth" => settings.depth = Some(number(1)? as u32),
+                "engine.movetime" => settings.movetime = Some(number(1)? as u64),
+                "engine.threads" => settings.threads = Some(number(1)? as usize),
+                "window.theme" => {
+                    let theme = value
+                        .as_str()
+                        .ok_or_else(|| invalid("the path of a theme file"))?;
+                    settings.theme = Some(directory.join(theme));
+                }
+                "window.width" => width = Some(number(200)? as u32),
+                "window.height" => height = Some(number(200)? as u32),
+                "window.fps" => settings.fps = Some(number(1)? as u32),
+                "window.animations" => {
+                    let on = value.as_bool().ok_or_else(|| invalid("true or false"))?;
+                    settings.animations = Some(on);
+                }
+                "lichess.token" => {
+                    let token = value.as_str().ok_or_else(|| invalid("a quoted token"))?;
+                    settings.lichess_token = Some(token.to_string());
+                }
+                _ => match key.strip_prefix("keys.") {
+                    Some(name) => {
+                        let command = COMMANDS
+                            .iter()
+                            .find(|(_, known, _)| *known == name)
+                            .map(|&(command, _, _)| command)
+                            .ok_or_else(|| {
+                                SettingsError::Invalid(format!(
+                                    "there's no command called {}",
+                                    name
+                                ))
+                            })?;
+                        let binding = value
+                            .as_str()
+                            .and_then(Binding::parse)
+                            .ok_or_else(|| invalid("a key like \"F\" or \"Ctrl+Z\""))?;
+                        settings.keys.set(command, binding);
+                    }
+                    None => {
+                        return Err(SettingsError::Invalid(format!(
+                            "there's no setting called {}",
+                            key
+                        )))
+                    }
+                },
+            }
+        }
+        settings.window_size = match (width, height) {
+            (Some(width), Some(height)) => Some((width, height)),
+            (None, None) => None,
+            _ => {
+                return Err(SettingsError::Invalid(
+                    "window.width and window.height go together".to_string(),
+                ))
+            }
+        };
+        // a key does one thing, whichever were set and whichever were left as they were
+        for (i, (command, binding)) in settings.keys.bindings.iter().enumerate() {
+            if let Some((other, _)) = settings.keys.bindings[i + 1..]
+                .iter()
+                .find(|(_, other)| other == binding)
+            {
+                return Err(SettingsError::Invalid(format!(
+                    "{} is bound to both {} and {}",
+                    binding,
+                    command.name(),
+                    other.name()
+                )));
+            }
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_lichess_token_comes_from_its_table() {
+        let settings = Settings::parse("[lichess]\ntoken = \"lip_abc\"\n", Path::new("")).unwrap();
+        assert_eq!(settings.lichess_token.as_deref(), Some("lip_abc"));
+        assert_eq!(Settings::default().lichess_token, None);
+        assert!(Settings::parse("[lichess]\ntoken = 12", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn a_bad_line_in_the_file_says_where_it_is() {
+        let text = "[game]\nclock = \"5+3\"\n[engine\n";
+        match Settings::parse(text, Path::new("")) {
+            Err(SettingsError::Config(error)) => assert_eq!(error.line, 3),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::board::{Board, CastlingRights, Color, PieceKind, Square};
+use std::fmt;
+
+// what's wrong with a position put together by hand, before a game can start from it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetupError {
+    // the side and how many kings it has, when it isn't one
+    KingCount(Color, u32),
+    PawnOnBackRank(Square),
+    // the side that just moved can't have left its own king attacked
+    OpponentInCheck(Color),
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetupError::KingCount(color, count) => {
+                write!(f, "{:?} has {} kings instead of one", color, count)
+            }
+            SetupError::PawnOnBackRank(square) => write!(f, "a pawn can't be on {}", square),
+            SetupError::OpponentInCheck(color) => {
+                write!(f, "{:?} is in check but it's not their move", color)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SetupError {}
+
+// the pieces a handicap game is given without, always from the standard start
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Odds {
+    // the f pawn
+    Pawn,
+    // the queen's knight, on b1 or b8
+    Knight,
+    // the queen's rook, which takes castling queenside with it
+    Rook,
+    Queen,
+}
+
+impl Odds {
+    pub const ALL: [Odds; 4] = [Odds::Pawn, Odds::Knight, Odds::Rook, Odds::Queen];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Odds::Pawn => "pawn",
+            Odds::Knight => "knight",
+            Odds::Rook => "rook",
+            Odds::Queen => "queen",
+        }
+    }
+
+    // the starting position with `giver` a piece short
+    pub fn board(self, giver: Color) -> Board {
+        let mut board = Board::new();
+        let rank = match giver {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        let square = match self {
+            Odds::Pawn if giver == Color::White => Square::new(5, 1),
+            Odds::Pawn => Square::new(5, 6),
+            Odds::Knight => Square::new(1, rank),
+            Odds::Rook => Square::new(0, rank),
+            Odds::Queen => Square::new(3, rank),
+        };
+        board.set_piece(square, None);
+        fix_castling(&mut board);
+        board
+    }
+}
+
+// one king each, no pawns on the first or last rank, and the side that isn't to move
+// not in check; castling rights the pieces can't have are left to fix_castling()
+pub fn validate(board: &Board) -> Result<(), SetupError> {
+    for &color in [Color::White, Color::Black].iter() {
+        let kings = board.pieces_of(PieceKind::King, color).count();
+        if kings != 1 {
+            return Err(SetupError::KingCount(color, kings));
+        }
+    }
+    let back_rank = board
+        .pieces()
+        .find(|&(square, piece)| {
+            piece.kind == PieceKind::Pawn && (square.rank() == 0 || square.rank() == 7)
+        })
+        .map(|(square, _)| square);
+    if let Some(square) = back_rank {
+        return Err(SetupError::PawnOnBackRank(square));
+    }
+    let waiting = board.side_to_move().opposite();
+    if board.is_in_check(waiting) {
+        return Err(SetupError::OpponentInCheck(waiting));
+    }
+    Ok(())
+}
+
+// whether the king and the rook are still where standard chess castles from
+pub fn can_castle(board: &Board, color: Color, kingside: bool) -> bool {
+    let rank = match color {
+        Color::White => 0,
+        Color::Black => 7,
+    };
+    let on = |file: u8, kind: PieceKind| {
+        board
+            .piece_at(Square::new(file, rank))
+            .is_some_and(|piece| piece.kind == kind && piece.color == color)
+    };
+    on(4, PieceKind::King) && on(if kingside { 7 } else { 0 }, PieceKind::Rook)
+}
+
+// takes away the castling rights of kings and rooks that have moved off their squares
+pub fn fix_castling(board: &mut Board) {
+    let mut rights = CastlingRights::none();
+    for &color in [Color::White, Color::Black].iter() {
+        let (kingside, queenside) = board.castling().get(color);
+        rights.set(
+            color,
+            kingside && can_castle(board, color, true),
+            queenside && can_castle(board, color, false),
+        );
+    }
+    board.set_castling(rights);
+}
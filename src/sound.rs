@@ -0,0 +1,224 @@
+use chess_game::board::Board;
+use chess_game::moves::Move;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+// sound effects, made up on the spot as wav files and played by whichever of the
+// usual command line players the system has, so there's no audio library to lean on
+
+const SAMPLE_RATE: u32 = 22050;
+
+// the programs tried in turn, each given the path of the file to play
+const PLAYERS: &[&[&str]] = &[&["paplay"], &["aplay", "-q"], &["afplay"], &["play", "-q"]];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sound {
+    Move,
+    Capture,
+    Castle,
+    Check,
+    GameEnd,
+}
+
+// a note of a sound: when it starts and how long it rings in milliseconds, its pitch
+// in hertz and how loud it is out of 1
+struct Tone {
+    start: u32,
+    length: u32,
+    pitch: f32,
+    volume: f32,
+}
+
+impl Sound {
+    const ALL: [Sound; 5] = [
+        Sound::Move,
+        Sound::Capture,
+        Sound::Castle,
+        Sound::Check,
+        Sound::GameEnd,
+    ];
+
+    // the sound of a legal move, from the position it's played in
+    pub fn of_move(board: &Board, mv: Move) -> Sound {
+        let mut after = board.clone();
+        after.apply_move(mv);
+        if after.is_in_check(after.side_to_move()) {
+            Sound::Check
+        } else if board.castling_squares(mv).is_some() {
+            Sound::Castle
+        } else if board.is_capture(mv) {
+            Sound::Capture
+        } else {
+            Sound::Move
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Sound::Move => "move",
+            Sound::Capture => "capture",
+            Sound::Castle => "castle",
+            Sound::Check => "check",
+            Sound::GameEnd => "end",
+        }
+    }
+
+    fn tones(self) -> Vec<Tone> {
+        let tone = |start, length, pitch, volume| Tone {
+            start,
+            length,
+            pitch,
+            volume,
+        };
+        match self {
+            // a short knock, like a piece put down on a wooden board
+            Sound::Move => vec![tone(0, 70, 520.0, 0.6)],
+            // lower and louder
+            Sound::Capture => vec![tone(0, 90, 330.0, 0.8), tone(0, 60, 660.0, 0.3)],
+            // the king and then the rook
+            Sound::Castle => vec![tone(0, 70, 520.0, 0.6), tone(110, 70, 440.0, 0.6)],
+            Sound::Check => vec![tone(0, 90, 520.0, 0.5), tone(60, 160, 880.0, 0.5)],
+            // a rising chord
+            Sound::GameEnd => vec![
+                tone(0, 400, 523.3, 0.4),
+                tone(150, 400, 659.3, 0.4),
+                tone(300, 600, 784.0, 0.4),
+            ],
+        }
+    }
+
+    // 16 bit mono pcm in a wav file
+    fn to_wav(self) -> Vec<u8> {
+        let tones = self.tones();
+        let end = tones.iter().map(|tone| tone.start + tone.length).max();
+        let samples = (end.unwrap_or(0) * SAMPLE_RATE / 1000) as usize;
+        let mut wave = vec![0.0f32; samples];
+        for tone in &tones {
+            let start = (tone.start * SAMPLE_RATE / 1000) as usize;
+            let length = (tone.length * SAMPLE_RATE / 1000) as usize;
+            for i in 0..length {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                // dies away quickly, and starts a little softly so it doesn't click
+                let fade = (-6.0 * i as f32 / length as f32).exp() * (i as f32 / 40.0).min(1.0);
+                wave[start + i] +=
+                    tone.volume * fade * (2.0 * std::f32::consts::PI * tone.pitch * t).sin();
+            }
+        }
+
+        let data = (samples * 2) as u32;
+        let mut bytes = Vec::with_capacity(44 + data as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        // pcm, one channel
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data.to_le_bytes());
+        for sample in wave {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32 * 0.8) as i16;
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+// plays the sounds in the background, or doesn't once it's muted or if there's no
+// way to play them
+pub struct Speaker {
+    muted: bool,
+    files: Vec<(Sound, PathBuf)>,
+    // the player that worked, once one has
+    player: Option<&'static [&'static str]>,
+    // set when none of them do, so it only gets reported once
+    failed: bool,
+}
+
+impl Speaker {
+    pub fn new() -> Speaker {
+        Speaker {
+            muted: false,
+            files: Vec::new(),
+            player: None,
+            failed: false,
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        if self.muted || self.failed {
+            return;
+        }
+        if let Err(error) = self.try_play(sound) {
+            eprintln!("Couldn't play sounds: {}", error);
+            self.failed = true;
+        }
+    }
+
+    fn try_play(&mut self, sound: Sound) -> io::Result<()> {
+        if self.files.is_empty() {
+            // one set of files for each running game
+            let directory = std::env::temp_dir();
+            for &sound in Sound::ALL.iter() {
+                let name = format!("chess-game-{}-{}.wav", std::process::id(), sound.name());
+                let path = directory.join(name);
+                std::fs::write(&path, sound.to_wav())?;
+                self.files.push((sound, path));
+            }
+        }
+        let path = &self
+            .files
+            .iter()
+            .find(|(known, _)| *known == sound)
+            .unwrap()
+            .1;
+        let candidates = match self.player {
+            Some(player) => vec![player],
+            None => PLAYERS.to_vec(),
+        };
+        for player in candidates {
+            let spawned = Command::new(player[0])
+                .args(&player[1..])
+                .arg(path)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            match spawned {
+                Ok(mut child) => {
+                    self.player = Some(player);
+                    // reaped on its own thread so it doesn't linger as a zombie
+                    std::thread::spawn(move || child.wait());
+                    return Ok(());
+                }
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "none of paplay, aplay, afplay or sox's play are installed",
+        ))
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        for (_, path) in &self.files {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
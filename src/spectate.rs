@@ -0,0 +1,289 @@
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+// showing the game to anyone watching over a websocket, like a stream overlay or a web
+// page: each of them is sent the position as json straight away when they connect and
+// again after every move. nothing they send is listened to
+
+// only this machine can watch unless asked otherwise
+pub const DEFAULT_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+// a browser's handshake is a few hundred bytes, one this long isn't a browser
+const MAX_REQUEST: usize = 8 * 1024;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+// a spectator who can't keep up is dropped rather than holding up the game
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+
+// what's sent after every move, see to_json
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Spectated {
+    pub fen: String,
+    // the move that led to the position, in uci and san
+    pub last_move: Option<(String, String)>,
+    // how many moves have been played
+    pub ply: usize,
+    pub white: String,
+    pub black: String,
+    // the pgn result, * while it's going
+    pub result: String,
+    // like "Checkmate, White wins", none while it's going
+    pub status: Option<String>,
+    // each side's time left in milliseconds, with a clock
+    pub clock: Option<(u64, u64)>,
+}
+
+impl Spectated {
+    // like {"fen":"...","lastMove":"e2e4","san":"e4","ply":1,"white":"Player",
+    // "black":"chess-game","result":"*","status":null,"clock":{"white":295000,"black":300000}}
+    pub fn to_json(&self) -> String {
+        let (uci, san) = match &self.last_move {
+            Some((uci, san)) => (json_string(uci), json_string(san)),
+            None => ("null".to_string(), "null".to_string()),
+        };
+        let status = self
+            .status
+            .as_deref()
+            .map_or("null".to_string(), json_string);
+        let clock = match self.clock {
+            Some((white, black)) => format!("{{\"white\":{},\"black\":{}}}", white, black),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"fen\":{},\"lastMove\":{},\"san\":{},\"ply\":{},\"white\":{},\"black\":{},\"result\":{},\"status\":{},\"clock\":{}}}",
+            json_string(&self.fen),
+            uci,
+            san,
+            self.ply,
+            json_string(&self.white),
+            json_string(&self.black),
+            json_string(&self.result),
+            status,
+            clock
+        )
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub struct SpectatorServer {
+    port: u16,
+    // spectators who've finished the handshake, from the thread taking connections
+    joined: Receiver<TcpStream>,
+    spectators: Vec<TcpStream>,
+    // the last json sent, for whoever joins next
+    latest: Option<String>,
+}
+
+impl SpectatorServer {
+    // listens on the address, port 0 picking any free one
+    pub fn bind(address: IpAddr, port: u16) -> io::Result<SpectatorServer> {
+        let listener = TcpListener::bind((address, port))?;
+        let port = listener.local_addr()?.port();
+        let (sender, joined) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                // a slow handshake mustn't keep anyone else waiting
+                thread::spawn(move || {
+                    if let Ok(stream) = handshake(stream) {
+                        let _ = sender.send(stream);
+                    }
+                });
+            }
+        });
+        Ok(SpectatorServer {
+            port,
+            joined,
+            spectators: Vec::new(),
+            latest: None,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.spectators.len()
+    }
+
+    // tells everyone watching, and anyone who joins later, about the position
+    pub fn broadcast(&mut self, state: &Spectated) {
+        let json = state.to_json();
+        let frame = text_frame(&json);
+        self.spectators
+            .retain_mut(|stream| stream.write_all(&frame).is_ok());
+        self.latest = Some(json);
+        self.update();
+    }
+
+    // takes in the spectators who've joined since last time, sending them the latest
+    // position; true if there were any
+    pub fn update(&mut self) -> bool {
+        let mut any = false;
+        while let Ok(mut stream) = self.joined.try_recv() {
+            let sent = match &self.latest {
+                Some(json) => stream.write_all(&text_frame(json)).is_ok(),
+                None => true,
+            };
+            if sent {
+                self.spectators.push(stream);
+                any = true;
+            }
+        }
+        any
+    }
+}
+
+// answers a browser's upgrade request, or turns away anything that isn't one
+fn handshake(mut stream: TcpStream) -> io::Result<TcpStream> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 || request.len() + read > MAX_REQUEST {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let key = request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        Some(value.trim()).filter(|_| name.trim().eq_ignore_ascii_case("sec-websocket-key"))
+    });
+    let key = match key {
+        Some(key) => key,
+        None => {
+            let body = "This is a websocket server for watching the game\n";
+            write!(
+                stream,
+                "HTTP/1.1 426 Upgrade Required\r\nUpgrade: websocket\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )?;
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+    };
+    // the key with the protocol's guid after it, hashed, proves the server read it
+    let accept = base64(&sha1(
+        format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key).as_bytes(),
+    ));
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+    Ok(stream)
+}
+
+// a single unmasked frame, as a server sends them
+fn text_frame(text: &str) -> Vec<u8> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x81];
+    match payload.len() {
+        length if length < 126 => frame.push(length as u8),
+        length if length <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+// only the handshake needs it, so it's here rather than pulling in a crate
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xefcd_ab89,
+        0x98ba_dcfe,
+        0x1032_5476,
+        0xc3d2_e1f0,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, add) in h.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(h.iter()) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
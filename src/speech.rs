@@ -0,0 +1,94 @@
+use crate::board::{Board, Color, Piece, PieceKind, Square};
+use crate::moves::Move;
+
+// moves, squares and positions written out the way they'd be read aloud, like
+// "White knight g1 to f3, check", for a screen reader or speech synthesiser to say
+// instead of notation
+
+pub fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+pub fn kind_name(kind: PieceKind) -> &'static str {
+    match kind {
+        PieceKind::Pawn => "pawn",
+        PieceKind::Knight => "knight",
+        PieceKind::Bishop => "bishop",
+        PieceKind::Rook => "rook",
+        PieceKind::Queen => "queen",
+        PieceKind::King => "king",
+    }
+}
+
+// "white knight"
+pub fn piece_name(piece: Piece) -> String {
+    format!(
+        "{} {}",
+        color_name(piece.color).to_lowercase(),
+        kind_name(piece.kind)
+    )
+}
+
+// what's on a square, like "e4, white pawn" or "e5, empty"
+pub fn describe_square(board: &Board, square: Square) -> String {
+    match board.piece_at(square) {
+        Some(piece) => format!("{}, {}", square, piece_name(piece)),
+        None => format!("{}, empty", square),
+    }
+}
+
+impl Board {
+    // a legal move in this position said in words: "White knight g1 to f3", "Black
+    // pawn e4 takes white pawn en passant on d3", "White castles kingside", "Black drops
+    // a knight on f2", with ", check" or ", checkmate" after it
+    pub fn describe_move(&self, mv: Move) -> String {
+        let color = self.side_to_move();
+        let side = color_name(color);
+        let mut text = if let Some(kind) = mv.drop {
+            format!("{} drops a {} on {}", side, kind_name(kind), mv.to)
+        } else if let Some((_, king_to, _)) = self.castling_squares(mv) {
+            let wing = if king_to.file() > mv.from.file() {
+                "kingside"
+            } else {
+                "queenside"
+            };
+            format!("{} castles {}", side, wing)
+        } else {
+            let kind = self
+                .piece_at(mv.from)
+                .map_or(PieceKind::Pawn, |piece| piece.kind);
+            let mut text = format!("{} {} {}", side, kind_name(kind), mv.from);
+            let en_passant = kind == PieceKind::Pawn && Some(mv.to) == self.en_passant();
+            match self.piece_at(mv.to) {
+                Some(taken) => text.push_str(&format!(" takes {} {}", piece_name(taken), mv.to)),
+                None if en_passant => {
+                    let taken = Piece::new(PieceKind::Pawn, color.opposite());
+                    text.push_str(&format!(
+                        " takes {} en passant on {}",
+                        piece_name(taken),
+                        mv.to
+                    ))
+                }
+                None => text.push_str(&format!(" to {}", mv.to)),
+            }
+            if let Some(promotion) = mv.promotion {
+                text.push_str(&format!(" and promotes to a {}", kind_name(promotion)));
+            }
+            text
+        };
+
+        let mut after = self.clone();
+        after.make_move(mv);
+        if after.is_in_check(color.opposite()) {
+            if after.legal_moves().is_empty() {
+                text.push_str(", checkmate");
+            } else {
+                text.push_str(", check");
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,14 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+// texture sampler
+uniform sampler2D texture1;
+// 0 for gone, 1 for as it is in the texture
+uniform float opacity;
+
+void main()
+{
+	FragColor = texture(texture1, TexCoord) * vec4(1.0, 1.0, 1.0, opacity);
+}
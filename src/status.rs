@@ -0,0 +1,264 @@
+use crate::bitboard::{self, Bitboard};
+use crate::board::{Board, Color, PieceKind};
+use crate::zobrist;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    // the colour that delivered mate
+    Checkmate(Color),
+    Stalemate,
+    // claimed under fide's rules, which only end the game themselves at seventy-five
+    // moves and the fifth time, or straight away otherwise
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    SeventyFiveMoveRule,
+    FivefoldRepetition,
+    InsufficientMaterial,
+    // the colour whose opponent ran out of time
+    Timeout(Color),
+    // the colour whose king reached the middle of the board in king of the hill
+    KingOfTheHill(Color),
+    // the colour whose opponent resigned
+    Resignation(Color),
+    // both sides agreed to a draw
+    DrawAgreed,
+}
+
+impl GameStatus {
+    pub fn is_over(self) -> bool {
+        self != GameStatus::Ongoing
+    }
+
+    // the colour that won, if anyone did
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            GameStatus::Checkmate(color)
+            | GameStatus::Timeout(color)
+            | GameStatus::KingOfTheHill(color)
+            | GameStatus::Resignation(color) => Some(color),
+            _ => None,
+        }
+    }
+
+    pub fn is_draw(self) -> bool {
+        !matches!(
+            self,
+            GameStatus::Ongoing
+                | GameStatus::Checkmate(_)
+                | GameStatus::Timeout(_)
+                | GameStatus::KingOfTheHill(_)
+                | GameStatus::Resignation(_)
+        )
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameStatus::Ongoing => write!(f, "In progress"),
+            GameStatus::Checkmate(Color::White) => write!(f, "Checkmate, White wins"),
+            GameStatus::Checkmate(Color::Black) => write!(f, "Checkmate, Black wins"),
+            GameStatus::Stalemate => write!(f, "Stalemate"),
+            GameStatus::FiftyMoveRule => write!(f, "Draw by the fifty move rule"),
+            GameStatus::ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+            GameStatus::SeventyFiveMoveRule => write!(f, "Draw by the seventy-five move rule"),
+            GameStatus::FivefoldRepetition => write!(f, "Draw by fivefold repetition"),
+            GameStatus::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+            GameStatus::Timeout(Color::White) => write!(f, "White wins on time"),
+            GameStatus::Timeout(Color::Black) => write!(f, "Black wins on time"),
+            GameStatus::KingOfTheHill(Color::White) => write!(f, "King of the hill, White wins"),
+            GameStatus::KingOfTheHill(Color::Black) => write!(f, "King of the hill, Black wins"),
+            GameStatus::Resignation(Color::White) => write!(f, "Black resigns, White wins"),
+            GameStatus::Resignation(Color::Black) => write!(f, "White resigns, Black wins"),
+            GameStatus::DrawAgreed => write!(f, "Draw by agreement"),
+        }
+    }
+}
+
+// when the fifty move rule and repetitions end a game
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrawRules {
+    // as soon as they apply, the way most programs and servers play
+    #[default]
+    Automatic,
+    // as fide's laws have it: a player can claim the draw once they apply, and it only
+    // happens by itself at seventy-five moves or the fifth time a position comes up
+    Fide,
+}
+
+impl DrawRules {
+    pub fn parse(text: &str) -> Option<DrawRules> {
+        match text.to_ascii_lowercase().as_str() {
+            "automatic" | "auto" => Some(DrawRules::Automatic),
+            "fide" => Some(DrawRules::Fide),
+            _ => None,
+        }
+    }
+}
+
+// light squares, a1 being dark
+const LIGHT_SQUARES: Bitboard = Bitboard(0x55aa_55aa_55aa_55aa);
+
+impl Board {
+    pub fn status(&self) -> GameStatus {
+        self.status_by(DrawRules::Automatic)
+    }
+
+    // with fide's rules the fifty move rule and threefold repetitions have to be
+    // claimed, see claimable_draw
+    pub fn status_by(&self, rules: DrawRules) -> GameStatus {
+        if self.legal_moves().is_empty() {
+            let color = self.side_to_move();
+            return if self.is_in_check(color) {
+                GameStatus::Checkmate(color.opposite())
+            } else {
+                GameStatus::Stalemate
+            };
+        }
+        match rules {
+            DrawRules::Automatic => {
+                if let Some(draw) = self.claimable_draw() {
+                    return draw;
+                }
+            }
+            DrawRules::Fide => {
+                if self.halfmove_clock() >= 150 {
+                    return GameStatus::SeventyFiveMoveRule;
+                }
+                if self.repetitions() >= 4 {
+                    return GameStatus::FivefoldRepetition;
+                }
+            }
+        }
+        if self.is_insufficient_material() {
+            return GameStatus::InsufficientMaterial;
+        }
+        GameStatus::Ongoing
+    }
+
+    // the draw a player could claim in this position, fifty moves without a capture or
+    // a pawn moving, or the third time the position has come up
+    pub fn claimable_draw(&self) -> Option<GameStatus> {
+        if self.halfmove_clock() >= 100 {
+            Some(GameStatus::FiftyMoveRule)
+        } else if self.repetitions() >= 2 {
+            Some(GameStatus::ThreefoldRepetition)
+        } else {
+            None
+        }
+    }
+
+    // how many times the current position came up earlier in the game
+    pub fn repetitions(&self) -> usize {
+        let key = self.position_key();
+        self.history().filter(|&earlier| earlier == key).count()
+    }
+
+    // the zobrist key identifying a position for the repetition rule and the engine's
+    // transposition table: the placement, side to move, castling rights, and the en
+    // passant square only if a pawn could actually take
+    pub fn position_key(&self) -> u64 {
+        let color = self.side_to_move();
+        let en_passant = self.en_passant().filter(|&square| {
+            !(bitboard::pawn_attacks(color.opposite(), square)
+                & self.pieces_of(PieceKind::Pawn, color))
+            .is_empty()
+        });
+        match en_passant {
+            Some(square) => self.partial_key() ^ zobrist::en_passant(square),
+            None => self.partial_key(),
+        }
+    }
+
+    // neither side could ever mate: bare kings, a single minor piece, or only bishops
+    // that all stand on squares of the same colour, with nothing in the pockets
+    pub fn is_insufficient_material(&self) -> bool {
+        if !self.pocket(Color::White).is_empty() || !self.pocket(Color::Black).is_empty() {
+            return false;
+        }
+        let heavy = [PieceKind::Pawn, PieceKind::Rook, PieceKind::Queen];
+        let any = |kind| {
+            !(self.pieces_of(kind, Color::White) | self.pieces_of(kind, Color::Black)).is_empty()
+        };
+        if heavy.iter().any(|&kind| any(kind)) {
+            return false;
+        }
+        let knights = self.pieces_of(PieceKind::Knight, Color::White)
+            | self.pieces_of(PieceKind::Knight, Color::Black);
+        let bishops = self.pieces_of(PieceKind::Bishop, Color::White)
+            | self.pieces_of(PieceKind::Bishop, Color::Black);
+        let minors = knights.count() + bishops.count();
+        if minors <= 1 {
+            return true;
+        }
+        knights.is_empty()
+            && ((bishops & LIGHT_SQUARES).is_empty() || (bishops & !LIGHT_SQUARES).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after(fen: &str, sans: &[&str]) -> Board {
+        let mut board = Board::from_fen(fen).unwrap();
+        for san in sans {
+            let mv = board.parse_san(san).unwrap();
+            board.make_move(mv);
+        }
+        board
+    }
+
+    // the kings go back and forth twice after the pawn's double step
+    const SHUFFLE: [&str; 9] = ["e4", "Ke7", "Ke2", "Ke8", "Ke1", "Ke7", "Ke2", "Ke8", "Ke1"];
+
+    #[test]
+    fn en_passant_only_makes_a_different_position_when_a_pawn_can_take() {
+        // the d4 pawn could take on e3 straight after e4, so that position never comes up again
+        let board = after("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", &SHUFFLE);
+        assert_eq!(board.repetitions(), 1);
+        assert_eq!(board.claimable_draw(), None);
+
+        let board = after("4k3/8/8/8/p7/8/4P3/4K3 w - - 0 1", &SHUFFLE);
+        assert_eq!(board.repetitions(), 2);
+        assert_eq!(
+            board.claimable_draw(),
+            Some(GameStatus::ThreefoldRepetition)
+        );
+    }
+
+    #[test]
+    fn fide_rules_leave_the_draw_to_be_claimed() {
+        let board = after("4k3/8/8/8/p7/8/4P3/4K3 w - - 0 1", &SHUFFLE);
+        assert_eq!(
+            board.status_by(DrawRules::Automatic),
+            GameStatus::ThreefoldRepetition
+        );
+        assert_eq!(board.status_by(DrawRules::Fide), GameStatus::Ongoing);
+
+        let board = Board::from_fen("4k3/8/8/8/p7/8/4P3/4K3 w - - 100 80").unwrap();
+        assert_eq!(
+            board.status_by(DrawRules::Automatic),
+            GameStatus::FiftyMoveRule
+        );
+        assert_eq!(board.status_by(DrawRules::Fide), GameStatus::Ongoing);
+        assert_eq!(board.claimable_draw(), Some(GameStatus::FiftyMoveRule));
+
+        let board = Board::from_fen("4k3/8/8/8/p7/8/4P3/4K3 w - - 150 105").unwrap();
+        assert_eq!(
+            board.status_by(DrawRules::Fide),
+            GameStatus::SeventyFiveMoveRule
+        );
+    }
+
+    #[test]
+    fn bishops_on_the_same_colour_cant_mate() {
+        let same = Board::from_fen("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert!(same.is_insufficient_material());
+        assert_eq!(same.status(), GameStatus::InsufficientMaterial);
+        let opposite = Board::from_fen("4kb2/8/8/8/8/8/8/1B2K3 w - - 0 1").unwrap();
+        assert!(!opposite.is_insufficient_material());
+    }
+}
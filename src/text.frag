@@ -0,0 +1,13 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+// the font only says where the letters are, the colour comes from here
+uniform sampler2D texture1;
+uniform vec4 color;
+
+void main()
+{
+	FragColor = vec4(color.rgb, color.a * texture(texture1, TexCoord).a);
+}
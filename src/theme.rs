@@ -0,0 +1,122 @@
+use chess_game::config::{self, Config};
+use std::path::{Path, PathBuf};
+
+// the colours and pieces the window is drawn with, read from a toml file like
+//
+//     background = "#334d4d"
+//     pieces = "sets/wood"
+//
+//     [board]
+//     light = "#f0d9b5"
+//     dark = "#b58863"
+//
+//     [highlight]
+//     last_move = "#e6cc3373"
+//
+// where every key can be left out to keep the look it has by default. colours are
+// #rrggbb or #rrggbbaa
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub background: (f32, f32, f32, f32),
+    pub light_squares: (f32, f32, f32, f32),
+    pub dark_squares: (f32, f32, f32, f32),
+    // tinting the square moved to, and the one moved from a little more faintly
+    pub last_move: (f32, f32, f32, f32),
+    // the arrow of a suggested move
+    pub hint: (f32, f32, f32, f32),
+    pub selected: (f32, f32, f32, f32),
+    // a move waiting to be played when it's the player's turn
+    pub premove: (f32, f32, f32, f32),
+    // a move the blunder check didn't like
+    pub blunder: (f32, f32, f32, f32),
+    // the dots and rings where the selected piece can go
+    pub move_targets: (f32, f32, f32, f32),
+    // the arrows of the line analysis expects, fading after the first move
+    pub analysis: (f32, f32, f32, f32),
+    // the arrows and circles drawn with the right mouse button
+    pub annotations: (f32, f32, f32, f32),
+    // the ring around the square the arrow keys are on
+    pub focus: (f32, f32, f32, f32),
+    // under a king in check, flashing brighter when the check is given
+    pub check: (f32, f32, f32, f32),
+    // a directory with a pieces.png in it, laid out like the bundled one with the king,
+    // queen, bishop, knight, rook and pawn from left to right and white on the top row;
+    // relative to the theme file
+    pub pieces: Option<PathBuf>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            background: (0.2, 0.3, 0.3, 1.0),
+            light_squares: (1.0, 1.0, 1.0, 1.0),
+            dark_squares: (0.0, 0.0, 0.0, 1.0),
+            last_move: (0.9, 0.8, 0.2, 0.45),
+            hint: (0.2, 0.5, 0.9, 0.7),
+            selected: (0.3, 0.6, 0.3, 0.5),
+            premove: (0.8, 0.3, 0.3, 0.45),
+            blunder: (0.9, 0.1, 0.1, 0.5),
+            move_targets: (0.2, 0.4, 0.2, 0.5),
+            analysis: (0.1, 0.4, 0.8, 0.8),
+            annotations: (0.1, 0.6, 0.2, 0.8),
+            focus: (0.2, 0.6, 1.0, 0.9),
+            check: (0.9, 0.1, 0.1, 0.45),
+            pieces: None,
+        }
+    }
+}
+
+impl Theme {
+    // theme.toml in the config directory
+    pub fn default_path() -> Option<PathBuf> {
+        config::directory().map(|directory| directory.join("theme.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Theme, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let config = Config::parse(&text).map_err(|error| error.to_string())?;
+        let mut theme = Theme::default();
+        for key in config.keys() {
+            let value = config.get(key).unwrap();
+            if key == "pieces" {
+                let directory = value.as_str().ok_or("pieces should be a directory")?;
+                let base = path.parent().unwrap_or_else(|| Path::new(""));
+                theme.pieces = Some(base.join(directory));
+                continue;
+            }
+            let color = match key {
+                "background" => &mut theme.background,
+                "board.light" => &mut theme.light_squares,
+                "board.dark" => &mut theme.dark_squares,
+                "highlight.last_move" => &mut theme.last_move,
+                "highlight.hint" => &mut theme.hint,
+                "highlight.selected" => &mut theme.selected,
+                "highlight.premove" => &mut theme.premove,
+                "highlight.blunder" => &mut theme.blunder,
+                "highlight.moves" => &mut theme.move_targets,
+                "highlight.analysis" => &mut theme.analysis,
+                "highlight.annotations" => &mut theme.annotations,
+                "highlight.focus" => &mut theme.focus,
+                "highlight.check" => &mut theme.check,
+                _ => return Err(format!("there's no setting called {}", key)),
+            };
+            *color = value
+                .as_str()
+                .and_then(parse_color)
+                .ok_or_else(|| format!("{} should be a colour like \"#f0d9b5\"", key))?;
+        }
+        Ok(theme)
+    }
+}
+
+fn parse_color(text: &str) -> Option<(f32, f32, f32, f32)> {
+    let hex = text.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| match hex.get(i..i + 2) {
+        Some(digits) => u8::from_str_radix(digits, 16).unwrap() as f32 / 255.0,
+        None => 1.0,
+    };
+    Some((channel(0), channel(2), channel(4), channel(6)))
+}
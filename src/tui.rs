@@ -0,0 +1,655 @@
+use crate::computer::{Computer, Opponent};
+#[cfg(feature = "audio")]
+use crate::sound::{Sound, Speaker};
+use chess_game::board::{Board, Color, Piece, PieceKind, Pocket, Square};
+use chess_game::book::Book;
+use chess_game::clock::{clock_text, TimeControl};
+use chess_game::correspondence::CorrespondenceGame;
+use chess_game::engine::report::{self, REPORT_TIME};
+use chess_game::engine::{Engine, SearchLimits};
+use chess_game::events::{GameObserver, Observers};
+use chess_game::fen::FenError;
+use chess_game::game::Game;
+use chess_game::material::Material;
+use chess_game::moves::Move;
+use chess_game::pgn::PgnGame;
+use chess_game::saved::SavedGame;
+use chess_game::status::{DrawRules, GameStatus};
+use chess_game::variant::Variant;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+const HELP: &str = "Type a move like e4, Nxf3, O-O, e2e4 or N@f3 in crazyhouse, or one of:
+  undo    take back the last move, or the last two against the computer
+  redo    play the moves taken back again
+  hint    the book's move in this position
+  report  look over every move with the engine, marking the bad ones in the pgn
+  moves   every legal move
+  claim   claim a draw by the fifty move rule or threefold repetition, with --draws fide
+  flip    turn the board around
+  mute    turn the sounds off, or on again, with the audio feature
+  board   draw the board again
+  save    write the game to a pgn file
+  quit    leave, saving the game if any moves were played and it wasn't loaded,
+          and keeping it for --resume if it isn't over";
+
+// 256 colour palette entries for the squares: plain, the last move, and a hint
+const LIGHT_SQUARES: [u8; 3] = [180, 186, 110];
+const DARK_SQUARES: [u8; 3] = [137, 143, 67];
+const WHITE_PIECES: u8 = 231;
+const BLACK_PIECES: u8 = 16;
+
+// the game in a terminal, for playing over ssh or where there's no opengl: the board
+// is drawn again after every move, and moves are typed in
+pub struct Terminal {
+    // its record is saved as pgn with save or on the way out
+    game: Game,
+    // black at the bottom
+    flipped: bool,
+    computer: Option<Computer>,
+    // kept under its name after every move instead, for a game played over days
+    correspondence: Option<CorrespondenceGame>,
+    // asked for with hint, for the position on the board
+    hint: Option<Move>,
+    book: Book,
+    // a game from a file, which isn't saved again on the way out
+    loaded: bool,
+    // squares are coloured with escape codes, unless the output isn't a terminal or
+    // NO_COLOR is set
+    colors: bool,
+    // told about the moves, captures, checks, clock and end of the game
+    observers: Observers,
+    #[cfg(feature = "audio")]
+    speaker: Speaker,
+}
+
+impl Terminal {
+    pub fn new(fen: &str, variant: Box<dyn Variant>) -> Result<Terminal, FenError> {
+        let mut board = Board::from_fen(fen)?;
+        variant.setup(&mut board);
+        let colors = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        let mut terminal = Terminal {
+            game: Game::new(board, variant, DrawRules::Automatic),
+            flipped: false,
+            computer: None,
+            correspondence: None,
+            hint: None,
+            book: Book::default(),
+            loaded: false,
+            colors,
+            observers: Observers::default(),
+            #[cfg(feature = "audio")]
+            speaker: Speaker::new(),
+        };
+        // the starting fen might already be a finished game
+        terminal.update_status();
+        Ok(terminal)
+    }
+
+    // hands one side over to an engine
+    pub fn play_against_computer(
+        &mut self,
+        color: Color,
+        mut opponent: Opponent,
+        limits: SearchLimits,
+    ) {
+        if let Opponent::Builtin(engine) = &mut opponent {
+            engine.set_variant(self.game.variant.as_ref());
+        }
+        self.game.record.set_player(color, opponent.name());
+        self.game.record.set_player(color.opposite(), "Player");
+        // the player's pieces start at the bottom
+        self.flipped = color == Color::White;
+        self.computer = Some(Computer::new(color, opponent, limits));
+    }
+
+    // sets up a loaded game at its start, with all of its moves ready to be stepped
+    // through with redo
+    pub fn replay(&mut self, mut game: PgnGame) {
+        self.computer = None;
+        while let Some(record) = game.pop() {
+            self.game.taken_back.push(record);
+        }
+        self.game.board = game.start().clone();
+        self.game.variant = game.variant();
+        self.game.record = game;
+        self.loaded = true;
+        println!(
+            "{} vs {}, {} moves to go through with redo",
+            self.game.record.header("White").unwrap_or("?"),
+            self.game.record.header("Black").unwrap_or("?"),
+            self.game.taken_back.len()
+        );
+        self.update_status();
+    }
+
+    // where hints come from
+    pub fn set_book(&mut self, book: Book) {
+        self.book = book;
+    }
+
+    pub fn set_draw_rules(&mut self, rules: DrawRules) {
+        self.game.draw_rules = rules;
+        self.update_status();
+    }
+
+    // plays with a clock, which starts running straight away
+    pub fn set_clock(&mut self, control: TimeControl) {
+        self.game.set_clock(control);
+    }
+
+    pub fn add_observer(&mut self, observer: impl GameObserver + 'static) {
+        self.observers.add(observer);
+    }
+
+    pub fn set_flipped(&mut self, flipped: bool) {
+        self.flipped = flipped;
+    }
+
+    pub fn game_loop(&mut self) {
+        println!("Type help for the commands");
+        self.draw();
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            if !self.game.status.is_over() && self.is_computer_turn() {
+                self.play_computer_move();
+                continue;
+            }
+            self.prompt();
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                // end of input, like Ctrl+D
+                _ => {
+                    println!();
+                    break;
+                }
+            };
+            self.update_clock();
+            if !self.command(line.trim()) {
+                break;
+            }
+        }
+        if self.correspondence.is_some() {
+            self.save_correspondence();
+        } else if !self.loaded && !self.game.record.moves().is_empty() {
+            self.save_pgn();
+            self.autosave();
+        }
+    }
+
+    // plays on from a correspondence game, with the side played here at the bottom
+    pub fn play_correspondence(&mut self, game: CorrespondenceGame) {
+        self.game.load(game.record.clone());
+        self.flipped = game.color == Color::Black;
+        self.update_status();
+        let turn = if game.is_our_move() {
+            "your move"
+        } else {
+            "waiting for the opponent"
+        };
+        println!("Correspondence game {}, {}", game.name, turn);
+        self.correspondence = Some(game);
+    }
+
+    fn save_correspondence(&mut self) {
+        let game = match &mut self.correspondence {
+            Some(game) => game,
+            None => return,
+        };
+        game.record = self.game.record.clone();
+        if let Err(error) = game.save() {
+            eprintln!(
+                "Couldn't keep the correspondence game {}: {}",
+                game.name, error
+            );
+        }
+    }
+
+    // plays on from a game left unfinished last time, its clock starting again from
+    // where it stopped
+    pub fn resume(&mut self, saved: SavedGame) {
+        self.game.clock = saved.clock();
+        self.game.load(saved.record);
+        self.update_status();
+        self.game.restart_turn();
+    }
+
+    // a game still going is kept to be resumed next time, and finishing one leaves
+    // nothing to resume
+    fn autosave(&self) {
+        if self.game.status.is_over() {
+            if let Err(error) = SavedGame::discard() {
+                eprintln!("Couldn't remove the unfinished game: {}", error);
+            }
+            return;
+        }
+        let computer = self.computer.as_ref().map(|computer| computer.color);
+        match SavedGame::new(&self.game.record, self.game.clock.as_ref(), computer).save() {
+            Ok(path) => println!("Kept the game in {} for --resume", path.display()),
+            Err(error) => eprintln!("Couldn't keep the game for --resume: {}", error),
+        }
+    }
+
+    fn save_pgn(&self) {
+        let path = self.game.record.default_file_name();
+        match self.game.record.write_to(&path) {
+            Ok(()) => println!("Saved the game to {}", path),
+            Err(error) => eprintln!("Couldn't save the game to {}: {}", path, error),
+        }
+    }
+
+    // false once the player wants to leave
+    fn command(&mut self, text: &str) -> bool {
+        match text {
+            "" => {}
+            "help" | "?" => println!("{}", HELP),
+            "quit" | "exit" => return false,
+            "save" => self.save_pgn(),
+            "board" => self.draw(),
+            "flip" => {
+                self.flipped = !self.flipped;
+                self.draw();
+            }
+            "undo" => self.take_back(),
+            "redo" => self.replay_taken_back(),
+            "hint" => self.show_hint(),
+            "report" => self.report(),
+            "claim" => self.claim_draw(),
+            #[cfg(feature = "audio")]
+            "mute" => {
+                let muted = !self.speaker.is_muted();
+                self.speaker.set_muted(muted);
+                println!("{}", if muted { "Sound off" } else { "Sound on" });
+            }
+            "moves" => {
+                let mut moves: Vec<String> = self
+                    .game
+                    .variant
+                    .legal_moves(&self.game.board)
+                    .into_iter()
+                    .map(|mv| mv.to_san(&self.game.board))
+                    .collect();
+                moves.sort();
+                println!("{}", moves.join(" "));
+            }
+            _ if self.game.status.is_over() => println!("The game is over: {}", self.game.status),
+            _ => {
+                // uci for whatever isn't san, like e7e8q
+                let mv = match self.game.board.parse_san(text) {
+                    Ok(mv) => mv,
+                    Err(error) => match self.game.board.parse_uci_move(text) {
+                        Some(mv) => mv,
+                        None => {
+                            println!("{}", error);
+                            return true;
+                        }
+                    },
+                };
+                self.play_move(mv);
+                self.draw();
+            }
+        }
+        true
+    }
+
+    fn prompt(&self) {
+        let mut prompt = String::new();
+        if let Some(clock) = &self.game.clock {
+            let color = self.game.board.side_to_move();
+            prompt.push_str(&format!("[{}] ", clock_text(clock.remaining(color))));
+        }
+        match self.game.board.side_to_move() {
+            Color::White => prompt.push_str(&format!("{}. ", self.game.board.fullmove_number())),
+            Color::Black => prompt.push_str(&format!("{}... ", self.game.board.fullmove_number())),
+        }
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+    }
+
+    // plays a legal move on the board and in the record, and prints it
+    fn play_move(&mut self, mv: Move) {
+        #[cfg(feature = "audio")]
+        let sound = Sound::of_move(&self.game.board, mv);
+        let before = self.game.board.clone();
+        println!("{}", self.game.play_move(mv));
+        self.observers.moved(&before, mv, &self.game.board);
+        self.hint = None;
+        self.update_status();
+        // a move that ends the game gets the sound for that instead
+        #[cfg(feature = "audio")]
+        if !self.game.status.is_over() {
+            self.speaker.play(sound);
+        }
+        self.save_correspondence();
+    }
+
+    fn is_computer_turn(&self) -> bool {
+        self.computer
+            .as_ref()
+            .is_some_and(|computer| computer.color == self.game.board.side_to_move())
+    }
+
+    // there's nothing else to do in the meantime, so this waits for the engine
+    fn play_computer_move(&mut self) {
+        let computer = self.computer.as_mut().unwrap();
+        println!("Thinking...");
+        computer.start_thinking(&self.game.board, self.game.clock.as_ref());
+        let mv = computer.wait_for_move();
+        // the engine can lose on time too
+        self.update_clock();
+        if self.game.status.is_over() {
+            return self.draw();
+        }
+        match mv {
+            Some(Ok(Some(mv))) => {
+                self.play_move(mv);
+                self.draw();
+            }
+            // the game isn't over so there was a move to find; give the side back to
+            // the player rather than asking again
+            Some(Ok(None)) => {
+                eprintln!("The engine didn't find a move");
+                self.computer = None;
+            }
+            Some(Err(error)) => {
+                eprintln!("The engine stopped working: {}", error);
+                self.computer = None;
+            }
+            None => self.computer = None,
+        }
+    }
+
+    // shows the book's favourite move in the position, if it has one
+    fn show_hint(&mut self) {
+        if self.game.status.is_over() {
+            return;
+        }
+        self.hint = self.book.best_move(&self.game.board);
+        match self.hint {
+            Some(mv) => {
+                println!("Book move: {}", mv.to_san(&self.game.board));
+                self.draw();
+            }
+            None => println!("No book move in this position"),
+        }
+    }
+
+    // goes over the game with the engine, which takes a while, and keeps what it
+    // thought of each move in the record as comments
+    fn report(&mut self) {
+        let total = self.game.record.moves().len();
+        if total == 0 {
+            return println!("There are no moves to look over");
+        }
+        let limits = SearchLimits::time(REPORT_TIME);
+        let report = report::analyse(&mut Engine::new(), &self.game.record, &limits, |done| {
+            print!("\rLooking over move {} of {}", done, total);
+            let _ = io::stdout().flush();
+        });
+        println!();
+        for (review, class) in &report.moves {
+            if let (Some(_), Some(best)) = (class.nag(), review.better) {
+                let number = review.board.fullmove_number();
+                let dots = match review.board.side_to_move() {
+                    Color::White => ".",
+                    Color::Black => "...",
+                };
+                println!(
+                    "{}{} {}: {}, {} was best",
+                    number,
+                    dots,
+                    review.mv.to_san(&review.board),
+                    class,
+                    best.to_san(&review.board)
+                );
+            }
+        }
+        println!("{}", report.summary(Color::White));
+        println!("{}", report.summary(Color::Black));
+        report.annotate(&mut self.game.record);
+    }
+
+    // takes back the last move, and the computer's reply before it so it's the
+    // player's turn again
+    fn take_back(&mut self) {
+        if !self.game.take_back(self.computer_color()) {
+            return println!("There's no move to take back");
+        }
+        self.history_changed();
+    }
+
+    // plays the moves taken back again, up to the player's next turn
+    fn replay_taken_back(&mut self) {
+        if !self.game.replay_taken_back(self.computer_color()) {
+            return println!("There's no move to play again");
+        }
+        self.history_changed();
+    }
+
+    fn computer_color(&self) -> Option<Color> {
+        self.computer.as_ref().map(|computer| computer.color)
+    }
+
+    fn history_changed(&mut self) {
+        self.hint = None;
+        self.update_status();
+        self.game.restart_turn();
+        self.draw();
+    }
+
+    fn update_status(&mut self) {
+        let status = self.game.rules_status();
+        self.set_status(status);
+    }
+
+    // ends the game with the draw there is to claim, saying so after the last move
+    fn claim_draw(&mut self) {
+        let color = match &self.computer {
+            Some(computer) => computer.color.opposite(),
+            None => self.game.board.side_to_move(),
+        };
+        let draw = match self.game.claim_draw(color) {
+            Some((draw, text)) => {
+                println!("{}", text);
+                draw
+            }
+            None if self.game.draw_rules == DrawRules::Fide => {
+                return println!("There's no draw to claim");
+            }
+            None => return println!("Draws are only claimed with --draws fide"),
+        };
+        self.set_status(draw);
+        self.draw();
+    }
+
+    fn set_status(&mut self, status: GameStatus) {
+        if self.game.set_status(status) {
+            self.observers.game_ended(status);
+            #[cfg(feature = "audio")]
+            self.speaker.play(Sound::GameEnd);
+        }
+    }
+
+    // running out of time loses the game
+    fn update_clock(&mut self) {
+        if self.game.status.is_over() {
+            return;
+        }
+        if let Some(clock) = &self.game.clock {
+            self.observers.clock_running(clock);
+        }
+        if let Some(timeout) = self.game.flagged() {
+            self.set_status(timeout);
+        }
+    }
+
+    fn draw(&self) {
+        let mut text = String::new();
+        let files: Vec<u8> = if self.flipped {
+            (0..8).rev().collect()
+        } else {
+            (0..8).collect()
+        };
+        let ranks: Vec<u8> = if self.flipped {
+            (0..8).collect()
+        } else {
+            (0..8).rev().collect()
+        };
+        let labels: String = files
+            .iter()
+            .map(|&file| format!(" {} ", (b'a' + file) as char))
+            .collect();
+        text.push_str(&format!("\n   {}\n", labels));
+        for &rank in &ranks {
+            text.push_str(&format!(" {} ", rank + 1));
+            for &file in &files {
+                text.push_str(&self.square_text(Square::new(file, rank)));
+            }
+            text.push_str(&format!(" {}\n", rank + 1));
+        }
+        text.push_str(&format!("   {}\n", labels));
+        if self.game.variant.has_drops() {
+            text.push_str(&format!(
+                "\n White {}   Black {}\n",
+                self.pocket_text(Color::White),
+                self.pocket_text(Color::Black)
+            ));
+        }
+
+        if let Some(clock) = &self.game.clock {
+            let running = |color| {
+                if clock.running() == Some(color) {
+                    "*"
+                } else {
+                    " "
+                }
+            };
+            text.push_str(&format!(
+                "\n {}White {}   {}Black {}\n",
+                running(Color::White),
+                clock_text(clock.remaining(Color::White)),
+                running(Color::Black),
+                clock_text(clock.remaining(Color::Black))
+            ));
+        }
+        let material = Material::of(&self.game.board);
+        let even = [Color::White, Color::Black]
+            .iter()
+            .all(|&color| material.captured(color).is_empty());
+        if !even {
+            text.push_str(&format!(
+                "\n Taken: White {}   Black {}\n",
+                self.captured_text(&material, Color::White),
+                self.captured_text(&material, Color::Black)
+            ));
+        }
+        let mover = self.game.board.side_to_move();
+        if self.game.status.is_over() {
+            text.push_str(&format!("\n{}\n", self.game.status));
+        } else if self.game.board.is_in_check(mover) {
+            text.push_str(&format!("\n{:?} to move, in check\n", mover));
+        } else {
+            text.push_str(&format!("\n{:?} to move\n", mover));
+        }
+        if let Some(draw) = self.game.claimable_draw() {
+            text.push_str(&format!(
+                "claim for a {}\n",
+                draw.to_string().to_lowercase()
+            ));
+        }
+        println!("{}", text);
+    }
+
+    // what a side can drop, most valuable first, or a dash for nothing
+    fn pocket_text(&self, color: Color) -> String {
+        let pocket = self.game.board.pocket(color);
+        let mut text = String::new();
+        for &kind in Pocket::KINDS.iter().rev() {
+            for _ in 0..pocket.count(kind) {
+                text.push(glyph(Piece::new(kind, color), color == Color::Black));
+            }
+        }
+        if text.is_empty() {
+            text.push('-');
+        }
+        text
+    }
+
+    // the other side's pieces `color` is ahead by and how many points that makes, or a
+    // dash for nothing
+    fn captured_text(&self, material: &Material, color: Color) -> String {
+        let opponent = color.opposite();
+        let mut text: String = material
+            .captured(color)
+            .into_iter()
+            .map(|kind| glyph(Piece::new(kind, opponent), opponent == Color::Black))
+            .collect();
+        match material.advantage_text(color) {
+            Some(advantage) => text.push_str(&format!(" {}", advantage)),
+            None if text.is_empty() => text.push('-'),
+            None => {}
+        }
+        text
+    }
+
+    // three characters wide, so the board comes out roughly square
+    fn square_text(&self, square: Square) -> String {
+        let piece = self.game.board.piece_at(square);
+        if !self.colors {
+            return match piece {
+                Some(piece) => format!(" {} ", glyph(piece, piece.color == Color::Black)),
+                None => " . ".to_string(),
+            };
+        }
+        let shade = if self
+            .hint
+            .is_some_and(|mv| mv.from == square || mv.to == square)
+        {
+            2
+        } else if self
+            .game
+            .board
+            .last_move()
+            .is_some_and(|mv| mv.from == square || mv.to == square)
+        {
+            1
+        } else {
+            0
+        };
+        let background = if (square.file() + square.rank()).is_multiple_of(2) {
+            DARK_SQUARES[shade]
+        } else {
+            LIGHT_SQUARES[shade]
+        };
+        match piece {
+            Some(piece) => {
+                let foreground = match piece.color {
+                    Color::White => WHITE_PIECES,
+                    Color::Black => BLACK_PIECES,
+                };
+                // the solid shapes show up better on a coloured square
+                format!(
+                    "\x1b[48;5;{};38;5;{}m {} \x1b[0m",
+                    background,
+                    foreground,
+                    glyph(piece, true)
+                )
+            }
+            None => format!("\x1b[48;5;{}m   \x1b[0m", background),
+        }
+    }
+}
+
+// the unicode chess symbols, which come in outlined and solid versions
+fn glyph(piece: Piece, solid: bool) -> char {
+    let offset = match piece.kind {
+        PieceKind::King => 0,
+        PieceKind::Queen => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Knight => 4,
+        PieceKind::Pawn => 5,
+    };
+    let first = if solid { 0x265a } else { 0x2654 };
+    char::from_u32(first + offset).unwrap()
+}
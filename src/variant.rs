@@ -0,0 +1,126 @@
+use crate::bitboard::Bitboard;
+use crate::board::{Board, Color, PieceKind, Pocket};
+use crate::moves::Move;
+use crate::status::{DrawRules, GameStatus};
+
+// the rules a game is played by: which moves are legal, how it can be won, and what
+// the board has to keep track of for them beyond the pieces. anything that knows the
+// variant of its game asks it rather than the board, which only plays standard chess
+// and carries the extra state; the board makes drops itself once it has pockets, so
+// notation and the engine work the same in every variant
+pub trait Variant: Send + Sync {
+    // as in pgn's Variant header
+    fn name(&self) -> &'static str;
+
+    // makes a starting position one of the variant's, giving the board whatever it
+    // needs to keep for the rules
+    fn setup(&self, _board: &mut Board) {}
+
+    // none once the variant has decided the game
+    fn legal_moves(&self, board: &Board) -> Vec<Move> {
+        if self.winner(board).is_some() {
+            return Vec::new();
+        }
+        board.legal_moves()
+    }
+
+    fn is_legal(&self, board: &Board, mv: Move) -> bool {
+        self.legal_moves(board).contains(&mv)
+    }
+
+    // a win the variant adds to checkmate, which ends the game before anything else
+    fn winner(&self, _board: &Board) -> Option<GameStatus> {
+        None
+    }
+
+    fn status(&self, board: &Board) -> GameStatus {
+        self.status_by(board, DrawRules::Automatic)
+    }
+
+    fn status_by(&self, board: &Board, rules: DrawRules) -> GameStatus {
+        self.winner(board).unwrap_or_else(|| board.status_by(rules))
+    }
+
+    // whether captured pieces can be dropped back on, so the pockets need showing
+    fn has_drops(&self) -> bool {
+        false
+    }
+}
+
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+// captured pieces change sides and go in the taker's pocket, from where they can be
+// put on any empty square instead of moving, pawns anywhere but the first and last
+// ranks; a piece that promoted goes back to being a pawn
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    // a position that already came with pockets keeps what's in them
+    fn setup(&self, board: &mut Board) {
+        if !board.has_pockets() {
+            board.set_pocket(Color::White, Pocket::default());
+            board.set_pocket(Color::Black, Pocket::default());
+        }
+    }
+
+    fn has_drops(&self) -> bool {
+        true
+    }
+}
+
+// bringing the king to one of the four squares in the middle wins, as long as it
+// doesn't walk into check on the way
+pub struct KingOfTheHill;
+
+// d4, e4, d5 and e5
+const HILL: Bitboard = Bitboard(0x0000_0018_1800_0000);
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn winner(&self, board: &Board) -> Option<GameStatus> {
+        [Color::White, Color::Black]
+            .iter()
+            .copied()
+            .find(|&color| !(board.pieces_of(PieceKind::King, color) & HILL).is_empty())
+            .map(GameStatus::KingOfTheHill)
+    }
+
+    // a bare king can still walk up the hill, so there's no such thing as too little
+    // material
+    fn status_by(&self, board: &Board, rules: DrawRules) -> GameStatus {
+        match self.winner(board).unwrap_or_else(|| board.status_by(rules)) {
+            GameStatus::InsufficientMaterial => GameStatus::Ongoing,
+            status => status,
+        }
+    }
+}
+
+// the variant from its name in a pgn header or on the command line, in any case and
+// with or without spaces, like "Crazyhouse" or "kingofthehill"
+pub fn by_name(name: &str) -> Option<Box<dyn Variant>> {
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    match name.as_str() {
+        // chess960 plays by the standard rules, its castling is the board's business
+        "standard" | "chess960" | "fischerrandom" => Some(Box::new(Standard)),
+        "crazyhouse" | "zh" => Some(Box::new(Crazyhouse)),
+        "kingofthehill" | "koth" => Some(Box::new(KingOfTheHill)),
+        _ => None,
+    }
+}
@@ -1,424 +1,3299 @@
 extern crate glfw;
 
 extern crate gl;
-use gl::types::*;
-use image::GenericImageView;
 
 extern crate image;
 
-use glfw::{Action, Context, Glfw, Key, Window, WindowEvent};
-use std::ffi::{CStr, CString};
-use std::os::raw::c_void;
+mod control;
+mod editor;
+mod menu;
+#[cfg(feature = "lichess")]
+mod online;
+mod openings;
+mod puzzles;
+mod search;
+#[cfg(feature = "spectate")]
+mod spectate;
+mod tabs;
+mod touch;
+
+use crate::animation::{self, Animation};
+use crate::computer::{Computer, Opponent};
+use crate::renderer::{Align, Framebuffer, Rect, Renderer, Shape, Texture};
+#[cfg(feature = "audio")]
+use crate::sound::{Sound, Speaker};
+use crate::theme::Theme;
+use chess_game::board::{Board, Color, Piece, PieceKind, Pocket, Square};
+use chess_game::book::Book;
+use chess_game::clock::{clock_text, Clock, TimeControl};
+use chess_game::correspondence::CorrespondenceGame;
+use chess_game::database::Hit;
+use chess_game::engine::analysis::Analysis;
+use chess_game::engine::coach::{Answer, Coach, Question, Review, BLUNDER_THRESHOLD};
+use chess_game::engine::report::{Classification, Report};
+use chess_game::engine::{mate_in, Engine, SearchLimits};
+use chess_game::error::ChessError;
+use chess_game::events::{GameObserver, Observers};
+use chess_game::game;
+use chess_game::material::Material;
+use chess_game::moves::Move;
+use chess_game::net::{ClockState, GameState, Host, Message, Peer};
+use chess_game::pgn::{Branches, PgnGame, PgnMove};
+use chess_game::saved::SavedGame;
+use chess_game::settings::{Command, KeyBindings};
+use chess_game::speech;
+use chess_game::status::{DrawRules, GameStatus};
+use chess_game::variant::{self, Variant};
+use glfw::{Action, Context, Glfw, Key, Modifiers, MouseButton, Window, WindowEvent};
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+// playing someone else's copy of the game over the network
+struct Network {
+    role: Role,
+    // the side played here; a guest only knows once the host says hello
+    color: Option<Color>,
+    peer: Option<Peer>,
+    last_attempt: Instant,
+}
+
+enum Role {
+    Host(Host),
+    // the address to reconnect to when the connection drops
+    Guest(String),
+}
+
+// how often a guest tries to get back to a host it lost the connection to
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+// how long a moved piece takes to slide over to its new square
+const SLIDE_TIME: Duration = Duration::from_millis(150);
+
+// how long a taken piece takes to fade away
+const FADE_TIME: Duration = Duration::from_millis(250);
+
+// how long the square of a king that's just been put in check flashes, and how many
+// times
+const CHECK_PULSE_TIME: Duration = Duration::from_millis(900);
+const CHECK_PULSE_BEATS: u32 = 2;
+
+// how often the clocks are drawn again while one is running, often enough for the
+// tenths shown once it's nearly out
+const CLOCK_TICK: Duration = Duration::from_millis(100);
+
+// how long to wait for input when nothing's moving on the screen, before looking in on
+// the engine and the network again
+const IDLE_WAIT: Duration = Duration::from_millis(50);
+
+// the width of the panel beside the board, compared to the board's
+const PANEL_WIDTH: f64 = 0.35;
+
+// the width of the evaluation bar between the board and the panel while analysing,
+// compared to the board's
+const EVAL_BAR_WIDTH: f64 = 0.04;
+
+// the width of the strips left of and under the board for the coordinates, compared
+// to the board's
+const COORDINATE_MARGIN: f64 = 0.05;
+
+// how many moves of the line analysis expects are drawn as arrows
+const PV_ARROWS: usize = 3;
+
+// the height of a row of the move list, in the panel's normalized device coordinates
+const ROW_HEIGHT: f32 = 0.09;
+
+// how many moves of a variation fit in its row above the move list
+const VARIATION_MOVES: usize = 6;
+
+// the height of a row of crazyhouse pocket pieces, in the panel's normalized device
+// coordinates
+const POCKET_HEIGHT: f32 = 0.12;
+
+// the width of a square in normalized device coordinates, the board spanning -1 to 1
+const SQUARE_SIZE: f32 = 2.0 / 8.0;
+
+// the width and height of an exported picture of the board, in pixels
+const EXPORT_SIZE: i32 = 800;
+
+// what's told about the game in words, see set_announcer
+type Announcer = Box<dyn FnMut(&str)>;
+
+// a piece picked up with the mouse, drawn under the cursor until it's let go
+struct Drag {
+    from: Square,
+    // releasing a piece that was already selected where it was picked up deselects it
+    was_selected: bool,
+}
+
+// arrows between squares and circles around them, drawn with the right mouse button
+// to point things out and gone again after the next move
+#[derive(Default)]
+struct Annotations {
+    arrows: Vec<(Square, Square)>,
+    circles: Vec<Square>,
+    // where the right button went down, the arrow's tail if it's let go elsewhere
+    started: Option<Square>,
+}
+
+impl Annotations {
+    // drawing the same arrow or circle again takes it away
+    fn toggle_arrow(&mut self, from: Square, to: Square) {
+        match self.arrows.iter().position(|&arrow| arrow == (from, to)) {
+            Some(i) => {
+                self.arrows.remove(i);
+            }
+            None => self.arrows.push((from, to)),
+        }
+    }
+
+    fn toggle_circle(&mut self, square: Square) {
+        match self.circles.iter().position(|&circle| circle == square) {
+            Some(i) => {
+                self.circles.remove(i);
+            }
+            None => self.circles.push(square),
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Annotations::default();
+    }
+}
+
+// a pawn waiting on the last rank for the player to choose what it becomes
+// a move being typed in, opened with / or : and played with enter
+#[derive(Default)]
+struct Entry {
+    text: String,
+    // why the last move typed couldn't be played
+    error: Option<String>,
+}
+
+struct Promotion {
+    from: Square,
+    to: Square,
+    color: Color,
+}
+
+impl Promotion {
+    // the pieces on offer, in a column from the promotion square towards the middle
+    fn choices(&self) -> [(Square, PieceKind); 4] {
+        let kinds = [
+            PieceKind::Queen,
+            PieceKind::Rook,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+        ];
+        let toward_middle = match self.color {
+            Color::White => -1,
+            Color::Black => 1,
+        };
+        let mut choices = [(self.to, PieceKind::Queen); 4];
+        for (i, &kind) in kinds.iter().enumerate() {
+            let square = self.to.offset(0, toward_middle * i as i8).unwrap();
+            choices[i] = (square, kind);
+        }
+        choices
+    }
+}
+
+// the last move's piece gliding from its old square to the new one, or both the king
+// and the rook when castling
+struct Slide {
+    paths: Vec<(Square, Square)>,
+    animation: Animation,
+}
+
+impl Slide {
+    // how far the piece has to go, from 1 down to 0 when it has arrived
+    fn remaining(&self) -> f32 {
+        1.0 - animation::ease_out(self.animation.progress())
+    }
+}
+
+// the pieces the last move took, fading out where they stood; more than one when an
+// atomic capture blows up the squares around it
+struct Fade {
+    pieces: Vec<(Square, Piece)>,
+    animation: Animation,
+}
+
+// an earlier position of the game on the board, picked from the move list, or one
+// down a variation played from there; the game carries on underneath
+struct Preview {
+    // the variation it's in, none for the game's own moves
+    branches: Branches,
+    // how many moves of that line are on the board, never 0 in a variation since
+    // that's a position of the line it came off
+    ply: usize,
+    board: Board,
+}
+
+// what clicking a part of the panel does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PanelTarget {
+    // shows the position after this many moves
+    Ply(usize),
+    BackToGame,
+    ClaimDraw,
+    // picks up a piece from the side to move's pocket to drop it
+    Pocket(PieceKind),
+    // plays a move from the explorer
+    Explore(Move),
+    // opens a game from the database at the position it was found for
+    DatabaseGame(Hit),
+    // picks a piece, changes a setting or starts a game in the board editor
+    Editor(editor::Action),
+    // shows the game open in that tab, counting from 0
+    Tab(usize),
+}
+
+// what the panel lists under the status
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Sidebar {
+    Moves,
+    // the moves played from the position shown, switched to with E
+    Explorer,
+    // the games of the database that reached it, switched to with D
+    Games,
+}
+
+// stepping through a loaded game instead of playing one
+struct Replay {
+    game: PgnGame,
+    // how many moves of the main line are on the board
+    ply: usize,
+}
+
+// fields drop in declaration order, so the gl objects have to come before the window
+// to be deleted while its context is still alive
+pub struct Game {
+    renderer: Renderer,
+    // king, queen, bishop, knight, rook and pawn from left to right, white on the top row
+    pieces: Texture,
+    theme: Theme,
+    // read again with T, so a theme can be worked on while the game is open
+    theme_path: Option<PathBuf>,
+    // the position, the moves played so far and the clock; the record is saved as pgn
+    // with S or when the window closes
+    game: game::Game,
+    selected: Option<Square>,
+    // a piece taken from the pocket in crazyhouse, dropped on the next square clicked
+    dropping: Option<PieceKind>,
+    // a move made while the opponent is thinking, played as soon as it's this side's
+    // turn if it's legal then
+    premove: Option<Move>,
+    // the move that led to the position on the board, tinted so it's easy to spot
+    last_move: Option<Move>,
+    annotations: Annotations,
+    entry: Option<Entry>,
+    // the square the arrow keys are on, for playing without the mouse; it shows up
+    // with Tab, Enter or the up and down arrows and goes again with Escape or a click
+    focus: Option<Square>,
+    // told about every move, the end of the game and the square the focus lands on
+    // in words, for a screen reader or speech synthesiser to say
+    announcer: Option<Announcer>,
+    // told about the moves, captures, checks, clock and end of every game open here,
+    // see add_observer
+    observers: Observers,
+    drag: Option<Drag>,
+    slide: Option<Slide>,
+    fade: Option<Fade>,
+    // the square of the king the last move checked, flashing for a moment
+    check_pulse: Option<(Square, Animation)>,
+    // the slides, fades and flashes, which can be switched off in the settings
+    animations: bool,
+    promotion: Option<Promotion>,
+    // black at the bottom
+    flipped: bool,
+    // file letters under the board and rank numbers beside it, switched off with C
+    coordinates: bool,
+    // what the keys do, from the settings
+    keys: KeyBindings,
+    computer: Option<Computer>,
+    network: Option<Network>,
+    // a resignation waiting to be confirmed, a draw offer that's standing, a rematch asked for
+    controls: control::Controls,
+    #[cfg(feature = "lichess")]
+    online: Option<online::Online>,
+    // whoever's watching over a websocket, sent the position after every move
+    #[cfg(feature = "spectate")]
+    spectators: Option<chess_game::spectate::SpectatorServer>,
+    // kept under its name after every move instead, for a game played over days
+    correspondence: Option<CorrespondenceGame>,
+    // asked for with H, for the position on the board
+    hint: Option<Move>,
+    // finds hints, and looks over every move played here while the blunder check is
+    // switched on with B
+    coach: Coach,
+    blunder_check: bool,
+    // the last move the blunder check didn't like, until the next one is played here
+    blunder: Option<Review>,
+    // how good each move was, worked out once the game is over
+    report: Option<Report>,
+    // the engine looking at the position shown, switched on and off with A
+    analysis: Option<Analysis>,
+    // how many the analysis searches on
+    threads: usize,
+    replay: Option<Replay>,
+    // tactics to solve instead of a game, skipped through with N
+    puzzles: Option<puzzles::Puzzles>,
+    // a position being set up by hand, opened with Ctrl+E
+    editor: Option<editor::Editor>,
+    preview: Option<Preview>,
+    // the opening's name, and the moves played from the position in a collection of games
+    openings: openings::Openings,
+    // the games searched for the position shown, once a database is open
+    search: Option<search::Search>,
+    sidebar: Sidebar,
+    // how many rows the move list is scrolled back from the latest moves
+    move_scroll: usize,
+    // how far the board's zoomed in and where to, with a pinch
+    gestures: touch::Gestures,
+    // where the panel was clickable when it was last drawn, in its own coordinates
+    panel_targets: Vec<(Rect, PanelTarget)>,
+    // drawn over everything, with the keys and the mouse to itself, while it's open
+    menu: Option<menu::Menu>,
+    // the other games open, opened with Ctrl+T and switched to with the number keys;
+    // the one shown is number `tab` among them, counting from 0
+    tabs: Vec<tabs::Tab>,
+    tab: usize,
+    // set while a game in the background is being updated, so it keeps to itself
+    in_background: bool,
+    // of the game shown, kept by each game in the background for when it's shown
+    title: String,
+    // the last thing the game had to say, under the status until the next move
+    notice: Option<String>,
+    // for the coach of each game opened
+    book: Option<Book>,
+    // muted and unmuted with M
+    #[cfg(feature = "audio")]
+    speaker: Speaker,
+    // set when something changed that has to be drawn, cleared once it is
+    redraw: bool,
+    last_frame: Instant,
+    // the shortest time between frames, when the frame rate is limited
+    frame_interval: Option<Duration>,
+    // in framebuffer pixels from the top left, like everything is laid out in
+    cursor: (f64, f64),
+    events: Receiver<(f64, WindowEvent)>,
+    window: Window,
+    glfw: Glfw,
+}
+
+impl Game {
+    pub fn new(fen: &str, variant: Box<dyn Variant>) -> Result<Game, ChessError> {
+        let mut board = Board::from_fen(fen)?;
+        variant.setup(&mut board);
+
+        // glfw reports errors as they happen, then create_window says it failed
+        let mut glfw = glfw::init(Some(glfw::Callback {
+            f: print_glfw_error as fn(glfw::Error, String, &()),
+            data: (),
+        }))
+        .map_err(|error| ChessError::WindowInit(error.to_string()))?;
+
+        // as big on a scaled screen as on any other, drawn at the screen's own resolution
+        glfw.window_hint(glfw::WindowHint::ScaleToMonitor(true));
+        glfw.window_hint(glfw::WindowHint::CocoaRetinaFramebuffer(true));
+        let (mut window, events) = glfw
+            .create_window(800, 800, "Chess", glfw::WindowMode::Windowed)
+            .ok_or_else(|| ChessError::WindowInit("glfw couldn't create it".to_string()))?;
+
+        window.set_key_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_mouse_button_polling(true);
+        window.set_scroll_polling(true);
+        window.set_char_polling(true);
+        // so a resized or uncovered window is drawn again
+        window.set_framebuffer_size_polling(true);
+        window.set_refresh_polling(true);
+        window.set_content_scale_polling(true);
+        window.set_size_limits(Some(200), Some(200), None, None);
+        window.make_current();
+        // a frame a refresh at most, swapping the buffers waits for the screen
+        glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+
+        window.get_proc_address("Chess");
+
+        gl::load_with(|s| window.get_proc_address(s) as *const std::os::raw::c_void);
+
+        let renderer = Renderer::new()?;
+        let pieces = Game::generate_textures()?;
+
+        let mut game = Game {
+            renderer,
+            pieces,
+            theme: Theme::default(),
+            theme_path: Theme::default_path(),
+            game: game::Game::new(board, variant, DrawRules::Automatic),
+            selected: None,
+            dropping: None,
+            premove: None,
+            last_move: None,
+            annotations: Annotations::default(),
+            entry: None,
+            focus: None,
+            announcer: None,
+            observers: Observers::default(),
+            drag: None,
+            slide: None,
+            fade: None,
+            check_pulse: None,
+            animations: true,
+            promotion: None,
+            flipped: false,
+            coordinates: true,
+            keys: KeyBindings::default(),
+            computer: None,
+            network: None,
+            controls: control::Controls::default(),
+            #[cfg(feature = "lichess")]
+            online: None,
+            #[cfg(feature = "spectate")]
+            spectators: None,
+            correspondence: None,
+            hint: None,
+            coach: Coach::new(Engine::new()),
+            blunder_check: false,
+            blunder: None,
+            report: None,
+            analysis: None,
+            threads: 1,
+            replay: None,
+            puzzles: None,
+            editor: None,
+            preview: None,
+            openings: openings::Openings::new(),
+            search: None,
+            sidebar: Sidebar::Moves,
+            move_scroll: 0,
+            gestures: touch::Gestures::default(),
+            panel_targets: Vec::new(),
+            menu: None,
+            tabs: Vec::new(),
+            tab: 0,
+            in_background: false,
+            title: "Chess".to_string(),
+            notice: None,
+            book: None,
+            #[cfg(feature = "audio")]
+            speaker: Speaker::new(),
+            redraw: true,
+            last_frame: Instant::now(),
+            frame_interval: None,
+            cursor: (0.0, 0.0),
+            events,
+            window,
+            glfw,
+        };
+        // the starting fen might already be a finished game
+        game.update_status();
+        if game.theme_path.as_ref().is_some_and(|path| path.exists()) {
+            game.reload_theme();
+        }
+        Ok(game)
+    }
+
+    // hands one side over to an engine
+    pub fn play_against_computer(
+        &mut self,
+        color: Color,
+        mut opponent: Opponent,
+        limits: SearchLimits,
+    ) {
+        if let Opponent::Builtin(engine) = &mut opponent {
+            engine.set_variant(self.game.variant.as_ref());
+        }
+        self.game.record.set_player(color, opponent.name());
+        self.game.record.set_player(color.opposite(), "Player");
+        // the player's pieces start at the bottom
+        self.flipped = color == Color::White;
+        self.computer = Some(Computer::new(color, opponent, limits));
+        self.selected = None;
+        self.dropping = None;
+    }
+
+    // shows a loaded game, the arrow keys step through its moves
+    pub fn replay(&mut self, game: PgnGame) {
+        self.computer = None;
+        self.selected = None;
+        self.dropping = None;
+        self.game.variant = game.variant();
+        self.replay = Some(Replay { game, ply: 0 });
+        self.show_replay_move(0);
+    }
+
+    fn show_replay_move(&mut self, ply: usize) {
+        let replay = match self.replay.as_mut() {
+            Some(replay) => replay,
+            None => return,
+        };
+        replay.ply = ply.min(replay.game.moves().len());
+        self.game.board = replay.game.board_at(replay.ply);
+        self.last_move = replay
+            .ply
+            .checked_sub(1)
+            .map(|last| replay.game.moves()[last].mv);
+        self.update_status();
+    }
+
+    pub fn set_key_bindings(&mut self, keys: KeyBindings) {
+        self.keys = keys;
+    }
 
-// following the tutorial from http://nercury.github.io/rust/opengl/tutorial/2018/02/10/opengl-in-rust-from-scratch-03-compiling-shaders.html
+    // in screen coordinates, which a scaled screen makes bigger
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.window.set_size(width as i32, height as i32);
+    }
+
+    // where hints come from, before the engine has to think of one
+    pub fn set_book(&mut self, book: Book) {
+        self.book = Some(book);
+        self.coach = self.new_coach();
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    // asks the coach for a move in the position, drawn as an arrow once it's found;
+    // like analysis it's only for games played at this window
+    fn show_hint(&mut self) {
+        if !self.can_move_pieces() {
+            return;
+        }
+        if self.is_remote_game() {
+            return self.notify("There are no hints while playing someone else");
+        }
+        self.coach.ask(Question::Hint(self.game.board.clone()));
+    }
+
+    fn toggle_blunder_check(&mut self) {
+        self.blunder = None;
+        if self.blunder_check {
+            self.blunder_check = false;
+            return self.notify("Blunder check off");
+        }
+        if self.is_remote_game() {
+            return self.notify("There's no blunder check while playing someone else");
+        }
+        self.blunder_check = true;
+        self.notify(format!(
+            "Blunder check on, for moves losing more than {} pawns",
+            BLUNDER_THRESHOLD as f32 / 100.0
+        ));
+    }
+
+    fn update_coach(&mut self) {
+        let answer = self.coach.answer();
+        if answer.is_some() {
+            self.redraw = true;
+        }
+        match answer {
+            Some(Answer::Hint(board, mv)) => {
+                // the game might have gone on while it was thought about
+                if board.position_key() != self.game.board.position_key() {
+                    return;
+                }
+                self.hint = mv;
+                match mv {
+                    Some(mv) => self.notify(format!("Hint: {}", mv.to_san(&board))),
+                    None => self.notify("No hint in this position"),
+                }
+            }
+            Some(Answer::Review(review)) if review.is_blunder() => {
+                let san = review.mv.to_san(&review.board);
+                let mut text = match mate_in(review.after) {
+                    Some(moves) if moves < 0 => format!("{} allows mate in {}", san, -moves),
+                    _ => format!("{} loses {:.1} pawns", san, review.loss() as f32 / 100.0),
+                };
+                if let Some(better) = review.better {
+                    text.push_str(&format!(", {} was better", better.to_san(&review.board)));
+                }
+                self.notify(format!("Blunder: {}", text));
+                // unless the move's already been taken back
+                if self
+                    .game
+                    .record
+                    .moves()
+                    .iter()
+                    .any(|played| played.mv == review.mv)
+                {
+                    self.blunder = Some(review);
+                }
+            } // the record takes the report's comments, to be saved along with it
+            Some(Answer::Report(report)) if report.is_for(&self.game.record) => {
+                report.annotate(&mut self.game.record);
+                self.notify(format!(
+                    "{}\n{}",
+                    report.summary(Color::White),
+                    report.summary(Color::Black)
+                ));
+                self.report = Some(report);
+            }
+            _ => {}
+        }
+    }
+
+    // analysis helps whoever's playing here, so it's kept to games played at this
+    // window
+    fn toggle_analysis(&mut self) {
+        if self.analysis.take().is_some() {
+            return self.notify("Analysis off");
+        }
+        if self.is_remote_game() {
+            return self.notify("There's no analysis while playing someone else");
+        }
+        if self.is_editing() {
+            return self.notify("Analyse in the editor starts it on the position set up");
+        }
+        self.notify("Analysis on");
+        let mut engine = Engine::new();
+        engine.set_threads(self.threads);
+        engine.set_variant(self.game.variant.as_ref());
+        self.analysis = Some(Analysis::start(engine, self.shown_board()));
+    }
+
+    // starts on the position shown whenever it changes, with the same engine to keep
+    // what it worked out about the last one
+    fn update_analysis(&mut self) {
+        let mut analysis = match self.analysis.take() {
+            Some(analysis) => analysis,
+            None => return,
+        };
+        let shown = self.shown_board();
+        if analysis.board().position_key() != shown.position_key() {
+            let shown = shown.clone();
+            let mut engine = analysis.stop();
+            // the game shown may be another tab's, in another variant
+            engine.set_variant(self.game.variant.as_ref());
+            analysis = Analysis::start(engine, &shown);
+        }
+        if analysis.update() {
+            self.redraw = true;
+        }
+        self.analysis = Some(analysis);
+    }
+
+    // the latest evaluation from white's point of view: how far ahead in pawns, or
+    // who mates in how many
+    fn analysis_text(&self) -> Option<String> {
+        let analysis = self.analysis.as_ref()?;
+        let result = analysis.latest()?;
+        let score = match analysis.board().side_to_move() {
+            Color::White => result.score,
+            Color::Black => -result.score,
+        };
+        Some(match mate_in(score) {
+            Some(moves) if moves > 0 => format!("White mates in {}", moves),
+            Some(moves) => format!("Black mates in {}", -moves),
+            None => format!("{:+.2} at depth {}", score as f32 / 100.0, result.depth),
+        })
+    }
+
+    pub fn game_loop(&mut self) {
+        while !self.window.should_close() {
+            self.wait_for_events();
+            self.handle_window_event();
+            self.update();
+            self.update_tabs();
+            if self.needs_redraw() {
+                self.draw();
+            }
+        }
+        self.save_tabs_on_exit();
+    }
+
+    // everything that goes on in a game between frames, whether it's shown or not
+    fn update(&mut self) {
+        self.update_network();
+        #[cfg(feature = "lichess")]
+        self.update_online();
+        #[cfg(feature = "spectate")]
+        self.update_spectators();
+        // the game waits while a position's being set up
+        if !self.is_editing() {
+            self.update_clock();
+            self.update_computer();
+            self.update_premove();
+            self.update_puzzles();
+            self.update_analysis();
+            self.update_coach();
+        }
+    }
+
+    // there's nothing to keep of a puzzle
+    fn save_on_exit(&mut self) {
+        if self.correspondence.is_some() {
+            return self.save_correspondence();
+        }
+        if !self.game.record.moves().is_empty() && self.puzzles.is_none() {
+            self.save_pgn();
+            self.autosave();
+        }
+    }
+
+    // at most this many frames a second, on top of the screen's own refresh rate
+    pub fn set_fps_limit(&mut self, fps: u32) {
+        self.frame_interval = Some(Duration::from_secs(1) / fps.max(1));
+    }
+
+    // which draws end the game by themselves and which have to be claimed
+    pub fn set_draw_rules(&mut self, rules: DrawRules) {
+        self.game.draw_rules = rules;
+        self.update_status();
+    }
+
+    // whether pieces slide, taken ones fade and checks flash; without, everything's
+    // just where it ends up
+    pub fn set_animations(&mut self, on: bool) {
+        self.animations = on;
+        if !on {
+            self.stop_animations();
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.slide.is_some() || self.fade.is_some() || self.check_pulse.is_some()
+    }
+
+    fn stop_animations(&mut self) {
+        self.slide = None;
+        self.fade = None;
+        self.check_pulse = None;
+    }
+
+    // the last move's piece sliding over, what it took fading and the king it checks
+    // flashing, starting as it's played; the one before's are cut short
+    fn animate_move(&mut self, mv: Move, before: &Board) {
+        self.stop_animations();
+        if !self.animations {
+            return;
+        }
+        let paths = match before.castling_squares(mv) {
+            Some((rook_from, king_to, rook_to)) => vec![(mv.from, king_to), (rook_from, rook_to)],
+            None => vec![(mv.from, mv.to)],
+        };
+        // whatever isn't on its square any more, apart from what moved away from it
+        let taken: Vec<(Square, Piece)> = before
+            .pieces()
+            .filter(|&(square, piece)| {
+                self.game.board.piece_at(square) != Some(piece)
+                    && !paths.iter().any(|&(from, _)| from == square)
+            })
+            .collect();
+        if !taken.is_empty() {
+            self.fade = Some(Fade {
+                pieces: taken,
+                animation: Animation::start(FADE_TIME),
+            });
+        }
+        self.slide = Some(Slide {
+            paths,
+            animation: Animation::start(SLIDE_TIME),
+        });
+        let checked = self.game.board.side_to_move();
+        if self.game.board.is_in_check(checked) {
+            if let Some(king) = self.game.board.king_square(checked) {
+                self.check_pulse = Some((king, Animation::start(CHECK_PULSE_TIME)));
+            }
+        }
+    }
+
+    // sleeps until there's input, or until the next frame is due when something's
+    // moving or a clock is running
+    fn wait_for_events(&mut self) {
+        let since = self.last_frame.elapsed();
+        let next_frame = self
+            .frame_interval
+            .map_or(Duration::ZERO, |interval| interval.saturating_sub(since));
+        let clock_running = self
+            .game
+            .clock
+            .as_ref()
+            .is_some_and(|clock| clock.running().is_some());
+        let timeout = if self.redraw || self.is_animating() {
+            next_frame
+        } else if clock_running {
+            CLOCK_TICK.saturating_sub(since).max(next_frame)
+        } else {
+            IDLE_WAIT
+        };
+        if timeout.is_zero() {
+            self.glfw.poll_events();
+        } else {
+            self.glfw.wait_events_timeout(timeout.as_secs_f64());
+        }
+    }
+
+    // whether there's anything new to draw, as often as the frame rate allows
+    fn needs_redraw(&self) -> bool {
+        let since = self.last_frame.elapsed();
+        if self.frame_interval.is_some_and(|interval| since < interval) {
+            return false;
+        }
+        let clock_running = self
+            .game
+            .clock
+            .as_ref()
+            .is_some_and(|clock| clock.running().is_some());
+        self.redraw || self.is_animating() || (clock_running && since >= CLOCK_TICK)
+    }
+
+    // plays on from a game left unfinished last time, its clock starting again from
+    // where it stopped
+    pub fn resume(&mut self, saved: SavedGame) {
+        self.game.clock = saved.clock();
+        self.game.load(saved.record);
+        self.history_changed();
+    }
+
+    // plays on from a correspondence game, with the side played here at the bottom
+    pub fn play_correspondence(&mut self, game: CorrespondenceGame) {
+        self.game.clock = None;
+        self.game.load(game.record.clone());
+        self.flipped = game.color == Color::Black;
+        self.history_changed();
+        let turn = if game.is_our_move() {
+            "your move"
+        } else {
+            "waiting for the opponent"
+        };
+        println!("Correspondence game {}, {}", game.name, turn);
+        self.correspondence = Some(game);
+    }
+
+    fn save_correspondence(&mut self) {
+        let game = match &mut self.correspondence {
+            Some(game) => game,
+            None => return,
+        };
+        game.record = self.game.record.clone();
+        if let Err(error) = game.save() {
+            eprintln!(
+                "Couldn't keep the correspondence game {}: {}",
+                game.name, error
+            );
+        }
+    }
+
+    // a game still going is kept to be resumed next time, and finishing one leaves
+    // nothing to resume
+    fn autosave(&self) {
+        if self.is_remote_game() || self.replay.is_some() {
+            return;
+        }
+        if self.game.status.is_over() {
+            if let Err(error) = SavedGame::discard() {
+                eprintln!("Couldn't remove the unfinished game: {}", error);
+            }
+            return;
+        }
+        let computer = self.computer.as_ref().map(|computer| computer.color);
+        match SavedGame::new(&self.game.record, self.game.clock.as_ref(), computer).save() {
+            Ok(path) => println!("Kept the game in {} for --resume", path.display()),
+            Err(error) => eprintln!("Couldn't keep the game for --resume: {}", error),
+        }
+    }
+
+    fn save_pgn(&self) {
+        let path = self.game.record.default_file_name();
+        match self.game.record.write_to(&path) {
+            Ok(()) => println!("Saved the game to {}", path),
+            Err(error) => eprintln!("Couldn't save the game to {}: {}", path, error),
+        }
+    }
+
+    // a picture of the board as it's shown, named after the game and the ply, like
+    // game-20201014-183005-12.png
+    fn save_picture(&mut self) {
+        let name = self.game.record.default_file_name();
+        let path = format!("{}-{}.png", name.trim_end_matches(".pgn"), self.shown_ply());
+        match self.export_png(Path::new(&path)) {
+            Ok(()) => println!("Saved a picture of the board to {}", path),
+            Err(error) => eprintln!("Couldn't save {}: {}", path, error),
+        }
+    }
+
+    // draws the board as it's shown, with its highlights, arrows and coordinates, into
+    // an offscreen framebuffer and writes that out as a png; the window isn't touched
+    pub fn export_png(&mut self, path: &Path) -> Result<(), ChessError> {
+        let framebuffer = Framebuffer::new(EXPORT_SIZE, EXPORT_SIZE)?;
+        {
+            let _binding = framebuffer.bind();
+            self.renderer.set_viewport(0, 0, EXPORT_SIZE, EXPORT_SIZE);
+            self.draw_board();
+            self.draw_highlights();
+            self.draw_coordinates();
+            self.draw_peices();
+            self.draw_annotations();
+            self.draw_analysis_arrows();
+            self.draw_hint();
+        }
+        let pixels = framebuffer.read_pixels();
+        // the viewport has to go back to the window's
+        self.redraw = true;
+        let size = EXPORT_SIZE as u32;
+        image::save_buffer(path, &pixels, size, size, image::ColorType::RGB(8))
+            .map_err(|error| ChessError::Screenshot(error.to_string()))
+    }
+
+    // plays a legal move on the board and in the record, and prints it
+    fn play_move(&mut self, mv: Move) {
+        // cleared first, so what a puzzle says about the move is kept
+        self.notice = None;
+        if !self.check_puzzle_move(mv) {
+            return;
+        }
+        self.redraw = true;
+        // the computer's moves and the other side's in a remote game aren't looked at,
+        // the player's are, to be shown if they were bad
+        if !self.is_computer_turn() && !self.is_remote_game() {
+            self.blunder = None;
+            if self.blunder_check {
+                self.coach
+                    .ask(Question::Review(self.game.board.clone(), mv));
+            }
+        }
+        let before = self.game.board.clone();
+        self.last_move = Some(mv);
+        self.annotations.clear();
+        #[cfg(feature = "audio")]
+        let sound = Sound::of_move(&self.game.board, mv);
+        let spoken = self.game.board.describe_move(mv);
+        self.controls_after_move(self.game.board.side_to_move());
+        println!("{}", self.game.play_move(mv));
+        self.observers.moved(&before, mv, &self.game.board);
+        self.animate_move(mv, &before);
+        self.hint = None;
+        self.announce(&spoken);
+        self.update_status();
+        // a move that ends the game gets the sound for that instead
+        #[cfg(feature = "audio")]
+        if !self.game.status.is_over() {
+            self.speaker.play(sound);
+        }
+        self.send_move(mv);
+        self.save_correspondence();
+    }
+
+    // waits for a guest to connect on `port`, who then plays the other side
+    pub fn host_network_game(&mut self, port: u16, color: Color) -> io::Result<()> {
+        let host = Host::bind(port)?;
+        println!("Waiting for an opponent on port {}", host.port()?);
+        self.set_title(&format!(
+            "Chess - waiting for an opponent on port {}",
+            host.port()?
+        ));
+        self.game.record.set_player(color, "Player");
+        self.game.record.set_player(color.opposite(), "Opponent");
+        self.flipped = color == Color::Black;
+        self.computer = None;
+        self.network = Some(Network {
+            role: Role::Host(host),
+            color: Some(color),
+            peer: None,
+            last_attempt: Instant::now(),
+        });
+        Ok(())
+    }
+
+    // connects to a hosted game at an address like 192.168.0.2:7272, which sends over
+    // the position and which side to play
+    pub fn join_network_game(&mut self, address: &str) -> io::Result<()> {
+        let peer = Peer::connect(address)?;
+        self.set_title("Chess - joining");
+        self.computer = None;
+        self.network = Some(Network {
+            role: Role::Guest(address.to_string()),
+            color: None,
+            peer: Some(peer),
+            last_attempt: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn update_network(&mut self) {
+        let network = match &mut self.network {
+            Some(network) => network,
+            None => return,
+        };
+        if network.peer.is_none() {
+            // nobody's time runs while the other side is away
+            if let Some(clock) = &mut self.game.clock {
+                clock.stop();
+            }
+            let peer = match &network.role {
+                Role::Host(host) => match host.accept() {
+                    Ok(peer) => peer,
+                    Err(error) => {
+                        eprintln!("Couldn't accept a connection: {}", error);
+                        None
+                    }
+                },
+                Role::Guest(address) if network.last_attempt.elapsed() >= RECONNECT_INTERVAL => {
+                    network.last_attempt = Instant::now();
+                    Peer::connect(address).ok()
+                }
+                Role::Guest(_) => None,
+            };
+            let mut peer = match peer {
+                Some(peer) => peer,
+                None => return,
+            };
+            if let (Role::Host(_), Some(color)) = (&network.role, network.color) {
+                // a guest coming back gets the whole game again, so it can carry on
+                let hello = Message::Hello(self.network_state(color.opposite()));
+                if let Err(error) = peer.send(&hello) {
+                    eprintln!("Couldn't reach the opponent: {}", error);
+                    return;
+                }
+                println!("The opponent connected");
+                self.network.as_mut().unwrap().peer = Some(peer);
+                self.resume_clock();
+                self.update_status();
+            } else {
+                self.network.as_mut().unwrap().peer = Some(peer);
+            }
+            return;
+        }
+
+        loop {
+            let network = self.network.as_mut().unwrap();
+            match network.peer.as_mut().unwrap().receive() {
+                Ok(Some(Message::Hello(state))) => {
+                    if let Role::Guest(_) = network.role {
+                        let color = state.color;
+                        match self.load_game_state(state) {
+                            Ok(()) => self.network.as_mut().unwrap().color = Some(color),
+                            Err(error) => eprintln!("Got a game that doesn't work: {}", error),
+                        }
+                    }
+                }
+                Ok(Some(Message::Move { uci, remaining })) => {
+                    let mover = self.game.board.side_to_move();
+                    if network.color == Some(mover) {
+                        continue;
+                    }
+                    match self.game.board.parse_uci_move(&uci) {
+                        Some(mv) => self.play_move(mv),
+                        None => {
+                            eprintln!("The opponent sent an illegal move {}", uci);
+                            continue;
+                        }
+                    }
+                    if let (Some(clock), Some(remaining)) = (&mut self.game.clock, remaining) {
+                        clock.set_remaining(mover, remaining);
+                    }
+                }
+                Ok(Some(
+                    message @ Message::Resign
+                    | message @ Message::DrawOffer
+                    | message @ Message::DrawAccept
+                    | message @ Message::DrawDecline
+                    | message @ Message::DrawClaim
+                    | message @ Message::Rematch,
+                )) => self.receive_control(message),
+                Ok(Some(Message::Ping)) | Ok(None) => break,
+                Err(error) => {
+                    self.lose_opponent(error);
+                    break;
+                }
+            }
+        }
+    }
+
+    // after a send or a receive fails; the opponent may still come back
+    fn lose_opponent(&mut self, error: io::Error) {
+        eprintln!("Lost the connection to the opponent: {}", error);
+        if let Some(network) = &mut self.network {
+            network.peer = None;
+        }
+        self.set_title("Chess - waiting for the opponent to reconnect");
+    }
+
+    // the game so far, for a guest playing `color`
+    fn network_state(&self, color: Color) -> GameState {
+        GameState {
+            color,
+            fen: self.game.record.start().to_fen(),
+            variant: self.game.variant.name().to_string(),
+            moves: self
+                .game
+                .record
+                .moves()
+                .iter()
+                .map(|mv| mv.mv.to_string())
+                .collect(),
+            clock: self.game.clock.as_ref().map(|clock| ClockState {
+                control: clock.control().clone(),
+                white: clock.remaining(Color::White),
+                black: clock.remaining(Color::Black),
+            }),
+        }
+    }
+
+    // takes over a game played somewhere else, replacing whatever was on the board,
+    // unless it doesn't make sense
+    fn load_game_state(&mut self, state: GameState) -> Result<(), ChessError> {
+        let variant = variant::by_name(&state.variant)
+            .ok_or_else(|| ChessError::UnknownVariant(state.variant.clone()))?;
+        let mut start = Board::from_fen(&state.fen)?;
+        variant.setup(&mut start);
+        let mut board = start.clone();
+        let mut record = PgnGame::new(&start);
+        record.set_variant(variant.as_ref());
+        for text in &state.moves {
+            let mv = board
+                .parse_uci_move(text)
+                .filter(|&mv| variant.is_legal(&board, mv))
+                .ok_or_else(|| ChessError::IllegalMove(text.clone()))?;
+            record.push(mv, None);
+            board.make_move(mv);
+        }
+        record.set_player(state.color, "Player");
+        record.set_player(state.color.opposite(), "Opponent");
+
+        let played = state.moves.len();
+        self.game.clock = state.clock.map(|times| {
+            record.set_header("TimeControl", &times.control.to_pgn());
+            Clock::resumed(
+                times.control,
+                start.side_to_move(),
+                played,
+                times.white,
+                times.black,
+            )
+        });
+        self.last_move = board.last_move();
+        self.game.board = board;
+        self.game.variant = variant;
+        self.game.record = record;
+        self.flipped = state.color == Color::Black;
+        self.selected = None;
+        self.dropping = None;
+        self.premove = None;
+        self.drag = None;
+        self.stop_animations();
+        self.promotion = None;
+        self.hint = None;
+        self.blunder = None;
+        self.report = None;
+        self.preview = None;
+        self.controls = control::Controls::default();
+        self.game.turn_started = Instant::now();
+        self.set_title("Chess");
+        self.update_status();
+        self.resume_clock();
+        Ok(())
+    }
+
+    fn resume_clock(&mut self) {
+        if let Some(clock) = &mut self.game.clock {
+            if !self.game.status.is_over() {
+                clock.start(self.game.board.side_to_move());
+            }
+        }
+    }
+
+    // tells the opponent about a move made here
+    fn send_move(&mut self, mv: Move) {
+        #[cfg(feature = "lichess")]
+        self.send_online_move(mv);
+        let network = match &mut self.network {
+            Some(network) => network,
+            None => return,
+        };
+        // the side that just moved
+        let mover = self.game.board.side_to_move().opposite();
+        if network.color != Some(mover) {
+            return;
+        }
+        let peer = match &mut network.peer {
+            Some(peer) => peer,
+            None => return,
+        };
+        let message = Message::Move {
+            uci: mv.to_string(),
+            remaining: self.game.clock.as_ref().map(|clock| clock.remaining(mover)),
+        };
+        if let Err(error) = peer.send(&message) {
+            self.lose_opponent(error);
+        }
+    }
+
+    // moves can't be taken back while the engine is thinking about the position, or
+    // once a flag has fallen
+    fn can_change_history(&self) -> bool {
+        self.replay.is_none()
+            && self.puzzles.is_none()
+            && !self.is_editing()
+            && !self.is_remote_game()
+            && !matches!(
+                self.game.status,
+                GameStatus::Timeout(_) | GameStatus::Resignation(_) | GameStatus::DrawAgreed
+            )
+            && self
+                .computer
+                .as_ref()
+                .is_none_or(|computer| !computer.is_thinking())
+    }
+
+    // takes back the last move, and against the computer its reply too so it's the
+    // player's turn again
+    fn take_back(&mut self) {
+        if !self.can_change_history() {
+            return;
+        }
+        self.game.take_back(self.computer_color());
+        self.history_changed();
+    }
+
+    // plays the moves taken back again, up to the player's next turn
+    fn replay_taken_back(&mut self) {
+        if !self.can_change_history() {
+            return;
+        }
+        self.game.replay_taken_back(self.computer_color());
+        self.history_changed();
+    }
+
+    fn computer_color(&self) -> Option<Color> {
+        self.computer.as_ref().map(|computer| computer.color)
+    }
+
+    // the clocks keep the time already used, only the side that's running changes
+    fn history_changed(&mut self) {
+        self.last_move = self.game.board.last_move();
+        self.annotations.clear();
+        self.hint = None;
+        self.blunder = None;
+        self.report = None;
+        self.notice = None;
+        self.preview = None;
+        self.selected = None;
+        self.dropping = None;
+        self.premove = None;
+        self.drag = None;
+        self.stop_animations();
+        self.promotion = None;
+        self.update_status();
+        self.game.restart_turn();
+    }
+
+    fn is_computer_turn(&self) -> bool {
+        self.computer
+            .as_ref()
+            .is_some_and(|computer| computer.color == self.game.board.side_to_move())
+    }
+
+    fn update_computer(&mut self) {
+        if self.game.status.is_over() {
+            if let Some(computer) = &mut self.computer {
+                computer.stop_pondering();
+            }
+            return;
+        }
+        if !self.is_computer_turn() {
+            return;
+        }
+        let computer = self.computer.as_mut().unwrap();
+        if !computer.is_thinking() {
+            computer.start_thinking(&self.game.board, self.game.clock.as_ref());
+        } else {
+            match computer.finished_move() {
+                Some(Ok(Some(mv))) => {
+                    self.play_move(mv);
+                    // on the player's time, about the reply it expects
+                    if !self.game.status.is_over() {
+                        if let Some(computer) = &mut self.computer {
+                            computer.start_pondering(&self.game.board, self.game.clock.as_ref());
+                        }
+                    }
+                }
+                // the game isn't over so there was a move to find; give the side back
+                // to the player rather than asking again every frame
+                Some(Ok(None)) => {
+                    eprintln!("The engine didn't find a move");
+                    self.computer = None;
+                }
+                Some(Err(error)) => {
+                    eprintln!("The engine stopped working: {}", error);
+                    self.computer = None;
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn handle_window_event(&mut self) {
+        let events: Vec<_> = glfw::flush_messages(&self.events).collect();
+        for (_, event) in events {
+            // moving the mouse only changes the picture while something's being dragged
+            let dragging = self.drag.is_some() || self.annotations.started.is_some();
+            if dragging || !matches!(event, WindowEvent::CursorPos(..)) {
+                self.redraw = true;
+            }
+            // a touch screen puts the cursor down where it's tapped, without always
+            // saying it moved there first
+            if let WindowEvent::MouseButton(..) = event {
+                let (x, y) = self.window.get_cursor_pos();
+                self.cursor = self.to_pixels(x, y);
+            }
+            if self.menu.is_some() {
+                self.menu_event(&event);
+                continue;
+            }
+            // the keys are for typing while a move's being typed
+            if self.entry.is_some() && self.type_into_entry(&event) {
+                continue;
+            }
+            match event {
+                glfw::WindowEvent::Char('/') | glfw::WindowEvent::Char(':') => {
+                    self.entry = Some(Entry::default())
+                }
+                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _)
+                    if self.focus.is_some() =>
+                {
+                    self.focus = None
+                }
+                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) if self.is_editing() => {
+                    self.toggle_editor()
+                }
+                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => self.show_menu(),
+                glfw::WindowEvent::Key(Key::Enter, _, Action::Press, _)
+                | glfw::WindowEvent::Key(Key::KpEnter, _, Action::Press, _) => self.press_focus(),
+                glfw::WindowEvent::Key(Key::Tab, _, Action::Press, modifiers)
+                | glfw::WindowEvent::Key(Key::Tab, _, Action::Repeat, modifiers) => {
+                    self.focus_next_piece(modifiers.contains(Modifiers::Shift))
+                }
+                glfw::WindowEvent::Key(key, _, action, modifiers)
+                    if action != Action::Release && self.command_for(key, modifiers).is_some() =>
+                {
+                    let command = self.command_for(key, modifiers).unwrap();
+                    if action == Action::Press || command.repeats() {
+                        self.run_command(command);
+                    }
+                }
+                // the number keys switch games, unless the settings put something on them
+                glfw::WindowEvent::Key(key, _, Action::Press, _)
+                    if tabs::tab_number(key).is_some() =>
+                {
+                    self.switch_tab(tabs::tab_number(key).unwrap())
+                }
+                // the arrows move the focus while there is one
+                glfw::WindowEvent::Key(key, _, Action::Press, _)
+                | glfw::WindowEvent::Key(key, _, Action::Repeat, _)
+                    if self.focus.is_some() || matches!(key, Key::Up | Key::Down) =>
+                {
+                    match key {
+                        Key::Left => self.move_focus(-1, 0),
+                        Key::Right => self.move_focus(1, 0),
+                        Key::Up => self.move_focus(0, 1),
+                        Key::Down => self.move_focus(0, -1),
+                        _ => {}
+                    }
+                }
+                // stepping through the moves, which goes back to the game at the end
+                glfw::WindowEvent::Key(key, _, Action::Press, _)
+                | glfw::WindowEvent::Key(key, _, Action::Repeat, _) => match key {
+                    Key::Left => self.step_through_moves(false),
+                    Key::Right => self.step_through_moves(true),
+                    Key::Home => self.show_earlier_position(0),
+                    Key::End => self.show_earlier_position(usize::MAX),
+                    _ => {}
+                },
+                glfw::WindowEvent::CursorPos(x, y) => self.cursor = self.to_pixels(x, y),
+                glfw::WindowEvent::Scroll(_, y)
+                    if self.panel_point(self.cursor.0, self.cursor.1).is_some() =>
+                {
+                    // the list is clamped to its length when it's drawn
+                    if y > 0.0 {
+                        self.move_scroll += 1;
+                    } else if y < 0.0 {
+                        self.move_scroll = self.move_scroll.saturating_sub(1);
+                    }
+                }
+                glfw::WindowEvent::Scroll(x, y) => self.scroll_board(x, y),
+                glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => {
+                    self.focus = None;
+                    if let Some((x, y)) = self.panel_point(self.cursor.0, self.cursor.1) {
+                        self.press_panel(x, y);
+                    } else if let Some(square) = self.square_at_pixel(self.cursor.0, self.cursor.1)
+                    {
+                        self.press_square(square);
+                    }
+                }
+                glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => {
+                    let square = self.square_at_pixel(self.cursor.0, self.cursor.1);
+                    self.release_square(square);
+                }
+                // right-clicking a square circles it, dragging draws an arrow
+                // where the editor takes a piece off instead
+                glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Press, _)
+                    if self.is_editing() =>
+                {
+                    if let Some(square) = self.square_at_pixel(self.cursor.0, self.cursor.1) {
+                        self.edit_square(square, false);
+                    }
+                }
+                glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Press, _) => {
+                    self.annotations.started = self.square_at_pixel(self.cursor.0, self.cursor.1);
+                }
+                glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Release, _) => {
+                    let square = self.square_at_pixel(self.cursor.0, self.cursor.1);
+                    match (self.annotations.started.take(), square) {
+                        (Some(from), Some(to)) if from == to => self.annotations.toggle_circle(to),
+                        (Some(from), Some(to)) => self.annotations.toggle_arrow(from, to),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // the command the settings put on the key, held with exactly these modifiers
+    fn command_for(&self, key: Key, modifiers: Modifiers) -> Option<Command> {
+        let name = key_name(key)?;
+        self.keys.command(
+            &name,
+            modifiers.contains(Modifiers::Control),
+            modifiers.contains(Modifiers::Shift),
+            modifiers.contains(Modifiers::Alt),
+        )
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Flip => self.flipped = !self.flipped,
+            Command::Undo => self.take_back(),
+            Command::Redo => self.replay_taken_back(),
+            Command::Hint => self.show_hint(),
+            Command::Resign => self.resign(),
+            Command::Save => self.save_pgn(),
+            Command::Picture => self.save_picture(),
+            Command::Analysis => self.toggle_analysis(),
+            Command::BlunderCheck => self.toggle_blunder_check(),
+            Command::Explorer => self.toggle_sidebar(Sidebar::Explorer),
+            Command::Database => self.toggle_search(),
+            Command::Coordinates => self.coordinates = !self.coordinates,
+            Command::ReloadTheme => self.reload_theme(),
+            Command::NextPuzzle => self.skip_puzzle(),
+            Command::OfferDraw => self.offer_draw(),
+            Command::DeclineDraw => self.decline_draw(),
+            Command::Rematch => self.rematch(),
+            Command::Editor => self.toggle_editor(),
+            Command::NewTab => self.new_tab(),
+            Command::CloseTab => self.close_tab(),
+            Command::NextVariation => self.switch_variation(true),
+            Command::PreviousVariation => self.switch_variation(false),
+            Command::PromoteVariation => self.promote_variation(),
+            Command::DeleteVariation => self.delete_variation(),
+            #[cfg(feature = "audio")]
+            Command::Mute => {
+                let muted = !self.speaker.is_muted();
+                self.speaker.set_muted(muted);
+                println!("{}", if muted { "Sound off" } else { "Sound on" });
+            }
+            #[cfg(not(feature = "audio"))]
+            Command::Mute => println!("There's no sound without the audio feature"),
+        }
+    }
+
+    // shown in the panel under the status, a line for each line of the text, where
+    // it stays until a move is played or taken back
+    fn notify(&mut self, text: impl Into<String>) {
+        self.notice = Some(text.into());
+        self.redraw = true;
+    }
+
+    fn update_status(&mut self) {
+        let status = self.game.rules_status();
+        self.set_status(status);
+        self.update_opening();
+    }
+
+    fn set_status(&mut self, status: GameStatus) {
+        self.redraw = true;
+        // a replay or a puzzle only shows how its position stands, the record and the
+        // clock are the game's
+        if self.replay.is_none() && self.puzzle_title().is_none() {
+            return self.set_game_status(status);
+        }
+        self.game.status = status;
+        #[cfg(feature = "spectate")]
+        self.broadcast_position();
+        if let Some(replay) = &self.replay {
+            let mut title = format!(
+                "Chess - {} vs {} - move {} of {}",
+                replay.game.header("White").unwrap_or("?"),
+                replay.game.header("Black").unwrap_or("?"),
+                replay.ply,
+                replay.game.moves().len()
+            );
+            if self.game.status.is_over() {
+                title.push_str(&format!(" - {}", self.game.status));
+            }
+            self.set_title(&title);
+            return;
+        }
+        if let Some(title) = self.puzzle_title() {
+            self.set_title(&title);
+        }
+    }
+
+    // the game's own status, announced and looked over once it's over
+    fn set_game_status(&mut self, status: GameStatus) {
+        let ended = self.game.set_status(status);
+        #[cfg(feature = "spectate")]
+        self.broadcast_position();
+        if status.is_over() {
+            self.premove = None;
+            self.set_title(&format!("Chess - {}", status));
+            if ended {
+                self.announce(&status.to_string());
+                self.observers.game_ended(status);
+            }
+            if ended && !self.game.record.moves().is_empty() {
+                println!("Looking over the game");
+                self.coach
+                    .ask(Question::Report(Box::new(self.game.record.clone())));
+                #[cfg(feature = "audio")]
+                self.speaker.play(Sound::GameEnd);
+            }
+        } else {
+            // a finished game can come back to life by taking moves back
+            self.set_title("Chess");
+        }
+    }
+
+    // plays with a clock, which starts running straight away
+    pub fn set_clock(&mut self, control: TimeControl) {
+        self.game.set_clock(control);
+    }
+
+    // running out of time loses the game
+    fn update_clock(&mut self) {
+        if self.game.status.is_over() {
+            return;
+        }
+        // only the game shown, since the observers can't tell games' clocks apart
+        if let Some(clock) = self.game.clock.as_ref().filter(|_| !self.in_background) {
+            self.observers.clock_running(clock);
+        }
+        if let Some(timeout) = self.game.flagged() {
+            self.selected = None;
+            self.dropping = None;
+            self.premove = None;
+            self.drag = None;
+            self.promotion = None;
+            self.set_status(timeout);
+        }
+    }
+
+    // glfw puts the cursor in screen coordinates, which on a high dpi screen are
+    // bigger than the framebuffer's pixels, by the content scale on windows and linux
+    // or twice on a retina mac
+    fn to_pixels(&self, x: f64, y: f64) -> (f64, f64) {
+        let (window_width, window_height) = self.window.get_size();
+        let (width, height) = self.window.get_framebuffer_size();
+        (
+            x * width as f64 / window_width.max(1) as f64,
+            y * height as f64 / window_height.max(1) as f64,
+        )
+    }
+
+    // the biggest square that fits in the window, centred, as (left, top, size) in
+    // framebuffer pixels like the cursor; zoomed in, it's bigger and only partly shown
+    fn board_rect(&self) -> (f64, f64, f64) {
+        let (width, height) = self.window.get_framebuffer_size();
+        let (board, _, _) = self.layout(width as f64, height as f64);
+        let [left, top, size, _] = self.gestures.zoomed(board);
+        (left, top, size)
+    }
+
+    // where the board, the evaluation bar and the panel go from left to right in an
+    // area of the given size, as (left, top, width, height) from the top left; the bar
+    // has no width unless analysis is on, and the board leaves room for the coordinates
+    // on its left and under it while they're shown
+    fn layout(&self, width: f64, height: f64) -> ([f64; 4], [f64; 4], [f64; 4]) {
+        let bar = if self.analysis.is_some() {
+            EVAL_BAR_WIDTH
+        } else {
+            0.0
+        };
+        let margin = if self.coordinates {
+            COORDINATE_MARGIN
+        } else {
+            0.0
+        };
+        let size = (width / (1.0 + margin + bar + PANEL_WIDTH)).min(height / (1.0 + margin));
+        let left = (width - size * (1.0 + margin + bar + PANEL_WIDTH)) / 2.0 + size * margin;
+        let top = (height - size * (1.0 + margin)) / 2.0;
+        let board = [left, top, size, size];
+        let bar_rect = [left + size, top, size * bar, size];
+        let panel = [left + size * (1.0 + bar), top, size * PANEL_WIDTH, size];
+        (board, bar_rect, panel)
+    }
+
+    // the strips beside and under the board the rank numbers and file letters go in
+    fn coordinate_strips([left, top, size, _]: [f64; 4]) -> ([f64; 4], [f64; 4]) {
+        let margin = size * COORDINATE_MARGIN;
+        let ranks = [left - margin, top, margin, size];
+        let files = [left, top + size, size, margin];
+        (ranks, files)
+    }
+
+    // a point of the window in the panel's normalized device coordinates, if it's in
+    // the panel
+    fn panel_point(&self, x: f64, y: f64) -> Option<(f32, f32)> {
+        let (width, height) = self.window.get_framebuffer_size();
+        let (_, _, [left, top, width, height]) = self.layout(width as f64, height as f64);
+        let (x, y) = ((x - left) / width, (y - top) / height);
+        if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+            return None;
+        }
+        Some((x as f32 * 2.0 - 1.0, 1.0 - y as f32 * 2.0))
+    }
+
+    fn press_panel(&mut self, x: f32, y: f32) {
+        let target = self.panel_targets.iter().find(|(rect, _)| {
+            x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height
+        });
+        match target.map(|&(_, target)| target) {
+            Some(PanelTarget::Ply(ply)) => self.show_earlier_position(ply),
+            Some(PanelTarget::BackToGame) => self.show_earlier_position(usize::MAX),
+            Some(PanelTarget::ClaimDraw) => self.claim_draw(),
+            // clicking the piece again puts it back
+            Some(PanelTarget::Pocket(kind)) if self.can_move_pieces() => {
+                self.selected = None;
+                self.promotion = None;
+                self.dropping = Some(kind).filter(|&kind| self.dropping != Some(kind));
+            }
+            Some(PanelTarget::Explore(mv))
+                if self.can_move_pieces() && self.game.variant.is_legal(&self.game.board, mv) =>
+            {
+                self.play_move(mv)
+            }
+            Some(PanelTarget::DatabaseGame(hit)) => self.open_database_game(hit),
+            Some(PanelTarget::Editor(action)) => self.run_editor_action(action),
+            Some(PanelTarget::Tab(index)) => self.switch_tab(index),
+            _ => {}
+        }
+    }
+
+    // shows the explorer or the games instead of the moves, or the moves again
+    fn toggle_sidebar(&mut self, sidebar: Sidebar) {
+        self.sidebar = if self.sidebar == sidebar {
+            Sidebar::Moves
+        } else {
+            sidebar
+        };
+        self.move_scroll = 0;
+    }
+
+    // the moves of the game in the move list, the replayed one or the one being played
+    fn shown_moves(&self) -> &[PgnMove] {
+        match &self.replay {
+            Some(replay) => replay.game.moves(),
+            None => self.game.record.moves(),
+        }
+    }
+
+    fn shown_ply(&self) -> usize {
+        match (&self.replay, &self.preview) {
+            (Some(replay), _) => replay.ply,
+            (None, Some(preview)) => {
+                preview.branches.iter().map(|&(at, _)| at).sum::<usize>() + preview.ply
+            }
+            (None, None) => self.game.record.moves().len(),
+        }
+    }
+
+    fn shown_board(&self) -> &Board {
+        if let Some(board) = self.edited_board() {
+            return board;
+        }
+        self.preview
+            .as_ref()
+            .map_or(&self.game.board, |preview| &preview.board)
+    }
+
+    fn shown_last_move(&self) -> Option<Move> {
+        if self.is_editing() {
+            return None;
+        }
+        match &self.preview {
+            Some(preview) => {
+                let line = self.game.record.line(&preview.branches)?;
+                preview.ply.checked_sub(1).map(|last| line[last].mv)
+            }
+            None => self.last_move,
+        }
+    }
+
+    // the position after `ply` moves, which is the game itself once it's all of them;
+    // in a replay it's the same as stepping through it
+    fn show_earlier_position(&mut self, ply: usize) {
+        self.annotations.clear();
+        if self.replay.is_some() {
+            return self.show_replay_move(ply);
+        }
+        self.show_line_position(Vec::new(), ply);
+    }
+
+    // the position after `ply` moves of a line of the variation tree; the start of a
+    // variation is the position in the line it came off, and the end of the game's
+    // own moves the game itself
+    fn show_line_position(&mut self, mut branches: Branches, mut ply: usize) {
+        if ply == 0 {
+            if let Some((at, _)) = branches.pop() {
+                ply = at;
+            }
+        }
+        let length = match self.game.record.line(&branches) {
+            Some(line) => line.len(),
+            None => return,
+        };
+        if branches.is_empty() && ply >= length {
+            self.preview = None;
+            return;
+        }
+        let board = match self.game.record.board_in_line(&branches, ply) {
+            Some(board) => board,
+            None => return,
+        };
+        self.selected = None;
+        self.dropping = None;
+        self.drag = None;
+        self.promotion = None;
+        self.preview = Some(Preview {
+            branches,
+            ply,
+            board,
+        });
+    }
 
-struct Shader {
-    id: GLuint,
-}
+    // the line and ply on the board, the game's own position if it's not an earlier one
+    fn shown_line_position(&self) -> (Branches, usize) {
+        match &self.preview {
+            Some(preview) => (preview.branches.clone(), preview.ply),
+            None => (Vec::new(), self.game.record.moves().len()),
+        }
+    }
 
-impl Shader {
-    fn from_source(source: &CStr, kind: GLenum) -> Result<Shader, String> {
-        let id = shader_from_source(source, kind)?;
-        Ok(Shader { id })
+    // a move back or on along the line on the board, back out of a variation at its start
+    fn step_through_moves(&mut self, forward: bool) {
+        if self.replay.is_some() {
+            let ply = self.shown_ply();
+            return self.show_earlier_position(if forward {
+                ply + 1
+            } else {
+                ply.saturating_sub(1)
+            });
+        }
+        self.annotations.clear();
+        let (branches, ply) = self.shown_line_position();
+        if forward {
+            let length = self.game.record.line(&branches).map_or(0, <[PgnMove]>::len);
+            if ply < length {
+                self.show_line_position(branches, ply + 1);
+            }
+        } else if ply > 0 {
+            self.show_line_position(branches, ply - 1);
+        }
     }
 
-    fn from_vert_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::VERTEX_SHADER)
+    // swaps the last move on the board for the next or previous of the moves that could
+    // have been played instead, going round
+    fn switch_variation(&mut self, forward: bool) {
+        if self.replay.is_some() || self.is_editing() {
+            return;
+        }
+        let (branches, ply) = self.shown_line_position();
+        let alternatives = self.game.record.alternatives(&branches, ply);
+        let current = match alternatives
+            .iter()
+            .position(|alternative| *alternative == (branches.clone(), ply))
+        {
+            Some(current) if alternatives.len() > 1 => current,
+            _ => return,
+        };
+        let next = if forward {
+            (current + 1) % alternatives.len()
+        } else {
+            (current + alternatives.len() - 1) % alternatives.len()
+        };
+        self.annotations.clear();
+        let (branches, ply) = alternatives[next].clone();
+        self.show_line_position(branches, ply);
     }
 
-    fn from_frag_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::FRAGMENT_SHADER)
+    // the variation on the board takes the place of the moves it's an alternative to,
+    // which can be the game's own moves unless they're still being played
+    fn promote_variation(&mut self) {
+        let (branches, ply) = self.shown_line_position();
+        let changes_game = branches.len() < 2;
+        if changes_game && self.is_live_game() {
+            return println!("The game's own moves stay what was played until it's over");
+        }
+        let (branches, ply) = match self.game.record.promote_variation(&branches, ply) {
+            Some(promoted) => promoted,
+            None => return,
+        };
+        if changes_game {
+            self.game.board = self.game.record.board_at(self.game.record.moves().len());
+            self.game.taken_back.clear();
+            self.history_changed();
+        }
+        self.show_line_position(branches, ply);
+        self.save_correspondence();
     }
 
-    fn id(&self) -> GLuint {
-        self.id
+    // a game that's going on against someone or something else, or against the clock
+    fn is_live_game(&self) -> bool {
+        self.is_remote_game()
+            || self.correspondence.is_some()
+            || (!self.game.status.is_over()
+                && (self.computer.is_some() || self.game.clock.is_some()))
     }
-}
 
-impl Drop for Shader {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteShader(self.id);
+    // takes out the variation on the board, going back to where it came off
+    fn delete_variation(&mut self) {
+        let (mut branches, _) = self.shown_line_position();
+        if !self.game.record.delete_variation(&branches) {
+            return;
         }
+        let (at, _) = branches.pop().unwrap();
+        self.show_line_position(branches, at);
+        self.save_correspondence();
     }
-}
-
-struct Program {
-    id: GLuint,
-}
 
-impl Program {
-    fn from_shaders(shaders: &[Shader]) -> Result<Program, String> {
-        let program_id = unsafe { gl::CreateProgram() };
+    // a move played on an earlier position, which goes into the variation tree and
+    // leaves the game as it is
+    fn can_play_variation(&self) -> bool {
+        self.preview.is_some()
+            && self.replay.is_none()
+            && !self.is_editing()
+            && self.puzzles.is_none()
+    }
 
-        for shader in shaders {
-            unsafe {
-                gl::AttachShader(program_id, shader.id());
-            }
+    fn play_variation_move(&mut self, mv: Move) {
+        let (branches, ply) = self.shown_line_position();
+        if let Some((branches, ply)) = self.game.record.add_move(&branches, ply, mv) {
+            self.redraw = true;
+            self.annotations.clear();
+            self.show_line_position(branches, ply);
+            self.save_correspondence();
         }
+    }
 
-        unsafe {
-            gl::LinkProgram(program_id);
+    // the player's own move, made on the board
+    fn play_board_move(&mut self, mv: Move) {
+        if self.can_play_variation() {
+            self.play_variation_move(mv);
+        } else {
+            self.play_move(mv);
         }
+    }
 
-        let mut success: GLint = 1;
-        unsafe {
-            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+    // the a1 corner is at the bottom left of the board, or the top right when flipped
+    fn square_at_pixel(&self, x: f64, y: f64) -> Option<Square> {
+        // the squares of a zoomed in board past its area aren't there to click
+        let (width, height) = self.window.get_framebuffer_size();
+        let ([left, top, size, _], _, _) = self.layout(width as f64, height as f64);
+        if x < left || y < top || x >= left + size || y >= top + size {
+            return None;
         }
+        let (left, top, size) = self.board_rect();
+        let (x, y) = (x - left, y - top);
+        if x < 0.0 || y < 0.0 || x >= size || y >= size {
+            return None;
+        }
+        let column = (x / size * 8.0) as u8;
+        let row = 7 - (y / size * 8.0) as u8;
+        let (file, rank) = self.screen_position(Square::new(column, row));
+        Some(Square::new(file, rank))
+    }
 
-        if success == 0 {
-            let mut len: GLint = 0;
-            unsafe {
-                gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut len);
-            }
+    fn can_move_pieces(&self) -> bool {
+        !self.game.status.is_over()
+            && !self.is_computer_turn()
+            && self.replay.is_none()
+            && self.preview.is_none()
+            && !self.is_editing()
+            && self.remote_allows_move()
+            && self.puzzle_allows_move()
+    }
 
-            let error = create_whitespace_cstring_with_len(len as usize);
+    // the side played in this window when the other one is played by an engine or
+    // somewhere else
+    fn player_color(&self) -> Option<Color> {
+        #[cfg(feature = "lichess")]
+        if let Some(online) = &self.online {
+            return online.color();
+        }
+        if let Some(network) = &self.network {
+            return network.color;
+        }
+        if let Some(puzzles) = &self.puzzles {
+            return puzzles.training.puzzle().map(|puzzle| puzzle.color());
+        }
+        self.computer
+            .as_ref()
+            .map(|computer| computer.color.opposite())
+    }
 
-            unsafe {
-                gl::GetProgramInfoLog(
-                    program_id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut GLchar,
-                );
-            }
+    // the player's pieces can be moved in advance while the opponent is to move
+    fn can_premove(&self) -> bool {
+        !self.game.status.is_over()
+            && self.replay.is_none()
+            && self.preview.is_none()
+            && self.player_color() == Some(self.game.board.side_to_move().opposite())
+    }
+
+    // plays the premove once it's the player's turn, or forgets it if it can't be
+    // played in the position the opponent left
+    fn update_premove(&mut self) {
+        if self.premove.is_none() || !self.can_move_pieces() {
+            return;
+        }
+        let premove = self.premove.take().unwrap();
+        match self.find_move(premove.from, premove.to) {
+            // there's no waiting for a choice, premoved pawns become queens
+            Some(mv) => self.play_move(Move {
+                promotion: mv.promotion.map(|_| PieceKind::Queen),
+                ..mv
+            }),
+            None => println!("Forgot the premove {}, it isn't legal any more", premove),
+        }
+    }
 
-            return Err(error.to_string_lossy().into_owned());
+    // a game where the other side is played somewhere else
+    fn is_remote_game(&self) -> bool {
+        #[cfg(feature = "lichess")]
+        if self.online.is_some() {
+            return true;
         }
+        self.network.is_some()
+    }
 
-        for shader in shaders {
-            unsafe {
-                gl::DetachShader(program_id, shader.id());
-            }
+    // whether the other end of a remote game is there and waiting for this side's move
+    fn remote_allows_move(&self) -> bool {
+        let side = self.game.board.side_to_move();
+        #[cfg(feature = "lichess")]
+        if let Some(online) = &self.online {
+            return online.allows_move(side);
         }
+        self.network
+            .as_ref()
+            .is_none_or(|network| network.peer.is_some() && network.color == Some(side))
+    }
+
+    // something to tell about the game in words as it goes, like a screen reader;
+    // see speech for what the sentences look like
+    pub fn set_announcer(&mut self, announcer: impl FnMut(&str) + 'static) {
+        self.announcer = Some(Box::new(announcer));
+    }
 
-        Ok(Program { id: program_id })
+    // told about the games played in the window as they go, for logging them or for
+    // something else to react to; see events for what's told
+    pub fn add_observer(&mut self, observer: impl GameObserver + 'static) {
+        self.observers.add(observer);
     }
 
-    fn set_used(&self) {
-        unsafe {
-            gl::UseProgram(self.id);
+    fn announce(&mut self, text: &str) {
+        if let Some(announcer) = &mut self.announcer {
+            announcer(text);
         }
     }
-}
 
-impl Drop for Program {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteProgram(self.id);
+    // where the focus starts: on the king of the player, or of the side to move
+    fn default_focus(&self) -> Square {
+        let color = self
+            .player_color()
+            .unwrap_or_else(|| self.game.board.side_to_move());
+        self.shown_board()
+            .king_square(color)
+            .unwrap_or_else(|| Square::new(4, 0))
+    }
+
+    fn set_focus(&mut self, square: Square) {
+        self.focus = Some(square);
+        let spoken = speech::describe_square(self.shown_board(), square);
+        self.announce(&spoken);
+    }
+
+    // a square over in the direction of an arrow key as the board is shown, so up is
+    // always away from the player; the first press only shows where the focus is
+    fn move_focus(&mut self, columns: i8, rows: i8) {
+        let focus = match self.focus {
+            Some(focus) => focus,
+            None => return self.set_focus(self.default_focus()),
+        };
+        let (files, ranks) = if self.flipped {
+            (-columns, -rows)
+        } else {
+            (columns, rows)
+        };
+        if let Some(square) = focus.offset(files, ranks) {
+            self.set_focus(square);
         }
     }
-}
 
-struct Texture {
-    id: GLuint,
-}
+    // the next piece, or the one before with Shift, that can move or premove, going
+    // through them a square at a time from a1
+    fn focus_next_piece(&mut self, backwards: bool) {
+        let mut squares: Vec<Square> = if self.can_move_pieces() {
+            self.game
+                .variant
+                .legal_moves(&self.game.board)
+                .into_iter()
+                .filter(|mv| mv.drop.is_none())
+                .map(|mv| mv.from)
+                .collect()
+        } else if self.can_premove() {
+            let color = self.game.board.side_to_move().opposite();
+            self.game
+                .board
+                .pieces()
+                .filter(|&(square, piece)| {
+                    piece.color == color && !self.game.board.premove_targets(square).is_empty()
+                })
+                .map(|(square, _)| square)
+                .collect()
+        } else {
+            return;
+        };
+        squares.sort_by_key(|square| square.index());
+        squares.dedup();
+        if backwards {
+            squares.reverse();
+        }
+        let next = match self
+            .focus
+            .and_then(|focus| squares.iter().position(|&s| s == focus))
+        {
+            Some(i) => squares.get(i + 1).or_else(|| squares.first()),
+            None => squares.first(),
+        };
+        if let Some(&square) = next {
+            self.set_focus(square);
+        }
+    }
 
-impl Texture {
-    fn from_file(path: &str) -> Result<Texture, String> {
-        let mut texture_id = 0;
-        unsafe {
-            gl::GenTextures(1, &mut texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-            let img = image::open(&std::path::Path::new(path)).expect("Failed to load texture");
-            let data = img.raw_pixels();
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGB as i32,
-                img.width() as i32,
-                img.height() as i32,
-                0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
-                &data[0] as *const u8 as *const c_void,
+    // Enter is a click on the focus: picking up the piece there, moving the one picked
+    // up to it, or choosing the piece to promote to
+    fn press_focus(&mut self) {
+        let square = match self.focus {
+            Some(square) => square,
+            None => return self.set_focus(self.default_focus()),
+        };
+        let was_selected = self.selected;
+        self.press_square(square);
+        self.release_square(Some(square));
+        if self.selected == Some(square) && was_selected != Some(square) {
+            let spoken = format!(
+                "{}, picked up",
+                speech::describe_square(&self.game.board, square)
             );
-            gl::GenerateMipmap(gl::TEXTURE_2D);
+            self.announce(&spoken);
+        }
+        if let Some(promotion) = &self.promotion {
+            let choices = promotion.choices();
+            let spoken = choices
+                .iter()
+                .map(|&(square, kind)| format!("{} on {}", speech::kind_name(kind), square))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.announce(&format!("Promote to a {}", spoken));
+            self.focus = Some(choices[0].0);
         }
+    }
 
-        let mut success: GLint = 1;
-        unsafe {
-            gl::GetProgramiv(texture_id, gl::LINK_STATUS, &mut success);
+    // pressing picks up a piece of the side to move, which can then be dragged or
+    // clicked over to its target; pressing a target with a piece selected moves there.
+    // during the opponent's turn the player's pieces move the same way to premove, and
+    // pressing anywhere takes a premove back
+    fn press_square(&mut self, square: Square) {
+        if self.is_editing() {
+            return self.edit_square(square, true);
+        }
+        let color = if self.can_move_pieces() || self.can_play_variation() {
+            self.shown_board().side_to_move()
+        } else if self.can_premove() {
+            if self.premove.take().is_some() {
+                return;
+            }
+            self.game.board.side_to_move().opposite()
+        } else {
+            return;
+        };
+        if let Some(promotion) = self.promotion.take() {
+            // picking one of the offered pieces makes the move, anywhere else cancels it
+            let choice = promotion
+                .choices()
+                .iter()
+                .copied()
+                .find(|&(choice, _)| choice == square);
+            if let Some((_, kind)) = choice {
+                self.play_board_move(Move::with_promotion(promotion.from, promotion.to, kind));
+            }
+            return;
+        }
+        if let Some(kind) = self.dropping.take() {
+            // anywhere it can't go puts it back, and might pick up a piece instead
+            let mv = Move::with_drop(kind, square);
+            if self.game.variant.is_legal(self.shown_board(), mv) {
+                return self.play_board_move(mv);
+            }
+        }
+        if self.selected == Some(square) {
+            self.drag = Some(Drag {
+                from: square,
+                was_selected: true,
+            });
+            return;
         }
+        if let Some(from) = self.selected.take() {
+            if self.try_move(from, square) {
+                return;
+            }
+        }
+        let own_piece = self
+            .shown_board()
+            .piece_at(square)
+            .is_some_and(|piece| piece.color == color);
+        if own_piece {
+            self.selected = Some(square);
+            self.drag = Some(Drag {
+                from: square,
+                was_selected: false,
+            });
+        }
+    }
 
-        if success == 0 {
-            let mut len: GLint = 0;
-            unsafe {
-                gl::GetProgramiv(texture_id, gl::INFO_LOG_LENGTH, &mut len);
+    // dropping on another square makes the move if it's legal and otherwise snaps the
+    // piece back, still selected; letting go where it was picked up is just a click
+    fn release_square(&mut self, square: Option<Square>) {
+        let drag = match self.drag.take() {
+            Some(drag) => drag,
+            None => return,
+        };
+        match square {
+            Some(to) if to != drag.from => {
+                let moved = self.try_move(drag.from, to);
+                if moved {
+                    // the piece is already where it was dropped
+                    self.slide = None;
+                }
             }
+            Some(_) if drag.was_selected => self.selected = None,
+            _ => {}
+        }
+    }
 
-            let error = create_whitespace_cstring_with_len(len as usize);
+    // the legal move from one square to the other, the first of them for a promotion
+    fn find_move(&self, from: Square, to: Square) -> Option<Move> {
+        // in chess960 the king castles by going onto its rook, but it can also be put
+        // where it ends up as long as that isn't a move of its own
+        let board = self.shown_board();
+        let moves = self.game.variant.legal_moves(board);
+        moves
+            .iter()
+            .find(|mv| mv.from == from && mv.to == to)
+            .or_else(|| {
+                moves.iter().find(|&&mv| {
+                    mv.from == from
+                        && board
+                            .castling_squares(mv)
+                            .is_some_and(|(_, king_to, _)| king_to == to)
+                })
+            })
+            .copied()
+    }
 
-            unsafe {
-                gl::GetProgramInfoLog(
-                    texture_id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut GLchar,
-                );
+    // makes the move if it's legal, except that a promotion waits for the player to
+    // pick a piece, or premoves it during the opponent's turn if the piece might be
+    // able to go there; either way the move is taken care of
+    fn try_move(&mut self, from: Square, to: Square) -> bool {
+        if !self.can_move_pieces() && !self.can_play_variation() {
+            if !self.game.board.premove_targets(from).contains(to) {
+                return false;
+            }
+            self.selected = None;
+            self.premove = Some(Move::new(from, to));
+            return true;
+        }
+        match self.find_move(from, to) {
+            Some(mv) => {
+                self.selected = None;
+                if mv.promotion.is_some() {
+                    self.promotion = Some(Promotion {
+                        from,
+                        to,
+                        color: self.shown_board().side_to_move(),
+                    });
+                } else {
+                    self.play_board_move(mv);
+                }
+                true
             }
+            None => false,
+        }
+    }
 
-            return Err(error.to_string_lossy().into_owned());
+    // true when the event was typing, so it doesn't do what the key otherwise does
+    fn type_into_entry(&mut self, event: &WindowEvent) -> bool {
+        let entry = self.entry.as_mut().unwrap();
+        match *event {
+            WindowEvent::Char(c) => entry.text.push(c),
+            WindowEvent::Key(key, _, Action::Press, _)
+            | WindowEvent::Key(key, _, Action::Repeat, _) => match key {
+                Key::Backspace => {
+                    entry.text.pop();
+                }
+                Key::Escape => self.entry = None,
+                Key::Enter | Key::KpEnter => self.enter_move(),
+                _ => {}
+            },
+            WindowEvent::Key(..) => {}
+            _ => return false,
         }
+        true
+    }
 
-        Ok(Texture { id: texture_id })
+    // plays the move typed in san or uci, leaving the entry open for the next one. the
+    // piece letters can be lower case too, except for b which could be a pawn's file
+    fn enter_move(&mut self) {
+        let text = self.entry.as_ref().unwrap().text.trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        let result = if !self.can_move_pieces() {
+            Err("It isn't your move".to_string())
+        } else {
+            let capitalized = match text.chars().next() {
+                Some(c @ ('n' | 'r' | 'q' | 'k')) => {
+                    format!("{}{}", c.to_ascii_uppercase(), &text[1..])
+                }
+                _ => text.clone(),
+            };
+            self.game
+                .board
+                .parse_san(&text)
+                .or_else(|error| {
+                    self.game
+                        .board
+                        .parse_san(&capitalized)
+                        .or_else(|_| self.game.board.parse_uci_move(&text).ok_or(error))
+                })
+                .map_err(|error| error.to_string())
+                .and_then(|mv| {
+                    if self.game.variant.is_legal(&self.game.board, mv) {
+                        Ok(mv)
+                    } else {
+                        Err(format!(
+                            "{} isn't allowed in {}",
+                            text,
+                            self.game.variant.name()
+                        ))
+                    }
+                })
+        };
+        match result {
+            Ok(mv) => {
+                self.selected = None;
+                self.dropping = None;
+                self.play_move(mv);
+                self.entry = Some(Entry::default());
+            }
+            Err(error) => {
+                // said as well as shown, for whoever can't see the window
+                println!("{}", error);
+                self.entry = Some(Entry {
+                    text,
+                    error: Some(error),
+                });
+            }
+        }
     }
 
-    fn set_used(&self) {
-        unsafe { gl::BindTexture(gl::TEXTURE_2D, self.id) }
+    // where a square is drawn, in normalized device coordinates
+    fn square_rect(&self, square: Square) -> Rect {
+        let (column, row) = self.screen_position(square);
+        Rect {
+            x: column as f32 * SQUARE_SIZE - 1.0,
+            y: row as f32 * SQUARE_SIZE - 1.0,
+            width: SQUARE_SIZE,
+            height: SQUARE_SIZE,
+        }
     }
-}
 
-pub struct Game {
-    glfw: Glfw,
-    window: Window,
-    events: Receiver<(f64, WindowEvent)>,
-    white_shader: Program,
-    black_shader: Program,
-    textures: [Texture; 12],
-    board: [GLuint; 64],
-}
+    // the centre of a square in normalized device coordinates
+    fn square_center(&self, square: Square) -> (f32, f32) {
+        let (column, row) = self.screen_position(square);
+        (
+            (column as f32 - 3.5) * SQUARE_SIZE,
+            (row as f32 - 3.5) * SQUARE_SIZE,
+        )
+    }
 
-impl Game {
-    pub fn new() -> Game {
-        let glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+    // the column from the left and row from the bottom a square is shown in, which
+    // are its file and rank unless the board is flipped; it works both ways
+    fn screen_position(&self, square: Square) -> (u8, u8) {
+        if self.flipped {
+            (7 - square.file(), 7 - square.rank())
+        } else {
+            (square.file(), square.rank())
+        }
+    }
 
-        let (mut window, events) = glfw
-            .create_window(800, 800, "Chess", glfw::WindowMode::Windowed)
-            .expect("Failed to create GLFW window.");
+    pub fn set_flipped(&mut self, flipped: bool) {
+        self.flipped = flipped;
+    }
 
-        window.set_key_polling(true);
-        window.make_current();
+    fn cursor_position(&self) -> (f32, f32) {
+        let (left, top, size) = self.board_rect();
+        (
+            ((self.cursor.0 - left) / size * 2.0 - 1.0) as f32,
+            (1.0 - (self.cursor.1 - top) / size * 2.0) as f32,
+        )
+    }
 
-        window.get_proc_address("Chess");
+    // the board keeps its square and the bars on the longer sides of the window are
+    // left in the clear colour instead of stretching it
+    fn draw(&mut self) {
+        // the last frame of each has everything where it ends up
+        let slid = self
+            .slide
+            .as_ref()
+            .is_some_and(|slide| slide.animation.is_done());
+        let faded = self
+            .fade
+            .as_ref()
+            .is_some_and(|fade| fade.animation.is_done());
+        let pulsed = self
+            .check_pulse
+            .as_ref()
+            .is_some_and(|(_, pulse)| pulse.is_done());
+        let (width, height) = self.window.get_framebuffer_size();
+        let (board, bar, panel) = self.layout(width as f64, height as f64);
+        // gl counts its rows from the bottom
+        let viewport = |[left, top, width, area_height]: [f64; 4]| {
+            (
+                left as i32,
+                (height as f64 - top - area_height) as i32,
+                width as i32,
+                area_height as i32,
+            )
+        };
+
+        self.renderer.clear(self.theme.background);
+        let (x, y, w, h) = viewport(self.gestures.zoomed(board));
+        self.renderer.set_viewport(x, y, w, h);
+        self.renderer.set_clip(Some(viewport(board)));
+        self.draw_board();
+        self.draw_highlights();
+        self.draw_peices();
+        self.draw_annotations();
+        self.draw_analysis_arrows();
+        self.draw_hint();
+        self.draw_promotion();
+        self.draw_focus();
+        self.draw_result();
+        self.renderer.set_clip(None);
+        if self.analysis.is_some() {
+            let (x, y, w, h) = viewport(bar);
+            self.renderer.set_viewport(x, y, w, h);
+            self.draw_eval_bar();
+        }
+        // they'd only be right for some of the squares of a zoomed in board
+        if self.coordinates && !self.gestures.is_zoomed() {
+            let (ranks, files) = Game::coordinate_strips(board);
+            let (x, y, w, h) = viewport(ranks);
+            self.renderer.set_viewport(x, y, w, h);
+            self.draw_edge_coordinates(false);
+            let (x, y, w, h) = viewport(files);
+            self.renderer.set_viewport(x, y, w, h);
+            self.draw_edge_coordinates(true);
+        }
+        let (x, y, w, h) = viewport(panel);
+        self.renderer.set_viewport(x, y, w, h);
+        self.draw_panel();
+        self.draw_menu();
 
-        let _gl = gl::load_with(|s| window.get_proc_address(s) as *const std::os::raw::c_void);
+        self.window.swap_buffers();
+        self.redraw = false;
+        self.last_frame = Instant::now();
+        if slid {
+            self.slide = None;
+        }
+        if faded {
+            self.fade = None;
+        }
+        if pulsed {
+            self.check_pulse = None;
+        }
+    }
 
-        let (white_shader, black_shader) = Game::generate_shaders();
-        let board = Game::generate_vaos();
-        let textures = Game::generate_textures();
+    // draws with the theme in the file from now on
+    pub fn set_theme_file(&mut self, path: PathBuf) {
+        self.theme_path = Some(path);
+        self.reload_theme();
+    }
 
-        Game {
-            glfw,
-            window,
-            events,
-            white_shader,
-            black_shader,
-            textures,
-            board,
+    // keeps the theme it has if the file is no good
+    fn reload_theme(&mut self) {
+        let path = match &self.theme_path {
+            Some(path) => path,
+            None => return eprintln!("There's no config directory to load a theme from"),
+        };
+        let loaded = Theme::load(path).and_then(|theme| {
+            let pieces = match &theme.pieces {
+                Some(directory) => {
+                    let sheet = directory.join("pieces.png");
+                    Texture::from_sprite_sheet(&sheet.to_string_lossy(), 6, 2)
+                }
+                None => Game::generate_textures(),
+            };
+            Ok((theme, pieces.map_err(|error| error.to_string())?))
+        });
+        match loaded {
+            Ok((theme, pieces)) => {
+                self.theme = theme;
+                self.pieces = pieces;
+            }
+            Err(error) => eprintln!("Couldn't load the theme {}: {}", path.display(), error),
         }
     }
 
-    pub fn game_loop(&mut self) {
-        while !self.window.should_close() {
-            self.handle_window_event();
-            self.draw(&[0; 5]);
+    fn generate_textures() -> Result<Texture, ChessError> {
+        let img = image::load_from_memory(include_bytes!("pieces.png"))
+            .map_err(|error| ChessError::TextureLoad(format!("pieces.png: {}", error)))?;
+        Texture::from_image(&img, 6, 2)
+    }
+
+    fn draw_board(&self) {
+        for i in 0..64 {
+            let square = Square::from_index(i);
+            let color = if (square.file() + square.rank()).is_multiple_of(2) {
+                self.theme.dark_squares
+            } else {
+                self.theme.light_squares
+            };
+            self.renderer.fill(self.square_rect(square), color);
         }
     }
 
-    fn handle_window_event(&mut self) {
-        self.glfw.poll_events();
-        for (_, event) in glfw::flush_messages(&self.events) {
-            match event {
-                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
-                    self.window.set_should_close(true)
+    // the last move's squares, the selected piece, and where it can go: dots on empty
+    // squares and rings around pieces it can take
+    fn draw_highlights(&self) {
+        let renderer = &self.renderer;
+        // the square moved from a little fainter than the one moved to
+        let tint = |mv: Move, (red, green, blue, alpha)| {
+            renderer.fill(self.square_rect(mv.from), (red, green, blue, alpha * 0.75));
+            renderer.fill(self.square_rect(mv.to), (red, green, blue, alpha));
+        };
+        if let Some(mv) = self.shown_last_move() {
+            tint(mv, self.theme.last_move);
+        }
+        if let Some(review) = self.blunder.as_ref().filter(|_| self.preview.is_none()) {
+            tint(review.mv, self.theme.blunder);
+        }
+        if let Some(mv) = self.premove {
+            tint(mv, self.theme.premove);
+        }
+        let board = self.shown_board();
+        let checked = board.side_to_move();
+        if let Some(king) = board
+            .king_square(checked)
+            .filter(|_| board.is_in_check(checked))
+        {
+            let (red, green, blue, alpha) = self.theme.check;
+            let flash = self
+                .check_pulse
+                .filter(|&(square, _)| square == king && self.preview.is_none())
+                .map_or(0.0, |(_, pulse)| {
+                    animation::pulse(pulse.progress(), CHECK_PULSE_BEATS)
+                });
+            let alpha = (alpha * (1.0 + flash)).min(1.0);
+            renderer.fill(self.square_rect(king), (red, green, blue, alpha));
+        }
+        if let Some(from) = self.selected {
+            renderer.fill(self.square_rect(from), self.theme.selected);
+            let mut targets: Vec<Square> = if self.can_move_pieces() || self.can_play_variation() {
+                self.game
+                    .variant
+                    .legal_moves(self.shown_board())
+                    .into_iter()
+                    .filter(|mv| mv.from == from)
+                    .map(|mv| mv.to)
+                    .collect()
+            } else {
+                self.game.board.premove_targets(from).squares().collect()
+            };
+            // each promotion is its own move to the same square
+            targets.dedup();
+            for to in targets {
+                let shape = if self.shown_board().is_capture(Move::new(from, to)) {
+                    Shape::Ring
+                } else {
+                    Shape::Dot
+                };
+                renderer.shape(self.square_rect(to), self.theme.move_targets, shape);
+            }
+        }
+        if let Some(kind) = self.dropping {
+            for mv in self.game.variant.legal_moves(self.shown_board()) {
+                if mv.drop == Some(kind) {
+                    renderer.shape(self.square_rect(mv.to), self.theme.move_targets, Shape::Dot);
                 }
-                _ => {}
             }
         }
     }
 
-    fn draw(&mut self, _board: &[i32]) {
-        unsafe {
-            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+    // file letters in the corners of the bottom row and rank numbers in the corners of
+    // the left column, each in the colour of the other squares so it shows on its own
+    fn draw_coordinates(&self) {
+        let size = SQUARE_SIZE * 0.22;
+        for i in 0..64 {
+            let square = Square::from_index(i);
+            let (column, row) = self.screen_position(square);
+            let color = if (square.file() + square.rank()).is_multiple_of(2) {
+                self.theme.light_squares
+            } else {
+                self.theme.dark_squares
+            };
+            let rect = self.square_rect(square);
+            if row == 0 {
+                let corner = Rect {
+                    x: rect.x + rect.width - size * 1.2,
+                    y: rect.y + size * 0.2,
+                    width: size,
+                    height: size,
+                };
+                let letter = ((b'a' + square.file()) as char).to_string();
+                self.renderer.text(&letter, corner, color, Align::Center);
+            }
+            if column == 0 {
+                let corner = Rect {
+                    x: rect.x + size * 0.2,
+                    y: rect.y + rect.height - size * 1.2,
+                    width: size,
+                    height: size,
+                };
+                let number = (square.rank() + 1).to_string();
+                self.renderer.text(&number, corner, color, Align::Center);
+            }
         }
-        self.draw_board();
-        self.draw_peices();
+    }
 
-        self.window.swap_buffers();
+    // the file letters along a strip under the board, or the rank numbers up one beside
+    // it, each lined up with its squares whichever way up the board is
+    fn draw_edge_coordinates(&self, files: bool) {
+        // a square's width in the strip's normalized device coordinates along it
+        let step = 2.0 / 8.0;
+        for i in 0..8 {
+            let square = if files {
+                Square::new(i, 0)
+            } else {
+                Square::new(0, i)
+            };
+            let (column, row) = self.screen_position(square);
+            let (label, rect) = if files {
+                let label = ((b'a' + i) as char).to_string();
+                let x = column as f32 * step - 1.0;
+                (
+                    label,
+                    Rect {
+                        x,
+                        y: -0.8,
+                        width: step,
+                        height: 1.6,
+                    },
+                )
+            } else {
+                let label = (i + 1).to_string();
+                // as tall as the letters, which take most of the strip's height
+                let y = row as f32 * step - 1.0 + step * 0.34;
+                (
+                    label,
+                    Rect {
+                        x: -1.0,
+                        y,
+                        width: 2.0,
+                        height: step * 0.32,
+                    },
+                )
+            };
+            self.renderer
+                .text(&label, rect, (0.8, 0.8, 0.8, 1.0), Align::Center);
+        }
     }
 
-    fn generate_textures() -> [Texture; 12] {
-        [
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-            Texture::from_file("black-pawn.png").unwrap(),
-        ]
-    }
-
-    fn generate_shaders() -> (Program, Program) {
-        let white_vert =
-            Shader::from_vert_source(&CString::new(include_str!("white.vert")).unwrap()).unwrap();
-
-        let white_frag =
-            Shader::from_frag_source(&CString::new(include_str!("white.frag")).unwrap()).unwrap();
-
-        let white_shaders = Program::from_shaders(&[white_vert, white_frag]).unwrap();
-
-        let black_vert =
-            Shader::from_vert_source(&CString::new(include_str!("black.vert")).unwrap()).unwrap();
-
-        let black_frag =
-            Shader::from_frag_source(&CString::new(include_str!("black.frag")).unwrap()).unwrap();
-
-        let black_shaders = Program::from_shaders(&[black_vert, black_frag]).unwrap();
-
-        (white_shaders, black_shaders)
-    }
-
-    fn generate_vaos() -> [GLuint; 64] {
-        let generate_vao = |x: f32, y: f32| -> GLuint {
-            let square_size: f32 = 2.0 / 8.0;
-            let vertices: [f32; 12] = [
-                x * square_size + square_size,
-                y * square_size + square_size,
-                0.0, // top right
-                x * square_size + square_size,
-                y * square_size,
-                0.0, // bottom right
-                x * square_size,
-                y * square_size,
-                0.0, // bottom left
-                x * square_size,
-                y * square_size + square_size,
-                0.0, // top left
-            ];
-
-            let indices = [
-                0, 1, 3, // first Triangle
-                1, 2, 3, // second Triangle
-            ];
-
-            let (mut vbo, mut vao, mut ebo) = (0, 0, 0);
-
-            unsafe {
-                gl::GenVertexArrays(1, &mut vao);
-                gl::GenBuffers(1, &mut vbo);
-                gl::GenBuffers(1, &mut ebo);
-
-                gl::BindVertexArray(vao);
-
-                gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,                                                // target
-                    (vertices.len() * std::mem::size_of::<GLfloat>()) as GLsizeiptr, // size of data in bytes
-                    &vertices[0] as *const f32 as *const GLvoid, // pointer to data
-                    gl::STATIC_DRAW,                             // usage
+    fn draw_peices(&self) {
+        let draw_peice = |piece: Piece, rect: Rect| {
+            self.renderer
+                .sprite(rect, &self.pieces, self.piece_sprite(piece));
+        };
+
+        // under the pieces, so one that took them slides over the top
+        if let Some(fade) = self.fade.as_ref().filter(|_| self.preview.is_none()) {
+            let opacity = 1.0 - fade.animation.progress();
+            for &(square, piece) in &fade.pieces {
+                self.renderer.faded_sprite(
+                    self.square_rect(square),
+                    &self.pieces,
+                    self.piece_sprite(piece),
+                    opacity,
                 );
+            }
+        }
+
+        let dragged = self.drag.as_ref().map(|drag| drag.from);
+        let slide = self
+            .slide
+            .as_ref()
+            .filter(|_| self.preview.is_none())
+            .filter(|slide| slide.remaining() > 0.0);
+        for (square, piece) in self.shown_board().pieces() {
+            if Some(square) == dragged {
+                continue;
+            }
+            let path = slide.and_then(|slide| {
+                let &(from, _) = slide.paths.iter().find(|&&(_, to)| to == square)?;
+                Some((from, slide.remaining()))
+            });
+            let rect = match path {
+                Some((from, remaining)) => {
+                    let (from_x, from_y) = self.square_center(from);
+                    let (to_x, to_y) = self.square_center(square);
+                    self.square_rect(square)
+                        .offset((from_x - to_x) * remaining, (from_y - to_y) * remaining)
+                }
+                _ => self.square_rect(square),
+            };
+            draw_peice(piece, rect);
+        }
 
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-                gl::BufferData(
-                    gl::ELEMENT_ARRAY_BUFFER,                                       // target
-                    (indices.len() * std::mem::size_of::<GLfloat>()) as GLsizeiptr, // size of data in bytes
-                    &indices[0] as *const i32 as *const GLvoid, // pointer to data
-                    gl::STATIC_DRAW,                            // usage
+        // last so it's on top of everything else
+        if let Some(square) = dragged {
+            if let Some(piece) = self.shown_board().piece_at(square) {
+                let (cursor_x, cursor_y) = self.cursor_position();
+                let (x, y) = self.square_center(square);
+                draw_peice(
+                    piece,
+                    self.square_rect(square).offset(cursor_x - x, cursor_y - y),
                 );
+            }
+        }
+    }
 
-                let stride = 3 * std::mem::size_of::<GLfloat>() as GLsizei;
+    // dims the board and shows the pieces the pawn can become over their squares
+    fn draw_promotion(&self) {
+        let promotion = match &self.promotion {
+            Some(promotion) => promotion,
+            None => return,
+        };
+        let board = Rect {
+            x: -1.0,
+            y: -1.0,
+            width: 2.0,
+            height: 2.0,
+        };
+        self.renderer.fill(board, (0.0, 0.0, 0.0, 0.5));
+        for (square, kind) in promotion.choices().iter() {
+            let rect = self.square_rect(*square);
+            self.renderer.fill(rect, (0.8, 0.8, 0.8, 0.9));
+            let sprite = self.piece_sprite(Piece::new(*kind, promotion.color));
+            self.renderer.sprite(rect, &self.pieces, sprite);
+        }
+    }
 
-                gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
-                gl::EnableVertexAttribArray(0);
+    // over the promotion choices too, since they're picked with it
+    fn draw_focus(&self) {
+        if let Some(square) = self.focus {
+            self.renderer
+                .shape(self.square_rect(square), self.theme.focus, Shape::Ring);
+        }
+    }
 
-                gl::BindBuffer(gl::ARRAY_BUFFER, 0); // unbind the buffer
-                gl::BindVertexArray(0);
-            }
+    // the circles and arrows drawn with the right button over the pieces, along with
+    // the arrow still being dragged out
+    fn draw_annotations(&self) {
+        let color = self.theme.annotations;
+        for &square in &self.annotations.circles {
+            self.renderer
+                .shape(self.square_rect(square), color, Shape::Ring);
+        }
+        let dragged = self.annotations.started.and_then(|from| {
+            self.square_at_pixel(self.cursor.0, self.cursor.1)
+                .filter(|&to| to != from)
+                .map(|to| (from, to))
+        });
+        for &(from, to) in self.annotations.arrows.iter().chain(dragged.iter()) {
+            self.draw_arrow(from, to, color);
+        }
+    }
 
-            vao
+    // from the middle of one square to the middle of the other
+    fn draw_arrow(&self, from: Square, to: Square, color: (f32, f32, f32, f32)) {
+        let board = Rect {
+            x: -1.0,
+            y: -1.0,
+            width: 2.0,
+            height: 2.0,
         };
+        // from the board's normalized device coordinates to fractions of it
+        let point = |(x, y): (f32, f32)| ((x + 1.0) / 2.0, (y + 1.0) / 2.0);
+        self.renderer.arrow(
+            board,
+            point(self.square_center(from)),
+            point(self.square_center(to)),
+            color,
+        );
+    }
 
-        let mut voas: [GLuint; 64] = [0; 64];
-        for i in 0..8 {
-            for j in 0..8 {
-                voas[i * 8 + j] = generate_vao(i as f32 - 4.0, j as f32 - 4.0);
-            }
+    // the hint, and what the blunder check would have played instead
+    fn draw_hint(&self) {
+        if self.preview.is_some() {
+            return;
+        }
+        if let Some(mv) = self.hint {
+            self.draw_arrow(mv.from, mv.to, self.theme.hint);
+        }
+        if let Some(better) = self.blunder.as_ref().and_then(|review| review.better) {
+            self.draw_arrow(better.from, better.to, self.theme.hint);
         }
-        voas
     }
 
-    fn draw_board(&self) {
-        let draw_square = |program: &Program, vao: GLuint| {
-            program.set_used();
-            unsafe {
-                gl::BindVertexArray(vao);
-                gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
-            }
+    // the first few moves of the line the engine expects, each fainter than the last,
+    // over the pieces so they can be told apart
+    fn draw_analysis_arrows(&self) {
+        let result = match self
+            .analysis
+            .as_ref()
+            .and_then(|analysis| analysis.latest())
+        {
+            Some(result) => result,
+            None => return,
         };
-        for i in 0..8 {
-            for j in 0..8 {
-                let square_color = if (i + j) % 2 == 0 {
-                    &self.black_shader
-                } else {
-                    &self.white_shader
+        let (red, green, blue, alpha) = self.theme.analysis;
+        for (i, mv) in result.pv.iter().take(PV_ARROWS).enumerate() {
+            self.draw_arrow(mv.from, mv.to, (red, green, blue, alpha / (i + 1) as f32));
+        }
+    }
+
+    // white's share of the bar growing from its side of the board the further ahead
+    // it is, all of it when it mates
+    fn draw_eval_bar(&self) {
+        let analysis = self.analysis.as_ref().unwrap();
+        let score = analysis
+            .latest()
+            .map_or(0, |result| match analysis.board().side_to_move() {
+                Color::White => result.score,
+                Color::Black => -result.score,
+            });
+        let white = match mate_in(score) {
+            Some(moves) => (moves > 0) as u8 as f32,
+            // about even up to a pawn, and nearly all one side's by five
+            None => 1.0 / (1.0 + 10f32.powf(-score as f32 / 400.0)),
+        };
+        let whole = Rect {
+            x: -1.0,
+            y: -1.0,
+            width: 2.0,
+            height: 2.0,
+        };
+        self.renderer.fill(whole, (0.2, 0.2, 0.2, 1.0));
+        let share = Rect {
+            height: 2.0 * white,
+            ..whole
+        };
+        let share = if self.flipped {
+            share.offset(0.0, 2.0 * (1.0 - white))
+        } else {
+            share
+        };
+        self.renderer.fill(share, (0.9, 0.9, 0.9, 1.0));
+    }
+
+    // how the game ended, on a band across the middle of the board
+    fn draw_result(&self) {
+        if !self.game.status.is_over() || self.preview.is_some() || self.is_editing() {
+            return;
+        }
+        let band = Rect {
+            x: -1.0,
+            y: -0.12,
+            width: 2.0,
+            height: 0.24,
+        };
+        self.renderer.fill(band, (0.0, 0.0, 0.0, 0.7));
+        let text = Rect {
+            x: -0.9,
+            y: -0.07,
+            width: 1.8,
+            height: 0.14,
+        };
+        self.renderer.text(
+            &self.game.status.to_string(),
+            text,
+            (1.0, 1.0, 1.0, 1.0),
+            Align::Center,
+        );
+        if let Some(hint) = self.rematch_hint() {
+            let line = Rect {
+                x: -0.9,
+                y: -0.11,
+                width: 1.8,
+                height: 0.05,
+            };
+            self.renderer
+                .text(&hint, line, (0.8, 0.8, 0.8, 1.0), Align::Center);
+        }
+    }
+
+    fn player_name(&self, color: Color) -> &str {
+        let record = self
+            .replay
+            .as_ref()
+            .map_or(&self.game.record, |replay| &replay.game);
+        let name = match color {
+            Color::White => record.header("White"),
+            Color::Black => record.header("Black"),
+        };
+        match name {
+            Some("?") | None => "Anonymous",
+            Some(name) => name,
+        }
+    }
+
+    // a timer for each side, on the same side as its pieces, lit up while it's running
+    fn draw_clock(&self) {
+        let clock = match &self.game.clock {
+            Some(clock) => clock,
+            None => return,
+        };
+        let (bottom, top) = if self.flipped {
+            (Color::Black, Color::White)
+        } else {
+            (Color::White, Color::Black)
+        };
+        for &(color, y) in [(top, 0.55), (bottom, -0.95)].iter() {
+            let area = Rect {
+                x: -0.9,
+                y,
+                width: 1.8,
+                height: 0.4,
+            };
+            let background = if clock.running() == Some(color) {
+                (0.4, 0.5, 0.5, 1.0)
+            } else {
+                (0.25, 0.3, 0.3, 1.0)
+            };
+            self.renderer.fill(area, background);
+
+            let remaining = clock.remaining(color);
+            let text_color = if remaining < Duration::from_secs(10) {
+                (1.0, 0.4, 0.4, 1.0)
+            } else {
+                (1.0, 1.0, 1.0, 1.0)
+            };
+            let text = Rect {
+                x: area.x + 0.15,
+                y: area.y + 0.1,
+                width: area.width - 0.3,
+                height: area.height - 0.2,
+            };
+            self.renderer
+                .seven_segment(&clock_text(remaining), text, text_color);
+        }
+    }
+
+    // the pieces a side has taken more of than it's lost, small and overlapping at the
+    // right of its name's row, with how many points it's up after them
+    fn draw_captured(&self, color: Color, material: &Material, row: Rect) {
+        let captured = material.captured(color);
+        let advantage = material.advantage_text(color);
+        let height = row.height * 0.8;
+        // as wide as it's high on the screen
+        let width = height / PANEL_WIDTH as f32;
+        let step = width * 0.45;
+        let text_width = if advantage.is_some() { 0.3 } else { 0.0 };
+        let right = row.x + row.width - text_width;
+        let mut x = right - width - step * captured.len().saturating_sub(1) as f32;
+        for &kind in &captured {
+            let cell = Rect {
+                x,
+                y: row.y + (row.height - height) / 2.0,
+                width,
+                height,
+            };
+            let sprite = self.piece_sprite(Piece::new(kind, color.opposite()));
+            self.renderer.sprite(cell, &self.pieces, sprite);
+            x += step;
+        }
+        if let Some(text) = advantage {
+            let rect = Rect {
+                x: right + 0.02,
+                width: text_width - 0.02,
+                ..row
+            };
+            self.renderer
+                .text(&text, rect, (0.8, 0.8, 0.8, 1.0), Align::Left);
+        }
+    }
+
+    // what a side has to drop in crazyhouse, each kind once with how many there are
+    // when it's more than one; the side to move's can be clicked to pick one up
+    fn draw_pocket(&self, color: Color, y: f32, targets: &mut Vec<(Rect, PanelTarget)>) {
+        // as wide as it's high on the screen
+        let width = POCKET_HEIGHT / PANEL_WIDTH as f32;
+        let pocket = self.shown_board().pocket(color);
+        let pickable = color == self.game.board.side_to_move() && self.can_move_pieces();
+        for (i, &kind) in Pocket::KINDS.iter().enumerate() {
+            let count = pocket.count(kind);
+            if count == 0 {
+                continue;
+            }
+            let cell = Rect {
+                x: -0.9 + width * i as f32,
+                y,
+                width,
+                height: POCKET_HEIGHT,
+            };
+            if pickable && self.dropping == Some(kind) {
+                self.renderer.fill(cell, self.theme.selected);
+            }
+            let sprite = self.piece_sprite(Piece::new(kind, color));
+            self.renderer.sprite(cell, &self.pieces, sprite);
+            if count > 1 {
+                let corner = Rect {
+                    x: cell.x + cell.width * 0.6,
+                    y: cell.y,
+                    width: cell.width * 0.4,
+                    height: cell.height * 0.4,
                 };
-                draw_square(square_color, self.board[i * 8 + j]);
+                self.renderer.text(
+                    &count.to_string(),
+                    corner,
+                    (1.0, 1.0, 1.0, 1.0),
+                    Align::Center,
+                );
+            }
+            if pickable {
+                targets.push((cell, PanelTarget::Pocket(kind)));
             }
         }
     }
 
-    fn draw_peices(&self) {
-        let draw_peice = |texture: &Texture, vao: GLuint| {
-            texture.set_used();
-            self.white_shader.set_used();
-            unsafe {
-                gl::BindVertexArray(vao);
-                gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+    // the clocks if there are any, each player's name towards the middle with their
+    // pocket in crazyhouse, the moves so far and whose turn it is, or a way back to the
+    // game when looking at an earlier position
+    fn draw_panel(&mut self) {
+        if self.is_editing() {
+            return self.draw_editor();
+        }
+        self.draw_clock();
+        let names = if self.game.clock.is_some() {
+            0.43
+        } else {
+            0.85
+        };
+        let material = Material::of(self.shown_board());
+        let (bottom, top) = if self.flipped {
+            (Color::Black, Color::White)
+        } else {
+            (Color::White, Color::Black)
+        };
+        for &(color, y) in [(top, names), (bottom, -names - 0.1)].iter() {
+            let rect = Rect {
+                x: -0.9,
+                y,
+                width: 1.8,
+                height: 0.1,
+            };
+            self.renderer.text(
+                self.player_name(color),
+                rect,
+                (0.8, 0.8, 0.8, 1.0),
+                Align::Left,
+            );
+            self.draw_captured(color, &material, rect);
+        }
+
+        let mut targets = Vec::new();
+        self.draw_tabs(&mut targets);
+        // the pockets go between the names and the moves
+        let pockets = if self.game.variant.has_drops() {
+            self.draw_pocket(top, names - 0.02 - POCKET_HEIGHT, &mut targets);
+            self.draw_pocket(bottom, -names + 0.02, &mut targets);
+            POCKET_HEIGHT + 0.04
+        } else {
+            0.0
+        };
+        let status = if self.preview.is_some() {
+            "Back to the game".to_string()
+        } else if self.game.status.is_over() {
+            self.game.status.to_string()
+        } else {
+            match self.analysis_text() {
+                Some(text) => format!("{:?} to move, {}", self.game.board.side_to_move(), text),
+                None => format!("{:?} to move", self.game.board.side_to_move()),
             }
         };
-        for i in 0..8 {
-            for j in 0..8 {
-                draw_peice(&self.textures[0], self.board[i * 8 + j]);
+        // long results like checkmates go on two lines, and a move being typed takes
+        // the place of the status, with what was wrong with the last one under it. a
+        // draw there is to claim gets a button of its own under the status
+        let claim = self
+            .claimable_draw()
+            .filter(|_| self.entry.is_none() && self.preview.is_none());
+        let mut lines: Vec<String> = match &self.entry {
+            Some(entry) => {
+                let mut lines = vec![format!("Move: {}_", entry.text)];
+                lines.extend(entry.error.clone());
+                lines
             }
+            None => status.split(", ").map(str::to_string).collect(),
+        };
+        // and under that the last notice, which can take more than one line
+        let notice: Vec<String> = match self.notice.as_ref().filter(|_| self.entry.is_none()) {
+            Some(notice) => notice.lines().map(str::to_string).collect(),
+            None => Vec::new(),
+        };
+        let notice_lines = lines.len()..lines.len() + notice.len();
+        lines.extend(notice);
+        if claim.is_some() {
+            lines.push("Claim draw".to_string());
+        }
+        let status_top = -names + pockets + 0.02 + 0.12 * lines.len() as f32;
+        if claim.is_some() {
+            let button = Rect {
+                x: -0.9,
+                y: status_top - 0.13 - 0.12 * (lines.len() - 1) as f32,
+                width: 1.8,
+                height: 0.12,
+            };
+            self.renderer.fill(button, (0.4, 0.5, 0.5, 1.0));
+            targets.push((button, PanelTarget::ClaimDraw));
+        }
+        if self.preview.is_some() {
+            let button = Rect {
+                x: -0.9,
+                y: status_top - 0.13,
+                width: 1.8,
+                height: 0.12,
+            };
+            self.renderer.fill(button, (0.4, 0.5, 0.5, 1.0));
+            targets.push((button, PanelTarget::BackToGame));
+        }
+        for (i, line) in lines.iter().enumerate() {
+            let rect = Rect {
+                x: -0.9,
+                y: status_top - 0.11 - 0.12 * i as f32,
+                width: 1.8,
+                height: 0.1,
+            };
+            let color = if self.entry.is_some() && i > 0 {
+                (1.0, 0.5, 0.5, 1.0)
+            } else if notice_lines.contains(&i) {
+                (0.8, 0.8, 0.6, 1.0)
+            } else {
+                (1.0, 1.0, 1.0, 1.0)
+            };
+            self.renderer.text(line, rect, color, Align::Center);
         }
-    }
-}
 
-fn shader_from_source(source: &CStr, kind: GLuint) -> Result<GLuint, String> {
-    let id = unsafe { gl::CreateShader(kind) };
-    unsafe {
-        gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
-        gl::CompileShader(id);
+        let mut list = Rect {
+            x: -0.9,
+            y: status_top + 0.02,
+            width: 1.8,
+            height: names - pockets - 0.02 - (status_top + 0.02),
+        };
+        list.height -= self.draw_opening(list);
+        match self.sidebar {
+            Sidebar::Moves => self.draw_move_list(list, &mut targets),
+            Sidebar::Explorer => self.draw_explorer(list, &mut targets),
+            Sidebar::Games => self.draw_games(list, &mut targets),
+        }
+        self.panel_targets = targets;
     }
 
-    let mut success: GLint = 1;
-    unsafe {
-        gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+    // the moves in san, a row for each move number, with the last move of the position
+    // on the board lit up and the ones with variations marked; clicking a move shows
+    // the position after it. a variation on the board goes in a row above them
+    fn draw_move_list(&mut self, mut area: Rect, targets: &mut Vec<(Rect, PanelTarget)>) {
+        if let Some(text) = self.variation_text() {
+            area.height -= ROW_HEIGHT;
+            let rect = Rect {
+                y: area.y + area.height,
+                height: ROW_HEIGHT,
+                ..area
+            };
+            self.renderer
+                .text(&text, rect, (0.6, 0.8, 1.0, 1.0), Align::Left);
+        }
+        let start = match &self.replay {
+            Some(replay) => replay.game.start(),
+            None => self.game.record.start(),
+        };
+        let first_number = start.fullmove_number();
+        // a game that starts with black to move leaves white's half of the first row empty
+        let skipped = (start.side_to_move() == Color::Black) as usize;
+        let moves = self.shown_moves();
+        let rows = (moves.len() + skipped).div_ceil(2);
+        let visible = ((area.height / ROW_HEIGHT) as usize).max(1);
+        self.move_scroll = self.move_scroll.min(rows.saturating_sub(visible));
+        let last_row = rows.saturating_sub(self.move_scroll);
+        let first_row = last_row.saturating_sub(visible);
+
+        let shown = self.shown_ply();
+        let in_game_moves = self
+            .preview
+            .as_ref()
+            .is_none_or(|preview| preview.branches.is_empty());
+        let moves = self.shown_moves();
+        for (i, row) in (first_row..last_row).enumerate() {
+            let san = |column: usize| match (row * 2 + column).checked_sub(skipped) {
+                Some(ply) => moves.get(ply).map_or("", |mv| mv.san.as_str()),
+                None => "...",
+            };
+            // the same width for every row, so they all come out the same size
+            let text = format!(
+                "{:>3}. {:<7} {:<7}",
+                first_number as usize + row,
+                san(0),
+                san(1)
+            );
+            let rect = Rect {
+                x: area.x,
+                y: area.y + area.height - ROW_HEIGHT * (i + 1) as f32,
+                width: area.width,
+                height: ROW_HEIGHT,
+            };
+            let drawn = self
+                .renderer
+                .text(&text, rect, (1.0, 1.0, 1.0, 1.0), Align::Left);
+            let column_width = drawn.width / text.chars().count() as f32;
+            for column in 0..2 {
+                let ply = match (row * 2 + column).checked_sub(skipped) {
+                    Some(ply) if ply < moves.len() => ply,
+                    _ => continue,
+                };
+                let cell = Rect {
+                    x: rect.x + column_width * (5 + column * 8) as f32,
+                    y: rect.y,
+                    width: column_width * 8.0,
+                    height: rect.height,
+                };
+                let class = self
+                    .report
+                    .as_ref()
+                    .filter(|_| self.replay.is_none())
+                    .and_then(|report| report.moves.get(ply))
+                    .map(|(_, class)| *class);
+                let tint = match class {
+                    Some(Classification::Inaccuracy) => Some((0.5, 0.8, 0.3, 0.4)),
+                    Some(Classification::Mistake) => Some((0.9, 0.5, 0.1, 0.4)),
+                    Some(Classification::Blunder) => Some(self.theme.blunder),
+                    _ => None,
+                };
+                if let Some(color) = tint {
+                    self.renderer.fill(cell, color);
+                }
+                if !moves[ply].variations.is_empty() {
+                    self.renderer.fill(cell, (0.3, 0.5, 0.9, 0.3));
+                }
+                if ply + 1 == shown && in_game_moves {
+                    self.renderer.fill(cell, (0.9, 0.8, 0.2, 0.35));
+                }
+                targets.push((cell, PanelTarget::Ply(ply + 1)));
+            }
+        }
     }
 
-    if success == 0 {
-        let mut len: GLint = 0;
-        unsafe {
-            gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
+    // the moves around the one on the board of the variation it's in, that one in
+    // brackets, like "... 12. Nf3 [Nc6] 13. Bb5 ..."
+    fn variation_text(&self) -> Option<String> {
+        let preview = self
+            .preview
+            .as_ref()
+            .filter(|preview| !preview.branches.is_empty() && self.replay.is_none())?;
+        let start = self.game.record.board_in_line(&preview.branches, 0)?;
+        let line = self.game.record.line(&preview.branches)?;
+        let black_first = start.side_to_move() == Color::Black;
+        let first = preview.ply.saturating_sub(3);
+        let last = (first + VARIATION_MOVES).min(line.len());
+        let mut words = Vec::new();
+        if first > 0 {
+            words.push("...".to_string());
+        }
+        for (i, mv) in line.iter().enumerate().take(last).skip(first) {
+            let number = start.fullmove_number() as usize + (i + black_first as usize) / 2;
+            let san = match (i + black_first as usize) % 2 {
+                0 => format!("{}. {}", number, mv.san),
+                _ if i == first => format!("{}... {}", number, mv.san),
+                _ => mv.san.clone(),
+            };
+            if i + 1 == preview.ply {
+                words.push(format!("[{}]", san));
+            } else {
+                words.push(san);
+            }
         }
-        let error: CString = create_whitespace_cstring_with_len(len as usize);
-        unsafe {
-            gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut GLchar);
+        if last < line.len() {
+            words.push("...".to_string());
         }
-        return Err(error.to_string_lossy().into_owned());
+        Some(words.join(" "))
+    }
+
+    fn piece_sprite(&self, piece: Piece) -> (f32, f32, f32, f32) {
+        let col = match piece.kind {
+            PieceKind::King => 0,
+            PieceKind::Queen => 1,
+            PieceKind::Bishop => 2,
+            PieceKind::Knight => 3,
+            PieceKind::Rook => 4,
+            PieceKind::Pawn => 5,
+        };
+        let row = match piece.color {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+        self.pieces.uv_rect(col, row)
     }
+}
 
-    Ok(id)
+// the name a key goes by in the settings, for the keys that can be bound
+fn key_name(key: Key) -> Option<String> {
+    let code = key as i32;
+    let name = match key {
+        Key::Space => "Space",
+        Key::Backspace => "Backspace",
+        Key::Delete => "Delete",
+        Key::Insert => "Insert",
+        Key::PageUp => "PageUp",
+        Key::PageDown => "PageDown",
+        // glfw numbers the letters and digits by their ascii codes
+        _ if (Key::A as i32..=Key::Z as i32).contains(&code)
+            || (Key::Num0 as i32..=Key::Num9 as i32).contains(&code) =>
+        {
+            return Some((code as u8 as char).to_string())
+        }
+        _ if (Key::F1 as i32..=Key::F12 as i32).contains(&code) => {
+            return Some(format!("F{}", code - Key::F1 as i32 + 1))
+        }
+        _ => return None,
+    };
+    Some(name.to_string())
 }
 
-fn create_whitespace_cstring_with_len(len: usize) -> CString {
-    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
-    buffer.extend([b' '].iter().cycle().take(len));
-    unsafe { CString::from_vec_unchecked(buffer) }
+fn print_glfw_error(_: glfw::Error, description: String, _: &()) {
+    eprintln!("GLFW error: {}", description);
 }
@@ -0,0 +1,391 @@
+use super::{Game, Role};
+use chess_game::board::Color;
+use chess_game::engine::{Engine, SearchLimits};
+use chess_game::net::Message;
+use chess_game::pgn::PgnGame;
+use chess_game::settings::Command;
+use chess_game::status::GameStatus;
+use std::time::{Duration, Instant};
+
+// resigning takes a second press this soon after the first
+const RESIGN_CONFIRM: Duration = Duration::from_secs(3);
+
+// how long the computer looks at the position before answering a draw offer
+const DRAW_THINKING: Duration = Duration::from_millis(300);
+// it takes a draw when it's at least this many centipawns behind,
+const DRAW_WHEN_LOSING: i32 = 200;
+// or no further than this either way once the game's this many plies old
+const DRAW_WHEN_LEVEL: i32 = 25;
+const DRAW_LEVEL_PLIES: usize = 60;
+
+// giving up, draw offers and rematches, the last two needing the other side to agree
+#[derive(Default)]
+pub struct Controls {
+    // when resign was first pressed
+    resign_asked: Option<Instant>,
+    // the side whose draw offer is standing
+    pub(super) draw_offer: Option<Color>,
+    // whether a rematch was asked for here, and by the opponent over the network
+    rematch_asked: bool,
+    rematch_offered: bool,
+}
+
+impl Game {
+    // gives up the game for the side played here, or the side to move when both are,
+    // once it's pressed twice
+    pub(super) fn resign(&mut self) {
+        if self.game.status.is_over() || self.replay.is_some() || self.puzzles.is_some() {
+            return;
+        }
+        #[cfg(feature = "lichess")]
+        if self
+            .online
+            .as_ref()
+            .is_some_and(|online| online.color().is_none())
+        {
+            return self.notify("Only the players can resign");
+        }
+        let asked = self.controls.resign_asked.take();
+        if asked.is_none_or(|asked| asked.elapsed() > RESIGN_CONFIRM) {
+            self.controls.resign_asked = Some(Instant::now());
+            return self.notify(format!(
+                "Press {} again to resign",
+                self.keys.binding(Command::Resign)
+            ));
+        }
+        let color = self
+            .player_color()
+            .unwrap_or_else(|| self.game.board.side_to_move());
+        #[cfg(feature = "lichess")]
+        if let Some(online) = &self.online {
+            if let Err(error) = online.resign() {
+                return self.notify(format!("Couldn't resign on lichess: {}", error));
+            }
+        }
+        if self.network.is_some() && !self.send_message(&Message::Resign) {
+            return self.notify("The opponent isn't there to resign to");
+        }
+        self.premove = None;
+        self.selected = None;
+        self.set_status(GameStatus::Resignation(color.opposite()));
+    }
+
+    // offers a draw, or takes the one the other side offered. the computer answers
+    // straight away, and between two players at the same board the second press agrees.
+    // a draw that can be claimed is claimed instead, there's no asking for it
+    pub(super) fn offer_draw(&mut self) {
+        if self.game.status.is_over() || self.replay.is_some() || self.puzzles.is_some() {
+            return;
+        }
+        if self.claimable_draw().is_some() {
+            return self.claim_draw();
+        }
+        let here = self.player_color();
+        match self.controls.draw_offer {
+            Some(by) if Some(by) != here => return self.accept_draw(),
+            Some(_) => return self.notify("The draw offer stands until it's answered"),
+            None => {}
+        }
+        self.redraw = true;
+        #[cfg(feature = "lichess")]
+        if let Some(online) = &self.online {
+            if here.is_none() {
+                return self.notify("Only the players can offer a draw");
+            }
+            if let Err(error) = online.answer_draw(true) {
+                return self.notify(format!("Couldn't offer a draw on lichess: {}", error));
+            }
+            self.controls.draw_offer = here;
+            return self.notify("Draw offered");
+        }
+        if self.network.is_some() {
+            if !self.send_message(&Message::DrawOffer) {
+                return self.notify("The opponent isn't there to offer a draw to");
+            }
+            self.controls.draw_offer = here;
+            return self.notify("Draw offered");
+        }
+        if let Some(computer) = &self.computer {
+            return self.computer_answers_draw(computer.color);
+        }
+        let color = self.game.board.side_to_move();
+        self.controls.draw_offer = Some(color);
+        self.notify(format!(
+            "{:?} offers a draw: {} to accept, {} to decline",
+            color,
+            self.keys.binding(Command::OfferDraw),
+            self.keys.binding(Command::DeclineDraw)
+        ));
+    }
+
+    fn accept_draw(&mut self) {
+        #[cfg(feature = "lichess")]
+        if let Some(online) = &self.online {
+            if let Err(error) = online.answer_draw(true) {
+                return self.notify(format!("Couldn't accept the draw on lichess: {}", error));
+            }
+        }
+        if self.network.is_some() && !self.send_message(&Message::DrawAccept) {
+            return self.notify("The opponent isn't there to agree with");
+        }
+        self.controls.draw_offer = None;
+        self.set_status(GameStatus::DrawAgreed);
+    }
+
+    // under fide's rules, the draw the players here can claim in the position; lichess
+    // makes its own draws, and the computer doesn't claim them
+    pub(super) fn claimable_draw(&self) -> Option<GameStatus> {
+        if self.replay.is_some() || self.puzzles.is_some() || self.is_editing() {
+            return None;
+        }
+        #[cfg(feature = "lichess")]
+        if self.online.is_some() {
+            return None;
+        }
+        self.game.claimable_draw()
+    }
+
+    // ends the game with the draw there is to claim, saying so in the record after the
+    // last move, which over the network the opponent does too
+    pub(super) fn claim_draw(&mut self) {
+        if self.claimable_draw().is_none() {
+            return;
+        }
+        if self.network.is_some() && !self.send_message(&Message::DrawClaim) {
+            return self.notify("The opponent isn't there to claim the draw from");
+        }
+        let color = self
+            .player_color()
+            .unwrap_or_else(|| self.game.board.side_to_move());
+        if let Some((draw, text)) = self.game.claim_draw(color) {
+            self.notify(text);
+            self.set_status(draw);
+        }
+    }
+
+    fn record_claim(&mut self, color: Color, draw: GameStatus) {
+        let text = self.game.record.note_claim(color, draw);
+        self.notify(text);
+    }
+
+    // turns down the other side's draw offer
+    pub(super) fn decline_draw(&mut self) {
+        match self.controls.draw_offer {
+            Some(by) if Some(by) != self.player_color() => {}
+            _ => return,
+        }
+        #[cfg(feature = "lichess")]
+        if let Some(online) = &self.online {
+            if let Err(error) = online.answer_draw(false) {
+                return self.notify(format!("Couldn't decline the draw on lichess: {}", error));
+            }
+        }
+        if self.network.is_some() {
+            self.send_message(&Message::DrawDecline);
+        }
+        self.controls.draw_offer = None;
+        self.redraw = true;
+        self.notify("Draw declined");
+    }
+
+    // the computer takes a draw when it's clearly losing, or when the game's long and
+    // still level
+    fn computer_answers_draw(&mut self, color: Color) {
+        let result = Engine::new().search(&self.game.board, SearchLimits::time(DRAW_THINKING));
+        let score = if self.game.board.side_to_move() == color {
+            result.score
+        } else {
+            -result.score
+        };
+        let level =
+            score.abs() <= DRAW_WHEN_LEVEL && self.game.record.moves().len() >= DRAW_LEVEL_PLIES;
+        if score <= -DRAW_WHEN_LOSING || level {
+            self.notify("The computer accepts the draw");
+            self.set_status(GameStatus::DrawAgreed);
+        } else {
+            self.notify("The computer declines the draw");
+        }
+    }
+
+    // a move by the side that was offered a draw turns it down, and any move forgets a
+    // first press of resign
+    pub(super) fn controls_after_move(&mut self, mover: Color) {
+        if self.controls.draw_offer.is_some_and(|by| by != mover) {
+            self.controls.draw_offer = None;
+        }
+        self.controls.resign_asked = None;
+    }
+
+    // another game from the same position once this one's over, each side playing the
+    // other colour; over the network both players have to ask
+    pub(super) fn rematch(&mut self) {
+        if !self.game.status.is_over() || self.replay.is_some() || self.puzzles.is_some() {
+            return;
+        }
+        #[cfg(feature = "lichess")]
+        if self.online.is_some() {
+            return self.notify("Rematches are offered on lichess itself");
+        }
+        if self.network.is_none() {
+            return self.start_rematch();
+        }
+        if self.controls.rematch_asked {
+            return;
+        }
+        if !self.send_message(&Message::Rematch) {
+            return self.notify("The opponent isn't there to play again");
+        }
+        self.controls.rematch_asked = true;
+        if !self.controls.rematch_offered {
+            self.notify("Asked the opponent for a rematch");
+        } else if self.is_host() {
+            self.start_rematch();
+        }
+    }
+
+    // under the result, how to play again
+    pub(super) fn rematch_hint(&self) -> Option<String> {
+        if self.replay.is_some() || self.puzzles.is_some() {
+            return None;
+        }
+        #[cfg(feature = "lichess")]
+        if self.online.is_some() {
+            return None;
+        }
+        let key = self.keys.binding(Command::Rematch);
+        Some(if self.controls.rematch_asked {
+            "Waiting for the opponent to agree to a rematch".to_string()
+        } else if self.controls.rematch_offered {
+            format!("The opponent wants a rematch, {} to play it", key)
+        } else {
+            format!("{} for a rematch with the colours swapped", key)
+        })
+    }
+
+    // what the opponent said about resigning, draws or a rematch
+    pub(super) fn receive_control(&mut self, message: Message) {
+        let opponent = self
+            .network
+            .as_ref()
+            .and_then(|network| network.color)
+            .map(Color::opposite);
+        let opponent = match opponent {
+            Some(opponent) => opponent,
+            None => return,
+        };
+        self.redraw = true;
+        match message {
+            Message::Resign if !self.game.status.is_over() => {
+                self.notify("The opponent resigns");
+                self.set_status(GameStatus::Resignation(opponent.opposite()));
+            }
+            Message::DrawOffer if !self.game.status.is_over() => {
+                self.controls.draw_offer = Some(opponent);
+                self.notify(format!(
+                    "The opponent offers a draw: {} to accept, {} to decline",
+                    self.keys.binding(Command::OfferDraw),
+                    self.keys.binding(Command::DeclineDraw)
+                ));
+            }
+            Message::DrawAccept if self.controls.draw_offer == Some(opponent.opposite()) => {
+                self.notify("The opponent accepts the draw");
+                self.controls.draw_offer = None;
+                self.set_status(GameStatus::DrawAgreed);
+            }
+            // the position here has to allow it as well
+            Message::DrawClaim if !self.game.status.is_over() => {
+                match self.game.board.claimable_draw() {
+                    Some(draw) => {
+                        self.record_claim(opponent, draw);
+                        self.set_status(draw);
+                    }
+                    None => self.notify("The opponent claims a draw the position doesn't allow"),
+                }
+            }
+            Message::DrawDecline if self.controls.draw_offer.is_some() => {
+                self.notify("The opponent declines the draw");
+                self.controls.draw_offer = None;
+            }
+            Message::Rematch if self.game.status.is_over() => {
+                self.controls.rematch_offered = true;
+                if !self.controls.rematch_asked {
+                    self.notify(format!(
+                        "The opponent wants a rematch: {} to play it",
+                        self.keys.binding(Command::Rematch)
+                    ));
+                } else if self.is_host() {
+                    self.start_rematch();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // a guest gets the new game from the host like it got the first one
+    fn start_rematch(&mut self) {
+        if self
+            .computer
+            .as_ref()
+            .is_some_and(|computer| computer.is_thinking())
+        {
+            return;
+        }
+        self.save_pgn();
+        let start = self.game.record.start().clone();
+        let mut record = PgnGame::new(&start);
+        record.set_variant(self.game.variant.as_ref());
+        // the players keep their names as they change colours
+        for (from, to) in [(Color::White, Color::Black), (Color::Black, Color::White)] {
+            if let Some(name) = self.game.record.header(&format!("{:?}", from)) {
+                record.set_player(to, name);
+            }
+        }
+        let control = self.game.clock.take().map(|clock| clock.control().clone());
+        self.game.record = record;
+        self.game.board = start;
+        self.game.taken_back.clear();
+        self.controls = Controls::default();
+        self.flipped = !self.flipped;
+        if let Some(computer) = &mut self.computer {
+            computer.color = computer.color.opposite();
+        }
+        if let Some(network) = &mut self.network {
+            network.color = network.color.map(Color::opposite);
+        }
+        self.history_changed();
+        if let Some(control) = control {
+            self.set_clock(control);
+        }
+        self.notify("Rematch");
+        if let Some(color) = self.network.as_ref().and_then(|network| network.color) {
+            let hello = Message::Hello(self.network_state(color.opposite()));
+            self.send_message(&hello);
+        }
+    }
+
+    fn is_host(&self) -> bool {
+        matches!(
+            self.network.as_ref().map(|network| &network.role),
+            Some(Role::Host(_))
+        )
+    }
+
+    // false if the opponent isn't connected, or the connection's just been lost
+    fn send_message(&mut self, message: &Message) -> bool {
+        let network = match &mut self.network {
+            Some(network) => network,
+            None => return false,
+        };
+        let peer = match &mut network.peer {
+            Some(peer) => peer,
+            None => return false,
+        };
+        match peer.send(message) {
+            Ok(()) => true,
+            Err(error) => {
+                self.lose_opponent(error);
+                false
+            }
+        }
+    }
+}
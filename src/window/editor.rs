@@ -0,0 +1,359 @@
+use super::{Game, PanelTarget, PANEL_WIDTH};
+use crate::renderer::{Align, Rect};
+use chess_game::board::{Board, Color, Piece, PieceKind, Square};
+use chess_game::clock::Clock;
+use chess_game::pgn::PgnGame;
+use chess_game::setup::{self, Odds};
+use std::time::Instant;
+
+// the kinds in the palette, from the left
+const KINDS: [PieceKind; 6] = [
+    PieceKind::King,
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Pawn,
+];
+
+const BUTTON: (f32, f32, f32, f32) = (0.4, 0.5, 0.5, 1.0);
+const BUTTON_OFF: (f32, f32, f32, f32) = (0.25, 0.25, 0.25, 1.0);
+const TEXT: (f32, f32, f32, f32) = (1.0, 1.0, 1.0, 1.0);
+const TEXT_DIM: (f32, f32, f32, f32) = (0.5, 0.5, 0.5, 1.0);
+
+// putting a position together by hand with the mouse, for a handicap game or to look
+// at one from a book; the game underneath waits until it's started or left with Escape
+pub struct Editor {
+    board: Board,
+    // what a left click puts down
+    piece: Piece,
+    // the analysis was on when the editor opened, so it comes back if nothing's started
+    analysing: bool,
+}
+
+// what clicking a part of the editor's panel does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Pick(Piece),
+    SideToMove,
+    // the side and whether it's kingside
+    Castling(Color, bool),
+    Clear,
+    StartPosition,
+    // the side at the top of the board gives them, which is usually the computer
+    Odds(Odds),
+    Start { analyse: bool },
+}
+
+impl Game {
+    pub(super) fn toggle_editor(&mut self) {
+        if self.editor.is_some() {
+            return self.close_editor();
+        }
+        if self.is_remote_game() {
+            return self.notify("There's no board editor while playing someone else");
+        }
+        if let Some(computer) = &mut self.computer {
+            if computer.is_thinking() {
+                return self.notify("The board can be set up once the computer has moved");
+            }
+            computer.stop_pondering();
+        }
+        // only the pieces, whose move it is and the castling rights come along
+        let shown = self.shown_board();
+        let mut board = Board::empty();
+        for (square, piece) in shown.pieces() {
+            board.set_piece(square, Some(piece));
+        }
+        board.set_side_to_move(shown.side_to_move());
+        board.set_castling(shown.castling());
+        setup::fix_castling(&mut board);
+
+        self.editor = Some(Editor {
+            board,
+            piece: Piece::new(PieceKind::Pawn, Color::White),
+            analysing: self.analysis.take().is_some(),
+        });
+        if let Some(clock) = &mut self.game.clock {
+            clock.stop();
+        }
+        self.annotations.clear();
+        self.entry = None;
+        self.focus = None;
+        self.hint = None;
+        self.preview = None;
+        self.selected = None;
+        self.dropping = None;
+        self.premove = None;
+        self.drag = None;
+        self.promotion = None;
+        self.stop_animations();
+        self.notify("Setting up a position, Escape goes back to the game");
+    }
+
+    // back to the game as it was
+    fn close_editor(&mut self) {
+        let editor = match self.editor.take() {
+            Some(editor) => editor,
+            None => return,
+        };
+        if let Some(clock) = &mut self.game.clock {
+            if !self.game.status.is_over() {
+                clock.start(self.game.board.side_to_move());
+            }
+        }
+        self.game.turn_started = Instant::now();
+        self.notify("Back to the game");
+        if editor.analysing {
+            self.toggle_analysis();
+        }
+    }
+
+    pub(super) fn is_editing(&self) -> bool {
+        self.editor.is_some()
+    }
+
+    pub(super) fn edited_board(&self) -> Option<&Board> {
+        self.editor.as_ref().map(|editor| &editor.board)
+    }
+
+    // a left click puts the picked piece down, or takes it off when it's already
+    // there; a right click takes off whatever's there
+    pub(super) fn edit_square(&mut self, square: Square, place: bool) {
+        let editor = match &mut self.editor {
+            Some(editor) => editor,
+            None => return,
+        };
+        let piece = Some(editor.piece)
+            .filter(|&piece| place && editor.board.piece_at(square) != Some(piece));
+        editor.board.set_piece(square, piece);
+        setup::fix_castling(&mut editor.board);
+        self.redraw = true;
+    }
+
+    pub(super) fn run_editor_action(&mut self, action: Action) {
+        let top = if self.flipped {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let editor = match &mut self.editor {
+            Some(editor) => editor,
+            None => return,
+        };
+        let board = &mut editor.board;
+        match action {
+            Action::Pick(piece) => editor.piece = piece,
+            Action::SideToMove => {
+                let color = board.side_to_move().opposite();
+                board.set_side_to_move(color);
+            }
+            Action::Castling(color, kingside) => {
+                let mut rights = board.castling();
+                let (mut king, mut queen) = rights.get(color);
+                if kingside {
+                    king = !king;
+                } else {
+                    queen = !queen;
+                }
+                rights.set(color, king, queen);
+                board.set_castling(rights);
+                setup::fix_castling(board);
+            }
+            Action::Clear => *board = Board::empty(),
+            Action::StartPosition => *board = Board::new(),
+            Action::Odds(odds) => *board = odds.board(top),
+            Action::Start { analyse } => self.start_from_editor(analyse),
+        }
+    }
+
+    // a new game from the position, against the computer if there was one unless it's
+    // for analysis, in which case the analysis comes on instead
+    fn start_from_editor(&mut self, analyse: bool) {
+        let mut board = match &self.editor {
+            Some(editor) => editor.board.clone(),
+            None => return,
+        };
+        if let Err(error) = setup::validate(&board) {
+            return self.notify(format!("Can't start from this position: {}", error));
+        }
+        self.game.variant.setup(&mut board);
+
+        let mut record = PgnGame::new(&board);
+        record.set_variant(self.game.variant.as_ref());
+        for &color in [Color::White, Color::Black].iter() {
+            let tag = format!("{:?}", color);
+            if let Some(player) = self.game.record.header(&tag).filter(|_| !analyse) {
+                record.set_player(color, player);
+            }
+        }
+        self.editor = None;
+        self.replay = None;
+        self.puzzles = None;
+        self.game.record = record;
+        self.game.board = board;
+        self.game.taken_back.clear();
+        self.controls = Default::default();
+        if analyse {
+            self.computer = None;
+            self.game.clock = None;
+        }
+        self.game.clock = self
+            .game
+            .clock
+            .as_ref()
+            .map(|clock| Clock::new(clock.control().clone()));
+        self.history_changed();
+        if analyse {
+            self.toggle_analysis();
+        } else {
+            self.notify("New game from the position set up");
+        }
+    }
+
+    // the palette of pieces, whose move it is, the castling rights, a few ways to start
+    // over, and what's wrong with the position if it can't be played yet
+    pub(super) fn draw_editor(&mut self) {
+        let top = if self.flipped {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let editor = match &self.editor {
+            Some(editor) => editor,
+            None => return,
+        };
+        let board = &editor.board;
+        let renderer = &self.renderer;
+        let mut targets = Vec::new();
+        let row = |y: f32, height: f32, column: usize, columns: usize| {
+            let width = (1.8 - 0.02 * (columns - 1) as f32) / columns as f32;
+            Rect {
+                x: -0.9 + (width + 0.02) * column as f32,
+                y,
+                width,
+                height,
+            }
+        };
+
+        renderer.text(
+            "Set up a position",
+            row(0.85, 0.1, 0, 1),
+            (0.8, 0.8, 0.8, 1.0),
+            Align::Center,
+        );
+        renderer.text(
+            "Right-click takes a piece off",
+            row(0.77, 0.05, 0, 1),
+            TEXT_DIM,
+            Align::Center,
+        );
+
+        // square on the screen like the pockets
+        let width = 1.8 / KINDS.len() as f32;
+        let height = width * PANEL_WIDTH as f32;
+        for (i, &color) in [Color::White, Color::Black].iter().enumerate() {
+            for (column, &kind) in KINDS.iter().enumerate() {
+                let piece = Piece::new(kind, color);
+                let cell = Rect {
+                    x: -0.9 + width * column as f32,
+                    y: 0.62 - (height + 0.01) * i as f32,
+                    width,
+                    height,
+                };
+                if piece == editor.piece {
+                    renderer.fill(cell, self.theme.selected);
+                }
+                renderer.sprite(cell, &self.pieces, self.piece_sprite(piece));
+                targets.push((cell, PanelTarget::Editor(Action::Pick(piece))));
+            }
+        }
+
+        let mut button = |label: &str, rect: Rect, fill, color, action| {
+            renderer.fill(rect, fill);
+            let inset = Rect {
+                x: rect.x + 0.02,
+                y: rect.y + rect.height * 0.15,
+                width: rect.width - 0.04,
+                height: rect.height * 0.7,
+            };
+            renderer.text(label, inset, color, Align::Center);
+            if let Some(action) = action {
+                targets.push((rect, PanelTarget::Editor(action)));
+            }
+        };
+
+        let side = format!("{:?} to move", board.side_to_move());
+        button(
+            &side,
+            row(0.36, 0.1, 0, 1),
+            BUTTON,
+            TEXT,
+            Some(Action::SideToMove),
+        );
+        for (i, &color) in [Color::White, Color::Black].iter().enumerate() {
+            let (kingside, queenside) = board.castling().get(color);
+            for (column, &(on, wing)) in [(kingside, true), (queenside, false)].iter().enumerate() {
+                let label = format!("{:?} {}", color, if wing { "O-O" } else { "O-O-O" });
+                let rect = row(0.22 - 0.12 * i as f32, 0.1, column, 2);
+                if setup::can_castle(board, color, wing) {
+                    let fill = if on { BUTTON } else { BUTTON_OFF };
+                    button(
+                        &label,
+                        rect,
+                        fill,
+                        TEXT,
+                        Some(Action::Castling(color, wing)),
+                    );
+                } else {
+                    button(&label, rect, BUTTON_OFF, TEXT_DIM, None);
+                }
+            }
+        }
+
+        button(
+            "Clear",
+            row(-0.06, 0.1, 0, 2),
+            BUTTON,
+            TEXT,
+            Some(Action::Clear),
+        );
+        let start = Some(Action::StartPosition);
+        button("Start position", row(-0.06, 0.1, 1, 2), BUTTON, TEXT, start);
+        renderer.text(
+            &format!("{:?} gives odds of", top),
+            row(-0.16, 0.06, 0, 1),
+            (0.8, 0.8, 0.8, 1.0),
+            Align::Center,
+        );
+        for (column, &odds) in Odds::ALL.iter().enumerate() {
+            let rect = row(-0.28, 0.1, column, Odds::ALL.len());
+            button(odds.name(), rect, BUTTON, TEXT, Some(Action::Odds(odds)));
+        }
+
+        // the position has to make sense before it can be played
+        let problem = setup::validate(board).err();
+        let (text, color) = match &problem {
+            Some(error) => (error.to_string(), (1.0, 0.5, 0.5, 1.0)),
+            None => ("Ready".to_string(), TEXT_DIM),
+        };
+        renderer.text(&text, row(-0.44, 0.06, 0, 1), color, Align::Center);
+        let (fill, color) = match problem {
+            Some(_) => (BUTTON_OFF, TEXT_DIM),
+            None => (BUTTON, TEXT),
+        };
+        let play = Some(Action::Start { analyse: false });
+        button("Play", row(-0.6, 0.12, 0, 2), fill, color, play);
+        let analyse = Some(Action::Start { analyse: true });
+        button("Analyse", row(-0.6, 0.12, 1, 2), fill, color, analyse);
+        // the last notice goes under the buttons, as it does under the game's status
+        if let Some(notice) = &self.notice {
+            for (i, line) in notice.lines().enumerate() {
+                let rect = row(-0.72 - 0.07 * i as f32, 0.06, 0, 1);
+                renderer.text(line, rect, (0.8, 0.8, 0.6, 1.0), Align::Center);
+            }
+        }
+        self.draw_tabs(&mut targets);
+        self.panel_targets = targets;
+    }
+}
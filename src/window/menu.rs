@@ -0,0 +1,440 @@
+use super::Game;
+use crate::computer::Opponent;
+use crate::renderer::{Align, Rect};
+use chess_game::board::{Board, Color};
+use chess_game::book::DEFAULT_BOOK_PLIES;
+use chess_game::chess960;
+use chess_game::clock::TimeControl;
+use chess_game::engine::{Engine, SearchLimits};
+use chess_game::fen::STARTING_FEN;
+use chess_game::pgn::PgnGame;
+use chess_game::status::DrawRules;
+use chess_game::variant;
+use glfw::{Action, Key, MouseButton, WindowEvent};
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// the clocks a new game can have, as they'd be given to --clock; the first is none
+const TIME_CONTROLS: [&str; 7] = ["", "1+0", "3+2", "5+3", "10+5", "15+10", "30+0"];
+// by the names variant::by_name knows, chess960 being the standard rules from a
+// random position
+const VARIANTS: [&str; 4] = ["Standard", "Chess960", "Crazyhouse", "King of the hill"];
+// the most recent of the games in the directory, to fit on the screen
+const MAX_FILES: usize = 8;
+// the height of a line of the menu, in the window's normalized device coordinates
+const ROW: f32 = 0.12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Page {
+    Main,
+    NewGame,
+    Settings,
+    Load,
+}
+
+// a line of the menu, which either does something or changes a choice
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Item {
+    NewGame,
+    Settings,
+    Load,
+    Resume,
+    Quit,
+    Color,
+    Opponent,
+    Clock,
+    Variant,
+    Start,
+    Animations,
+    Coordinates,
+    #[cfg(feature = "audio")]
+    Sound,
+    Draws,
+    File(usize),
+    Back,
+}
+
+// drawn over the game when escape's pressed, or at the start when nothing was asked
+// for on the command line: a new game, the settings, a saved game or quitting, without
+// a config file or starting the program again. the lines are worked out afresh every
+// frame from what's chosen, and while it's open it has the keys and the mouse; the
+// game underneath carries on like one in a background tab
+pub struct Menu {
+    page: Page,
+    selected: usize,
+    // the new game being chosen
+    color: Color,
+    computer: bool,
+    time_control: usize,
+    variant: usize,
+    // the games the load page found
+    files: Vec<PathBuf>,
+}
+
+impl Menu {
+    fn new() -> Menu {
+        Menu {
+            page: Page::Main,
+            selected: 0,
+            color: Color::White,
+            computer: true,
+            time_control: 0,
+            variant: 0,
+            files: Vec::new(),
+        }
+    }
+
+    fn items(&self) -> Vec<Item> {
+        let mut items = match self.page {
+            Page::Main => vec![
+                Item::NewGame,
+                Item::Settings,
+                Item::Load,
+                Item::Resume,
+                Item::Quit,
+            ],
+            Page::NewGame => vec![
+                Item::Color,
+                Item::Opponent,
+                Item::Clock,
+                Item::Variant,
+                Item::Start,
+            ],
+            Page::Settings => vec![
+                Item::Animations,
+                Item::Coordinates,
+                #[cfg(feature = "audio")]
+                Item::Sound,
+                Item::Draws,
+            ],
+            Page::Load => (0..self.files.len()).map(Item::File).collect(),
+        };
+        if self.page != Page::Main {
+            items.push(Item::Back);
+        }
+        items
+    }
+
+    fn title(&self) -> &str {
+        match self.page {
+            Page::Main => "Chess",
+            Page::NewGame => "New game",
+            Page::Settings => "Settings",
+            Page::Load if self.files.is_empty() => "No .pgn files here",
+            Page::Load => "Load a game",
+        }
+    }
+
+    fn show(&mut self, page: Page) {
+        self.page = page;
+        self.selected = 0;
+    }
+
+    // a step through the choices of a line, either way round
+    fn cycle(value: usize, count: usize, forward: bool) -> usize {
+        if forward {
+            (value + 1) % count
+        } else {
+            (value + count - 1) % count
+        }
+    }
+}
+
+impl Game {
+    pub fn show_menu(&mut self) {
+        self.menu = Some(Menu::new());
+        self.redraw = true;
+    }
+
+    // the keys and the mouse while the menu's open
+    pub(super) fn menu_event(&mut self, event: &WindowEvent) {
+        let mut menu = match self.menu.take() {
+            Some(menu) => menu,
+            None => return,
+        };
+        let items = menu.items();
+        let open = match *event {
+            WindowEvent::Key(Key::Escape, _, Action::Press, _) if menu.page == Page::Main => false,
+            WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                menu.show(Page::Main);
+                true
+            }
+            WindowEvent::Key(key, _, action, _) if action != Action::Release => {
+                let item = items[menu.selected];
+                match key {
+                    Key::Up => menu.selected = Menu::cycle(menu.selected, items.len(), false),
+                    Key::Down => menu.selected = Menu::cycle(menu.selected, items.len(), true),
+                    Key::Left => self.change_menu_choice(&mut menu, item, false),
+                    Key::Right => self.change_menu_choice(&mut menu, item, true),
+                    Key::Enter | Key::KpEnter if action == Action::Press => {
+                        return self.choose_menu_item(menu, item)
+                    }
+                    _ => {}
+                }
+                true
+            }
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = self.to_pixels(x, y);
+                if let Some(index) = self.menu_item_at(items.len()) {
+                    self.redraw |= index != menu.selected;
+                    menu.selected = index;
+                }
+                true
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => {
+                if let Some(index) = self.menu_item_at(items.len()) {
+                    menu.selected = index;
+                    return self.choose_menu_item(menu, items[index]);
+                }
+                true
+            }
+            _ => true,
+        };
+        if open && self.menu.is_none() {
+            self.menu = Some(menu);
+        }
+    }
+
+    // the menu's put back unless what was chosen closes it
+    fn choose_menu_item(&mut self, mut menu: Menu, item: Item) {
+        match item {
+            Item::NewGame => menu.show(Page::NewGame),
+            Item::Settings => menu.show(Page::Settings),
+            Item::Load => {
+                menu.files = pgn_files().unwrap_or_else(|error| {
+                    self.notify(format!("Couldn't look for games: {}", error));
+                    Vec::new()
+                });
+                menu.show(Page::Load);
+            }
+            Item::Resume => return,
+            Item::Quit => return self.window.set_should_close(true),
+            Item::Start => {
+                if self.start_menu_game(&menu) {
+                    return;
+                }
+            }
+            Item::File(index) => {
+                if self.load_menu_game(&menu.files[index]) {
+                    return;
+                }
+            }
+            Item::Back => menu.show(Page::Main),
+            item => self.change_menu_choice(&mut menu, item, true),
+        }
+        self.menu = Some(menu);
+    }
+
+    fn change_menu_choice(&mut self, menu: &mut Menu, item: Item, forward: bool) {
+        match item {
+            Item::Color => menu.color = menu.color.opposite(),
+            Item::Opponent => menu.computer = !menu.computer,
+            Item::Clock => {
+                menu.time_control = Menu::cycle(menu.time_control, TIME_CONTROLS.len(), forward)
+            }
+            Item::Variant => menu.variant = Menu::cycle(menu.variant, VARIANTS.len(), forward),
+            Item::Animations => self.set_animations(!self.animations),
+            Item::Coordinates => self.coordinates = !self.coordinates,
+            #[cfg(feature = "audio")]
+            Item::Sound => {
+                let muted = !self.speaker.is_muted();
+                self.speaker.set_muted(muted);
+            }
+            Item::Draws => self.set_draw_rules(match self.game.draw_rules {
+                DrawRules::Automatic => DrawRules::Fide,
+                DrawRules::Fide => DrawRules::Automatic,
+            }),
+            _ => {}
+        }
+    }
+
+    // what's on a line, with the choice it's at for the ones that change
+    fn menu_label(&self, menu: &Menu, item: Item) -> String {
+        let on = |on: bool| if on { "on" } else { "off" };
+        match item {
+            Item::NewGame => "New game".to_string(),
+            Item::Settings => "Settings".to_string(),
+            Item::Load => "Load PGN".to_string(),
+            Item::Resume => "Back to the game".to_string(),
+            Item::Quit => "Quit".to_string(),
+            Item::Color => format!("Play as: {:?}", menu.color),
+            Item::Opponent if menu.computer => "Opponent: computer".to_string(),
+            Item::Opponent => "Opponent: human".to_string(),
+            Item::Clock => match TIME_CONTROLS[menu.time_control] {
+                "" => "Clock: none".to_string(),
+                control => format!("Clock: {}", control),
+            },
+            Item::Variant => format!("Variant: {}", VARIANTS[menu.variant]),
+            Item::Start => "Start".to_string(),
+            Item::Animations => format!("Animations: {}", on(self.animations)),
+            Item::Coordinates => format!("Coordinates: {}", on(self.coordinates)),
+            #[cfg(feature = "audio")]
+            Item::Sound => format!("Sound: {}", on(!self.speaker.is_muted())),
+            Item::Draws => match self.game.draw_rules {
+                DrawRules::Automatic => "Draws: automatic".to_string(),
+                DrawRules::Fide => "Draws: claimed, as fide has it".to_string(),
+            },
+            Item::File(index) => menu.files[index]
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned()),
+            Item::Back => "Back".to_string(),
+        }
+    }
+
+    // a game against someone somewhere else isn't given up by starting another
+    fn can_replace_game(&mut self) -> bool {
+        if self.is_remote_game() {
+            self.notify("The game against someone else has to be finished or closed first");
+            return false;
+        }
+        true
+    }
+
+    // the game chosen on the new game page, in place of the one shown
+    fn start_menu_game(&mut self, menu: &Menu) -> bool {
+        if !self.can_replace_game() {
+            return false;
+        }
+        let variant = variant::by_name(VARIANTS[menu.variant]).unwrap();
+        let number = if VARIANTS[menu.variant] == "Chess960" {
+            Some(chess960::random_number())
+        } else {
+            None
+        };
+        let mut board = match number {
+            Some(number) => chess960::starting_position(number).unwrap(),
+            None => Board::from_fen(STARTING_FEN).unwrap(),
+        };
+        variant.setup(&mut board);
+        self.replace_game(board, variant);
+        if menu.computer {
+            // the built in engine at a second a move, as the command line has it
+            let mut engine = Engine::new();
+            engine.set_threads(self.threads);
+            engine.set_book(self.book.clone(), DEFAULT_BOOK_PLIES);
+            let limits = SearchLimits {
+                time: Some(Duration::from_secs(1)),
+                ..SearchLimits::default()
+            };
+            let opponent = Opponent::Builtin(engine);
+            self.play_against_computer(menu.color.opposite(), opponent, limits);
+        } else {
+            self.set_flipped(menu.color == Color::Black);
+        }
+        if let Some(control) = TimeControl::parse(TIME_CONTROLS[menu.time_control]) {
+            self.set_clock(control);
+        }
+        // so it can be played again with --chess960
+        if let Some(number) = number {
+            self.notify(format!("Chess960 position {}", number));
+        }
+        true
+    }
+
+    // like --pgn, the game's replayed in place of the one shown
+    fn load_menu_game(&mut self, path: &Path) -> bool {
+        if !self.can_replace_game() {
+            return false;
+        }
+        let game = match fs::read_to_string(path) {
+            Ok(text) => match PgnGame::from_pgn(&text) {
+                Ok(game) => game,
+                Err(error) => {
+                    self.notify(format!("Invalid PGN in {}: {}", path.display(), error));
+                    return false;
+                }
+            },
+            Err(error) => {
+                self.notify(format!("Couldn't read {}: {}", path.display(), error));
+                return false;
+            }
+        };
+        self.replace_game(game.start().clone(), game.variant());
+        self.replay(game);
+        true
+    }
+
+    // the line of the menu under the cursor, if there is one
+    fn menu_item_at(&self, count: usize) -> Option<usize> {
+        let (width, height) = self.window.get_framebuffer_size();
+        let x = (self.cursor.0 / width.max(1) as f64) as f32 * 2.0 - 1.0;
+        let y = 1.0 - (self.cursor.1 / height.max(1) as f64) as f32 * 2.0;
+        (0..count).find(|&index| {
+            let rect = menu_row(index, count);
+            x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height
+        })
+    }
+
+    // over the whole window, after everything else
+    pub(super) fn draw_menu(&mut self) {
+        let menu = match &self.menu {
+            Some(menu) => menu,
+            None => return,
+        };
+        let (width, height) = self.window.get_framebuffer_size();
+        self.renderer.set_viewport(0, 0, width, height);
+        let whole = Rect {
+            x: -1.0,
+            y: -1.0,
+            width: 2.0,
+            height: 2.0,
+        };
+        self.renderer.fill(whole, (0.0, 0.0, 0.0, 0.6));
+        let items = menu.items();
+        let title = menu_row(0, items.len()).offset(0.0, ROW);
+        self.renderer
+            .text(menu.title(), title, (1.0, 1.0, 1.0, 1.0), Align::Center);
+        for (index, &item) in items.iter().enumerate() {
+            let row = menu_row(index, items.len());
+            let background = if index == menu.selected {
+                (0.4, 0.5, 0.5, 1.0)
+            } else {
+                (0.2, 0.25, 0.25, 0.9)
+            };
+            self.renderer.fill(row, background);
+            let text = Rect {
+                y: row.y + row.height * 0.2,
+                height: row.height * 0.6,
+                ..row
+            };
+            self.renderer.text(
+                &self.menu_label(menu, item),
+                text,
+                (1.0, 1.0, 1.0, 1.0),
+                Align::Center,
+            );
+        }
+    }
+}
+
+// where a line goes, the lines and the title above them centred in the window
+fn menu_row(index: usize, count: usize) -> Rect {
+    let top = (count + 1) as f32 * ROW / 2.0;
+    Rect {
+        x: -0.4,
+        y: top - (index + 2) as f32 * ROW,
+        width: 0.8,
+        height: ROW * 0.85,
+    }
+}
+
+// the .pgn files where the program was started, which is where games are saved,
+// newest first
+fn pgn_files() -> io::Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(".")?;
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "pgn"))
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+            (modified.unwrap_or(SystemTime::UNIX_EPOCH), path)
+        })
+        .collect();
+    files.sort_by_key(|(modified, _)| Reverse(*modified));
+    files.truncate(MAX_FILES);
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
@@ -0,0 +1,273 @@
+use super::{Game, RECONNECT_INTERVAL};
+use chess_game::board::Color;
+use chess_game::clock::TimeControl;
+use chess_game::fen::STARTING_FEN;
+use chess_game::lichess::{Client, GameEvent, GameFull, GameState, GameStream};
+use chess_game::moves::Move;
+use chess_game::net::{self, ClockState};
+use chess_game::settings::Command;
+use chess_game::status::GameStatus;
+use std::io;
+use std::time::Instant;
+
+// a game on lichess, which has the final say on the moves and the clocks
+pub struct Online {
+    client: Client,
+    game: String,
+    account: String,
+    // the side played here, none when the account isn't playing in the game
+    color: Option<Color>,
+    stream: Option<GameStream>,
+    last_attempt: Instant,
+    // a move sent that lichess hasn't mentioned yet
+    unconfirmed: Option<Move>,
+    // set while playing moves lichess sent, which mustn't be sent back
+    receiving: bool,
+    finished: bool,
+}
+
+impl Online {
+    // the side played here, none when the account is only watching
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    // gives up the game on lichess, for the account playing it
+    pub fn resign(&self) -> io::Result<()> {
+        self.client.resign(&self.game)
+    }
+
+    // offers a draw or takes the opponent's with accept, turns theirs down without
+    pub fn answer_draw(&self, accept: bool) -> io::Result<()> {
+        self.client.answer_draw(&self.game, accept)
+    }
+
+    pub fn allows_move(&self, side: Color) -> bool {
+        !self.finished && self.stream.is_some() && self.color == Some(side)
+    }
+}
+
+impl Game {
+    // plays one of the account's ongoing games, found by the id in its url
+    pub fn play_on_lichess(&mut self, client: Client, game: &str) -> io::Result<()> {
+        let account = client.account_id()?;
+        let stream = client.stream_game(game)?;
+        self.set_title("Chess - connecting to lichess");
+        self.computer = None;
+        self.online = Some(Online {
+            client,
+            game: game.to_string(),
+            account,
+            color: None,
+            stream: Some(stream),
+            last_attempt: Instant::now(),
+            unconfirmed: None,
+            receiving: false,
+            finished: false,
+        });
+        Ok(())
+    }
+
+    pub(super) fn update_online(&mut self) {
+        let online = match &mut self.online {
+            Some(online) => online,
+            None => return,
+        };
+        if online.stream.is_none() {
+            // lichess closes the stream when the game ends, otherwise try following it again
+            if online.finished || online.last_attempt.elapsed() < RECONNECT_INTERVAL {
+                return;
+            }
+            online.last_attempt = Instant::now();
+            match online.client.stream_game(&online.game) {
+                Ok(stream) => online.stream = Some(stream),
+                Err(error) => eprintln!("Couldn't reach lichess: {}", error),
+            }
+            return;
+        }
+        loop {
+            let online = self.online.as_mut().unwrap();
+            // loading the game can start the stream over
+            let stream = match online.stream.as_mut() {
+                Some(stream) => stream,
+                None => break,
+            };
+            match stream.poll() {
+                Ok(Some(GameEvent::Full(full))) => self.load_lichess_game(full),
+                Ok(Some(GameEvent::State(state))) => self.update_lichess_state(state),
+                Ok(None) => break,
+                Err(error) => {
+                    online.stream = None;
+                    online.last_attempt = Instant::now();
+                    if !online.finished {
+                        eprintln!("Lost the lichess game: {}", error);
+                        self.set_title("Chess - reconnecting to lichess");
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    fn load_lichess_game(&mut self, full: GameFull) {
+        let online = self.online.as_mut().unwrap();
+        let account = Some(&online.account);
+        let color = if full.white.id.as_ref() == account {
+            Some(Color::White)
+        } else if full.black.id.as_ref() == account {
+            Some(Color::Black)
+        } else {
+            eprintln!("The account isn't playing in this game, only watching it");
+            None
+        };
+        online.color = color;
+        online.unconfirmed = None;
+        let fen = match full.initial_fen.as_str() {
+            "startpos" => STARTING_FEN.to_string(),
+            fen => fen.to_string(),
+        };
+        let state = net::GameState {
+            color: color.unwrap_or(Color::White),
+            fen,
+            variant: full.variant.clone(),
+            moves: full.state.moves.clone(),
+            clock: full.clock.map(|(initial, increment)| ClockState {
+                control: TimeControl::new(initial, increment),
+                white: full.state.white_time,
+                black: full.state.black_time,
+            }),
+        };
+        if let Err(error) = self.load_game_state(state) {
+            return eprintln!("Lichess sent a game that doesn't work: {}", error);
+        }
+        self.game.record.set_player(Color::White, &full.white.name);
+        self.game.record.set_player(Color::Black, &full.black.name);
+        self.game
+            .record
+            .set_header("Site", &format!("https://lichess.org/{}", full.id));
+        self.update_lichess_state(full.state);
+    }
+
+    fn update_lichess_state(&mut self, state: GameState) {
+        let played: Vec<String> = self
+            .game
+            .record
+            .moves()
+            .iter()
+            .map(|mv| mv.mv.to_string())
+            .collect();
+        let online = self.online.as_mut().unwrap();
+        if state.moves.len() < played.len() && state.moves[..] == played[..state.moves.len()] {
+            // our own move on its way, or moves taken back on lichess
+            let waiting = played.len() - state.moves.len() == 1
+                && online.unconfirmed.map(|mv| mv.to_string()).as_ref() == played.last();
+            if !waiting {
+                self.reload_online_game();
+                return;
+            }
+        } else if state.moves.len() >= played.len() && state.moves[..played.len()] == played[..] {
+            online.unconfirmed = None;
+            online.receiving = true;
+            for text in &state.moves[played.len()..] {
+                match self.game.board.parse_uci_move(text) {
+                    Some(mv) => self.play_move(mv),
+                    None => {
+                        eprintln!("Lichess sent an illegal move {}", text);
+                        break;
+                    }
+                }
+            }
+            self.online.as_mut().unwrap().receiving = false;
+        } else {
+            self.reload_online_game();
+            return;
+        }
+
+        if let Some(clock) = &mut self.game.clock {
+            clock.set_remaining(Color::White, state.white_time);
+            clock.set_remaining(Color::Black, state.black_time);
+        }
+        self.update_lichess_draw_offer(state.draw_offer);
+        if state.is_over() {
+            self.online.as_mut().unwrap().finished = true;
+            if let Some(clock) = &mut self.game.clock {
+                clock.stop();
+            }
+            match (state.status.as_str(), state.winner) {
+                ("outoftime", Some(winner)) => self.set_status(GameStatus::Timeout(winner)),
+                ("resign", Some(winner)) => self.set_status(GameStatus::Resignation(winner)),
+                ("draw", None) if !self.game.status.is_over() => {
+                    self.set_status(GameStatus::DrawAgreed)
+                }
+                // mates and draws on the board have already been noticed
+                _ if self.game.status.is_over() => {}
+                (status, winner) => {
+                    let text = match winner {
+                        Some(Color::White) => format!("{}, White wins", status),
+                        Some(Color::Black) => format!("{}, Black wins", status),
+                        None => status.to_string(),
+                    };
+                    println!("The game ended on lichess: {}", text);
+                    self.set_title(&format!("Chess - {}", text));
+                }
+            }
+        }
+    }
+
+    // an offer from the opponent is told about once; one of ours going away without
+    // the game ending means it was turned down
+    fn update_lichess_draw_offer(&mut self, offer: Option<Color>) {
+        let color = self.online.as_ref().unwrap().color;
+        match offer {
+            Some(by) if self.controls.draw_offer != Some(by) => {
+                self.controls.draw_offer = Some(by);
+                self.redraw = true;
+                if Some(by) != color {
+                    self.notify(format!(
+                        "The opponent offers a draw: {} to accept, {} to decline",
+                        self.keys.binding(Command::OfferDraw),
+                        self.keys.binding(Command::DeclineDraw)
+                    ));
+                }
+            }
+            None => {
+                if let Some(by) = self.controls.draw_offer.take() {
+                    self.redraw = true;
+                    if Some(by) == color {
+                        self.notify("The opponent declines the draw");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // starts following the game from scratch, lichess sends all of it again
+    fn reload_online_game(&mut self) {
+        let online = self.online.as_mut().unwrap();
+        online.stream = None;
+        online.last_attempt = Instant::now()
+            .checked_sub(RECONNECT_INTERVAL)
+            .unwrap_or_else(Instant::now);
+    }
+
+    // sends a move made here, on its own thread so the window doesn't wait for lichess
+    pub(super) fn send_online_move(&mut self, mv: Move) {
+        let online = match &mut self.online {
+            Some(online) => online,
+            None => return,
+        };
+        let mover = self.game.board.side_to_move().opposite();
+        if online.color != Some(mover) || online.finished || online.receiving {
+            return;
+        }
+        online.unconfirmed = Some(mv);
+        let client = online.client.clone();
+        let game = online.game.clone();
+        std::thread::spawn(move || {
+            if let Err(error) = client.make_move(&game, mv) {
+                eprintln!("Lichess didn't take the move {}: {}", mv, error);
+            }
+        });
+    }
+}
@@ -0,0 +1,133 @@
+use super::{Game, PanelTarget, ROW_HEIGHT};
+use crate::renderer::{Align, Rect};
+use chess_game::eco::{Eco, Opening};
+use chess_game::explorer::Explorer;
+
+// the name of the opening being played, and the moves played from the position shown
+// in a collection of games, which takes the move list's place with E
+pub struct Openings {
+    eco: Eco,
+    explorer: Explorer,
+    // of the game on the board, printed each time it changes
+    pub(super) opening: Option<Opening>,
+}
+
+impl Openings {
+    pub fn new() -> Openings {
+        Openings {
+            eco: Eco::bundled(),
+            explorer: Explorer::bundled(),
+            opening: None,
+        }
+    }
+}
+
+impl Game {
+    // the explorer goes by these games instead of the lines of the bundled book
+    pub fn set_explorer(&mut self, explorer: Explorer) {
+        self.openings.explorer = explorer;
+    }
+
+    // names the opening after a move, or once a game's loaded
+    pub(super) fn update_opening(&mut self) {
+        let opening = match &self.replay {
+            Some(replay) => {
+                let moves: Vec<_> = replay.game.moves()[..replay.ply]
+                    .iter()
+                    .map(|record| record.mv)
+                    .collect();
+                self.openings.eco.classify(replay.game.start(), &moves)
+            }
+            None => {
+                let moves: Vec<_> = self
+                    .game
+                    .record
+                    .moves()
+                    .iter()
+                    .map(|record| record.mv)
+                    .collect();
+                self.openings.eco.classify(self.game.record.start(), &moves)
+            }
+        }
+        .cloned();
+        if opening != self.openings.opening {
+            if let Some(opening) = &opening {
+                self.notify(format!("Opening: {} {}", opening.eco, opening.name));
+            }
+            self.openings.opening = opening;
+        }
+    }
+
+    // a row of the panel for the opening's name, if there is one
+    pub(super) fn draw_opening(&mut self, area: Rect) -> f32 {
+        let opening = match &self.openings.opening {
+            Some(opening) => opening,
+            None => return 0.0,
+        };
+        let rect = Rect {
+            x: area.x,
+            y: area.y + area.height - ROW_HEIGHT,
+            width: area.width,
+            height: ROW_HEIGHT,
+        };
+        let text = format!("{} {}", opening.eco, opening.name);
+        self.renderer
+            .text(&text, rect, (0.8, 0.8, 0.6, 1.0), Align::Left);
+        ROW_HEIGHT
+    }
+
+    // a row for each move played in the position shown, the most common first, with
+    // how often white won, drew and lost after it; clicking one plays it
+    pub(super) fn draw_explorer(&mut self, area: Rect, targets: &mut Vec<(Rect, PanelTarget)>) {
+        let board = self.shown_board().clone();
+        let continuations = self.openings.explorer.continuations(&board);
+        let visible = ((area.height / ROW_HEIGHT) as usize).max(2) - 1;
+        self.move_scroll = self
+            .move_scroll
+            .min(continuations.len().saturating_sub(visible));
+        // the same width for every row, so they all come out the same size
+        let header = if continuations.is_empty() {
+            "Not in the explorer".to_string()
+        } else {
+            format!(
+                "{:<7} {:>5} {:>4} {:>4} {:>4}",
+                "", "games", "1-0", "=", "0-1"
+            )
+        };
+        let row = |i: usize| Rect {
+            x: area.x,
+            y: area.y + area.height - ROW_HEIGHT * (i + 1) as f32,
+            width: area.width,
+            height: ROW_HEIGHT,
+        };
+        self.renderer
+            .text(&header, row(0), (0.8, 0.8, 0.8, 1.0), Align::Left);
+        let playable = self.can_move_pieces();
+        for (i, continuation) in continuations
+            .iter()
+            .skip(self.move_scroll)
+            .take(visible)
+            .enumerate()
+        {
+            let decided = continuation.decided();
+            let percent = |count: u32| match decided {
+                0 => "-".to_string(),
+                _ => format!("{}%", count * 100 / decided),
+            };
+            let text = format!(
+                "{:<7} {:>5} {:>4} {:>4} {:>4}",
+                continuation.mv.to_san(&board),
+                continuation.games,
+                percent(continuation.white),
+                percent(continuation.draws),
+                percent(continuation.black)
+            );
+            let rect = row(i + 1);
+            self.renderer
+                .text(&text, rect, (1.0, 1.0, 1.0, 1.0), Align::Left);
+            if playable {
+                targets.push((rect, PanelTarget::Explore(continuation.mv)));
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use super::Game;
+use chess_game::board::Color;
+use chess_game::moves::Move;
+use chess_game::pgn::PgnGame;
+use chess_game::puzzle::{Attempt, Training};
+use chess_game::settings::Command;
+use std::time::{Duration, Instant};
+
+// how long the opponent's moves of a solution wait, so it's clear what they did
+const REPLY_DELAY: Duration = Duration::from_millis(600);
+// how long a solved puzzle stays up before the next one
+const NEXT_DELAY: Duration = Duration::from_millis(1500);
+
+// solving tactics instead of playing a game: the window plays the opponent's moves
+// of each solution and checks the player's against it
+pub struct Puzzles {
+    pub(super) training: Training,
+    // when the opponent's next move, or the next puzzle once this one's solved, is due
+    due: Option<Instant>,
+}
+
+impl Game {
+    pub fn play_puzzles(&mut self, training: Training) {
+        self.computer = None;
+        self.puzzles = Some(Puzzles {
+            training,
+            due: None,
+        });
+        self.start_puzzle();
+    }
+
+    // sets up the current puzzle, or says how it went once there are no more
+    fn start_puzzle(&mut self) {
+        let puzzles = self.puzzles.as_mut().unwrap();
+        let (number, count) = puzzles.training.position();
+        let puzzle = match puzzles.training.puzzle() {
+            Some(puzzle) => puzzle.clone(),
+            None => {
+                let training = &puzzles.training;
+                let text = format!(
+                    "That was the last puzzle: {} solved, {} failed, best streak {}",
+                    training.solved(),
+                    training.failed(),
+                    training.best_streak()
+                );
+                puzzles.due = None;
+                self.notify(text);
+                return;
+            }
+        };
+        puzzles.due = Some(Instant::now() + REPLY_DELAY);
+        let side = match puzzle.color() {
+            Color::White => "White",
+            Color::Black => "Black",
+        };
+
+        let mut record = PgnGame::new(&puzzle.board);
+        record.set_header("Event", &format!("Puzzle {}", puzzle.id));
+        if let Some(url) = &puzzle.url {
+            record.set_header("Site", url);
+        }
+        self.game.record = record;
+        self.game.board = puzzle.board.clone();
+        self.flipped = puzzle.color() == Color::Black;
+        self.game.taken_back.clear();
+        self.history_changed();
+        self.notify(format!(
+            "Puzzle {} of {}: {}, rated {}, {} to play",
+            number, count, puzzle.id, puzzle.rating, side
+        ));
+    }
+
+    // N gives up on a puzzle, showing the rest of the solution over the next one
+    pub(super) fn skip_puzzle(&mut self) {
+        let puzzles = match &mut self.puzzles {
+            Some(puzzles) => puzzles,
+            None => return,
+        };
+        if puzzles.training.puzzle().is_none() {
+            return;
+        }
+        let mut solution = None;
+        if !puzzles.training.is_solved() {
+            let mut board = self.game.board.clone();
+            let mut line = Vec::new();
+            for &mv in puzzles.training.remaining() {
+                line.push(mv.to_san(&board));
+                board.make_move(mv);
+            }
+            solution = Some(format!("The solution was {}", line.join(" ")));
+        }
+        puzzles.training.next_puzzle();
+        self.start_puzzle();
+        if let Some(solution) = solution {
+            let text = match self.notice.take() {
+                Some(next) => format!("{}\n{}", solution, next),
+                None => solution,
+            };
+            self.notify(text);
+        }
+    }
+
+    // whether the player may move now: on their side, and not while the solution's
+    // about to carry on by itself
+    pub(super) fn puzzle_allows_move(&self) -> bool {
+        self.puzzles.as_ref().is_none_or(|puzzles| {
+            puzzles.due.is_none()
+                && puzzles
+                    .training
+                    .puzzle()
+                    .is_some_and(|puzzle| puzzle.color() == self.game.board.side_to_move())
+        })
+    }
+
+    // checks a move of the player's against the solution before it's played; a wrong
+    // one isn't played at all so it can be tried again
+    pub(super) fn check_puzzle_move(&mut self, mv: Move) -> bool {
+        let puzzles = match &mut self.puzzles {
+            Some(puzzles) => puzzles,
+            None => return true,
+        };
+        let solver = puzzles.training.puzzle().map(|puzzle| puzzle.color());
+        if solver != Some(self.game.board.side_to_move()) {
+            return true;
+        }
+        match puzzles.training.attempt(&self.game.board, mv) {
+            Attempt::Right => {
+                puzzles.due = Some(Instant::now() + REPLY_DELAY);
+                true
+            }
+            Attempt::Solved => {
+                let training = &puzzles.training;
+                let text = if training.streak() > 0 {
+                    format!("Solved, {} in a row", training.streak())
+                } else {
+                    "Solved, though not first time".to_string()
+                };
+                puzzles.due = Some(Instant::now() + NEXT_DELAY);
+                self.notify(text);
+                true
+            }
+            Attempt::Wrong(_) => {
+                self.notify(format!(
+                    "Not {}, try another move or {} for the next puzzle",
+                    mv.to_san(&self.game.board),
+                    self.keys.binding(Command::NextPuzzle)
+                ));
+                self.selected = None;
+                self.redraw = true;
+                false
+            }
+        }
+    }
+
+    pub(super) fn update_puzzles(&mut self) {
+        let puzzles = match &mut self.puzzles {
+            Some(puzzles) => puzzles,
+            None => return,
+        };
+        if puzzles.due.is_none_or(|due| Instant::now() < due) {
+            return;
+        }
+        puzzles.due = None;
+        if puzzles.training.is_solved() {
+            puzzles.training.next_puzzle();
+            return self.start_puzzle();
+        }
+        if let Some(mv) = puzzles.training.reply() {
+            self.play_move(mv);
+        }
+    }
+
+    // the title while solving, like "Chess - puzzle 3 of 50 (1650) - streak 2"
+    pub(super) fn puzzle_title(&self) -> Option<String> {
+        let training = &self.puzzles.as_ref()?.training;
+        let puzzle = training.puzzle()?;
+        let (number, count) = training.position();
+        Some(format!(
+            "Chess - puzzle {} of {} ({}) - streak {}",
+            number,
+            count,
+            puzzle.rating,
+            training.streak()
+        ))
+    }
+}
@@ -0,0 +1,130 @@
+use super::{Game, PanelTarget, Sidebar, ROW_HEIGHT};
+use crate::renderer::{Align, Rect};
+use chess_game::database::{self, Database, Hit};
+
+// at most this many games are listed for a position
+const MAX_HITS: usize = 100;
+
+// the games of a database that reached the position shown, listed instead of the
+// moves with D
+pub struct Search {
+    database: Database,
+    // the position_key() the hits are for, and a line about each game
+    key: Option<u64>,
+    hits: Vec<(Hit, String)>,
+    // how many there were before the list was cut short
+    total: usize,
+}
+
+impl Game {
+    pub fn set_database(&mut self, database: Database) {
+        self.search = Some(Search {
+            database,
+            key: None,
+            hits: Vec::new(),
+            total: 0,
+        });
+    }
+
+    pub(super) fn toggle_search(&mut self) {
+        match &self.search {
+            Some(search) if search.database.is_empty() => {
+                self.notify("There are no games in the database; the database command imports some")
+            }
+            Some(_) => self.toggle_sidebar(Sidebar::Games),
+            None => self.notify("There's no database of games open"),
+        }
+    }
+
+    // opens a game from the list at the position it was found for, unless there's a
+    // game being played that it would take the place of
+    pub(super) fn open_database_game(&mut self, hit: Hit) {
+        let search = match &self.search {
+            Some(search) => search,
+            None => return,
+        };
+        let playing = self.replay.is_none()
+            && self.puzzles.is_none()
+            && !self.game.status.is_over()
+            && !self.game.record.moves().is_empty();
+        if playing {
+            return self.notify(format!(
+                "Finish the game first, or see the game with: database show {}",
+                hit.game + 1
+            ));
+        }
+        match search.database.game(hit.game) {
+            Ok(game) => {
+                let summary = database::summary(&game);
+                self.replay(game);
+                self.show_replay_move(hit.ply);
+                self.notify(summary);
+            }
+            Err(error) => self.notify(format!("Couldn't read game {}: {}", hit.game + 1, error)),
+        }
+    }
+
+    // a row for each game that reached the position shown
+    pub(super) fn draw_games(&mut self, area: Rect, targets: &mut Vec<(Rect, PanelTarget)>) {
+        let board = self.shown_board().clone();
+        let search = match &mut self.search {
+            Some(search) => search,
+            None => return,
+        };
+        if search.key != Some(board.position_key()) {
+            let hits = search.database.search(&board);
+            search.total = hits.len();
+            search.hits = hits
+                .into_iter()
+                .take(MAX_HITS)
+                .map(|hit| {
+                    let summary = match search.database.game(hit.game) {
+                        Ok(game) => database::summary(&game),
+                        Err(_) => "?".to_string(),
+                    };
+                    (hit, summary)
+                })
+                .collect();
+            search.key = Some(board.position_key());
+        }
+
+        let visible = ((area.height / ROW_HEIGHT) as usize).max(2) - 1;
+        self.move_scroll = self
+            .move_scroll
+            .min(search.hits.len().saturating_sub(visible));
+        let row = |i: usize| Rect {
+            x: area.x,
+            y: area.y + area.height - ROW_HEIGHT * (i + 1) as f32,
+            width: area.width,
+            height: ROW_HEIGHT,
+        };
+        let header = match search.total {
+            0 => "No games reached this".to_string(),
+            1 => "1 game".to_string(),
+            total if total > search.hits.len() => format!("{} games, {} shown", total, MAX_HITS),
+            total => format!("{} games", total),
+        };
+        self.renderer
+            .text(&header, row(0), (0.8, 0.8, 0.8, 1.0), Align::Left);
+        // the longest line sets the size for all of them
+        let width = search
+            .hits
+            .iter()
+            .map(|(_, summary)| summary.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (i, (hit, summary)) in search
+            .hits
+            .iter()
+            .skip(self.move_scroll)
+            .take(visible)
+            .enumerate()
+        {
+            let rect = row(i + 1);
+            let text = format!("{:<width$}", summary, width = width);
+            self.renderer
+                .text(&text, rect, (1.0, 1.0, 1.0, 1.0), Align::Left);
+            targets.push((rect, PanelTarget::DatabaseGame(*hit)));
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use super::Game;
+use chess_game::board::Color;
+use chess_game::pgn::result_token;
+use chess_game::spectate::{Spectated, SpectatorServer};
+use std::io;
+use std::net::IpAddr;
+
+impl Game {
+    // sends the position to whoever connects to the port over a websocket, and again
+    // after every move, see chess_game::spectate
+    pub fn spectate(&mut self, address: IpAddr, port: u16) -> io::Result<()> {
+        let server = SpectatorServer::bind(address, port)?;
+        println!("Spectators can watch on ws://{}:{}", address, server.port());
+        self.spectators = Some(server);
+        self.broadcast_position();
+        Ok(())
+    }
+
+    // the game's position, or the replay's
+    pub(super) fn broadcast_position(&mut self) {
+        if self.spectators.is_none() {
+            return;
+        }
+        let (record, ply) = match &self.replay {
+            Some(replay) => (&replay.game, replay.ply),
+            None => (&self.game.record, self.game.record.moves().len()),
+        };
+        let last_move = ply
+            .checked_sub(1)
+            .and_then(|last| record.moves().get(last))
+            .map(|mv| (mv.mv.to_string(), mv.san.clone()));
+        let state = Spectated {
+            fen: self.game.board.to_fen(),
+            last_move,
+            ply,
+            white: self.player_name(Color::White).to_string(),
+            black: self.player_name(Color::Black).to_string(),
+            result: result_token(self.game.status).to_string(),
+            status: Some(self.game.status.to_string()).filter(|_| self.game.status.is_over()),
+            clock: self.game.clock.as_ref().map(|clock| {
+                (
+                    clock.remaining(Color::White).as_millis() as u64,
+                    clock.remaining(Color::Black).as_millis() as u64,
+                )
+            }),
+        };
+        self.spectators.as_mut().unwrap().broadcast(&state);
+    }
+
+    pub(super) fn update_spectators(&mut self) {
+        if let Some(server) = &mut self.spectators {
+            if server.update() {
+                println!("{} watching", server.spectator_count());
+            }
+        }
+    }
+}
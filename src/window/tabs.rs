@@ -0,0 +1,297 @@
+use super::{
+    control, editor, puzzles, Annotations, Drag, Fade, Game, Network, PanelTarget, Preview,
+    Promotion, Replay, Sidebar, Slide,
+};
+use crate::animation::Animation;
+use crate::computer::Computer;
+use crate::renderer::{Align, Rect};
+use chess_game::board::{Board, PieceKind, Square};
+use chess_game::correspondence::CorrespondenceGame;
+use chess_game::eco::Opening;
+use chess_game::engine::analysis::Analysis;
+use chess_game::engine::coach::{Coach, Review};
+use chess_game::engine::report::Report;
+use chess_game::engine::Engine;
+use chess_game::game;
+use chess_game::moves::Move;
+use chess_game::variant::Variant;
+use glfw::Key;
+use std::mem;
+
+// at most this many games are open, one for each number key
+const MAX_TABS: usize = 9;
+
+// a game open in the window but not the one shown: everything that belongs to a game
+// rather than the window, swapped with the game's own fields when it's switched to.
+// it carries on meanwhile, so an engine still moves and a clock still runs
+pub struct Tab {
+    game: game::Game,
+    selected: Option<Square>,
+    dropping: Option<PieceKind>,
+    premove: Option<Move>,
+    last_move: Option<Move>,
+    annotations: Annotations,
+    drag: Option<Drag>,
+    slide: Option<Slide>,
+    fade: Option<Fade>,
+    check_pulse: Option<(Square, Animation)>,
+    promotion: Option<Promotion>,
+    flipped: bool,
+    computer: Option<Computer>,
+    network: Option<Network>,
+    controls: control::Controls,
+    #[cfg(feature = "lichess")]
+    online: Option<super::online::Online>,
+    #[cfg(feature = "spectate")]
+    spectators: Option<chess_game::spectate::SpectatorServer>,
+    correspondence: Option<CorrespondenceGame>,
+    hint: Option<Move>,
+    coach: Coach,
+    blunder_check: bool,
+    blunder: Option<Review>,
+    report: Option<Report>,
+    analysis: Option<Analysis>,
+    replay: Option<Replay>,
+    puzzles: Option<puzzles::Puzzles>,
+    editor: Option<editor::Editor>,
+    preview: Option<Preview>,
+    opening: Option<Opening>,
+    sidebar: Sidebar,
+    move_scroll: usize,
+    title: String,
+    notice: Option<String>,
+}
+
+impl Tab {
+    // nothing going on yet, for either side to be played from here
+    fn new(game: game::Game, coach: Coach) -> Tab {
+        Tab {
+            game,
+            selected: None,
+            dropping: None,
+            premove: None,
+            last_move: None,
+            annotations: Annotations::default(),
+            drag: None,
+            slide: None,
+            fade: None,
+            check_pulse: None,
+            promotion: None,
+            flipped: false,
+            computer: None,
+            network: None,
+            controls: control::Controls::default(),
+            #[cfg(feature = "lichess")]
+            online: None,
+            #[cfg(feature = "spectate")]
+            spectators: None,
+            correspondence: None,
+            hint: None,
+            coach,
+            blunder_check: false,
+            blunder: None,
+            report: None,
+            analysis: None,
+            replay: None,
+            puzzles: None,
+            editor: None,
+            preview: None,
+            opening: None,
+            sidebar: Sidebar::Moves,
+            move_scroll: 0,
+            title: "Chess".to_string(),
+            notice: None,
+        }
+    }
+}
+
+impl Game {
+    // what the window shows goes in the tab, and the tab's game is shown instead
+    fn swap_tab(&mut self, tab: &mut Tab) {
+        mem::swap(&mut self.game, &mut tab.game);
+        mem::swap(&mut self.selected, &mut tab.selected);
+        mem::swap(&mut self.dropping, &mut tab.dropping);
+        mem::swap(&mut self.premove, &mut tab.premove);
+        mem::swap(&mut self.last_move, &mut tab.last_move);
+        mem::swap(&mut self.annotations, &mut tab.annotations);
+        mem::swap(&mut self.drag, &mut tab.drag);
+        mem::swap(&mut self.slide, &mut tab.slide);
+        mem::swap(&mut self.fade, &mut tab.fade);
+        mem::swap(&mut self.check_pulse, &mut tab.check_pulse);
+        mem::swap(&mut self.promotion, &mut tab.promotion);
+        mem::swap(&mut self.flipped, &mut tab.flipped);
+        mem::swap(&mut self.computer, &mut tab.computer);
+        mem::swap(&mut self.network, &mut tab.network);
+        mem::swap(&mut self.controls, &mut tab.controls);
+        #[cfg(feature = "lichess")]
+        mem::swap(&mut self.online, &mut tab.online);
+        #[cfg(feature = "spectate")]
+        mem::swap(&mut self.spectators, &mut tab.spectators);
+        mem::swap(&mut self.correspondence, &mut tab.correspondence);
+        mem::swap(&mut self.hint, &mut tab.hint);
+        mem::swap(&mut self.coach, &mut tab.coach);
+        mem::swap(&mut self.blunder_check, &mut tab.blunder_check);
+        mem::swap(&mut self.blunder, &mut tab.blunder);
+        mem::swap(&mut self.report, &mut tab.report);
+        mem::swap(&mut self.analysis, &mut tab.analysis);
+        mem::swap(&mut self.replay, &mut tab.replay);
+        mem::swap(&mut self.puzzles, &mut tab.puzzles);
+        mem::swap(&mut self.editor, &mut tab.editor);
+        mem::swap(&mut self.preview, &mut tab.preview);
+        mem::swap(&mut self.openings.opening, &mut tab.opening);
+        mem::swap(&mut self.sidebar, &mut tab.sidebar);
+        mem::swap(&mut self.move_scroll, &mut tab.move_scroll);
+        mem::swap(&mut self.title, &mut tab.title);
+        mem::swap(&mut self.notice, &mut tab.notice);
+    }
+
+    // the title of the game shown; a game in the background keeps its own for later
+    pub(super) fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        if !self.in_background {
+            self.window.set_title(title);
+        }
+    }
+
+    // brought to the front, with whatever was half done with the keyboard and mouse
+    // in the last one dropped
+    fn tab_shown(&mut self) {
+        self.entry = None;
+        self.focus = None;
+        self.redraw = true;
+        let title = self.title.clone();
+        self.window.set_title(&title);
+    }
+
+    // a game from where this one started, left for the player to set up with an
+    // opponent, the editor or the analysis
+    pub(super) fn new_tab(&mut self) {
+        if self.tabs.len() + 1 >= MAX_TABS {
+            return self.notify(format!("There can only be {} games open", MAX_TABS));
+        }
+        let board = self.game.record.start().clone();
+        let game = game::Game::new(board, self.game.record.variant(), self.game.draw_rules);
+        let mut tab = Tab::new(game, self.new_coach());
+        tab.flipped = self.flipped;
+        self.swap_tab(&mut tab);
+        self.tabs.insert(self.tab, tab);
+        self.tab = self.tabs.len();
+        self.update_status();
+        self.tab_shown();
+        self.notify(format!("Game {} of {}", self.tab + 1, self.tabs.len() + 1));
+    }
+
+    // the game shown is saved like it would be when it's closed, and one from `board`
+    // takes its place, for the menu to set up
+    pub(super) fn replace_game(&mut self, board: Board, variant: Box<dyn Variant>) {
+        self.save_on_exit();
+        let game = game::Game::new(board, variant, self.game.draw_rules);
+        let mut tab = Tab::new(game, self.new_coach());
+        self.swap_tab(&mut tab);
+        self.update_status();
+        self.tab_shown();
+    }
+
+    // shows the game with that number, counting from 0; the order stays the same
+    pub(super) fn switch_tab(&mut self, index: usize) {
+        if index == self.tab || index > self.tabs.len() {
+            return;
+        }
+        let shown = if index < self.tab { index } else { index - 1 };
+        let mut tab = self.tabs.remove(shown);
+        self.swap_tab(&mut tab);
+        let hidden = if self.tab < index {
+            self.tab
+        } else {
+            self.tab - 1
+        };
+        self.tabs.insert(hidden, tab);
+        self.tab = index;
+        self.tab_shown();
+    }
+
+    // the game shown is saved like it would be when the window closes, and the one
+    // after it takes its place
+    pub(super) fn close_tab(&mut self) {
+        if self.tabs.is_empty() {
+            return self.notify("The last game can't be closed, only the window");
+        }
+        self.save_on_exit();
+        let next = self.tab.min(self.tabs.len() - 1);
+        let mut tab = self.tabs.remove(next);
+        self.swap_tab(&mut tab);
+        self.tab = next;
+        self.tab_shown();
+    }
+
+    // the games in the background carry on as if they were shown, without drawing
+    pub(super) fn update_tabs(&mut self) {
+        let mut tabs = mem::take(&mut self.tabs);
+        self.in_background = true;
+        for tab in tabs.iter_mut() {
+            self.swap_tab(tab);
+            self.update();
+            self.swap_tab(tab);
+        }
+        self.in_background = false;
+        self.tabs = tabs;
+    }
+
+    // every game that's open, the one shown last
+    pub(super) fn save_tabs_on_exit(&mut self) {
+        let mut tabs = mem::take(&mut self.tabs);
+        for tab in tabs.iter_mut() {
+            self.swap_tab(tab);
+            self.save_on_exit();
+            self.swap_tab(tab);
+        }
+        self.save_on_exit();
+    }
+
+    pub(super) fn new_coach(&self) -> Coach {
+        let mut engine = Engine::new();
+        if let Some(book) = &self.book {
+            engine.set_book(Some(book.clone()), chess_game::book::DEFAULT_BOOK_PLIES);
+        }
+        Coach::new(engine)
+    }
+
+    // a number for each game along the top of the panel once there's more than one,
+    // the shown one lit up
+    pub(super) fn draw_tabs(&self, targets: &mut Vec<(Rect, PanelTarget)>) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        for i in 0..=self.tabs.len() {
+            let cell = Rect {
+                x: -0.9 + 0.2 * i as f32,
+                y: 0.955,
+                width: 0.18,
+                height: 0.04,
+            };
+            let background = if i == self.tab {
+                (0.4, 0.5, 0.5, 1.0)
+            } else {
+                (0.25, 0.3, 0.3, 1.0)
+            };
+            self.renderer.fill(cell, background);
+            self.renderer.text(
+                &(i + 1).to_string(),
+                cell,
+                (1.0, 1.0, 1.0, 1.0),
+                Align::Center,
+            );
+            targets.push((cell, PanelTarget::Tab(i)));
+        }
+    }
+}
+
+// the game a number key switches to, counting from 0
+pub(super) fn tab_number(key: Key) -> Option<usize> {
+    let code = key as i32;
+    if (Key::Num1 as i32..=Key::Num9 as i32).contains(&code) {
+        Some((code - Key::Num1 as i32) as usize)
+    } else {
+        None
+    }
+}
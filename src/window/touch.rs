@@ -0,0 +1,118 @@
+use super::Game;
+use glfw::{Action, Key};
+
+// how far in the board can be zoomed, in board sizes
+const MAX_ZOOM: f64 = 3.0;
+// how much one step of a scroll wheel zooms; trackpads send fractions of steps
+const ZOOM_STEP: f64 = 1.15;
+// how far one step pans a zoomed in board, in fractions of the area it's shown in
+const PAN_STEP: f64 = 0.1;
+
+// what trackpads and touch screens do to the board, past the clicks and drags a tap
+// already is. glfw only sees gestures as scrolling: two fingers dragged scroll, and a
+// pinch comes as scrolling with Ctrl held, the way precision touchpads send it
+pub struct Gestures {
+    // how many times bigger than its area the board is drawn
+    scale: f64,
+    // where the board's top left corner is from its area's, in fractions of the
+    // area's size; never more than 0 so the board always covers its area
+    offset: (f64, f64),
+    // scrolled but not enough for another move yet
+    swipe: f64,
+}
+
+impl Default for Gestures {
+    fn default() -> Gestures {
+        Gestures {
+            scale: 1.0,
+            offset: (0.0, 0.0),
+            swipe: 0.0,
+        }
+    }
+}
+
+impl Gestures {
+    pub fn is_zoomed(&self) -> bool {
+        self.scale > 1.0
+    }
+
+    // where the board goes when its area is [left, top, size, size], which is only the
+    // area itself while it isn't zoomed in
+    pub fn zoomed(&self, [left, top, size, _]: [f64; 4]) -> [f64; 4] {
+        [
+            left + self.offset.0 * size,
+            top + self.offset.1 * size,
+            size * self.scale,
+            size * self.scale,
+        ]
+    }
+
+    // zooms by `factor` keeping the point `at` where it is, both of its coordinates in
+    // fractions of the area from its top left
+    fn zoom(&mut self, factor: f64, at: (f64, f64)) {
+        let scale = (self.scale * factor).clamp(1.0, MAX_ZOOM);
+        // the same point of the board before and after
+        let on_board = |at: f64, offset: f64| (at - offset) / self.scale;
+        self.offset = (
+            at.0 - on_board(at.0, self.offset.0) * scale,
+            at.1 - on_board(at.1, self.offset.1) * scale,
+        );
+        self.scale = scale;
+        self.clamp_offset();
+    }
+
+    fn pan(&mut self, x: f64, y: f64) {
+        self.offset.0 += x * PAN_STEP;
+        self.offset.1 += y * PAN_STEP;
+        self.clamp_offset();
+    }
+
+    fn clamp_offset(&mut self) {
+        let least = 1.0 - self.scale;
+        self.offset = (
+            self.offset.0.clamp(least, 0.0),
+            self.offset.1.clamp(least, 0.0),
+        );
+    }
+}
+
+impl Game {
+    // scrolling over the board: a pinch zooms it, and two fingers pan it while it's
+    // zoomed in or step through the moves otherwise, like a mouse wheel does
+    pub(super) fn scroll_board(&mut self, x: f64, y: f64) {
+        let pinch = [Key::LeftControl, Key::RightControl]
+            .iter()
+            .any(|&key| self.window.get_key(key) == Action::Press);
+        if pinch {
+            self.zoom_board(y);
+        } else if self.gestures.is_zoomed() {
+            self.gestures.pan(x, y);
+        } else {
+            // whichever way the fingers mostly went, back for left or up
+            self.swipe_history(if x.abs() > y.abs() { x } else { y });
+        }
+    }
+
+    fn zoom_board(&mut self, steps: f64) {
+        let (width, height) = self.window.get_framebuffer_size();
+        let ([left, top, size, _], _, _) = self.layout(width as f64, height as f64);
+        let at = (
+            ((self.cursor.0 - left) / size).clamp(0.0, 1.0),
+            ((self.cursor.1 - top) / size).clamp(0.0, 1.0),
+        );
+        self.gestures.zoom(ZOOM_STEP.powf(steps), at);
+    }
+
+    // a move back or forward for each whole step scrolled, the rest kept for the next
+    fn swipe_history(&mut self, steps: f64) {
+        if self.is_editing() {
+            return;
+        }
+        self.gestures.swipe += steps;
+        while self.gestures.swipe.abs() >= 1.0 {
+            let forward = self.gestures.swipe < 0.0;
+            self.gestures.swipe += if forward { 1.0 } else { -1.0 };
+            self.step_through_moves(forward);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::board::{CastlingRights, Color, Piece, PieceKind, Square};
+use std::sync::OnceLock;
+
+// zobrist hashing: every feature of a position gets a random number and the key of a
+// position is all of its features' numbers xored together, so making a move only has
+// to xor out what changed and xor in what replaced it
+//
+// the numbers are laid out the way polyglot books expect: 768 for the pieces, four
+// for the castling rights, eight for the en passant files and one for white to move,
+// then the crazyhouse pockets, which polyglot doesn't have. they're generated rather
+// than polyglot's published table, so books written by this program are found again
+// but books made by other programs won't match
+const PIECES: usize = 0;
+const CASTLING: usize = 768;
+const EN_PASSANT: usize = 772;
+const TURN: usize = 780;
+// a number for every count from 1 to 32 of each kind in each side's pocket
+const POCKETS: usize = 781;
+const NUMBERS: usize = 1101;
+
+fn random() -> &'static [u64; NUMBERS] {
+    static RANDOM: OnceLock<[u64; NUMBERS]> = OnceLock::new();
+    RANDOM.get_or_init(|| {
+        // a fixed seed so keys are the same every run
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut random = [0; NUMBERS];
+        for number in random.iter_mut() {
+            // xorshift64*
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            *number = state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        }
+        random
+    })
+}
+
+// polyglot orders the pieces black pawn, white pawn, black knight and so on
+pub fn piece(piece: Piece, square: Square) -> u64 {
+    let kind = piece.kind as usize * 2 + (piece.color == Color::White) as usize;
+    random()[PIECES + 64 * kind + square.index()]
+}
+
+pub fn side(color: Color) -> u64 {
+    match color {
+        Color::White => random()[TURN],
+        Color::Black => 0,
+    }
+}
+
+pub fn castling(rights: CastlingRights) -> u64 {
+    let random = random();
+    let mut key = 0;
+    for (i, &right) in [
+        rights.white_kingside,
+        rights.white_queenside,
+        rights.black_kingside,
+        rights.black_queenside,
+    ]
+    .iter()
+    .enumerate()
+    {
+        if right {
+            key ^= random[CASTLING + i];
+        }
+    }
+    key
+}
+
+pub fn en_passant(square: Square) -> u64 {
+    random()[EN_PASSANT + square.file() as usize]
+}
+
+// nothing for an empty pocket, so boards without pockets keep their keys
+pub fn pocket(color: Color, kind: PieceKind, count: u8) -> u64 {
+    if count == 0 || kind == PieceKind::King {
+        return 0;
+    }
+    let slot = color as usize * 5 + kind as usize;
+    random()[POCKETS + slot * 32 + (count as usize).min(32) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::fen::STARTING_FEN;
+
+    // the keys polyglot's specification gives for these positions. they only come out
+    // once the first 781 numbers are polyglot's published table, which isn't in this
+    // tree yet, so the numbers above are still the generated ones
+    #[test]
+    #[ignore = "needs polyglot's published random64 table"]
+    fn positions_key_the_way_polyglot_books_do() {
+        let mut board = Board::from_fen(STARTING_FEN).unwrap();
+        assert_eq!(board.position_key(), 0x463b_9618_1691_fc9c);
+        let mv = board.parse_san("e4").unwrap();
+        board.make_move(mv);
+        assert_eq!(board.position_key(), 0x823c_9b50_fd11_4196);
+    }
+}